@@ -0,0 +1,53 @@
+use cdf::cdf::{Cdf, ReadOptions};
+use cdf::error::CdfError;
+use std::env;
+
+// List the variables and attributes of a CDF file for scripts, one tab-separated line each, or as
+// JSON with `--json`, which needs the `serde` feature:
+//
+//     cargo run --example ls --features serde -- examples/data/ulysses.cdf --json
+fn main() -> Result<(), CdfError> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let json = args.iter().any(|a| a == "--json");
+    let Some(path) = args.iter().find(|a| !a.starts_with("--")) else {
+        return Err(CdfError::Other("Usage: ls FILE [--json]".to_string()));
+    };
+    let options = ReadOptions {
+        lazy: true,
+        ..Default::default()
+    };
+    let listing = Cdf::read_cdf_file_with(path, options)?.listing()?;
+
+    if json {
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string_pretty(&listing)
+                .map_err(|err| CdfError::Other(err.to_string()))?;
+            println!("{json}");
+            return Ok(());
+        }
+        #[cfg(not(feature = "serde"))]
+        return Err(CdfError::Other(
+            "JSON output needs the serde feature.".to_string(),
+        ));
+    }
+
+    for v in &listing.variables {
+        println!(
+            "variable\t{}\t{}\t{:?}\t{:?}\t{}\t{}",
+            v.name,
+            if v.zvariable { "z" } else { "r" },
+            v.data_type,
+            v.dims,
+            v.records,
+            v.compression
+                .as_ref()
+                .map_or_else(|| "-".to_string(), |c| format!("{c:?}"))
+        );
+    }
+    for a in &listing.attributes {
+        let scope = if a.global { "global" } else { "variable" };
+        println!("attribute\t{}\t{scope}\t{}", a.name, a.entries);
+    }
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use cdf::error::CdfError;
+use cdf::transcode::merge_files;
+use std::env;
+
+// Concatenate the records of CDF files holding the same variables into one file, in the order
+// given, for instance daily files into a monthly one:
+//
+//     cargo run --example merge -- day1.cdf day2.cdf --out combined.cdf
+fn main() -> Result<(), CdfError> {
+    let mut args = env::args().skip(1);
+    let mut inputs = Vec::new();
+    let mut output = None;
+    while let Some(arg) = args.next() {
+        if arg == "--out" {
+            output = args.next();
+        } else {
+            inputs.push(arg);
+        }
+    }
+    let (Some(output), false) = (output, inputs.is_empty()) else {
+        return Err(CdfError::Other(
+            "Usage: merge FILE... --out OUTPUT".to_string(),
+        ));
+    };
+    merge_files(&inputs, &output)?;
+    println!("Merged {} files into {output}.", inputs.len());
+    Ok(())
+}
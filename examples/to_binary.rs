@@ -0,0 +1,39 @@
+#[cfg(feature = "serde")]
+use cdf::{cdf::Cdf, error::CdfError};
+#[cfg(feature = "serde")]
+use std::{fs, path::PathBuf};
+
+// Convert a CDF file to CBOR and to MessagePack, which are much smaller than pretty JSON and are
+// suited to caching decoded files, and read both back.
+#[cfg(feature = "serde")]
+fn to_binary_from_binary(filename: &str) -> Result<(), CdfError> {
+    let input_file: PathBuf = [env!("CARGO_MANIFEST_DIR"), "examples", "data", filename]
+        .iter()
+        .collect();
+
+    let cdf = Cdf::read_cdf_file(input_file.clone())?;
+
+    let cbor = serde_cbor::to_vec(&cdf).map_err(|err| CdfError::Other(err.to_string()))?;
+    fs::write(input_file.with_extension("cbor"), &cbor)?;
+    let from_cbor: Cdf = serde_cbor::from_slice(&fs::read(input_file.with_extension("cbor"))?)
+        .map_err(|err| CdfError::Other(err.to_string()))?;
+
+    let msgpack = rmp_serde::to_vec_named(&cdf).map_err(|err| CdfError::Other(err.to_string()))?;
+    fs::write(input_file.with_extension("msgpack"), &msgpack)?;
+    let from_msgpack: Cdf = rmp_serde::from_slice(&fs::read(input_file.with_extension("msgpack"))?)
+        .map_err(|err| CdfError::Other(err.to_string()))?;
+
+    println!(
+        "{filename}: {} bytes of CBOR, {} bytes of MessagePack",
+        cbor.len(),
+        msgpack.len()
+    );
+    assert_eq!(format!("{:?}", from_cbor.cdr), format!("{:?}", cdf.cdr));
+    assert_eq!(format!("{:?}", from_msgpack.cdr), format!("{:?}", cdf.cdr));
+    Ok(())
+}
+
+fn main() {
+    #[cfg(feature = "serde")]
+    to_binary_from_binary("test_alltypes.cdf").unwrap();
+}
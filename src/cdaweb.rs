@@ -0,0 +1,430 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::ops::Range;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::cdf::{Cdf, ReadOptions};
+use crate::error::CdfError;
+use crate::time::TimeComponents;
+
+/// Base URL of the CDAS REST web services of CDAWeb.
+pub const CDAS_URL: &str = "https://cdaweb.gsfc.nasa.gov/WS/cdasr/1";
+
+/// Number of bytes downloaded by each range request of an [`HttpReader`].
+const BLOCK_SIZE: u64 = 256 * 1024;
+
+/// A CDF file of a dataset returned by CDAWeb for a time range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDescription {
+    /// URL of the file.
+    pub url: String,
+    /// Start of the data in the file, in ISO 8601.
+    pub start: String,
+    /// End of the data in the file, in ISO 8601.
+    pub end: String,
+    /// Size of the file in bytes.
+    pub length: u64,
+}
+
+/// Client of the CDAS REST API, which resolves datasets and time ranges to the CDF files that
+/// hold their data, and opens these files without downloading them first.
+#[derive(Debug, Clone)]
+pub struct Client {
+    agent: ureq::Agent,
+    base_url: String,
+    dataview: String,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Client::new(CDAS_URL)
+    }
+}
+
+impl Client {
+    /// Create a client of the CDAS REST API at `base_url`, such as [`CDAS_URL`], using the
+    /// `sp_phys` dataview.
+    pub fn new(base_url: &str) -> Self {
+        Client {
+            agent: ureq::Agent::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            dataview: "sp_phys".to_string(),
+        }
+    }
+
+    /// Use the dataview called `dataview` instead of `sp_phys`.
+    pub fn dataview(mut self, dataview: &str) -> Self {
+        self.dataview = dataview.to_string();
+        self
+    }
+
+    /// The CDF files of `dataset` that hold data between the start and the end of `range`.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if the request fails or its response is invalid.
+    pub fn files(
+        &self,
+        dataset: &str,
+        range: Range<TimeComponents>,
+    ) -> Result<Vec<FileDescription>, CdfError> {
+        let url = format!(
+            "{}/dataviews/{}/datasets/{dataset}/orig_data/{},{}",
+            self.base_url,
+            self.dataview,
+            basic_iso8601(&range.start),
+            basic_iso8601(&range.end)
+        );
+        let response = self
+            .agent
+            .get(&url)
+            .set("Accept", "application/json")
+            .call()
+            .map_err(|e| request_error(&url, e))?;
+        // CDAS answers without content when there is no data in the range.
+        if response.status() == 204 {
+            return Ok(Vec::new());
+        }
+        let body = response
+            .into_string()
+            .map_err(|e| CdfError::Other(format!("Invalid response from {url} - {e}.")))?;
+        parse_files(&body)
+    }
+
+    /// Open the CDF file described by `file` lazily, so that only the metadata of the file and
+    /// the values of the variables that are read are downloaded.
+    /// # Errors
+    /// Returns a [`CdfError`] if the file cannot be downloaded or decoded.
+    pub fn open(&self, file: &FileDescription) -> Result<Cdf, CdfError> {
+        let reader = HttpReader::with_agent(self.agent.clone(), &file.url)?;
+        Cdf::from_reader(
+            reader,
+            ReadOptions {
+                lazy: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Open the first CDF file of `dataset` that holds data in `range`. Most datasets have one
+    /// file per day, so see [`Client::fetch_all`] for longer ranges.
+    /// # Errors
+    /// Returns a [`CdfError`] if no file holds data in the range, or if it cannot be opened.
+    pub fn fetch(&self, dataset: &str, range: Range<TimeComponents>) -> Result<Cdf, CdfError> {
+        let files = self.files(dataset, range.clone())?;
+        let file = files.first().ok_or_else(|| {
+            CdfError::Other(format!(
+                "No file of {dataset} holds data between {} and {}.",
+                basic_iso8601(&range.start),
+                basic_iso8601(&range.end)
+            ))
+        })?;
+        self.open(file)
+    }
+
+    /// Open every CDF file of `dataset` that holds data in `range`, in order of time.
+    /// # Errors
+    /// Returns a [`CdfError`] if the files cannot be listed or opened.
+    pub fn fetch_all(
+        &self,
+        dataset: &str,
+        range: Range<TimeComponents>,
+    ) -> Result<Vec<Cdf>, CdfError> {
+        self.files(dataset, range)?
+            .iter()
+            .map(|file| self.open(file))
+            .collect()
+    }
+}
+
+/// Open the first CDF file of `dataset` that holds data in `range` from CDAWeb, see
+/// [`Client::fetch`].
+/// ```no_run
+/// use cdf::{cdaweb, time::TimeComponents};
+///
+/// let start = TimeComponents::new(2020, 1, 1, 0, 0, 0);
+/// let end = TimeComponents::new(2020, 1, 1, 12, 0, 0);
+/// let cdf = cdaweb::fetch("AC_H2_MFI", start..end)?;
+/// let magnitude = cdf.variable("Magnitude")?.read_all()?;
+/// # Ok::<(), cdf::error::CdfError>(())
+/// ```
+/// # Errors
+/// Returns a [`CdfError`] if no file holds data in the range, or if it cannot be opened.
+pub fn fetch(dataset: &str, range: Range<TimeComponents>) -> Result<Cdf, CdfError> {
+    Client::default().fetch(dataset, range)
+}
+
+/// Reader of a file served over HTTP that downloads blocks of the file as they are read, using
+/// range requests. Servers that do not support range requests send the whole file at once.
+#[derive(Debug)]
+pub struct HttpReader {
+    agent: ureq::Agent,
+    url: String,
+    len: u64,
+    position: u64,
+    block_start: u64,
+    block: Vec<u8>,
+}
+
+impl HttpReader {
+    /// Open the file at `url`, downloading its first block.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if the request fails.
+    pub fn open(url: &str) -> Result<Self, CdfError> {
+        Self::with_agent(ureq::Agent::new(), url)
+    }
+
+    fn with_agent(agent: ureq::Agent, url: &str) -> Result<Self, CdfError> {
+        let mut reader = HttpReader {
+            agent,
+            url: url.to_string(),
+            len: u64::MAX,
+            position: 0,
+            block_start: 0,
+            block: Vec::new(),
+        };
+        reader.fetch(0)?;
+        Ok(reader)
+    }
+
+    /// Size of the file in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Download the block that starts at `start`, and learn the size of the file on the way.
+    fn fetch(&mut self, start: u64) -> io::Result<()> {
+        let end = (start + BLOCK_SIZE).min(self.len) - 1;
+        let response = self
+            .agent
+            .get(&self.url)
+            .set("Range", &format!("bytes={start}-{end}"))
+            .call()
+            .map_err(|e| io::Error::other(request_error(&self.url, e)))?;
+        let partial = response.status() == 206;
+        let total = response
+            .header("Content-Range")
+            .and_then(|range| range.rsplit_once('/'))
+            .and_then(|(_, total)| total.parse().ok());
+        let mut block = Vec::new();
+        _ = response.into_reader().read_to_end(&mut block)?;
+        if partial {
+            self.len = total.ok_or_else(|| {
+                io::Error::other(format!("Missing size of {} in Content-Range.", self.url))
+            })?;
+            self.block_start = start;
+        } else {
+            self.len = block.len() as u64;
+            self.block_start = 0;
+        }
+        self.block = block;
+        Ok(())
+    }
+}
+
+impl Read for HttpReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let in_block = self
+            .position
+            .checked_sub(self.block_start)
+            .filter(|offset| *offset < self.block.len() as u64);
+        let offset = match in_block {
+            Some(offset) => offset as usize,
+            None => {
+                self.fetch(self.position)?;
+                (self.position - self.block_start) as usize
+            }
+        };
+        let n = buf.len().min(self.block.len().saturating_sub(offset));
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{} ended before its announced size.", self.url),
+            ));
+        }
+        buf[..n].copy_from_slice(&self.block[offset..offset + n]);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for HttpReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::End(d) => self.len.checked_add_signed(d),
+            SeekFrom::Current(d) => self.position.checked_add_signed(d),
+        };
+        self.position = position.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Seek to a negative position.")
+        })?;
+        Ok(self.position)
+    }
+}
+
+// Times in the URLs of CDAS are in the basic format of ISO 8601, like 20200101T000000Z.
+fn basic_iso8601(c: &TimeComponents) -> String {
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        c.year, c.month, c.day, c.hour, c.minute, c.second
+    )
+}
+
+fn request_error(url: &str, error: ureq::Error) -> CdfError {
+    CdfError::Other(format!("Request to {url} failed - {error}."))
+}
+
+// Parse the files listed in a response to an `orig_data` request.
+fn parse_files(body: &str) -> Result<Vec<FileDescription>, CdfError> {
+    let invalid =
+        |e: &dyn core::fmt::Display| CdfError::Other(format!("Invalid CDAS response - {e}."));
+    let value: serde_json::Value = serde_json::from_str(body).map_err(|e| invalid(&e))?;
+    let Some(files) = value.get("FileDescription").and_then(|f| f.as_array()) else {
+        return Ok(Vec::new());
+    };
+    files
+        .iter()
+        .map(|file| {
+            let field = |name: &str| {
+                file.get(name)
+                    .and_then(|v| v.as_str())
+                    .map(ToString::to_string)
+                    .ok_or_else(|| invalid(&format!("missing {name}")))
+            };
+            Ok(FileDescription {
+                url: field("Name")?,
+                start: field("StartTime")?,
+                end: field("EndTime")?,
+                length: file.get("Length").and_then(|v| v.as_u64()).unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+
+    // Serve `file` and a CDAS response that lists it on a local port, answering range requests,
+    // and return the base URL.
+    fn _serve(file: Vec<u8>, requests: usize) -> Result<String, CdfError> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        let listing = format!(
+            r#"{{"FileDescription":[{{"Name":"{base_url}/data/test.cdf","MimeType":"application/x-cdf","StartTime":"2020-01-01T00:00:00.000Z","EndTime":"2020-01-02T00:00:00.000Z","Length":{}}}]}}"#,
+            file.len()
+        );
+        std::thread::spawn(move || -> io::Result<()> {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream?;
+                let mut lines = BufReader::new(stream.try_clone()?).lines();
+                let request = lines.next().transpose()?.unwrap_or_default();
+                let mut range = None;
+                for line in lines {
+                    let line = line?;
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(r) = line.strip_prefix("Range: bytes=") {
+                        range = r.split_once('-').map(|(a, b)| {
+                            (a.parse::<usize>().unwrap(), b.parse::<usize>().unwrap())
+                        });
+                    }
+                }
+                let (status, headers, body) = if request.contains("/orig_data/") {
+                    ("200 OK", String::new(), listing.as_bytes().to_vec())
+                } else if let Some((start, end)) = range {
+                    let end = end.min(file.len() - 1);
+                    let headers = format!("Content-Range: bytes {start}-{end}/{}\r\n", file.len());
+                    ("206 Partial Content", headers, file[start..=end].to_vec())
+                } else {
+                    ("200 OK", String::new(), file.clone())
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                )?;
+                stream.write_all(&body)?;
+            }
+            Ok(())
+        });
+        Ok(base_url)
+    }
+
+    #[test]
+    fn test_cdaweb_fetch() -> Result<(), CdfError> {
+        let path: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let bytes = std::fs::read(&path)?;
+        let base_url = _serve(bytes.clone(), 100)?;
+        let client = Client::new(&base_url);
+
+        let start = TimeComponents::new(2020, 1, 1, 0, 0, 0);
+        let end = TimeComponents::new(2020, 1, 1, 12, 0, 0);
+        let files = client.files("TEST", start..end)?;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].length, bytes.len() as u64);
+
+        let remote = client.fetch("TEST", start..end)?;
+        let local = Cdf::read_cdf_file(path)?;
+        for name in ["Longitude", "Temp"] {
+            assert_eq!(
+                format!("{:?}", remote.variable(name)?.read_all()?),
+                format!("{:?}", local.variable(name)?.read_all()?)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_http_reader() -> Result<(), CdfError> {
+        let file: Vec<u8> = (0..BLOCK_SIZE as usize * 2 + 10).map(|i| i as u8).collect();
+        let base_url = _serve(file.clone(), 10)?;
+        let mut reader = HttpReader::open(&format!("{base_url}/file"))?;
+        assert_eq!(reader.len(), file.len() as u64);
+
+        // Reads that cross a block boundary download the next block.
+        let mut buffer = [0; 20];
+        _ = reader.seek(SeekFrom::Start(BLOCK_SIZE - 10))?;
+        reader.read_exact(&mut buffer)?;
+        let start = BLOCK_SIZE as usize - 10;
+        assert_eq!(buffer, file[start..start + 20]);
+        _ = reader.seek(SeekFrom::End(-5))?;
+        let mut tail = Vec::new();
+        _ = reader.read_to_end(&mut tail)?;
+        assert_eq!(tail, file[file.len() - 5..]);
+        assert!(reader.seek(SeekFrom::Current(-100_000_000)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_files() -> Result<(), CdfError> {
+        assert!(parse_files("{}")?.is_empty());
+        assert!(parse_files("not json").is_err());
+        assert!(parse_files(r#"{"FileDescription":[{"Name":"a"}]}"#).is_err());
+        assert_eq!(
+            basic_iso8601(&TimeComponents::new(2020, 1, 2, 3, 4, 5)),
+            "20200102T030405Z"
+        );
+        Ok(())
+    }
+}
@@ -1,38 +1,991 @@
-use std::fs::File;
-use std::io::{self, BufReader};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{DerefMut, Index};
+#[cfg(feature = "std-fs")]
+use std::{fs::File, path::Path};
 
+use crate::io::{self, Seek, SeekFrom};
+
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::decode::{Decodable, Decoder};
+use crate::checksum::verify_md5;
+use crate::compression::decompress_limited;
+use crate::dataset::Dataset;
+use crate::decode::{
+    checked_usize, decode_version3_int4_int8, CancelToken, Decodable, DecodeWarning, Decoder,
+    DecoderBuilder, ProgressFn, WarningFn,
+};
 use crate::error::CdfError;
+use crate::record::adr::AttributeDescriptorRecord;
+use crate::record::ccr::CompressedCdfRecord;
 use crate::record::cdr::CdfDescriptorRecord;
-use crate::repr::CdfVersion;
-use crate::types::CdfUint4;
+use crate::record::cpr::{CdfCompressionKind, CompressedParametersRecord};
+use crate::record::unknown::{is_known_record_type, UnknownRecord};
+use crate::repr::{CdfEncoding, CdfVersion};
+use crate::time::leap_seconds;
+use crate::types::{CdfInt4, CdfType, CdfUint4, DataType};
+use crate::variable::{
+    Promotion, Variable, VariableAttributes, VariableDescriptor, VirtualFn, VirtualVariable,
+};
 
 /// General struct to hold the contents of the CDF file.
 // #[cfg(feature = "serde")]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct Cdf {
     /// Whether this CDF file is compressed.
     pub is_compressed: bool,
+    /// Compressed Parameters Record describing the compression of the file, if compressed.
+    pub cpr: Option<CompressedParametersRecord>,
     /// Contents of the CDF Descriptor Record.
     pub cdr: CdfDescriptorRecord,
+    /// The reader this file was decoded from, kept to read variable values that were not loaded.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub(crate) source: Option<CdfSource>,
+    /// Warnings reported while this file was decoded.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub(crate) decode_warnings: Vec<DecodeWarning>,
+    /// Exact bytes of the records of this file by offset, kept if [`ReadOptions::keep_raw`] is set.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub(crate) raw_records: BTreeMap<u64, Vec<u8>>,
+    /// Variables computed from other variables, registered with [`Cdf::register_virtual`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub(crate) virtual_variables: Vec<VirtualVariable>,
+    /// Type that numeric values are converted to when read, set by [`ReadOptions::promote`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub(crate) promotion: Option<Promotion>,
+    /// Records of types this crate does not know, found when reading in file order.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub(crate) unknown_records: Vec<UnknownRecord>,
+}
+
+/// File-level facts about a CDF file, taken from its CDR and GDR, see [`Cdf::info`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CdfInfo {
+    /// Version of the CDF library that wrote the file.
+    pub version: CdfVersion,
+    /// Encoding of the values stored in the file.
+    pub encoding: CdfEncoding,
+    /// Order in which the values of multi-dimensional records are stored.
+    pub majority: Majority,
+    /// Whether the whole file is compressed.
+    pub compressed: bool,
+    /// Whether the file ends with an MD5 checksum.
+    pub checksum: bool,
+    /// Number of rVariables and zVariables.
+    pub n_variables: usize,
+    /// Number of global and variable attributes.
+    pub n_attributes: usize,
+    /// Date of the last leap second known to the library that wrote the file, as YYYYMMDD, if the
+    /// file records one.
+    pub last_leap_second: Option<i32>,
+}
+
+/// What the format of a CDF file can represent, which depends on the version of the file, see
+/// [`Cdf::format`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatInfo {
+    /// Version of the CDF library that wrote the file.
+    pub version: CdfVersion,
+    /// Whether file offsets and record sizes are stored in 8 bytes rather than 4, so that the file
+    /// can be larger than 2 GiB.
+    pub uses_64bit_offsets: bool,
+    /// Maximum length in bytes of the names of variables and attributes.
+    pub max_name_len: usize,
+    /// Whether variables and attribute entries can hold [`DataType::TimeTt2000`] values.
+    pub supports_tt2000: bool,
+    /// Whether the file can end with an MD5 checksum.
+    pub supports_checksum: bool,
+}
+
+impl FormatInfo {
+    /// The capabilities of the format of files of `version`.
+    pub fn for_version(version: &CdfVersion) -> Self {
+        let version3 = version.major >= 3;
+        FormatInfo {
+            version: version.clone(),
+            uses_64bit_offsets: version3,
+            max_name_len: if version3 { 256 } else { 64 },
+            supports_tt2000: *version >= CdfVersion::new(3, 4, 0),
+            supports_checksum: *version >= CdfVersion::new(3, 2, 0),
+        }
+    }
+}
+
+/// The variables and attributes of a CDF file for tools and scripts, see [`Cdf::listing`]. With
+/// the `serde` feature, field names and values are stable, e.g. when serialized as JSON.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listing {
+    /// Variables in the order of [`Cdf::variables`].
+    pub variables: Vec<VariableListing>,
+    /// Global and variable attributes in the order they are stored.
+    pub attributes: Vec<AttributeListing>,
+}
+
+/// One variable of a [`Listing`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariableListing {
+    /// Name of the variable.
+    pub name: String,
+    /// Whether the variable is a zVariable, or else an rVariable.
+    pub zvariable: bool,
+    /// Data type of the values.
+    pub data_type: DataType,
+    /// Number of elements of the data type in each value, i.e. the length of strings.
+    pub num_elements: usize,
+    /// Sizes of all dimensions.
+    pub dims: Vec<usize>,
+    /// Whether the values vary along each dimension.
+    pub dim_variances: Vec<bool>,
+    /// Number of records.
+    pub records: usize,
+    /// Whether the values vary from record to record.
+    pub record_varies: bool,
+    /// Compression of the values, if they are compressed.
+    pub compression: Option<CdfCompressionKind>,
+}
+
+/// One attribute of a [`Listing`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeListing {
+    /// Name of the attribute.
+    pub name: String,
+    /// Whether the attribute describes the file, or else variables.
+    pub global: bool,
+    /// Number of entries of the attribute, for all variables if it is a variable attribute.
+    pub entries: usize,
+}
+
+/// The structure of a CDF file without its values, see [`Cdf::schema`]: its variables, their
+/// data types and shapes, and its attributes. Schemas of two files, or of two versions of a file,
+/// are equal if the files can be read the same way. With the `serde` feature, schemas can be
+/// stored, e.g. as JSON, to catalog files or generate code for them.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    /// Order in which the values of multi-dimensional records are stored.
+    pub majority: Majority,
+    /// Entries of each global attribute, in the order they are numbered.
+    pub attributes: BTreeMap<String, Vec<EntrySchema>>,
+    /// Variables in the order of [`Cdf::variables`].
+    pub variables: Vec<VariableSchema>,
+}
+
+/// One variable of a [`Schema`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariableSchema {
+    /// Name of the variable.
+    pub name: String,
+    /// Whether the variable is a zVariable, or else an rVariable.
+    pub zvariable: bool,
+    /// Data type of the values.
+    pub data_type: DataType,
+    /// Number of elements of the data type in each value, i.e. the length of strings.
+    pub num_elements: usize,
+    /// Shape of each record, see [`Variable::record_shape`].
+    pub shape: Vec<usize>,
+    /// Whether the values vary along each dimension.
+    pub dim_variances: Vec<bool>,
+    /// Whether the values vary from record to record.
+    pub record_varies: bool,
+    /// Entry of each variable attribute that describes this variable.
+    pub attributes: BTreeMap<String, EntrySchema>,
+}
+
+/// One entry of an attribute in a [`Schema`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct EntrySchema {
+    /// Data type of the values.
+    pub data_type: DataType,
+    /// The values, or a single [`CdfType::String`] for character data.
+    pub values: Vec<CdfType>,
+}
+
+impl PartialEq for EntrySchema {
+    fn eq(&self, other: &Self) -> bool {
+        // Values are compared as printed, so that NaN fill values are equal to themselves.
+        self.data_type == other.data_type
+            && format!("{:?}", self.values) == format!("{:?}", other.values)
+    }
+}
+
+/// Order in which the values of multi-dimensional records are stored.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Majority {
+    /// The last dimension varies fastest, as in C.
+    Row,
+    /// The first dimension varies fastest, as in Fortran.
+    Column,
+}
+
+/// Options that control how much of a CDF file is read by [`Cdf::read_cdf_file_with`].
+#[derive(Clone, Default)]
+pub struct ReadOptions {
+    /// Only decode the metadata of the file. Variable values are read on demand using
+    /// [`Variable::read_records`].
+    pub lazy: bool,
+    /// Names of the variables whose values are loaded. All variables are loaded if this is `None`.
+    pub variables: Option<Vec<String>>,
+    /// Decompress compressed files and compressed variable values while decoding.
+    pub decompress: bool,
+    /// Verify the MD5 checksum of the file, if it has one.
+    pub verify_checksum: bool,
+    /// Callback that reports the progress of decoding and of reading variable values.
+    pub progress: Option<Arc<ProgressFn>>,
+    /// Callback that reports warnings found while decoding.
+    pub warning: Option<Arc<WarningFn>>,
+    /// Number of readers that [`Cdf::read_cdf_file_with`] opens over the file, so that as many
+    /// variable reads can run in parallel. Zero means one reader.
+    pub readers: usize,
+    /// Keep the exact bytes of each record that is decoded, see [`Cdf::raw_records`].
+    pub keep_raw: bool,
+    /// Token that cancels decoding and reading variable values on demand.
+    pub cancel: Option<CancelToken>,
+    /// Convert the values of numeric variables to a single type as their records are read, so
+    /// that they do not need to be handled for each integer and real type. Values keep their
+    /// stored type if this is `None`.
+    pub promote: Option<Promotion>,
+    /// Size of the buffer that [`Cdf::from_reader`] wraps the reader in, see
+    /// [`io::BufferedReader`]. The reader is used as given if this is `None`, while files opened
+    /// by [`Cdf::read_cdf_file_with`] use a buffer of [`io::DEFAULT_BUFFER_SIZE`] bytes.
+    pub buffer_size: Option<usize>,
+    /// Number of bytes read at once when reading the records of a variable on demand, so that
+    /// consecutive VVRs are read together, which makes a difference on network filesystems.
+    /// Zero reads each VVR separately. Reads are no larger than the records to read and the
+    /// headers of their VVRs.
+    pub prefetch: usize,
+    /// Read the records of the file once in file order, then decode them from memory, which makes
+    /// a difference on spinning disks and network filesystems. See
+    /// [`crate::decode::DecodeOptions::sequential`].
+    pub sequential: bool,
+}
+
+impl fmt::Debug for ReadOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReadOptions")
+            .field("lazy", &self.lazy)
+            .field("variables", &self.variables)
+            .field("decompress", &self.decompress)
+            .field("verify_checksum", &self.verify_checksum)
+            .field("progress", &self.progress.is_some())
+            .field("warning", &self.warning.is_some())
+            .field("readers", &self.readers)
+            .field("keep_raw", &self.keep_raw)
+            .field("cancel", &self.cancel)
+            .field("promote", &self.promote)
+            .field("buffer_size", &self.buffer_size)
+            .field("prefetch", &self.prefetch)
+            .field("sequential", &self.sequential)
+            .finish()
+    }
+}
+
+impl ReadOptions {
+    /// Create a [`DecoderBuilder`] configured with these options.
+    pub fn builder(&self) -> DecoderBuilder {
+        let mut builder = Decoder::builder()
+            .lazy(self.lazy)
+            .decompress(self.decompress)
+            .verify_checksum(self.verify_checksum)
+            .keep_raw(self.keep_raw);
+        if let Some(names) = &self.variables {
+            builder = builder.variables(names.iter().cloned());
+        }
+        if let Some(progress) = &self.progress {
+            let progress = Arc::clone(progress);
+            builder = builder.progress(move |p| progress(p));
+        }
+        if let Some(warning) = &self.warning {
+            let warning = Arc::clone(warning);
+            builder = builder.warning(move |w| warning(w));
+        }
+        if let Some(cancel) = &self.cancel {
+            builder = builder.cancel(cancel.clone());
+        }
+        if let Some(buffer_size) = self.buffer_size {
+            builder = builder.buffer_size(buffer_size);
+        }
+        builder.prefetch(self.prefetch).sequential(self.sequential)
+    }
+}
+
+/// Object-safe combination of [`io::Read`] and [`io::Seek`]. Readers must be [`Send`] so that a
+/// [`Cdf`] that keeps its reader can be shared between threads.
+pub trait ReadSeek: io::Read + io::Seek + Send {}
+impl<T: io::Read + io::Seek + Send> ReadSeek for T {}
+
+#[cfg(feature = "std")]
+type SourceCell<T> = std::sync::Mutex<T>;
+#[cfg(not(feature = "std"))]
+type SourceCell<T> = core::cell::RefCell<T>;
+
+/// The decoders a [`Cdf`] was read with, retained so that variable values can be read later. Each
+/// decoder has its own reader over the same file, which is handed out to one read at a time. With
+/// the `std` feature the decoders are behind mutexes, so that variables of the same file can be
+/// read from several threads, in parallel if there are several readers.
+pub(crate) struct CdfSource {
+    decoders: Vec<SourceCell<Decoder<Box<dyn ReadSeek>>>>,
+    #[cfg(feature = "std")]
+    next: core::sync::atomic::AtomicUsize,
+}
+
+impl CdfSource {
+    pub(crate) fn new<R>(decoder: Decoder<R>) -> Self
+    where
+        R: io::Read + io::Seek + Send + 'static,
+    {
+        // Records read on demand later on are not kept.
+        let mut decoder = Decoder {
+            reader: Box::new(decoder.reader) as Box<dyn ReadSeek>,
+            context: decoder.context,
+            options: decoder.options,
+        };
+        decoder.options.keep_raw = false;
+        decoder.context.raw_records.clear();
+        CdfSource {
+            decoders: vec![SourceCell::new(decoder)],
+            #[cfg(feature = "std")]
+            next: core::sync::atomic::AtomicUsize::new(0),
+        }
+    }
+
+    /// Add another reader over the same file to the pool.
+    fn add_reader(&mut self, reader: Box<dyn ReadSeek>) {
+        let decoder = {
+            let first = self.lock();
+            Decoder {
+                reader,
+                context: first.context.clone(),
+                options: first.options.clone(),
+            }
+        };
+        self.decoders.push(SourceCell::new(decoder));
+    }
+
+    /// Exclusive access to a decoder that is not in use, or else to the next decoder in turn once
+    /// the read from another thread that uses it has finished.
+    pub(crate) fn lock(&self) -> impl DerefMut<Target = Decoder<Box<dyn ReadSeek>>> + '_ {
+        #[cfg(feature = "std")]
+        {
+            use core::sync::atomic::Ordering;
+            use std::sync::{PoisonError, TryLockError};
+
+            for decoder in &self.decoders {
+                match decoder.try_lock() {
+                    Ok(guard) => return guard,
+                    Err(TryLockError::Poisoned(poisoned)) => return poisoned.into_inner(),
+                    Err(TryLockError::WouldBlock) => {}
+                }
+            }
+            let i = self.next.fetch_add(1, Ordering::Relaxed) % self.decoders.len();
+            self.decoders[i]
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            let free = self.decoders.iter().find_map(|d| d.try_borrow_mut().ok());
+            free.unwrap_or_else(|| self.decoders[0].borrow_mut())
+        }
+    }
+}
+
+impl fmt::Debug for CdfSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CdfSource").finish_non_exhaustive()
+    }
+}
+
+/// Entry numbers and values of the entries of a global attribute, as returned by
+/// [`GlobalAttributes::numbered_entries`].
+pub type NumberedEntries<'a> = Vec<(usize, &'a [CdfType])>;
+
+/// The global attributes of a [`Cdf`], see [`Cdf::global_attributes`]. Indexing with the name of
+/// an attribute, as in `cdf.global_attributes()["Project"]`, gives the value of its first entry
+/// and panics if it has none.
+#[derive(Debug, Clone, Copy)]
+pub struct GlobalAttributes<'a> {
+    cdf: &'a Cdf,
+}
+
+impl<'a> GlobalAttributes<'a> {
+    /// Values of the entries of the attribute called `name`, in the order they are stored.
+    pub fn entries(&self, name: &str) -> Option<Vec<&'a [CdfType]>> {
+        let adr = self.adr(name)?;
+        Some(adr.agredr_vec.iter().map(|e| e.value.as_slice()).collect())
+    }
+
+    /// Entry numbers and values of the entries of the attribute called `name`, in the order they
+    /// are stored. Entry numbers may leave gaps and need not follow the order of the entries.
+    /// # Errors
+    /// Returns a [`CdfError::InvalidCount`] if an entry number is negative.
+    pub fn numbered_entries(&self, name: &str) -> Result<Option<NumberedEntries<'a>>, CdfError> {
+        let Some(adr) = self.adr(name) else {
+            return Ok(None);
+        };
+        adr.agredr_vec
+            .iter()
+            .map(|e| Ok((checked_usize("num", *e.num)?, e.value.as_slice())))
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+
+    /// Value of the first entry of the attribute called `name`, whatever its type.
+    pub fn value(&self, name: &str) -> Option<&'a [CdfType]> {
+        self.entries(name)?.first().copied()
+    }
+
+    /// Value of the first entry of the attribute called `name`, if it is a string.
+    pub fn get(&self, name: &str) -> Option<&'a str> {
+        match self.value(name)? {
+            [CdfType::String(s)] => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Whether the attribute called `name` has an entry.
+    pub fn contains(&self, name: &str) -> bool {
+        self.value(name).is_some()
+    }
+
+    // Descriptor of the global attribute called `name`.
+    fn adr(&self, name: &str) -> Option<&'a AttributeDescriptorRecord> {
+        self.cdf
+            .cdr
+            .gdr
+            .adr_vec
+            .iter()
+            .find(|adr| matches!(*adr.scope, 1 | 3) && *adr.name == name)
+    }
+}
+
+impl Index<&str> for GlobalAttributes<'_> {
+    type Output = [CdfType];
+
+    fn index(&self, name: &str) -> &[CdfType] {
+        self.value(name)
+            .unwrap_or_else(|| panic!("Global attribute {name} has no entry."))
+    }
+}
+
+/// The variables of a [`Cdf`], see [`Cdf::vars`]. Indexing with the name of a variable, as in
+/// `cdf.vars()["Epoch"]`, gives its handle and panics if there is no such variable.
+///
+/// `Cdf` itself cannot be indexed by name: [`Variable`] handles borrow the file they belong to,
+/// and the result of [`Index::index`] cannot borrow from the file, so the handles are kept here.
+#[derive(Debug, Clone)]
+pub struct Variables<'a> {
+    variables: Vec<Variable<'a>>,
+}
+
+impl<'a> Variables<'a> {
+    /// Handle to the variable called `name`.
+    pub fn get(&self, name: &str) -> Option<&Variable<'a>> {
+        self.variables.iter().find(|v| v.name() == name)
+    }
+
+    /// Whether there is a variable called `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Handles to the variables, in the order of [`Cdf::variables`].
+    pub fn iter(&self) -> impl Iterator<Item = &Variable<'a>> {
+        self.variables.iter()
+    }
+
+    /// Number of variables.
+    pub fn len(&self) -> usize {
+        self.variables.len()
+    }
+
+    /// Whether there are no variables.
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+}
+
+impl<'a> Index<&str> for Variables<'a> {
+    type Output = Variable<'a>;
+
+    fn index(&self, name: &str) -> &Variable<'a> {
+        self.get(name)
+            .unwrap_or_else(|| panic!("Variable {name} not found."))
+    }
 }
 
 impl Cdf {
     /// Decode or deserialize a CDF file.
-    pub fn read_cdf_file<P: AsRef<std::path::Path>>(file_path: P) -> Result<Self, CdfError> {
-        let f = File::open(file_path)?;
-        let reader = BufReader::new(f);
-        let mut decoder = Decoder::new(reader)?;
-        Cdf::decode_be(&mut decoder)
+    #[cfg(feature = "std-fs")]
+    pub fn read_cdf_file<P: AsRef<Path>>(file_path: P) -> Result<Self, CdfError> {
+        Cdf::read_cdf_file_with(file_path, ReadOptions::default())
+    }
+
+    /// Decode a CDF file using the given [`ReadOptions`]. The file is kept open so that values of
+    /// variables that were not loaded can be read later through [`Cdf::variable`].
+    #[cfg(feature = "std-fs")]
+    pub fn read_cdf_file_with<P: AsRef<Path>>(
+        file_path: P,
+        options: ReadOptions,
+    ) -> Result<Self, CdfError> {
+        let path = file_path.as_ref();
+        let readers = options.readers;
+        let buffer_size = options.buffer_size.unwrap_or(io::DEFAULT_BUFFER_SIZE);
+        let mut cdf = Cdf::from_reader(
+            File::open(path)?,
+            ReadOptions {
+                buffer_size: Some(buffer_size),
+                ..options
+            },
+        )?;
+        if !cdf.is_compressed {
+            for _ in 1..readers {
+                cdf.add_reader(io::BufferedReader::with_capacity(
+                    buffer_size,
+                    File::open(path)?,
+                ))?;
+            }
+        }
+        Ok(cdf)
+    }
+
+    /// Decode a CDF file that is already in memory, for example a file uploaded to a browser.
+    /// The bytes are copied so that values of variables can be read later.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CdfError> {
+        Cdf::from_cursor(io::Cursor::new(bytes.to_vec()))
+    }
+
+    /// Decode a CDF file that is already in memory using the given [`ReadOptions`], for example
+    /// to only load values when they are read with [`ReadOptions::lazy`]. The bytes are copied
+    /// as by [`Cdf::from_bytes`].
+    pub fn from_bytes_with(bytes: &[u8], options: ReadOptions) -> Result<Self, CdfError> {
+        Cdf::from_vec(bytes.to_vec(), options)
+    }
+
+    /// Decode a CDF file held in `bytes` using the given [`ReadOptions`], keeping the bytes
+    /// without copying them so that values of variables can be read later.
+    pub fn from_vec(bytes: Vec<u8>, options: ReadOptions) -> Result<Self, CdfError> {
+        Cdf::from_reader(io::Cursor::new(bytes), options)
+    }
+
+    /// Decode a CDF file from an in-memory cursor.
+    pub fn from_cursor<T>(cursor: io::Cursor<T>) -> Result<Self, CdfError>
+    where
+        T: AsRef<[u8]> + Send + 'static,
+    {
+        Cdf::from_reader(cursor, ReadOptions::default())
+    }
+
+    /// Decode a CDF file from any reader using the given [`ReadOptions`]. The reader is kept so
+    /// that values of variables that were not loaded can be read later through [`Cdf::variable`].
+    /// It is buffered if [`ReadOptions::buffer_size`] is set.
+    pub fn from_reader<R>(reader: R, options: ReadOptions) -> Result<Self, CdfError>
+    where
+        R: io::Read + io::Seek + Send + 'static,
+    {
+        let builder = options.builder();
+        match options.buffer_size {
+            Some(_) => Cdf::from_decoder(builder.build_buffered(reader)?, options.promote),
+            None => Cdf::from_decoder(builder.build(reader)?, options.promote),
+        }
+    }
+
+    fn from_decoder<R>(
+        mut decoder: Decoder<R>,
+        promote: Option<Promotion>,
+    ) -> Result<Self, CdfError>
+    where
+        R: io::Read + io::Seek + Send + 'static,
+    {
+        let mut cdf = Cdf::decode_be(&mut decoder)?;
+        cdf.promotion = promote;
+        if cdf.source.is_none() {
+            cdf.source = Some(CdfSource::new(decoder));
+        }
+        Ok(cdf)
+    }
+
+    /// Add another reader over the same file, such as another open file or memory map, so that
+    /// one more variable read can run in parallel with the others.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if this file was not read from a reader that is kept, or if
+    /// it is compressed, in which case values are read from the decompressed file in memory.
+    pub fn add_reader<R>(&mut self, reader: R) -> Result<(), CdfError>
+    where
+        R: io::Read + io::Seek + Send + 'static,
+    {
+        match &mut self.source {
+            Some(source) if !self.is_compressed => {
+                source.add_reader(Box::new(reader));
+                Ok(())
+            }
+            _ => Err(CdfError::Other(
+                "Readers can only be added to uncompressed files that keep their reader."
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// Number of readers over the file that variable values are read from.
+    pub fn num_readers(&self) -> usize {
+        self.source
+            .as_ref()
+            .map_or(0, |source| source.decoders.len())
+    }
+
+    /// All variables of this file, rVariables first, followed by zVariables and by the virtual
+    /// variables that were registered.
+    pub fn variables(&self) -> Vec<Variable<'_>> {
+        let gdr = &self.cdr.gdr;
+        gdr.rvdr_vec
+            .iter()
+            .map(VariableDescriptor::R)
+            .chain(gdr.zvdr_vec.iter().map(VariableDescriptor::Z))
+            .map(|descriptor| Variable::new(self, descriptor))
+            .chain(
+                self.virtual_variables
+                    .iter()
+                    .map(|v| Variable::new_virtual(self, v)),
+            )
+            .collect()
+    }
+
+    /// Register a virtual variable called `name`, whose records are computed by `compute` from the
+    /// records of the variables called `inputs` with the same number, e.g. the magnitude of a
+    /// vector with `cdf.register_virtual("B_mag", &["B_RTN"], |b| magnitude(b[0]))`. Inputs that do
+    /// not vary from record to record give their only record to each record.
+    ///
+    /// The variable is listed with the variables of the file as a zVariable, and its records are
+    /// computed when they are read, by reading the same records of its inputs. Its data type and
+    /// shape are those of the record computed from the pad records of the inputs, which `compute`
+    /// must keep for all records. Virtual variables can be inputs of other virtual variables.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if there is already a variable called `name`, if an input
+    /// does not exist, or if `compute` fails or returns no values for the pad records.
+    pub fn register_virtual<F>(
+        &mut self,
+        name: &str,
+        inputs: &[&str],
+        compute: F,
+    ) -> Result<(), CdfError>
+    where
+        F: Fn(&[&[CdfType]]) -> Result<Vec<CdfType>, CdfError> + Send + Sync + 'static,
+    {
+        if self.variable(name).is_ok() {
+            return Err(CdfError::Other(format!("Variable {name} already exists.")));
+        }
+        let compute: Box<VirtualFn> = Box::new(compute);
+        let virtual_variable = VirtualVariable::new(self, name, inputs, compute)?;
+        self.virtual_variables.push(virtual_variable);
+        Ok(())
+    }
+
+    /// Find a variable by name.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if there is no variable called `name`.
+    pub fn variable(&self, name: &str) -> Result<Variable<'_>, CdfError> {
+        self.variables()
+            .into_iter()
+            .find(|v| v.name() == name)
+            .ok_or_else(|| CdfError::Other(format!("Variable {name} not found.")))
+    }
+
+    /// Find the variables whose attributes match `predicate`, e.g. the science variables with
+    /// `cdf.find_variables(|attrs| attrs.get("VAR_TYPE") == Some("data"))`.
+    pub fn find_variables<F>(&self, mut predicate: F) -> Vec<Variable<'_>>
+    where
+        F: FnMut(&VariableAttributes) -> bool,
+    {
+        self.variables()
+            .into_iter()
+            .filter(|v| predicate(&v.attributes()))
+            .collect()
+    }
+
+    /// The variables of this file, which can be indexed by name, as in `cdf.vars()["Epoch"]`.
+    pub fn vars(&self) -> Variables<'_> {
+        Variables {
+            variables: self.variables(),
+        }
+    }
+
+    /// The global attributes of this file, which can be indexed by name, as in
+    /// `cdf.global_attributes()["Project"]`.
+    pub fn global_attributes(&self) -> GlobalAttributes<'_> {
+        GlobalAttributes { cdf: self }
+    }
+
+    /// Group the variables of this file into datasets, one for each epoch variable that is named
+    /// by the `DEPEND_0` attribute of other variables. Only variables that vary from record to
+    /// record are included.
+    pub fn datasets(&self) -> Vec<Dataset<'_>> {
+        let variables = self.variables();
+        variables
+            .iter()
+            .filter(|epoch| {
+                variables
+                    .iter()
+                    .any(|v| v.attribute_str("DEPEND_0") == Some(epoch.name()))
+            })
+            .map(|epoch| Self::dataset_of(&variables, *epoch))
+            .collect()
+    }
+
+    /// The dataset of the variables whose `DEPEND_0` attribute is `epoch`.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if there is no such variable, or if no variable depends on
+    /// it.
+    pub fn dataset(&self, epoch: &str) -> Result<Dataset<'_>, CdfError> {
+        let variables = self.variables();
+        let dataset = Self::dataset_of(&variables, self.variable(epoch)?);
+        if dataset.variables().is_empty() {
+            return Err(CdfError::Other(format!(
+                "No variable depends on {epoch} through DEPEND_0."
+            )));
+        }
+        Ok(dataset)
+    }
+
+    /// Exact bytes of the records of this file by file offset, including their reserved fields
+    /// and padding, if it was read with [`ReadOptions::keep_raw`]. The magic numbers are kept at
+    /// offset 0 and the bytes after the last record, such as the checksum, after it. Values
+    /// records of variables whose values were not loaded are missing. For compressed files, these
+    /// are the records of the compressed file.
+    pub fn raw_records(&self) -> &BTreeMap<u64, Vec<u8>> {
+        &self.raw_records
+    }
+
+    /// Records of types that this crate does not know, such as records of a later version of the
+    /// CDF specification, found when the file is read with [`ReadOptions::sequential`]. They are
+    /// not linked from the records this crate decodes, so they are only found by reading the
+    /// records in file order. They are kept in [`Cdf::raw_records`] with
+    /// [`ReadOptions::keep_raw`], so that [`Cdf::write_raw`] writes them back. Records of unknown
+    /// types that are children of a VXR are kept in the VXR instead, see
+    /// [`VariableIndexRecordChild::Unknown`](crate::record::vxr::VariableIndexRecordChild).
+    pub fn unknown_records(&self) -> &[UnknownRecord] {
+        &self.unknown_records
+    }
+
+    /// Write the bytes kept in [`Cdf::raw_records`] at their offsets, which re-creates the file
+    /// byte for byte if all of its records were decoded. Gaps left by records that were not
+    /// decoded are filled with zeros.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if no records were kept, or a [`CdfError::Io`] if writing
+    /// fails.
+    pub fn write_raw<W: io::Write>(&self, writer: &mut W) -> Result<(), CdfError> {
+        if self.raw_records.is_empty() {
+            return Err(CdfError::Other(
+                "No records were kept - read the file with keep_raw set.".to_string(),
+            ));
+        }
+        let mut position = 0;
+        for (offset, bytes) in &self.raw_records {
+            if *offset > position {
+                writer.write_all(&vec![0; usize::try_from(offset - position)?])?;
+                position = *offset;
+            }
+            let skip = usize::try_from(position - offset)?.min(bytes.len());
+            writer.write_all(&bytes[skip..])?;
+            position = position.max(offset + bytes.len() as u64);
+        }
+        Ok(())
+    }
+
+    /// File-level facts about this file, such as its version and number of variables. Reading the
+    /// file with [`ReadOptions::lazy`] is enough to get these without loading any values.
+    pub fn info(&self) -> CdfInfo {
+        let gdr = &self.cdr.gdr;
+        let last_leap_second = *gdr.date_last_leapsecond_update;
+        CdfInfo {
+            version: self.cdr.cdf_version.clone(),
+            encoding: self.cdr.encoding.clone(),
+            majority: if self.cdr.flags.row_major {
+                Majority::Row
+            } else {
+                Majority::Column
+            },
+            compressed: self.is_compressed,
+            checksum: self.cdr.flags.has_checksum && self.cdr.flags.md5_checksum,
+            n_variables: gdr.rvdr_vec.len() + gdr.zvdr_vec.len(),
+            n_attributes: gdr.adr_vec.len(),
+            last_leap_second: (last_leap_second > 0).then_some(last_leap_second),
+        }
+    }
+
+    /// The variables and attributes of this file in a form meant for other programs, unlike the
+    /// summary for people printed by [`fmt::Display`].
+    /// # Errors
+    /// Returns a [`CdfError`] if the data type or the dimensions of a variable are invalid.
+    pub fn listing(&self) -> Result<Listing, CdfError> {
+        let variables = self
+            .variables()
+            .iter()
+            .map(|v| {
+                Ok(VariableListing {
+                    name: v.name().to_string(),
+                    zvariable: v.is_zvariable(),
+                    data_type: DataType::try_from(v.data_type().clone())?,
+                    num_elements: v.num_elements()?,
+                    dims: v.dim_sizes()?,
+                    dim_variances: v.dim_variances().to_vec(),
+                    records: v.n_records(),
+                    record_varies: v.record_varies(),
+                    compression: v.compression().map(|c| c.compression_type.clone()),
+                })
+            })
+            .collect::<Result<_, CdfError>>()?;
+        let attributes = self
+            .cdr
+            .gdr
+            .adr_vec
+            .iter()
+            .map(|adr| AttributeListing {
+                name: adr.name.to_string(),
+                global: matches!(*adr.scope, 1 | 3),
+                entries: adr.agredr_vec.len() + adr.azedr_vec.len(),
+            })
+            .collect();
+        Ok(Listing {
+            variables,
+            attributes,
+        })
+    }
+
+    /// The structure of this file without its values, to catalog files or to detect changes
+    /// between versions of a file. Reading the file with [`ReadOptions::lazy`] is enough.
+    /// # Errors
+    /// Returns a [`CdfError`] if the data type or the dimensions of a variable, or the data type
+    /// of an attribute entry, are invalid.
+    pub fn schema(&self) -> Result<Schema, CdfError> {
+        let entry = |data_type: &CdfInt4, values: &[CdfType]| -> Result<EntrySchema, CdfError> {
+            Ok(EntrySchema {
+                data_type: DataType::try_from(data_type.clone())?,
+                values: values.to_vec(),
+            })
+        };
+        let mut attributes = BTreeMap::new();
+        for adr in self
+            .cdr
+            .gdr
+            .adr_vec
+            .iter()
+            .filter(|a| matches!(*a.scope, 1 | 3))
+        {
+            let entries = adr
+                .agredr_vec
+                .iter()
+                .map(|e| entry(&e.data_type, &e.value))
+                .collect::<Result<_, _>>()?;
+            attributes.insert(adr.name.to_string(), entries);
+        }
+
+        let mut variables = Vec::new();
+        for v in self.variables() {
+            let num = **v.num();
+            let mut variable_attributes = BTreeMap::new();
+            for adr in &self.cdr.gdr.adr_vec {
+                let found = if v.is_zvariable() {
+                    adr.azedr_vec
+                        .iter()
+                        .find(|e| *e.num == num)
+                        .map(|e| (&e.data_type, &e.value))
+                } else {
+                    adr.agredr_vec
+                        .iter()
+                        .find(|e| *e.num == num)
+                        .map(|e| (&e.data_type, &e.value))
+                };
+                if let (false, Some((data_type, values))) = (matches!(*adr.scope, 1 | 3), found) {
+                    variable_attributes.insert(adr.name.to_string(), entry(data_type, values)?);
+                }
+            }
+            variables.push(VariableSchema {
+                name: v.name().to_string(),
+                zvariable: v.is_zvariable(),
+                data_type: DataType::try_from(v.data_type().clone())?,
+                num_elements: v.num_elements()?,
+                shape: v.record_shape()?,
+                dim_variances: v.dim_variances().to_vec(),
+                record_varies: v.record_varies(),
+                attributes: variable_attributes,
+            });
+        }
+        Ok(Schema {
+            majority: self.info().majority,
+            attributes,
+            variables,
+        })
+    }
+
+    /// What the format of this file can represent, such as whether it can hold TT2000 values, to
+    /// check up front whether a change to the file is representable in its version.
+    pub fn format(&self) -> FormatInfo {
+        FormatInfo::for_version(&self.cdr.cdf_version)
+    }
+
+    /// Warnings about this file that do not prevent it from being read. These are the warnings
+    /// that were reported to [`ReadOptions::warning`] while decoding, in order, followed by the
+    /// problems found in the records of the file as they are now.
+    pub fn warnings(&self) -> Vec<DecodeWarning> {
+        let mut warnings = self.decode_warnings.clone();
+        let mut push = |warning| {
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        };
+        let file = *self.cdr.gdr.date_last_leapsecond_update;
+        let library = leap_seconds().last_update();
+        if file > library {
+            push(DecodeWarning::OutdatedLeapSeconds { file, library });
+        }
+        for variable in self.variables() {
+            for problem in variable.check_index() {
+                push(DecodeWarning::InconsistentIndex {
+                    variable: variable.name().to_string(),
+                    problem,
+                });
+            }
+        }
+        warnings
+    }
+
+    fn dataset_of<'a>(variables: &[Variable<'a>], epoch: Variable<'a>) -> Dataset<'a> {
+        let members = variables
+            .iter()
+            .filter(|v| v.record_varies() && v.attribute_str("DEPEND_0") == Some(epoch.name()))
+            .copied()
+            .collect();
+        Dataset::new(epoch, members)
     }
 }
-impl Decodable for Cdf {
-    /// Decode a value from the input that implements `io::Read`.
-    fn decode_be<R>(decoder: &mut Decoder<R>) -> Result<Self, CdfError>
+
+impl Cdf {
+    /// Decode a CDF file from its magic numbers on.
+    fn decode_file<R>(decoder: &mut Decoder<R>) -> Result<Self, CdfError>
     where
         R: io::Read + io::Seek,
     {
@@ -43,24 +996,334 @@ impl Decodable for Cdf {
         // This is mostly a hack to get a hint of the CDF version. We read in the actual version
         // properly in the CDR. We need to know before reading the CDR if the CDF is >= v3.0 or
         // not.
-        let version = match m1.into() {
+        let version = match m1.clone().into() {
             0xcdf30001 => CdfVersion::new(3, 0, 0),
             0xcdf26002 => CdfVersion::new(2, 6, 0),
             0x0000ffff => CdfVersion::new(2, 0, 0),
             v => return Err(CdfError::Decode(format!("Invalid magic number - {v}"))),
         };
         decoder.context.version = Some(version);
+        if decoder.options.keep_raw {
+            let mut magic = m1.clone().to_be_bytes().to_vec();
+            magic.extend_from_slice(&m2.clone().to_be_bytes());
+            _ = decoder.context.raw_records.insert(0, magic);
+        }
 
-        let is_compressed: bool = match m2.into() {
-            0x0000ffffu32 => false,
-            0xcccc0001u32 => true,
+        match m2.into() {
+            0x0000ffffu32 => {}
+            0xcccc0001u32 => return Cdf::decode_compressed(decoder, m1),
             v => return Err(CdfError::Decode(format!("Invalid magic number - {v}"))),
         };
 
+        if !decoder.options.sequential {
+            return Cdf::decode_records(decoder);
+        }
+
+        // The records are decoded from memory, while other reads, such as those of the bytes
+        // after the last record, still go to the file.
+        let start = decoder.reader.stream_position()?;
+        let records = read_records_in_order(decoder, start)?;
+        let header = if decoder.context.version()?.major >= 3 {
+            12
+        } else {
+            8
+        };
+        // Records of unknown types are linked from no other record, so they are only found here.
+        let mut unknown_records = Vec::new();
+        for (offset, bytes) in &records {
+            let record = UnknownRecord::from_raw(*offset, bytes, header)?;
+            if !is_known_record_type(*record.record_type) {
+                if decoder.options.keep_raw {
+                    _ = decoder.context.raw_records.insert(*offset, bytes.clone());
+                }
+                unknown_records.push(record);
+            }
+        }
+        let mut reader = io::PrefetchedReader::new(&mut decoder.reader, records);
+        _ = reader.seek(SeekFrom::Start(start))?;
+        let mut inner = Decoder {
+            reader,
+            context: core::mem::take(&mut decoder.context),
+            options: decoder.options.clone(),
+        };
+        let cdf = Cdf::decode_records(&mut inner);
+        decoder.context = inner.context;
+        let mut cdf = cdf?;
+        cdf.unknown_records = unknown_records;
+        Ok(cdf)
+    }
+
+    /// Decode an uncompressed CDF file from its CDR on.
+    fn decode_records<R>(decoder: &mut Decoder<R>) -> Result<Self, CdfError>
+    where
+        R: io::Read + io::Seek,
+    {
         // Parse the CDF Descriptor Record that is present after the magic numbers.
         let cdr = CdfDescriptorRecord::decode_be(decoder)?;
+        decoder.context.record_start = None;
+
+        if decoder.options.verify_checksum && cdr.flags.has_checksum {
+            verify_checksum(decoder, &cdr)?;
+        }
+
+        // The MD5 checksum, if any, is stored in the 16 bytes that follow the end of the file.
+        if let Some(eof) = &cdr.gdr.eof {
+            let end = u64::try_from(**eof)? + if cdr.flags.has_checksum { 16 } else { 0 };
+            let len = decoder.reader.seek(SeekFrom::End(0))?;
+            if len > end {
+                decoder.report_warning(DecodeWarning::TrailingBytes { bytes: len - end });
+            }
+        }
 
-        Ok(Cdf { is_compressed, cdr })
+        Ok(Cdf {
+            is_compressed: false,
+            cpr: None,
+            cdr,
+            source: None,
+            decode_warnings: core::mem::take(&mut decoder.context.warnings),
+            raw_records: take_raw_records(decoder)?,
+            virtual_variables: Vec::new(),
+            promotion: None,
+            unknown_records: Vec::new(),
+        })
+    }
+
+    /// Decode a compressed CDF file. The CCR that follows the magic numbers holds the rest of the
+    /// file in compressed form, which is decompressed into memory and decoded from there.
+    fn decode_compressed<R>(decoder: &mut Decoder<R>, m1: CdfUint4) -> Result<Self, CdfError>
+    where
+        R: io::Read + io::Seek,
+    {
+        if !decoder.options.decompress {
+            return Err(CdfError::Decode(
+                "This CDF file is compressed - enable decompression to read it.".to_string(),
+            ));
+        }
+
+        let ccr = CompressedCdfRecord::decode_be(decoder)?;
+        _ = decoder.seek_offset(*ccr.cpr_offset)?;
+        let cpr = CompressedParametersRecord::decode_be(decoder)?;
+        let raw_records = take_raw_records(decoder)?;
+
+        // The uncompressed data is the file without its magic numbers, so put them back in.
+        let limit = decoder.options.max_alloc.unwrap_or(usize::MAX);
+        let uncompressed =
+            decompress_limited(&cpr.compression_type, &ccr.data, limit.saturating_sub(8))?;
+        let mut bytes = Vec::with_capacity(uncompressed.len() + 8);
+        bytes.extend_from_slice(&m1.to_be_bytes());
+        bytes.extend_from_slice(&0x0000ffffu32.to_be_bytes());
+        bytes.extend_from_slice(&uncompressed);
+
+        // The checksum covers the compressed file, so it is verified on the original reader.
+        let mut inner = decoder.nested(io::Cursor::new(bytes));
+        inner.options.verify_checksum = false;
+        let mut cdf = Cdf::decode_be(&mut inner)?;
+        if decoder.options.verify_checksum && cdf.cdr.flags.has_checksum {
+            verify_checksum(decoder, &cdf.cdr)?;
+        }
+
+        decoder.context = inner.context.clone();
+        cdf.is_compressed = true;
+        cdf.cpr = Some(cpr);
+        cdf.raw_records = raw_records;
+        cdf.source = Some(CdfSource::new(inner));
+        Ok(cdf)
+    }
+}
+
+/// A summary of the file for people, in the spirit of the `cdfinquire` tool: the version,
+/// encoding, compression, checksum and number of attributes, followed by a table of the variables
+/// with their data type, shape and number of records.
+impl fmt::Display for Cdf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cdr = &self.cdr;
+        let gdr = &cdr.gdr;
+        let version = &cdr.cdf_version;
+        writeln!(
+            f,
+            "CDF {}.{}.{}, {:?} encoding, {}",
+            version.major,
+            version.minor,
+            version.patch,
+            cdr.encoding,
+            if cdr.flags.row_major {
+                "row-major"
+            } else {
+                "column-major"
+            }
+        )?;
+        match &self.cpr {
+            Some(cpr) => writeln!(f, "Compression: {:?}", cpr.compression_type)?,
+            None => writeln!(f, "Compression: None")?,
+        }
+        let checksum = match (cdr.flags.has_checksum, cdr.flags.md5_checksum) {
+            (false, _) => "None",
+            (true, true) => "MD5",
+            (true, false) => "Unknown",
+        };
+        writeln!(f, "Checksum: {checksum}")?;
+        let global = gdr
+            .adr_vec
+            .iter()
+            .filter(|adr| matches!(*adr.scope, 1 | 3))
+            .count();
+        writeln!(
+            f,
+            "Attributes: {global} global, {} variable",
+            gdr.adr_vec.len() - global
+        )?;
+
+        let variables = self.variables();
+        let rows: Vec<[String; 6]> = variables
+            .iter()
+            .map(|v| {
+                let data_type = DataType::try_from(v.data_type().clone())
+                    .map_or_else(|_| format!("{}", **v.data_type()), |t| format!("{t:?}"));
+                let shape = v
+                    .record_shape()
+                    .map_or_else(|_| "?".to_string(), |shape| format!("{shape:?}"));
+                let compression = v
+                    .compression()
+                    .map_or_else(|| "-".to_string(), |c| format!("{:?}", c.compression_type));
+                [
+                    v.name().to_string(),
+                    (match (v.is_virtual(), v.is_zvariable()) {
+                        (true, _) => "virtual",
+                        (false, true) => "z",
+                        (false, false) => "r",
+                    })
+                    .to_string(),
+                    data_type,
+                    shape,
+                    v.n_records().to_string(),
+                    compression,
+                ]
+            })
+            .collect();
+        writeln!(f, "Variables: {}", rows.len())?;
+        let header = ["Name", "Kind", "Type", "Shape", "Records", "Compression"].map(String::from);
+        let mut widths = header.clone().map(|h| h.len());
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        for row in core::iter::once(&header).chain(&rows) {
+            let line = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            writeln!(f, "  {}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Take the records kept by `decoder`, adding the bytes that follow the last record, such as the
+/// checksum of the file.
+fn take_raw_records<R>(decoder: &mut Decoder<R>) -> Result<BTreeMap<u64, Vec<u8>>, CdfError>
+where
+    R: io::Read + io::Seek,
+{
+    let mut raw_records = core::mem::take(&mut decoder.context.raw_records);
+    let Some((offset, bytes)) = raw_records.last_key_value() else {
+        return Ok(raw_records);
+    };
+    let end = offset + bytes.len() as u64;
+    let len = decoder.reader.seek(SeekFrom::End(0))?;
+    if len > end {
+        let mut trailer = vec![0; usize::try_from(len - end)?];
+        _ = decoder.reader.seek(SeekFrom::Start(end))?;
+        decoder.reader.read_exact(&mut trailer)?;
+        _ = raw_records.insert(end, trailer);
+    }
+    Ok(raw_records)
+}
+
+/// Read the records of the file in file order, from the record at `start` until the end of the
+/// file or a record header that does not fit in the file, keyed by their offset. Value records are
+/// skipped unless the values of all variables are loaded while decoding, since the values of the
+/// other variables are read on demand.
+fn read_records_in_order<R>(
+    decoder: &mut Decoder<R>,
+    start: u64,
+) -> Result<BTreeMap<u64, Vec<u8>>, CdfError>
+where
+    R: io::Read + io::Seek,
+{
+    let file_size = decoder.reader.seek(SeekFrom::End(0))?;
+    let header = if decoder.context.version()?.major >= 3 {
+        12
+    } else {
+        8
+    };
+    let values = !decoder.options.lazy && decoder.options.variables.is_none();
+    let mut records = BTreeMap::new();
+    let mut offset = start;
+    while offset < file_size {
+        decoder.check_cancelled()?;
+        _ = decoder.reader.seek(SeekFrom::Start(offset))?;
+        // Anything that is not a record is left to the decode to report.
+        let Ok((record_size, record_type)) =
+            decode_version3_int4_int8(decoder).and_then(|s| Ok((s, CdfInt4::decode_be(decoder)?)))
+        else {
+            break;
+        };
+        let Some(end) = u64::try_from(*record_size)
+            .ok()
+            .filter(|size| *size >= header)
+            .and_then(|size| offset.checked_add(size))
+            .filter(|end| *end <= file_size)
+        else {
+            break;
+        };
+        // Record types 7 and 13 are VVRs and CVVRs.
+        if values || !matches!(*record_type, 7 | 13) {
+            // The header is put back together rather than read again, so that reads never go
+            // back.
+            let len = usize::try_from(end - offset)?;
+            decoder.check_alloc(len)?;
+            let mut bytes = Vec::with_capacity(len);
+            if header == 12 {
+                bytes.extend_from_slice(&(*record_size).to_be_bytes());
+            } else {
+                bytes.extend_from_slice(&i32::try_from(*record_size)?.to_be_bytes());
+            }
+            bytes.extend_from_slice(&(*record_type).to_be_bytes());
+            let read = bytes.len();
+            bytes.resize(len, 0);
+            decoder.reader.read_exact(&mut bytes[read..])?;
+            _ = records.insert(offset, bytes);
+        }
+        offset = end;
+    }
+    Ok(records)
+}
+
+fn verify_checksum<R>(decoder: &mut Decoder<R>, cdr: &CdfDescriptorRecord) -> Result<(), CdfError>
+where
+    R: io::Read + io::Seek,
+{
+    if !cdr.flags.md5_checksum {
+        return Err(CdfError::Decode(
+            "Only MD5 checksums can be verified.".to_string(),
+        ));
+    }
+    verify_md5(&mut decoder.reader)
+}
+
+impl Decodable for Cdf {
+    /// Decode a value from the input that implements `io::Read`.
+    fn decode_be<R>(decoder: &mut Decoder<R>) -> Result<Self, CdfError>
+    where
+        R: io::Read + io::Seek,
+    {
+        match Cdf::decode_file(decoder) {
+            Err(e) => Err(decoder.explain_truncation(e)),
+            cdf => cdf,
+        }
     }
 
     fn decode_le<R>(_: &mut Decoder<R>) -> Result<Self, CdfError>
@@ -73,13 +1336,18 @@ impl Decodable for Cdf {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std-fs"))]
 mod tests {
 
+    use crate::decode::DecodeProgress;
     use crate::error::CdfError;
+    use crate::transcode::{transcode, RecordTransform, RenameVariable};
+    use crate::view::CdfView;
     use std::fs::File;
     use std::io::BufReader;
     use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::vec;
 
     use super::*;
 
@@ -105,4 +1373,1041 @@ mod tests {
         // dbg!(cdf);
         Ok(())
     }
+
+    #[test]
+    fn test_read_cdf_verify_checksum() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+
+        let mut bytes = std::fs::read(path_test_file)?;
+        let mut decoder = Decoder::builder()
+            .verify_checksum(true)
+            .build(io::Cursor::new(bytes.as_slice()))?;
+        assert!(Cdf::decode_be(&mut decoder).is_ok());
+
+        // Flip a byte inside the copyright string, which decodes fine but breaks the checksum.
+        bytes[100] ^= 0x01;
+        let mut decoder = Decoder::builder()
+            .verify_checksum(true)
+            .build(io::Cursor::new(bytes.as_slice()))?;
+        assert!(Cdf::decode_be(&mut decoder).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_cdf_from_bytes() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+
+        let bytes = std::fs::read(path_test_file)?;
+        let cdf = Cdf::from_bytes(&bytes)?;
+        assert_eq!(cdf.variable("Latitude")?.read_all()?.len(), 1);
+
+        let lazy = ReadOptions {
+            lazy: true,
+            ..Default::default()
+        };
+        let expected = format!("{:?}", cdf.variable("Latitude")?.read_all()?);
+        for cdf in [
+            Cdf::from_bytes_with(&bytes, lazy.clone())?,
+            Cdf::from_vec(bytes.clone(), lazy)?,
+        ] {
+            assert_eq!(
+                format!("{:?}", cdf.variable("Latitude")?.read_all()?),
+                expected
+            );
+        }
+
+        let cdf = Cdf::from_cursor(io::Cursor::new(bytes))?;
+        assert_eq!(cdf.variables().len(), 21);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_cdf_buffered() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let bytes = std::fs::read(&path_test_file)?;
+        let eager = Cdf::from_bytes(&bytes)?;
+
+        // Buffers smaller than a record, than the file and larger than the file give the same
+        // values, whether they are decoded eagerly or read on demand.
+        for (buffer_size, lazy) in [(0, false), (16, true), (1000, false), (1 << 20, true)] {
+            let options = ReadOptions {
+                lazy,
+                buffer_size: Some(buffer_size),
+                ..Default::default()
+            };
+            let cdf = Cdf::from_reader(io::Cursor::new(bytes.clone()), options)?;
+            for (a, b) in cdf.variables().iter().zip(eager.variables().iter()) {
+                assert_eq!(
+                    format!("{:?}", a.read_all()?),
+                    format!("{:?}", b.read_all()?)
+                );
+            }
+        }
+        let options = ReadOptions {
+            buffer_size: Some(100),
+            readers: 2,
+            ..Default::default()
+        };
+        let cdf = Cdf::read_cdf_file_with(path_test_file, options)?;
+        assert_eq!(cdf.num_readers(), 2);
+        assert_eq!(cdf.variable("Latitude")?.read_all()?.len(), 1);
+        Ok(())
+    }
+
+    // Reader that records the position of each read of a cursor.
+    struct ReadPositions {
+        cursor: io::Cursor<Vec<u8>>,
+        positions: std::sync::Arc<std::sync::Mutex<Vec<u64>>>,
+    }
+
+    impl io::Read for ReadPositions {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let position = self.cursor.position();
+            self.positions.lock().unwrap().push(position);
+            self.cursor.read(buf)
+        }
+    }
+
+    impl io::Seek for ReadPositions {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.cursor.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_read_cdf_sequential() -> Result<(), CdfError> {
+        for filename in ["test_alltypes.cdf", "ulysses.cdf"] {
+            let path_test_file: PathBuf =
+                [env!("CARGO_MANIFEST_DIR"), "examples", "data", filename]
+                    .iter()
+                    .collect();
+            let bytes = std::fs::read(&path_test_file)?;
+            let eager = Cdf::from_bytes(&bytes)?;
+            let name = eager.variables()[0].name().to_string();
+
+            // Files read in file order decode to the same records and values, whether all values
+            // are loaded or some of them are read on demand.
+            for (lazy, variables) in [(false, None), (true, None), (false, Some(vec![name]))] {
+                let positions = std::sync::Arc::default();
+                let reader = ReadPositions {
+                    cursor: io::Cursor::new(bytes.clone()),
+                    positions: std::sync::Arc::clone(&positions),
+                };
+                let options = ReadOptions {
+                    lazy,
+                    variables: variables.clone(),
+                    keep_raw: true,
+                    sequential: true,
+                    ..Default::default()
+                };
+                let cdf = Cdf::from_reader(reader, options)?;
+                let reads = positions.lock().unwrap().clone();
+                if !lazy && variables.is_none() {
+                    // The bytes after the last record are read again when they are kept.
+                    let eof = u64::try_from(**cdf.cdr.gdr.eof.as_ref().unwrap())?;
+                    let reads: Vec<_> = reads.into_iter().filter(|p| *p < eof).collect();
+                    assert!(
+                        reads.windows(2).all(|w| w[0] <= w[1]),
+                        "{filename} {reads:?}"
+                    );
+                }
+                let options = ReadOptions {
+                    lazy,
+                    variables,
+                    keep_raw: true,
+                    ..Default::default()
+                };
+                let linked = Cdf::from_reader(io::Cursor::new(bytes.clone()), options)?;
+                assert_eq!(cdf.raw_records(), linked.raw_records());
+                assert_eq!(cdf.warnings(), linked.warnings());
+                assert_eq!(cdf.variables().len(), eager.variables().len());
+                for (a, b) in cdf.variables().iter().zip(eager.variables().iter()) {
+                    assert_eq!(
+                        format!("{:?}", a.read_all()?),
+                        format!("{:?}", b.read_all()?)
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_cdf_unknown_records() -> Result<(), CdfError> {
+        let path: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "ulysses.cdf",
+        ]
+        .iter()
+        .collect();
+        // Renaming the epoch leaves the old entries that refer to it behind, linked from nothing.
+        let mut file = io::Cursor::new(std::fs::read(path)?);
+        crate::edit::rename_variable(&mut file, "Epoch", "Epoch_UT", true)?;
+        let mut bytes = file.into_inner();
+        let keep_raw = ReadOptions {
+            keep_raw: true,
+            ..Default::default()
+        };
+        let linked = Cdf::from_bytes_with(&bytes, keep_raw.clone())?;
+        let (offset, raw) = linked
+            .raw_records()
+            .iter()
+            .find(|(offset, raw)| {
+                let end = **offset + raw.len() as u64;
+                !linked.raw_records().contains_key(&end)
+            })
+            .expect("a record is left behind");
+        let gap = usize::try_from(offset + raw.len() as u64)?;
+        // Records of this v2 file start with a 4-byte size followed by their type.
+        bytes[gap + 4..gap + 8].copy_from_slice(&99i32.to_be_bytes());
+
+        let sequential = ReadOptions {
+            sequential: true,
+            ..keep_raw
+        };
+        let cdf = Cdf::from_bytes_with(&bytes, sequential)?;
+        let [unknown] = cdf.unknown_records() else {
+            panic!("{:?}", cdf.unknown_records());
+        };
+        assert_eq!((*unknown.record_type, unknown.offset), (99, gap as u64));
+        assert_eq!(
+            8 + unknown.bytes.len(),
+            usize::try_from(*unknown.record_size)?
+        );
+        // The other records left behind are still missing from the raw records, but not this one.
+        let mut written = Vec::new();
+        cdf.write_raw(&mut written)?;
+        let end = gap + usize::try_from(*unknown.record_size)?;
+        assert_eq!(written[gap..end], bytes[gap..end]);
+        let copy = crate::transcode::copy_lossless(io::Cursor::new(&bytes), Vec::new())?;
+        assert!(copy == bytes);
+        assert!(Cdf::from_bytes(&bytes)?.unknown_records().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_cdf_max_alloc() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+
+        let f = File::open(path_test_file)?;
+        let mut decoder = Decoder::builder().max_alloc(16).build(BufReader::new(f))?;
+        assert!(Cdf::decode_be(&mut decoder).is_err());
+        Ok(())
+    }
+
+    // Build a compressed CDF file from an uncompressed one, using gzip with stored blocks.
+    fn _compress_cdf(bytes: &[u8]) -> Vec<u8> {
+        let content = &bytes[8..];
+        let mut gz = vec![0x1f, 0x8b, 0x08, 0, 0, 0, 0, 0, 0, 0xff];
+        let mut chunks = content.chunks(0xffff).peekable();
+        while let Some(chunk) = chunks.next() {
+            gz.push(u8::from(chunks.peek().is_none()));
+            let len = u16::try_from(chunk.len()).unwrap();
+            gz.extend_from_slice(&len.to_le_bytes());
+            gz.extend_from_slice(&(!len).to_le_bytes());
+            gz.extend_from_slice(chunk);
+        }
+        gz.extend_from_slice(&crate::compression::crc32(content).to_le_bytes());
+        gz.extend_from_slice(&u32::try_from(content.len()).unwrap().to_le_bytes());
+
+        let ccr_size = 32 + gz.len() as u64;
+        let mut file = vec![];
+        file.extend_from_slice(&0xcdf30001u32.to_be_bytes());
+        file.extend_from_slice(&0xcccc0001u32.to_be_bytes());
+        file.extend_from_slice(&ccr_size.to_be_bytes());
+        file.extend_from_slice(&10i32.to_be_bytes());
+        file.extend_from_slice(&(8 + ccr_size).to_be_bytes());
+        file.extend_from_slice(&(content.len() as u64).to_be_bytes());
+        file.extend_from_slice(&0i32.to_be_bytes());
+        file.extend_from_slice(&gz);
+        // CPR: record size, record type, gzip, rfu_a, one parameter, compression level.
+        file.extend_from_slice(&32u64.to_be_bytes());
+        for v in [11i32, 5, 0, 1, 6] {
+            file.extend_from_slice(&v.to_be_bytes());
+        }
+        file
+    }
+
+    #[test]
+    fn test_read_cdf_compressed() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+
+        let bytes = _compress_cdf(&std::fs::read(path_test_file)?);
+
+        let mut decoder = Decoder::new(io::Cursor::new(bytes.clone()))?;
+        assert!(Cdf::decode_be(&mut decoder).is_err());
+
+        let mut decoder = Decoder::builder()
+            .decompress(true)
+            .build(io::Cursor::new(bytes))?;
+        let cdf = Cdf::decode_be(&mut decoder)?;
+        assert!(cdf.is_compressed);
+        assert_eq!(cdf.variables().len(), 21);
+        assert_eq!(cdf.variable("Latitude")?.read_all()?.len(), 1);
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_cdf_serde() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let bytes = std::fs::read(path_test_file)?;
+        let lazy = ReadOptions {
+            lazy: true,
+            ..Default::default()
+        };
+        let compressed = ReadOptions {
+            decompress: true,
+            ..Default::default()
+        };
+
+        // Files with and without values, compression and a checksum all go through JSON and
+        // back unchanged.
+        for cdf in [
+            Cdf::from_bytes(&bytes)?,
+            Cdf::from_reader(io::Cursor::new(bytes.clone()), lazy)?,
+            Cdf::from_reader(io::Cursor::new(_compress_cdf(&bytes)), compressed)?,
+        ] {
+            let json =
+                serde_json::to_value(&cdf).map_err(|err| CdfError::Other(err.to_string()))?;
+            let back: Cdf = serde_json::from_value(json.clone())
+                .map_err(|err| CdfError::Other(err.to_string()))?;
+            assert_eq!(
+                serde_json::to_value(&back).map_err(|err| CdfError::Other(err.to_string()))?,
+                json
+            );
+            assert_eq!(back.variables().len(), cdf.variables().len());
+            assert!(serde_json::to_value(cdf.warnings()).is_ok());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_cdf_progress() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "ulysses.cdf",
+        ]
+        .iter()
+        .collect();
+
+        // The callback counts all calls and the calls while reading Epoch.
+        let calls = Arc::new([AtomicUsize::new(0), AtomicUsize::new(0)]);
+        let counts = Arc::clone(&calls);
+        let cdf = Cdf::read_cdf_file_with(
+            path_test_file,
+            ReadOptions {
+                lazy: true,
+                progress: Some(Arc::new(move |progress: DecodeProgress| {
+                    _ = counts[0].fetch_add(1, Ordering::Relaxed);
+                    if progress.variable.as_deref() == Some("Epoch") {
+                        _ = counts[1].fetch_add(1, Ordering::Relaxed);
+                    }
+                })),
+                ..Default::default()
+            },
+        )?;
+        let decoded = calls[0].load(Ordering::Relaxed);
+        assert!(decoded > 0);
+        assert!(calls[1].load(Ordering::Relaxed) > 0);
+
+        // Reading values on demand reports progress as well.
+        let variable_calls = calls[1].load(Ordering::Relaxed);
+        _ = cdf.variable("Epoch")?.read_all()?;
+        assert!(calls[0].load(Ordering::Relaxed) > decoded);
+        assert!(calls[1].load(Ordering::Relaxed) > variable_calls);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_cdf_cancel() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "ulysses.cdf",
+        ]
+        .iter()
+        .collect();
+
+        // The decode stops at the first record after the first VVR.
+        let token = CancelToken::new();
+        let shared = token.clone();
+        let result = Cdf::read_cdf_file_with(
+            &path_test_file,
+            ReadOptions {
+                progress: Some(Arc::new(move |progress: DecodeProgress| {
+                    if progress.record_type == 7 {
+                        shared.cancel();
+                    }
+                })),
+                cancel: Some(token.clone()),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(result, Err(CdfError::Cancelled)));
+
+        token.reset();
+        let cdf = Cdf::read_cdf_file_with(
+            &path_test_file,
+            ReadOptions {
+                lazy: true,
+                cancel: Some(token.clone()),
+                ..Default::default()
+            },
+        )?;
+        token.cancel();
+        assert!(matches!(
+            cdf.variable("Epoch")?.read_all(),
+            Err(CdfError::Cancelled)
+        ));
+        token.reset();
+        assert_eq!(cdf.variable("Epoch")?.read_all()?.len(), 134_640);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_cdf_outdated_leap_seconds() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let mut bytes = std::fs::read(path_test_file)?;
+        let cdf = Cdf::from_bytes(&bytes)?;
+        assert!(cdf.warnings().is_empty());
+
+        // Pretend that the file was written after a leap second unknown to this library.
+        let date = 20_170_101i32.to_be_bytes();
+        let position = bytes
+            .windows(4)
+            .position(|w| w == date)
+            .ok_or_else(|| CdfError::Other("Leap second date not found.".to_string()))?;
+        bytes[position..position + 4].copy_from_slice(&20_990_101i32.to_be_bytes());
+
+        // Warnings are passed to the callback as they are found.
+        let reported = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let collect = Arc::clone(&reported);
+        let options = ReadOptions {
+            warning: Some(Arc::new(move |warning| {
+                collect.lock().unwrap().push(warning)
+            })),
+            ..Default::default()
+        };
+        let cdf = Cdf::from_reader(io::Cursor::new(bytes), options)?;
+        assert_eq!(*reported.lock().unwrap(), cdf.warnings());
+        match cdf.warnings().as_slice() {
+            [DecodeWarning::OutdatedLeapSeconds { file, library }] => {
+                assert_eq!(*file, 20_990_101);
+                assert!(*library < *file);
+            }
+            w => panic!("Unexpected warnings {w:?}"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_cdf_warnings() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let mut bytes = std::fs::read(path_test_file)?;
+
+        // Garbage after the name of a variable, an unknown encoding in the CDR and bytes after
+        // the end of the file.
+        let position = bytes
+            .windows(10)
+            .position(|w| w == b"Longitude\0")
+            .ok_or_else(|| CdfError::Other("Variable name not found.".to_string()))?;
+        bytes[position + 12] = b'x';
+        bytes[36..40].copy_from_slice(&99i32.to_be_bytes());
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5]);
+        assert!(Cdf::from_bytes(&bytes).is_err());
+
+        let mut decoder =
+            Decoder::with_encoding(io::Cursor::new(bytes), crate::repr::CdfEncoding::IbmPc)?;
+        let cdf = Cdf::decode_be(&mut decoder)?;
+        assert_eq!(
+            cdf.warnings(),
+            vec![
+                DecodeWarning::UnknownEncoding { encoding: 99 },
+                DecodeWarning::EmbeddedNul {
+                    text: "Longitude".to_string()
+                },
+                DecodeWarning::TrailingBytes { bytes: 5 },
+            ]
+        );
+        assert_eq!(
+            cdf.warnings()[2].to_string(),
+            "The file has 5 bytes after its end, which are ignored."
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_cdf_truncated() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "ulysses.cdf",
+        ]
+        .iter()
+        .collect();
+        let bytes = std::fs::read(path_test_file)?;
+        let truncated = bytes[..bytes.len() - 1000].to_vec();
+        let file_size = u64::try_from(truncated.len())?;
+
+        // The file ends within the values of the last VVR.
+        let error = Cdf::from_bytes(&truncated).err();
+        let Some(
+            error @ CdfError::Truncated {
+                record_type: Some(7),
+                missing: Some(missing),
+                offset,
+                ..
+            },
+        ) = error
+        else {
+            return Err(CdfError::Other(format!("Unexpected result {error:?}.")));
+        };
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "File truncated at byte {file_size}: record of type 7 at offset {offset} needs \
+                 {missing} more bytes."
+            )
+        );
+
+        // Values read on demand report the VVR that is cut off, and the one after the end.
+        let cdf = Cdf::from_reader(
+            io::Cursor::new(truncated),
+            ReadOptions {
+                lazy: true,
+                ..Default::default()
+            },
+        )?;
+        let errors = cdf
+            .variables()
+            .iter()
+            .filter_map(|v| v.read_all().err().map(|e| e.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].ends_with(&format!("at offset {offset} needs {missing} more bytes.")));
+        assert!(errors[1].ends_with("is past the end of the file."));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_cdf_record_size() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "ulysses.cdf",
+        ]
+        .iter()
+        .collect();
+        let bytes = std::fs::read(path_test_file)?;
+        let is_mismatch = |w: &DecodeWarning| matches!(w, DecodeWarning::RecordSizeMismatch { .. });
+        let cdf = Cdf::from_bytes(&bytes)?;
+        assert!(!cdf.warnings().iter().any(is_mismatch));
+
+        // The record size of the CDR of this version 2 file is stored in 4 bytes after the magic
+        // numbers. Bytes declared after its copyright are skipped.
+        let record_size = i32::from_be_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+        let mut larger = bytes.clone();
+        larger[8..12].copy_from_slice(&(record_size + 4).to_be_bytes());
+        let cdf = Cdf::from_bytes(&larger)?;
+        assert_eq!(
+            cdf.warnings(),
+            vec![DecodeWarning::RecordSizeMismatch {
+                offset: 8,
+                record_type: 1,
+                record_size: u64::try_from(record_size + 4)?,
+                consumed: u64::try_from(record_size)?,
+            }]
+        );
+        assert_eq!(cdf.variables().len(), 15);
+
+        // Reading past the end of a record is an error, unless the decoder is not strict.
+        let mut smaller = bytes;
+        smaller[8..12].copy_from_slice(&(record_size - 4).to_be_bytes());
+        assert!(Cdf::from_bytes(&smaller).is_err());
+        let mut decoder = Decoder::builder()
+            .strict(false)
+            .build(io::Cursor::new(smaller))?;
+        let cdf = Cdf::decode_be(&mut decoder)?;
+        assert!(cdf.warnings().iter().any(is_mismatch));
+        assert_eq!(cdf.variables().len(), 15);
+        Ok(())
+    }
+
+    // A file in the layout of CDF v2.0 or v2.1, with one rVariable of three records and one
+    // global attribute. Fields that are undefined in these versions hold garbage.
+    fn _legacy_cdf(release: i32) -> Vec<u8> {
+        let put = |bytes: &mut Vec<u8>, values: &[i32]| {
+            for v in values {
+                bytes.extend_from_slice(&v.to_be_bytes());
+            }
+        };
+        let put_text = |bytes: &mut Vec<u8>, text: &str, len: usize| {
+            bytes.extend_from_slice(text.as_bytes());
+            bytes.resize(bytes.len() + len - text.len(), 0);
+        };
+        let (cdr, gdr, rvdr, vxr, vvr, adr, agredr) = (8, 2001, 2065, 2197, 2229, 2261, 2377);
+        let eof = 2429;
+        let (eof_field, uir_head) = if release == 0 { (-7, 4242) } else { (eof, 0) };
+
+        let mut bytes = vec![];
+        put(&mut bytes, &[0x0000ffff, 0x0000ffff]);
+        put(
+            &mut bytes,
+            &[gdr - cdr, 1, gdr, 2, release, 1, 3, 0, 0, 5, -1, -1],
+        );
+        put_text(&mut bytes, "\nNSSDC Common Data Format (CDF)\n", 1945);
+        put(
+            &mut bytes,
+            &[rvdr - gdr, 2, rvdr, 12345, adr, eof_field, 1, 1, 0, 1, 3],
+        );
+        put(&mut bytes, &[uir_head, 0, -1, -1, 2]);
+        put(
+            &mut bytes,
+            &[
+                vxr - rvdr,
+                3,
+                0,
+                4,
+                2,
+                vxr,
+                vxr,
+                1 | 4,
+                1,
+                0,
+                -1,
+                -1,
+                1,
+                0,
+                777,
+                0,
+            ],
+        );
+        put_text(&mut bytes, "counts", 64);
+        put(&mut bytes, &[-1]);
+        put(&mut bytes, &[vvr - vxr, 6, 0, 1, 1, 0, 2, vvr]);
+        put(&mut bytes, &[adr - vvr, 7, 1, 2, 3, 4, 5, 6]);
+        put(
+            &mut bytes,
+            &[agredr - adr, 4, 0, agredr, 1, 0, 1, 0, 0, 999, 2, 1, -1],
+        );
+        put_text(&mut bytes, "Project", 64);
+        put(
+            &mut bytes,
+            &[eof - agredr, 5, 0, 0, 51, 0, 4, 0, 0, 0, -1, -1],
+        );
+        put_text(&mut bytes, "ISEE", 4);
+        assert_eq!(bytes.len(), usize::try_from(eof).unwrap());
+        bytes
+    }
+
+    #[test]
+    fn test_read_cdf_legacy() -> Result<(), CdfError> {
+        for release in [0, 1] {
+            let bytes = _legacy_cdf(release);
+            let cdf = Cdf::from_bytes(&bytes)?;
+            assert_eq!(
+                cdf.cdr.cdf_version,
+                CdfVersion::new(2, u16::try_from(release)?, 5)
+            );
+            assert!(cdf.warnings().is_empty());
+            let gdr = &cdf.cdr.gdr;
+            assert_eq!(gdr.eof.is_some(), release == 1);
+            assert!(gdr.zvdr_head.is_none() && gdr.uir_head.is_none());
+            assert_eq!(*gdr.num_zvars, 0);
+            assert!(gdr.adr_vec[0].azedr_head.is_none());
+
+            let counts = cdf.variable("counts")?;
+            assert!(counts.compression().is_none());
+            assert_eq!(
+                format!("{:?}", counts.read_records(1..3)?[1].data),
+                "[Int4(5), Int4(6)]"
+            );
+            assert_eq!(
+                format!("{:?}", gdr.adr_vec[0].agredr_vec[0].value),
+                "[String(ISEE)]"
+            );
+
+            let view = CdfView::parse(&bytes)?;
+            let counts = view
+                .variable("counts")
+                .ok_or(CdfError::Other("counts".into()))?;
+            assert!(counts.compression().is_none());
+            assert_eq!(
+                format!("{:?}", counts.read_record(2)?),
+                "Some([Int4(5), Int4(6)])"
+            );
+            assert_eq!(view.attributes()[0].entries().len(), 1);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cdf_keep_raw() -> Result<(), CdfError> {
+        for filename in ["test_alltypes.cdf", "ulysses.cdf"] {
+            let path_test_file: PathBuf =
+                [env!("CARGO_MANIFEST_DIR"), "examples", "data", filename]
+                    .iter()
+                    .collect();
+            let bytes = std::fs::read(&path_test_file)?;
+            let options = ReadOptions {
+                keep_raw: true,
+                ..Default::default()
+            };
+            let cdf = Cdf::read_cdf_file_with(&path_test_file, options)?;
+            let mut written = Vec::new();
+            cdf.write_raw(&mut written)?;
+            assert!(written == bytes, "{filename} was not re-created");
+
+            // Lazily read files miss the values records, but keep the others.
+            let options = ReadOptions {
+                keep_raw: true,
+                lazy: true,
+                ..Default::default()
+            };
+            let lazy = Cdf::read_cdf_file_with(&path_test_file, options)?;
+            assert!(lazy.raw_records().len() < cdf.raw_records().len());
+            let cdr = &lazy.raw_records()[&8];
+            assert_eq!(cdr, &bytes[8..8 + cdr.len()]);
+        }
+
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let cdf = Cdf::read_cdf_file(path_test_file)?;
+        assert!(cdf.raw_records().is_empty());
+        assert!(cdf.write_raw(&mut Vec::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cdf_info() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let options = ReadOptions {
+            lazy: true,
+            ..Default::default()
+        };
+        let info = Cdf::read_cdf_file_with(path_test_file, options)?.info();
+        assert_eq!(
+            info,
+            CdfInfo {
+                version: CdfVersion::new(3, 8, 1),
+                encoding: CdfEncoding::IbmPc,
+                majority: Majority::Row,
+                compressed: false,
+                checksum: true,
+                n_variables: 21,
+                n_attributes: 11,
+                last_leap_second: Some(20_170_101),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_cdf_listing() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "ulysses.cdf",
+        ]
+        .iter()
+        .collect();
+        let options = ReadOptions {
+            lazy: true,
+            ..Default::default()
+        };
+        let listing = Cdf::read_cdf_file_with(path_test_file, options)?.listing()?;
+        assert_eq!(listing.variables.len(), 15);
+        assert_eq!(
+            listing.variables[8],
+            VariableListing {
+                name: "B_scalar".to_string(),
+                zvariable: false,
+                data_type: DataType::Real4,
+                num_elements: 1,
+                dims: vec![3],
+                dim_variances: vec![false],
+                records: 134_640,
+                record_varies: true,
+                compression: None,
+            }
+        );
+        let global = listing.attributes.iter().filter(|a| a.global).count();
+        assert_eq!((listing.attributes.len(), global), (27, 10));
+        assert!(listing
+            .attributes
+            .iter()
+            .any(|a| a.name == "FILLVAL" && !a.global && a.entries == 12));
+
+        #[cfg(feature = "serde")]
+        {
+            let json =
+                serde_json::to_value(&listing).map_err(|err| CdfError::Other(err.to_string()))?;
+            assert_eq!(json["variables"][8]["name"], "B_scalar");
+            assert_eq!(json["variables"][8]["data_type"], "Real4");
+            assert_eq!(json["variables"][8]["compression"], serde_json::Value::Null);
+            assert_eq!(json["attributes"][0]["global"], true);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cdf_schema() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let bytes = std::fs::read(path_test_file)?;
+        let schema = Cdf::from_bytes(&bytes)?.schema()?;
+        assert_eq!(schema.variables.len(), 21);
+        assert_eq!(schema.attributes.len(), 6);
+        assert_eq!(schema.attributes["Test"].len(), 17);
+        let name = &schema.variables[8];
+        assert_eq!(
+            (name.data_type, name.shape.as_slice()),
+            (DataType::Char, &[2][..])
+        );
+
+        let lazy = ReadOptions {
+            lazy: true,
+            ..Default::default()
+        };
+        let cdf = Cdf::from_reader(io::Cursor::new(bytes.clone()), lazy)?;
+        assert_eq!(cdf.schema()?, schema);
+
+        // Renaming a variable shows up as a change of schema, and nothing else does.
+        let copy = |transform: &mut dyn RecordTransform| -> Result<Schema, CdfError> {
+            let writer = transcode(
+                io::Cursor::new(bytes.clone()),
+                io::Cursor::new(Vec::new()),
+                transform,
+            )?;
+            Cdf::from_bytes(writer.get_ref())?.schema()
+        };
+        let copied = copy(&mut ())?;
+        let mut renamed = copy(&mut RenameVariable::new("Name", "Names"))?;
+        assert_ne!(renamed, copied);
+        renamed.variables[8].name = "Name".to_string();
+        assert_eq!(renamed, copied);
+
+        #[cfg(feature = "serde")]
+        {
+            let json =
+                serde_json::to_value(&schema).map_err(|err| CdfError::Other(err.to_string()))?;
+            assert_eq!(json["variables"][8]["data_type"], "Char");
+            let back: Schema =
+                serde_json::from_value(json).map_err(|err| CdfError::Other(err.to_string()))?;
+            assert_eq!(back, schema);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cdf_format() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [env!("CARGO_MANIFEST_DIR"), "examples", "data"]
+            .iter()
+            .collect();
+        let format = Cdf::read_cdf_file(path_test_file.join("test_alltypes.cdf"))?.format();
+        assert_eq!(
+            format,
+            FormatInfo {
+                version: CdfVersion::new(3, 8, 1),
+                uses_64bit_offsets: true,
+                max_name_len: 256,
+                supports_tt2000: true,
+                supports_checksum: true,
+            }
+        );
+        let format = Cdf::read_cdf_file(path_test_file.join("ulysses.cdf"))?.format();
+        assert_eq!(
+            format,
+            FormatInfo {
+                version: CdfVersion::new(2, 5, 22),
+                uses_64bit_offsets: false,
+                max_name_len: 64,
+                supports_tt2000: false,
+                supports_checksum: false,
+            }
+        );
+        assert!(!FormatInfo::for_version(&CdfVersion::new(3, 3, 0)).supports_tt2000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_attributes() -> Result<(), CdfError> {
+        let path: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "ulysses.cdf",
+        ]
+        .iter()
+        .collect();
+        let cdf = Cdf::read_cdf_file(path)?;
+        let global = cdf.global_attributes();
+        let project = global.get("Project").expect("ulysses.cdf has a Project");
+        assert_eq!(
+            format!("{:?}", &global["Project"]),
+            format!("[String({project})]")
+        );
+        assert!(global
+            .entries("TEXT")
+            .is_some_and(|entries| entries.len() > 1));
+        assert!(!global.contains("UNITS") && global.value("Missing").is_none());
+
+        let vars = cdf.vars();
+        assert_eq!(vars.len(), cdf.variables().len());
+        assert_eq!(vars["B_scalar"].name(), "B_scalar");
+        assert_eq!(
+            vars["Epoch"].n_records(),
+            cdf.variable("Epoch")?.n_records()
+        );
+        assert!(vars.contains("Epoch") && vars.get("Missing").is_none());
+        let missing = std::panic::AssertUnwindSafe(|| vars["Missing"].name());
+        assert!(std::panic::catch_unwind(missing).is_err());
+
+        let attributes = cdf.variable("B_scalar")?.attributes();
+        assert_eq!(format!("{:?}", &attributes["DEPEND_0"]), "[String(Epoch)]");
+        let missing = std::panic::AssertUnwindSafe(|| global["Missing"].len());
+        assert!(std::panic::catch_unwind(missing).is_err());
+        let missing = std::panic::AssertUnwindSafe(|| attributes["Project"].len());
+        assert!(std::panic::catch_unwind(missing).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cdf_display() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let cdf = Cdf::read_cdf_file(path_test_file)?;
+        let summary = cdf.to_string();
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines[0], "CDF 3.8.1, IbmPc encoding, row-major");
+        assert_eq!(lines[2], "Checksum: MD5");
+        assert_eq!(lines[3], "Attributes: 6 global, 5 variable");
+        assert_eq!(lines[4], "Variables: 21");
+        assert!(lines[5].starts_with("  Name            Kind  Type"));
+        assert_eq!(
+            lines[8],
+            "  Longitude       z     Int2        [3]        20       Gzip"
+        );
+        assert_eq!(lines.len(), 6 + 21);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cdf_add_reader() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let bytes = std::fs::read(path_test_file)?;
+        let eager = Cdf::from_bytes(&bytes)?;
+
+        let options = ReadOptions {
+            lazy: true,
+            ..Default::default()
+        };
+        let mut lazy = Cdf::from_reader(io::Cursor::new(bytes.clone()), options)?;
+        lazy.add_reader(io::Cursor::new(bytes))?;
+        assert_eq!(lazy.num_readers(), 2);
+        for (a, b) in lazy.variables().iter().zip(eager.variables().iter()) {
+            assert_eq!(
+                format!("{:?}", a.read_all()?),
+                format!("{:?}", b.read_all()?)
+            );
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,231 @@
+use alloc::{format, string::String, vec};
+
+use crate::io::{self, SeekFrom};
+
+use crate::error::CdfError;
+
+/// Size in bytes of the MD5 checksum stored at the end of a CDF file.
+pub const MD5_SIZE: usize = 16;
+
+// Per-round shift amounts (RFC 1321).
+const S: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9,
+    14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15,
+    21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+// Integer part of the sines of integers (in radians) * 2^32 (RFC 1321).
+const K: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+/// Streaming MD5 hasher. CDF files optionally store an MD5 checksum of the whole file (excluding
+/// the checksum itself) in the last 16 bytes of the file.
+#[derive(Debug, Clone)]
+pub struct Md5 {
+    state: [u32; 4],
+    buffer: [u8; 64],
+    buffer_len: usize,
+    total_len: u64,
+}
+
+impl Default for Md5 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Md5 {
+    /// Create a new hasher with an empty input.
+    pub fn new() -> Self {
+        Md5 {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            buffer: [0u8; 64],
+            buffer_len: 0,
+            total_len: 0,
+        }
+    }
+
+    /// Feed more bytes into the hasher.
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.total_len = self.total_len.wrapping_add(data.len() as u64);
+
+        if self.buffer_len > 0 {
+            let n = (64 - self.buffer_len).min(data.len());
+            self.buffer[self.buffer_len..self.buffer_len + n].copy_from_slice(&data[..n]);
+            self.buffer_len += n;
+            data = &data[n..];
+            if self.buffer_len < 64 {
+                return;
+            }
+            let block = self.buffer;
+            self.process_block(&block);
+            self.buffer_len = 0;
+        }
+
+        let mut chunks = data.chunks_exact(64);
+        for block in &mut chunks {
+            self.process_block(block.try_into().unwrap());
+        }
+        let rest = chunks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffer_len = rest.len();
+    }
+
+    /// Consume the hasher and return the 16-byte digest.
+    pub fn finalize(mut self) -> [u8; MD5_SIZE] {
+        let bit_len = self.total_len.wrapping_mul(8);
+
+        let mut padding = [0u8; 72];
+        padding[0] = 0x80;
+        let pad_len = if self.buffer_len < 56 {
+            56 - self.buffer_len
+        } else {
+            120 - self.buffer_len
+        };
+        padding[pad_len..pad_len + 8].copy_from_slice(&bit_len.to_le_bytes());
+        // `update` would change the total length, but it has already been captured above.
+        self.update(&padding[..pad_len + 8]);
+
+        let mut digest = [0u8; MD5_SIZE];
+        for (chunk, s) in digest.chunks_exact_mut(4).zip(self.state.iter()) {
+            chunk.copy_from_slice(&s.to_le_bytes());
+        }
+        digest
+    }
+
+    fn process_block(&mut self, block: &[u8; 64]) {
+        let mut m = [0u32; 16];
+        for (word, bytes) in m.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f.wrapping_add(a).wrapping_add(K[i]).wrapping_add(m[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(S[i]));
+        }
+
+        self.state[0] = self.state[0].wrapping_add(a);
+        self.state[1] = self.state[1].wrapping_add(b);
+        self.state[2] = self.state[2].wrapping_add(c);
+        self.state[3] = self.state[3].wrapping_add(d);
+    }
+}
+
+/// Verify the MD5 checksum stored in the last 16 bytes of a CDF file against the contents of the
+/// rest of the file. The position of the reader is not restored.
+/// # Errors
+/// Returns a [`CdfError::Decode`] if the checksum does not match, or a [`CdfError::Io`] if the
+/// file cannot be read.
+pub fn verify_md5<R>(reader: &mut R) -> Result<(), CdfError>
+where
+    R: io::Read + io::Seek,
+{
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let content_len = file_len.checked_sub(MD5_SIZE as u64).ok_or_else(|| {
+        CdfError::Decode(format!(
+            "File is too small ({file_len} bytes) to contain an MD5 checksum."
+        ))
+    })?;
+
+    let computed = md5_prefix(reader, content_len)?;
+    let mut stored = [0u8; MD5_SIZE];
+    reader.read_exact(&mut stored)?;
+    if computed != stored {
+        return Err(CdfError::Decode(format!(
+            "MD5 checksum mismatch - stored {}, computed {}",
+            to_hex(&stored),
+            to_hex(&computed)
+        )));
+    }
+    Ok(())
+}
+
+// MD5 checksum of the first `len` bytes of `reader`, which is left right after them.
+pub(crate) fn md5_prefix<R>(reader: &mut R, len: u64) -> Result<[u8; MD5_SIZE], CdfError>
+where
+    R: io::Read + io::Seek,
+{
+    _ = reader.seek(SeekFrom::Start(0))?;
+    let mut hasher = Md5::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut remaining = len;
+    while remaining > 0 {
+        let n = usize::try_from(remaining.min(buffer.len() as u64))?;
+        reader.read_exact(&mut buffer[..n])?;
+        hasher.update(&buffer[..n]);
+        remaining -= n as u64;
+    }
+    Ok(hasher.finalize())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn md5_hex(data: &[u8]) -> String {
+        let mut hasher = Md5::new();
+        hasher.update(data);
+        to_hex(&hasher.finalize())
+    }
+
+    #[test]
+    fn test_md5_known_values() {
+        assert_eq!(md5_hex(b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(md5_hex(b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            md5_hex(b"The quick brown fox jumps over the lazy dog"),
+            "9e107d9d372bb6826bd81d3542a419d6"
+        );
+
+        // Feeding the input in pieces must give the same digest.
+        let data = vec![7u8; 1000];
+        let mut hasher = Md5::new();
+        for chunk in data.chunks(37) {
+            hasher.update(chunk);
+        }
+        assert_eq!(to_hex(&hasher.finalize()), md5_hex(&data));
+    }
+
+    #[test]
+    fn test_verify_md5() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+
+        let mut bytes = fs::read(path_test_file)?;
+        verify_md5(&mut io::Cursor::new(bytes.as_slice()))?;
+
+        bytes[100] ^= 0xff;
+        assert!(verify_md5(&mut io::Cursor::new(bytes.as_slice())).is_err());
+        Ok(())
+    }
+}
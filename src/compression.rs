@@ -0,0 +1,745 @@
+use crate::{error::CdfError, record::cpr::CdfCompressionKind};
+use alloc::{format, string::ToString, vec, vec::Vec};
+
+/// Decompress `data` that was compressed using the compression algorithm `kind`.
+/// # Errors
+/// Returns a [`CdfError::Decode`] if the data is corrupt, or if the compression algorithm is not
+/// supported. Only RLE and GZIP compression are currently supported.
+pub fn decompress(kind: &CdfCompressionKind, data: &[u8]) -> Result<Vec<u8>, CdfError> {
+    decompress_limited(kind, data, usize::MAX)
+}
+
+/// Decompress `data` like [`decompress`], but stop as soon as the decompressed data would exceed
+/// `limit` bytes. Highly compressible data can otherwise expand to absurd sizes.
+/// # Errors
+/// Returns a [`CdfError::Decode`] if the data is corrupt, if the compression algorithm is not
+/// supported, or if the decompressed data is larger than `limit`.
+pub fn decompress_limited(
+    kind: &CdfCompressionKind,
+    data: &[u8],
+    limit: usize,
+) -> Result<Vec<u8>, CdfError> {
+    match kind {
+        CdfCompressionKind::None if data.len() > limit => Err(limit_error(limit)),
+        CdfCompressionKind::None => Ok(data.to_vec()),
+        CdfCompressionKind::Rle => rle_decompress(data, limit),
+        CdfCompressionKind::Gzip => gzip_decompress(data, limit),
+        k => Err(CdfError::Decode(format!(
+            "Decompression of {k} is not supported."
+        ))),
+    }
+}
+
+fn limit_error(limit: usize) -> CdfError {
+    CdfError::Decode(format!(
+        "Decompressed data exceeds the limit of {limit} bytes."
+    ))
+}
+
+/// The run-length encoding used by CDF only encodes runs of zeros. Each zero byte is followed by
+/// a byte that stores the number of additional zeros in the run.
+fn rle_decompress(data: &[u8], limit: usize) -> Result<Vec<u8>, CdfError> {
+    let mut result = Vec::with_capacity(data.len().min(limit));
+    let mut bytes = data.iter();
+    while let Some(&b) = bytes.next() {
+        if b == 0 {
+            let count = bytes.next().ok_or_else(|| {
+                CdfError::Decode("RLE data ends in the middle of a run of zeros.".to_string())
+            })?;
+            let len = result.len() + usize::from(*count) + 1;
+            if len > limit {
+                return Err(limit_error(limit));
+            }
+            result.resize(len, 0);
+        } else if result.len() == limit {
+            return Err(limit_error(limit));
+        } else {
+            result.push(b);
+        }
+    }
+    Ok(result)
+}
+
+/// Decompress a GZIP member (RFC 1952), which wraps a DEFLATE stream (RFC 1951).
+fn gzip_decompress(data: &[u8], limit: usize) -> Result<Vec<u8>, CdfError> {
+    const FHCRC: u8 = 2;
+    const FEXTRA: u8 = 4;
+    const FNAME: u8 = 8;
+    const FCOMMENT: u8 = 16;
+
+    let corrupt = || CdfError::Decode("Invalid GZIP header.".to_string());
+    if data.len() < 18 || data[0] != 0x1f || data[1] != 0x8b || data[2] != 8 {
+        return Err(corrupt());
+    }
+    let flags = data[3];
+    let mut pos = 10;
+    if flags & FEXTRA != 0 {
+        let xlen = data.get(pos..pos + 2).ok_or_else(corrupt)?;
+        pos += 2 + usize::from(u16::from_le_bytes([xlen[0], xlen[1]]));
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let len = data
+                .get(pos..)
+                .and_then(|rest| rest.iter().position(|b| *b == 0))
+                .ok_or_else(corrupt)?;
+            pos += len + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+    let stream = data.get(pos..).ok_or_else(corrupt)?;
+
+    let (result, consumed) = backend::inflate(stream, limit)?;
+    let trailer = stream
+        .get(consumed..consumed + 8)
+        .ok_or_else(|| CdfError::Decode("GZIP data is missing its trailer.".to_string()))?;
+    let crc = u32::from_le_bytes(trailer[0..4].try_into().unwrap());
+    let size = u32::from_le_bytes(trailer[4..8].try_into().unwrap());
+    if size != result.len() as u32 || crc != crc32(&result) {
+        return Err(CdfError::Decode(
+            "GZIP data does not match its checksum.".to_string(),
+        ));
+    }
+    Ok(result)
+}
+
+/// Compress `data` using the compression algorithm `kind`.
+/// # Errors
+/// Returns a [`CdfError::Encode`] if the compression algorithm is not supported. Only RLE and
+/// GZIP compression are currently supported.
+pub fn compress(kind: &CdfCompressionKind, data: &[u8]) -> Result<Vec<u8>, CdfError> {
+    match kind {
+        CdfCompressionKind::None => Ok(data.to_vec()),
+        CdfCompressionKind::Rle => Ok(rle_compress(data)),
+        CdfCompressionKind::Gzip => Ok(gzip_compress(data, backend::deflate)),
+        k => Err(CdfError::Encode(format!(
+            "Compression of {k} is not supported."
+        ))),
+    }
+}
+
+/// Compress `data` like [`compress`], but always with the built-in DEFLATE implementation, so that
+/// the result does not depend on the enabled features. See [`DEFLATE_BACKEND`].
+/// # Errors
+/// Returns a [`CdfError::Encode`] if the compression algorithm is not supported.
+pub(crate) fn compress_portable(
+    kind: &CdfCompressionKind,
+    data: &[u8],
+) -> Result<Vec<u8>, CdfError> {
+    match kind {
+        CdfCompressionKind::Gzip => Ok(gzip_compress(data, deflate)),
+        k => compress(k, data),
+    }
+}
+
+/// Replace each run of zeros by a zero byte followed by the number of additional zeros, so that
+/// a run can be at most 256 bytes long.
+fn rle_compress(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] == 0 {
+            let run = data[i..].iter().take(256).take_while(|b| **b == 0).count();
+            result.push(0);
+            result.push((run - 1) as u8);
+            i += run;
+        } else {
+            result.push(data[i]);
+            i += 1;
+        }
+    }
+    result
+}
+
+/// Compress `data` into a GZIP member, with no modification time, using `deflate`.
+fn gzip_compress(data: &[u8], deflate: fn(&[u8]) -> Vec<u8>) -> Vec<u8> {
+    let mut result = vec![0x1f, 0x8b, 0x08, 0, 0, 0, 0, 0, 0, 0xff];
+    result.extend(deflate(data));
+    result.extend_from_slice(&crc32(data).to_le_bytes());
+    result.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    result
+}
+
+/// Name of the DEFLATE implementation used for GZIP compression. The `zlib-ng` feature selects
+/// zlib-ng, the fastest, and the `miniz_oxide` feature selects miniz_oxide, which is pure Rust and
+/// builds for targets such as musl and wasm. Otherwise, the built-in implementation is used,
+/// which has no dependencies but only compresses with the fixed Huffman codes.
+pub const DEFLATE_BACKEND: &str = backend::NAME;
+
+#[cfg(feature = "zlib-ng")]
+mod backend {
+    use alloc::{string::ToString, vec::Vec};
+    use flate2::{bufread::DeflateDecoder, write::DeflateEncoder, Compression};
+    use std::io::{Read, Write};
+
+    use crate::error::CdfError;
+
+    pub(super) const NAME: &str = "zlib-ng";
+
+    pub(super) fn inflate(data: &[u8], limit: usize) -> Result<(Vec<u8>, usize), CdfError> {
+        let mut decoder = DeflateDecoder::new(data);
+        let mut result = Vec::new();
+        // Read one byte past the limit to tell a stream of exactly `limit` bytes from a longer one.
+        _ = (&mut decoder)
+            .take(u64::try_from(limit).unwrap_or(u64::MAX).saturating_add(1))
+            .read_to_end(&mut result)
+            .map_err(|e| super::inflate_error(&e.to_string()))?;
+        if result.len() > limit {
+            return Err(super::limit_error(limit));
+        }
+        Ok((result, data.len() - decoder.into_inner().len()))
+    }
+
+    pub(super) fn deflate(data: &[u8]) -> Vec<u8> {
+        // Writing into a vector cannot fail.
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        _ = encoder.write_all(data);
+        encoder.finish().unwrap_or_default()
+    }
+}
+
+#[cfg(all(feature = "miniz_oxide", not(feature = "zlib-ng")))]
+mod backend {
+    use alloc::{format, vec, vec::Vec};
+    use miniz_oxide::inflate::stream::{inflate as inflate_stream, InflateState};
+    use miniz_oxide::{DataFormat, MZError, MZFlush, MZStatus};
+
+    use crate::error::CdfError;
+
+    pub(super) const NAME: &str = "miniz_oxide";
+
+    pub(super) fn inflate(data: &[u8], limit: usize) -> Result<(Vec<u8>, usize), CdfError> {
+        let mut state = InflateState::new_boxed(DataFormat::Raw);
+        // One byte past the limit tells a stream of exactly `limit` bytes from a longer one.
+        let max_len = limit.saturating_add(1);
+        let mut result = vec![0; data.len().saturating_mul(4).max(1024).min(max_len)];
+        let (mut consumed, mut written) = (0, 0);
+        loop {
+            let step = inflate_stream(
+                &mut state,
+                &data[consumed..],
+                &mut result[written..],
+                MZFlush::None,
+            );
+            consumed += step.bytes_consumed;
+            written += step.bytes_written;
+            match step.status {
+                Ok(MZStatus::StreamEnd) if written > limit => {
+                    return Err(super::limit_error(limit));
+                }
+                Ok(MZStatus::StreamEnd) => {
+                    result.truncate(written);
+                    return Ok((result, consumed));
+                }
+                Ok(_) | Err(MZError::Buf) if written == max_len => {
+                    return Err(super::limit_error(limit));
+                }
+                Ok(_) | Err(MZError::Buf) if written == result.len() => {
+                    result.resize(result.len().saturating_mul(2).min(max_len), 0);
+                }
+                Ok(_) | Err(MZError::Buf) if step.bytes_consumed + step.bytes_written > 0 => {}
+                Ok(_) | Err(MZError::Buf) => {
+                    return Err(super::inflate_error("unexpected end of data"));
+                }
+                Err(e) => return Err(super::inflate_error(&format!("{e:?}"))),
+            }
+        }
+    }
+
+    pub(super) fn deflate(data: &[u8]) -> Vec<u8> {
+        miniz_oxide::deflate::compress_to_vec(data, 6)
+    }
+}
+
+#[cfg(not(any(feature = "zlib-ng", feature = "miniz_oxide")))]
+mod backend {
+    pub(super) use super::{deflate, inflate};
+
+    pub(super) const NAME: &str = "built-in";
+}
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for b in data {
+        crc ^= u32::from(*b);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+const MAX_BITS: usize = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+fn inflate_error(msg: &str) -> CdfError {
+    CdfError::Decode(format!("Invalid DEFLATE data - {msg}."))
+}
+
+/// Reads bits from a byte slice, least-significant bit first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, n: u32) -> Result<u32, CdfError> {
+        while self.bit_count < n {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| inflate_error("unexpected end of data"))?;
+            self.pos += 1;
+            self.bit_buf |= u32::from(byte) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buf & ((1u32 << n) - 1);
+        self.bit_buf >>= n;
+        self.bit_count -= n;
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        self.bit_buf = 0;
+        self.bit_count = 0;
+    }
+}
+
+/// Canonical Huffman code stored as the number of codes of each length and the symbols ordered
+/// by code.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, CdfError> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for len in lengths {
+            counts[usize::from(*len)] += 1;
+        }
+        counts[0] = 0;
+
+        // Reject over-subscribed codes. Incomplete codes are allowed, as by zlib.
+        let mut left: i32 = 1;
+        for count in counts.iter().skip(1) {
+            left = (left << 1) - i32::from(*count);
+            if left < 0 {
+                return Err(inflate_error("over-subscribed Huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for len in 1..=MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, len) in lengths.iter().enumerate() {
+            if *len != 0 {
+                let offset = &mut offsets[usize::from(*len)];
+                symbols[usize::from(*offset)] = symbol as u16;
+                *offset += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, CdfError> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for len in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+            let count = i32::from(self.counts[len]);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(inflate_error("invalid Huffman code"))
+    }
+}
+
+/// Decompress a raw DEFLATE stream of at most `limit` bytes, returning the decompressed data and
+/// the number of bytes of `data` consumed.
+#[cfg_attr(any(feature = "zlib-ng", feature = "miniz_oxide"), allow(dead_code))]
+fn inflate(data: &[u8], limit: usize) -> Result<(Vec<u8>, usize), CdfError> {
+    let mut reader = BitReader {
+        data,
+        pos: 0,
+        bit_buf: 0,
+        bit_count: 0,
+    };
+    let mut out: Vec<u8> = Vec::with_capacity(data.len().saturating_mul(4).min(limit));
+
+    loop {
+        let is_final = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align_to_byte();
+                let header = data
+                    .get(reader.pos..reader.pos + 4)
+                    .ok_or_else(|| inflate_error("unexpected end of data"))?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                let nlen = u16::from_le_bytes([header[2], header[3]]);
+                if len != !nlen {
+                    return Err(inflate_error("stored block length mismatch"));
+                }
+                let start = reader.pos + 4;
+                let block = data
+                    .get(start..start + usize::from(len))
+                    .ok_or_else(|| inflate_error("unexpected end of data"))?;
+                if out.len() + block.len() > limit {
+                    return Err(limit_error(limit));
+                }
+                out.extend_from_slice(block);
+                reader.pos = start + usize::from(len);
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[0..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..288].fill(8);
+                let lit = Huffman::new(&lengths)?;
+                let dist = Huffman::new(&[5u8; 30])?;
+                inflate_block(&mut reader, &mut out, &lit, &dist, limit)?;
+            }
+            2 => {
+                let (lit, dist) = read_dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &mut out, &lit, &dist, limit)?;
+            }
+            _ => return Err(inflate_error("invalid block type")),
+        }
+        if is_final {
+            break;
+        }
+    }
+    Ok((out, reader.pos))
+}
+
+fn read_dynamic_tables(reader: &mut BitReader) -> Result<(Huffman, Huffman), CdfError> {
+    let num_lit = reader.bits(5)? as usize + 257;
+    let num_dist = reader.bits(5)? as usize + 1;
+    let num_code = reader.bits(4)? as usize + 4;
+    if num_lit > 286 || num_dist > 30 {
+        return Err(inflate_error("too many length or distance codes"));
+    }
+
+    let mut code_lengths = [0u8; 19];
+    for i in CODE_LENGTH_ORDER.iter().take(num_code) {
+        code_lengths[*i] = reader.bits(3)? as u8;
+    }
+    let code = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; num_lit + num_dist];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = code.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .get(i.wrapping_sub(1))
+                    .ok_or_else(|| inflate_error("repeat with no previous length"))?;
+                (previous, 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        let run = lengths
+            .get_mut(i..i + repeat)
+            .ok_or_else(|| inflate_error("too many code lengths"))?;
+        run.fill(value);
+        i += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(inflate_error("missing end-of-block code"));
+    }
+
+    let lit = Huffman::new(&lengths[..num_lit])?;
+    let dist = Huffman::new(&lengths[num_lit..])?;
+    Ok((lit, dist))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    lit: &Huffman,
+    dist: &Huffman,
+    limit: usize,
+) -> Result<(), CdfError> {
+    loop {
+        let symbol = lit.decode(reader)?;
+        match symbol {
+            0..=255 if out.len() == limit => return Err(limit_error(limit)),
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let i = usize::from(symbol - 257);
+                if i >= LENGTH_BASE.len() {
+                    return Err(inflate_error("invalid length code"));
+                }
+                let len =
+                    usize::from(LENGTH_BASE[i]) + reader.bits(u32::from(LENGTH_EXTRA[i]))? as usize;
+
+                let d = usize::from(dist.decode(reader)?);
+                if d >= DIST_BASE.len() {
+                    return Err(inflate_error("invalid distance code"));
+                }
+                let distance =
+                    usize::from(DIST_BASE[d]) + reader.bits(u32::from(DIST_EXTRA[d]))? as usize;
+                if distance > out.len() {
+                    return Err(inflate_error("distance too far back"));
+                }
+                if out.len() + len > limit {
+                    return Err(limit_error(limit));
+                }
+
+                // The copied region may overlap with the bytes being written.
+                let start = out.len() - distance;
+                for k in 0..len {
+                    out.push(out[start + k]);
+                }
+            }
+        }
+    }
+}
+
+/// Writes bits into a byte vector, least-significant bit first.
+struct BitWriter {
+    out: Vec<u8>,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    fn bits(&mut self, value: u32, n: u32) {
+        self.bit_buf |= value << self.bit_count;
+        self.bit_count += n;
+        while self.bit_count >= 8 {
+            self.out.push(self.bit_buf as u8);
+            self.bit_buf >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// Huffman codes are stored starting from their most-significant bit.
+    fn code(&mut self, code: u32, len: u32) {
+        self.bits(code.reverse_bits() >> (32 - len), len);
+    }
+
+    fn literal(&mut self, symbol: u16) {
+        let symbol = u32::from(symbol);
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.out.push(self.bit_buf as u8);
+        }
+        self.out
+    }
+}
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+
+/// Compress `data` into a raw DEFLATE stream made of one block with the fixed Huffman codes,
+/// replacing repeated sequences by back-references found through a hash of their first bytes.
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let hash = |i: usize| {
+        let v = u32::from(data[i]) << 16 | u32::from(data[i + 1]) << 8 | u32::from(data[i + 2]);
+        (v.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+    };
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut writer = BitWriter {
+        out: Vec::with_capacity(data.len() / 2),
+        bit_buf: 0,
+        bit_count: 0,
+    };
+    writer.bits(1, 1);
+    writer.bits(1, 2);
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut length = 0;
+        let mut distance = 0;
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(i);
+            let candidate = head[h];
+            head[h] = i;
+            if candidate != usize::MAX && i - candidate <= WINDOW_SIZE {
+                length = data[candidate..]
+                    .iter()
+                    .zip(&data[i..])
+                    .take(MAX_MATCH)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                distance = i - candidate;
+            }
+        }
+        if length < MIN_MATCH {
+            writer.literal(u16::from(data[i]));
+            i += 1;
+            continue;
+        }
+
+        let l = LENGTH_BASE.partition_point(|b| usize::from(*b) <= length) - 1;
+        writer.literal(257 + l as u16);
+        writer.bits(
+            (length - usize::from(LENGTH_BASE[l])) as u32,
+            u32::from(LENGTH_EXTRA[l]),
+        );
+        let d = DIST_BASE.partition_point(|b| usize::from(*b) <= distance) - 1;
+        writer.code(d as u32, 5);
+        writer.bits(
+            (distance - usize::from(DIST_BASE[d])) as u32,
+            u32::from(DIST_EXTRA[d]),
+        );
+        for k in i + 1..(i + length).min(data.len().saturating_sub(MIN_MATCH - 1)) {
+            head[hash(k)] = k;
+        }
+        i += length;
+    }
+    writer.literal(256);
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rle_decompress() -> Result<(), CdfError> {
+        let data = [1u8, 0, 3, 2, 0, 0, 5];
+        assert_eq!(
+            decompress(&CdfCompressionKind::Rle, &data)?,
+            vec![1, 0, 0, 0, 0, 2, 0, 5]
+        );
+        assert!(decompress(&CdfCompressionKind::Rle, &[1, 0]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_gzip_decompress() -> Result<(), CdfError> {
+        // "hello hello hello\n" compressed with gzip using fixed Huffman codes.
+        let data = [
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48, 0xcd, 0xc9,
+            0xc9, 0x57, 0xc8, 0x40, 0x90, 0x5c, 0x00, 0x3b, 0x7c, 0x8a, 0xdf, 0x12, 0x00, 0x00,
+            0x00,
+        ];
+        assert_eq!(
+            decompress(&CdfCompressionKind::Gzip, &data)?,
+            b"hello hello hello\n".to_vec()
+        );
+
+        // Stored (uncompressed) DEFLATE block.
+        let mut data = vec![0x1f, 0x8b, 0x08, 0x00, 0, 0, 0, 0, 0, 0xff];
+        data.extend_from_slice(&[0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c']);
+        data.extend_from_slice(&crc32(b"abc").to_le_bytes());
+        data.extend_from_slice(&3u32.to_le_bytes());
+        assert_eq!(
+            decompress(&CdfCompressionKind::Gzip, &data)?,
+            b"abc".to_vec()
+        );
+
+        // Corrupting the payload must be detected.
+        let last = data.len() - 10;
+        data[last] = b'x';
+        assert!(decompress(&CdfCompressionKind::Gzip, &data).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_round_trip() -> Result<(), CdfError> {
+        let mut data = b"hello hello hello\n".to_vec();
+        data.extend(vec![0u8; 1000]);
+        data.extend((0..5000u32).map(|i| (i * i % 251) as u8));
+        data.extend(vec![7u8; 300]);
+        for kind in [
+            CdfCompressionKind::None,
+            CdfCompressionKind::Rle,
+            CdfCompressionKind::Gzip,
+        ] {
+            let compressed = compress(&kind, &data)?;
+            assert_eq!(decompress(&kind, &compressed)?, data);
+            assert_eq!(decompress(&kind, &compress(&kind, &[])?)?, Vec::<u8>::new());
+        }
+        assert_eq!(
+            rle_compress(&[1, 0, 0, 0, 0, 2, 0, 5]),
+            vec![1u8, 0, 3, 2, 0, 0, 5]
+        );
+        assert!(compress(&CdfCompressionKind::Gzip, &data)?.len() < data.len() / 2);
+        assert!(compress(&CdfCompressionKind::Huff, &data).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompress_limited() -> Result<(), CdfError> {
+        // A few kilobytes that expand to 4 MiB must be rejected without inflating all of it.
+        let data = vec![0u8; 1 << 22];
+        for kind in [CdfCompressionKind::Rle, CdfCompressionKind::Gzip] {
+            let compressed = compress(&kind, &data)?;
+            assert!(compressed.len() < data.len() / 100);
+            assert!(decompress_limited(&kind, &compressed, 1000).is_err());
+            assert!(decompress_limited(&kind, &compressed, data.len() - 1).is_err());
+            assert_eq!(decompress_limited(&kind, &compressed, data.len())?, data);
+        }
+        let stream = gzip_compress(&data, deflate);
+        assert!(gzip_decompress(&stream, 1000).is_err());
+        assert!(decompress_limited(&CdfCompressionKind::None, &data, 1000).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_deflate_backends() -> Result<(), CdfError> {
+        // Streams of the selected backend and of the built-in implementation are interchangeable.
+        let mut data = b"hello hello hello\n".to_vec();
+        data.extend((0..20000u32).map(|i| (i * i % 251) as u8));
+        let stream = backend::deflate(&data);
+        assert_eq!(inflate(&stream, usize::MAX)?, (data.clone(), stream.len()));
+        let mut stream = deflate(&data);
+        let len = stream.len();
+        stream.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(backend::inflate(&stream, usize::MAX)?, (data, len));
+        assert!(backend::inflate(&stream[..len / 2], usize::MAX).is_err());
+        assert!(!DEFLATE_BACKEND.is_empty());
+        Ok(())
+    }
+}
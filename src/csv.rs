@@ -0,0 +1,488 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::cdf::Cdf;
+use crate::decode::checked_usize;
+use crate::error::CdfError;
+use crate::io;
+use crate::time::TimeComponents;
+use crate::types::{
+    CdfByte, CdfEpoch, CdfEpoch16, CdfInt1, CdfInt2, CdfInt4, CdfInt8, CdfReal4, CdfReal8,
+    CdfString, CdfTimeTt2000, CdfType, CdfUint1, CdfUint2, CdfUint4, DataType,
+};
+use crate::variable::Variable;
+use crate::writer::{CdfWriter, VariableSpec};
+
+/// Options that control how [`import`] reads the CSV records.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Character that separates the cells of a line, `,` by default and `\t` for TSV.
+    pub delimiter: char,
+    /// Lines starting with this character are skipped, such as the comments that precede the
+    /// header of many instrument files.
+    pub comment: Option<char>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            comment: Some('#'),
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Options to read tab-separated values.
+    pub fn tsv() -> Self {
+        CsvOptions {
+            delimiter: '\t',
+            ..Default::default()
+        }
+    }
+}
+
+/// Fill the variables of the skeleton `master` with the records read from a CSV or TSV stream,
+/// and write the result as a new CDF file to `writer`, which is returned.
+///
+/// The first line that is not a comment is a header that names the variable of each column. A
+/// variable with several values per record spans as many columns, which are named after the
+/// variable, optionally followed by an index such as `B_vector[0]`. Character data has one column
+/// per string. Each following line is a record of all the variables of the header. Times are
+/// written in ISO 8601 like `2001-02-03T04:05:06.789`, or as their raw value, and empty cells hold
+/// the `FILLVAL` attribute of the variable, or else its pad value.
+///
+/// Variables of `master` that are not in the header keep the records of `master`, such as labels
+/// and other metadata. Quoted cells may contain the delimiter but not line breaks.
+/// # Errors
+/// Returns a [`CdfError::Other`] if the header names unknown variables or a cell cannot be parsed,
+/// with the number of the line at fault, or a [`CdfError`] if the records of `master` cannot be
+/// read or the file cannot be written.
+pub fn import<R, W>(master: &Cdf, reader: R, writer: W, options: &CsvOptions) -> Result<W, CdfError>
+where
+    R: io::Read,
+    W: io::Write + io::Seek,
+{
+    let variables = master.variables();
+    let r_dim_sizes = master
+        .cdr
+        .gdr
+        .size_r_dims
+        .iter()
+        .map(|s| checked_usize("size_r_dims", **s))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut builder = CdfWriter::builder()
+        .encoding(master.cdr.encoding.clone())
+        .row_major(master.cdr.flags.row_major)
+        .r_dim_sizes(&r_dim_sizes);
+    for variable in &variables {
+        builder = builder.variable(VariableSpec::from_variable(variable)?);
+    }
+    let mut writer = builder.build(writer)?;
+
+    let mut lines = Lines::new(reader, options);
+    let (number, header) = lines
+        .next_line()?
+        .ok_or_else(|| CdfError::Other("The CSV stream has no header.".to_string()))?;
+    let columns = columns(
+        &variables,
+        &split(&header, options.delimiter, number)?,
+        number,
+    )?;
+
+    for variable in &variables {
+        if !columns.iter().any(|c| c.variable.name() == variable.name()) {
+            let records: Vec<_> = variable
+                .read_all()?
+                .into_iter()
+                .map(|record| record.data)
+                .collect();
+            writer.write_records(variable.name(), &records)?;
+        }
+    }
+
+    while let Some((number, line)) = lines.next_line()? {
+        let cells = split(&line, options.delimiter, number)?;
+        if cells.len() != header_len(&columns) {
+            return Err(CdfError::Other(format!(
+                "Line {number} has {} cells instead of {}.",
+                cells.len(),
+                header_len(&columns)
+            )));
+        }
+        for column in &columns {
+            let record = column.record(&cells).map_err(|e| {
+                CdfError::Other(format!(
+                    "Line {number}, variable {} - {e}",
+                    column.variable.name()
+                ))
+            })?;
+            writer.write_records(column.variable.name(), &[record])?;
+        }
+    }
+    writer.close()
+}
+
+/// Fill the skeleton CDF file at `master_path` with the records of the CSV or TSV file at
+/// `csv_path`, and create the result at `cdf_path`, see [`import`].
+/// # Errors
+/// Returns a [`CdfError`] if a file cannot be opened, or if the conversion fails.
+#[cfg(feature = "std-fs")]
+pub fn import_file<P, Q, S>(
+    master_path: P,
+    csv_path: Q,
+    cdf_path: S,
+    options: &CsvOptions,
+) -> Result<(), CdfError>
+where
+    P: AsRef<std::path::Path>,
+    Q: AsRef<std::path::Path>,
+    S: AsRef<std::path::Path>,
+{
+    use std::{fs::File, io::BufReader, io::BufWriter};
+
+    let master = Cdf::read_cdf_file(master_path)?;
+    let reader = BufReader::new(File::open(csv_path)?);
+    let writer = BufWriter::new(File::create(cdf_path)?);
+    _ = import(&master, reader, writer, options)?;
+    Ok(())
+}
+
+// A variable of the header along with the columns that hold its values.
+struct Column<'a> {
+    variable: Variable<'a>,
+    cells: Vec<usize>,
+    data_type: DataType,
+    num_elements: usize,
+    fill: CdfType,
+}
+
+impl Column<'_> {
+    fn record(&self, cells: &[String]) -> Result<Vec<CdfType>, CdfError> {
+        if self.data_type.is_string() {
+            let mut s = String::new();
+            for cell in self.cells.iter().map(|c| &cells[*c]) {
+                if cell.chars().count() > self.num_elements {
+                    return Err(CdfError::Other(format!(
+                        "String {cell} is longer than {} characters.",
+                        self.num_elements
+                    )));
+                }
+                s.push_str(cell);
+                let padding = self.num_elements - cell.chars().count();
+                s.extend(core::iter::repeat_n(' ', padding));
+            }
+            return Ok(Vec::from([CdfType::String(CdfString::from(s))]));
+        }
+        self.cells
+            .iter()
+            .map(|c| match cells[*c].trim() {
+                "" => Ok(self.fill.clone()),
+                cell => parse_value(cell, self.data_type),
+            })
+            .collect()
+    }
+}
+
+fn header_len(columns: &[Column]) -> usize {
+    columns.iter().map(|c| c.cells.len()).sum()
+}
+
+// Match the names of the header with the variables they hold.
+fn columns<'a>(
+    variables: &[Variable<'a>],
+    header: &[String],
+    number: usize,
+) -> Result<Vec<Column<'a>>, CdfError> {
+    let mut columns: Vec<Column<'a>> = Vec::new();
+    for (i, name) in header.iter().enumerate() {
+        let name = name.trim();
+        let name = match name.split_once('[') {
+            Some((name, index)) if index.ends_with(']') => name,
+            _ => name,
+        };
+        if let Some(column) = columns.iter_mut().find(|c| c.variable.name() == name) {
+            column.cells.push(i);
+            continue;
+        }
+        let variable = *variables.iter().find(|v| v.name() == name).ok_or_else(|| {
+            CdfError::Other(format!("Line {number} - no variable is called {name}."))
+        })?;
+        let data_type = DataType::try_from(variable.data_type().clone())?;
+        let pad = variable.pad_record()?.data.into_iter().next();
+        let fill = match variable.attribute("FILLVAL") {
+            Some([fill]) if fill.is_of_type(&data_type) => Some(fill.clone()),
+            _ => pad,
+        };
+        columns.push(Column {
+            variable,
+            cells: Vec::from([i]),
+            data_type,
+            num_elements: variable.num_elements()?,
+            fill: fill
+                .ok_or_else(|| CdfError::Other(format!("Variable {name} has no pad value.")))?,
+        });
+    }
+    for column in &columns {
+        let expected = match column.data_type.is_string() {
+            true => column.variable.values_per_record()? / column.num_elements.max(1),
+            false => column.variable.values_per_record()?,
+        };
+        if column.cells.len() != expected {
+            return Err(CdfError::Other(format!(
+                "Line {number} - variable {} has {} columns instead of {expected}.",
+                column.variable.name(),
+                column.cells.len()
+            )));
+        }
+    }
+    Ok(columns)
+}
+
+// Parse a single value of `data_type`. Times are either ISO 8601 dates or raw values.
+fn parse_value(cell: &str, data_type: DataType) -> Result<CdfType, CdfError> {
+    let invalid = || CdfError::Other(format!("Invalid value {cell} of type {data_type:?}."));
+    macro_rules! parse {
+        ($variant:ident, $cdf_type:ident, $rust_type:ty) => {
+            CdfType::$variant($cdf_type::from(
+                cell.parse::<$rust_type>().map_err(|_| invalid())?,
+            ))
+        };
+    }
+    let is_time = cell.contains(['T', ':']) || cell.get(1..).is_some_and(|c| c.contains('-'));
+    Ok(match data_type {
+        DataType::Int1 => parse!(Int1, CdfInt1, i8),
+        DataType::Int2 => parse!(Int2, CdfInt2, i16),
+        DataType::Int4 => parse!(Int4, CdfInt4, i32),
+        DataType::Int8 => parse!(Int8, CdfInt8, i64),
+        DataType::Uint1 => parse!(Uint1, CdfUint1, u8),
+        DataType::Uint2 => parse!(Uint2, CdfUint2, u16),
+        DataType::Uint4 => parse!(Uint4, CdfUint4, u32),
+        DataType::Real4 | DataType::Float => parse!(Real4, CdfReal4, f32),
+        DataType::Real8 | DataType::Double => parse!(Real8, CdfReal8, f64),
+        DataType::Byte => parse!(Byte, CdfByte, i8),
+        DataType::Epoch if is_time => {
+            let c = TimeComponents::parse_iso8601(cell)?;
+            CdfType::Epoch(CdfEpoch::from_ymdhms(
+                c.year,
+                c.month,
+                c.day,
+                c.hour,
+                c.minute,
+                c.second,
+                c.millisecond,
+            )?)
+        }
+        DataType::Epoch => parse!(Epoch, CdfEpoch, f64),
+        DataType::Epoch16 if is_time => {
+            let c = TimeComponents::parse_iso8601(cell)?;
+            CdfType::Epoch16(CdfEpoch16::from_parts(
+                c.year,
+                c.month,
+                c.day,
+                c.hour,
+                c.minute,
+                c.second,
+                c.millisecond,
+                c.microsecond,
+                c.nanosecond,
+                0,
+            )?)
+        }
+        DataType::TimeTt2000 if is_time => CdfType::TimeTt2000(CdfTimeTt2000::from_utc(
+            &TimeComponents::parse_iso8601(cell)?,
+        )?),
+        DataType::TimeTt2000 => parse!(TimeTt2000, CdfTimeTt2000, i64),
+        DataType::Epoch16 | DataType::Char | DataType::Uchar => return Err(invalid()),
+    })
+}
+
+// Split `line` into cells at `delimiter`. Cells may be quoted with `"` to contain the delimiter,
+// and `""` within quotes stands for a quote.
+fn split(line: &str, delimiter: char, number: usize) -> Result<Vec<String>, CdfError> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                cell.push('"');
+                _ = chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if cell.trim().is_empty() => {
+                cell.clear();
+                quoted = true;
+            }
+            c if c == delimiter && !quoted => cells.push(core::mem::take(&mut cell)),
+            c => cell.push(c),
+        }
+    }
+    if quoted {
+        return Err(CdfError::Other(format!(
+            "Line {number} has an unclosed quote."
+        )));
+    }
+    cells.push(cell);
+    Ok(cells)
+}
+
+// Reads the lines of a stream one at a time, skipping blank lines and comments.
+struct Lines<'o, R> {
+    reader: R,
+    options: &'o CsvOptions,
+    buffer: Vec<u8>,
+    number: usize,
+    eof: bool,
+}
+
+impl<'o, R> Lines<'o, R>
+where
+    R: io::Read,
+{
+    fn new(reader: R, options: &'o CsvOptions) -> Self {
+        Lines {
+            reader,
+            options,
+            buffer: Vec::new(),
+            number: 0,
+            eof: false,
+        }
+    }
+
+    // The next line along with its number, counted from 1.
+    fn next_line(&mut self) -> Result<Option<(usize, String)>, CdfError> {
+        loop {
+            let end = match self.buffer.iter().position(|b| *b == b'\n') {
+                Some(end) => end + 1,
+                None if !self.eof => {
+                    let mut chunk = [0; 8192];
+                    match self.reader.read(&mut chunk)? {
+                        0 => self.eof = true,
+                        n => self.buffer.extend_from_slice(&chunk[..n]),
+                    }
+                    continue;
+                }
+                None if self.buffer.is_empty() => return Ok(None),
+                None => self.buffer.len(),
+            };
+            let bytes: Vec<u8> = self.buffer.drain(..end).collect();
+            self.number += 1;
+            let line = String::from_utf8(bytes).map_err(|_| {
+                CdfError::Other(format!("Line {} is not valid UTF-8.", self.number))
+            })?;
+            let line = line.trim_end_matches(['\n', '\r']);
+            let skipped =
+                line.trim().is_empty() || self.options.comment.is_some_and(|c| line.starts_with(c));
+            if !skipped {
+                return Ok(Some((self.number, line.to_string())));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::CdfTime;
+    use crate::writer::CdfWriter;
+
+    fn _master() -> Result<Cdf, CdfError> {
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("Epoch", DataType::TimeTt2000))
+            .variable(VariableSpec::new("B", DataType::Real4).dims(&[3]))
+            .variable(VariableSpec::new("flag", DataType::Int2))
+            .variable(VariableSpec::new("label", DataType::Char).num_elements(5))
+            .variable(VariableSpec::new("units", DataType::Char).num_elements(2))
+            .build(io::Cursor::new(Vec::new()))?;
+        let units = [CdfType::String(CdfString::from("nT".to_string()))];
+        writer.write_records("units", &[units.to_vec()])?;
+        Cdf::from_bytes(&writer.close()?.into_inner())
+    }
+
+    #[test]
+    fn test_import() -> Result<(), CdfError> {
+        let csv = "# Magnetometer data\n\
+            Epoch,B[0],B[1],B[2],flag,label\n\
+            2020-01-01T00:00:00.5,1.5,2,-3,0,\"a,b\"\n\
+            \n\
+            2020-01-01T00:00:01Z,4,5,,1,c\r\n";
+        let master = _master()?;
+        let bytes = import(
+            &master,
+            io::Cursor::new(csv),
+            io::Cursor::new(Vec::new()),
+            &CsvOptions::default(),
+        )?;
+        let cdf = Cdf::from_bytes(&bytes.into_inner())?;
+
+        let epoch = cdf.variable("Epoch")?.read_all()?;
+        assert_eq!(epoch.len(), 2);
+        let CdfType::TimeTt2000(t) = &epoch[0].data[0] else {
+            panic!("Unexpected epoch {:?}", epoch[0].data);
+        };
+        assert_eq!(t.to_iso8601(), "2020-01-01T00:00:00.500000000");
+
+        let b = cdf.variable("B")?.read_all()?;
+        assert_eq!(
+            format!("{:?}", b[0].data),
+            "[Real4(1.5), Real4(2), Real4(-3)]"
+        );
+        // The empty cell holds the pad value of the variable.
+        assert_eq!(
+            format!("{:?}", b[1].data[2]),
+            format!("{:?}", cdf.variable("B")?.pad_record()?.data[2])
+        );
+        let label = cdf.variable("label")?.read_all()?;
+        assert_eq!(format!("{:?}", label[0].data), "[String(a,b  )]");
+        // Variables that are not in the CSV keep the records of the master.
+        assert_eq!(
+            format!("{:?}", cdf.variable("units")?.read_all()?[0].data),
+            "[String(nT)]"
+        );
+
+        let tsv = "flag\tlabel\n7\ta b\n";
+        let bytes = import(
+            &master,
+            io::Cursor::new(tsv),
+            io::Cursor::new(Vec::new()),
+            &CsvOptions::tsv(),
+        )?;
+        let cdf = Cdf::from_bytes(&bytes.into_inner())?;
+        assert_eq!(
+            format!("{:?}", cdf.variable("flag")?.read_all()?[0].data),
+            "[Int2(7)]"
+        );
+        assert_eq!(cdf.variable("Epoch")?.n_records(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_invalid() -> Result<(), CdfError> {
+        let master = _master()?;
+        for (csv, error) in [
+            ("Epoch,Bz\n", "no variable is called Bz"),
+            ("Epoch,B[0]\n", "B has 1 columns instead of 3"),
+            ("flag\nx\n", "Line 2, variable flag"),
+            ("flag,label\n1\n", "Line 2 has 1 cells instead of 2"),
+            ("label\n\"abc\n", "unclosed quote"),
+            ("label\nabcdef\n", "longer than 5"),
+        ] {
+            let result = import(
+                &master,
+                io::Cursor::new(csv),
+                io::Cursor::new(Vec::new()),
+                &CsvOptions::default(),
+            );
+            match result {
+                Err(e) => assert!(format!("{e}").contains(error), "{e}"),
+                Ok(_) => panic!("{csv} should not be imported."),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,864 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use core::cmp::Ordering;
+use core::ops::Range;
+use core::time::Duration;
+
+use crate::error::CdfError;
+use crate::record::vvr::VariableRecord;
+use crate::time::CdfTime;
+use crate::types::{
+    CdfByte, CdfEpoch, CdfInt1, CdfInt2, CdfInt4, CdfInt8, CdfReal4, CdfReal8, CdfTimeTt2000,
+    CdfType, CdfUint1, CdfUint2, CdfUint4,
+};
+use crate::variable::Variable;
+
+/// A group of variables that share the same epoch variable through their `DEPEND_0` attribute,
+/// much like a dataset in xarray. The epoch is used as the coordinate to select records, and the
+/// values of all variables are returned aligned on it.
+#[derive(Debug, Clone)]
+pub struct Dataset<'a> {
+    epoch: Variable<'a>,
+    variables: Vec<Variable<'a>>,
+    coordinates: bool,
+}
+
+/// Records selected from a [`Dataset`], with the values of each variable stored in columns that
+/// are aligned on the epoch.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Selection {
+    /// Records of the dataset that were selected.
+    pub records: Range<usize>,
+    /// Value of the epoch for each selected record.
+    pub time: Vec<CdfType>,
+    /// Values of each variable of the dataset, in the same order.
+    pub columns: Vec<Column>,
+}
+
+/// How [`resample`] computes the value of a variable at a time that lies between its records.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Take the record closest in time, or the earlier one if both are as close.
+    Nearest,
+    /// Interpolate linearly between the records before and after. Values that are not numbers,
+    /// such as strings, take the value of the closest record, and values equal to the `FILLVAL`
+    /// of the variable in either record stay `FILLVAL`.
+    Linear,
+}
+
+/// Options of [`resample`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResampleOptions {
+    /// How values between records are computed.
+    pub method: Interpolation,
+    /// Largest gap across which values are resampled. With [`Interpolation::Nearest`] this is the
+    /// largest distance to the closest record, and with [`Interpolation::Linear`] the largest
+    /// distance between the records before and after. `None` resamples across any gap.
+    pub max_gap: Option<Duration>,
+}
+
+impl Default for ResampleOptions {
+    fn default() -> Self {
+        ResampleOptions {
+            method: Interpolation::Linear,
+            max_gap: None,
+        }
+    }
+}
+
+/// Values of one variable for the records of a [`Selection`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Column {
+    /// Name of the variable.
+    pub name: String,
+    /// Shape of the values of each record, see [`Variable::record_shape`].
+    pub shape: Vec<usize>,
+    /// Values of all selected records, one record after the other. Character data contributes one
+    /// [`CdfType::String`] per record.
+    pub values: Vec<CdfType>,
+    /// Coordinates of the dimensions of the values, read from the variables named by the
+    /// `DEPEND_1`, `DEPEND_2`, ... attributes when [`Dataset::with_coordinates`] is set.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub coordinates: Vec<Coordinate>,
+}
+
+/// Values of a coordinate variable, such as energies or pitch angles, along one dimension of a
+/// [`Column`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Coordinate {
+    /// Name of the coordinate variable.
+    pub name: String,
+    /// Index of the dimension in [`Column::shape`] along which the coordinate varies.
+    pub dim: usize,
+    /// Whether the coordinate varies from record to record, rather than being the same for all.
+    pub record_varies: bool,
+    /// Values of the coordinate along the dimension for each selected record, one record after
+    /// the other. Coordinates that do not vary are repeated for every record.
+    pub values: Vec<CdfType>,
+}
+
+impl<'a> Dataset<'a> {
+    /// Create a dataset of `variables` indexed by the `epoch` variable.
+    pub fn new(epoch: Variable<'a>, variables: Vec<Variable<'a>>) -> Self {
+        Dataset {
+            epoch,
+            variables,
+            coordinates: false,
+        }
+    }
+
+    /// Also read the coordinates of the values of each variable, named by its `DEPEND_1`,
+    /// `DEPEND_2`, ... attributes, into [`Column::coordinates`] of selections.
+    pub fn with_coordinates(mut self, coordinates: bool) -> Self {
+        self.coordinates = coordinates;
+        self
+    }
+
+    /// The epoch variable that indexes this dataset.
+    pub fn epoch(&self) -> &Variable<'a> {
+        &self.epoch
+    }
+
+    /// Variables of this dataset, not including the epoch.
+    pub fn variables(&self) -> &[Variable<'a>] {
+        &self.variables
+    }
+
+    /// Number of records of this dataset, which is the number of records of the epoch.
+    pub fn n_records(&self) -> usize {
+        self.epoch.n_records()
+    }
+
+    /// Read the epoch value of every record.
+    pub fn times(&self) -> Result<Vec<CdfType>, CdfError> {
+        self.times_in(0..self.n_records())
+    }
+
+    /// Select the records whose epoch lies within `range`. The bounds must have the same type as
+    /// the epoch variable, i.e. [`CdfType::Epoch`], [`CdfType::Epoch16`] or
+    /// [`CdfType::TimeTt2000`], and the epoch must increase monotonically.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if the type of the bounds does not match the epoch, or if
+    /// values cannot be read.
+    pub fn sel_time(&self, range: Range<CdfType>) -> Result<Selection, CdfError> {
+        let times = self.times()?;
+        let start = partition(&times, &range.start)?;
+        let end = partition(&times, &range.end)?.max(start);
+        self.select(start..end, times[start..end].to_vec())
+    }
+
+    /// Select the records in `records`.
+    /// # Errors
+    /// Returns a [`CdfError`] if the range is out of bounds, or if values cannot be read.
+    pub fn sel_records(&self, records: Range<usize>) -> Result<Selection, CdfError> {
+        let time = self.times_in(records.clone())?;
+        self.select(records, time)
+    }
+
+    /// Match the records of this dataset with those of `other` whose epochs are within
+    /// `tolerance` of each other, see [`join`].
+    /// # Errors
+    /// Returns a [`CdfError`] if the epochs cannot be read.
+    pub fn join(
+        &self,
+        other: &Dataset,
+        tolerance: Duration,
+    ) -> Result<Vec<(usize, usize)>, CdfError> {
+        join(&self.epoch, &other.epoch, tolerance)
+    }
+
+    fn times_in(&self, records: Range<usize>) -> Result<Vec<CdfType>, CdfError> {
+        epoch_values(&self.epoch, records)
+    }
+
+    fn select(&self, records: Range<usize>, time: Vec<CdfType>) -> Result<Selection, CdfError> {
+        let columns = self
+            .variables
+            .iter()
+            .map(|v| {
+                let mut column = column(v, records.clone())?;
+                if self.coordinates {
+                    column.coordinates = coordinates(v, records.clone())?;
+                }
+                Ok(column)
+            })
+            .collect::<Result<_, CdfError>>()?;
+        Ok(Selection {
+            records,
+            time,
+            columns,
+        })
+    }
+}
+
+impl Selection {
+    /// Number of selected records.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether no record was selected.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// The column of the variable called `name`.
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+}
+
+/// Resample `variable` onto the times of the `epoch` variable, such as the epoch of another
+/// instrument with a different cadence. The times of `variable` are read from the epoch named by
+/// its `DEPEND_0` attribute, and may be of a different time type than `epoch`, which must increase
+/// monotonically. One record is returned for each record of `epoch`, or `None` where the time is
+/// a fill value, lies outside the records of `variable` when interpolating linearly, or is further
+/// than [`ResampleOptions::max_gap`] from them. Variables that do not vary from record to record
+/// return their only record at every time, and variables without records return `None`.
+/// # Errors
+/// Returns a [`CdfError::Other`] if `variable` has no `DEPEND_0` epoch, if either epoch does not
+/// hold times, or if values cannot be read.
+pub fn resample(
+    variable: &Variable,
+    epoch: &Variable,
+    options: &ResampleOptions,
+) -> Result<Vec<Option<VariableRecord>>, CdfError> {
+    let targets = epoch_values(epoch, 0..epoch.n_records())?
+        .iter()
+        .map(unix_nanos)
+        .collect::<Result<Vec<_>, _>>()?;
+    if !variable.record_varies() {
+        // A variable without records has no value to give to the targets.
+        let stored = 0..variable.n_records().min(1);
+        let record = variable.read_records(stored)?.into_iter().next();
+        return Ok(targets.iter().map(|t| t.and(record.clone())).collect());
+    }
+
+    let source = variable.companion("DEPEND_0").ok_or_else(|| {
+        CdfError::Other(format!(
+            "Variable {} has no epoch named by DEPEND_0.",
+            variable.name()
+        ))
+    })?;
+    let records = source.n_records().min(variable.n_records());
+    let mut times = Vec::with_capacity(records);
+    for (record, time) in epoch_values(&source, 0..records)?.iter().enumerate() {
+        if let Some(time) = unix_nanos(time)? {
+            times.push((time, record));
+        }
+    }
+
+    let max_gap = options
+        .max_gap
+        .map(|gap| i128::try_from(gap.as_nanos()).unwrap_or(i128::MAX));
+    let within = |gap: i128| max_gap.is_none_or(|max| gap <= max);
+    let samples: Vec<Sample> = targets
+        .iter()
+        .map(|target| {
+            let Some(target) = *target else {
+                return Sample::Missing;
+            };
+            let after = times.partition_point(|(t, _)| *t < target);
+            let before = after.checked_sub(1).map(|i| times[i]);
+            let after = times.get(after).copied();
+            match (options.method, before, after) {
+                (_, _, Some((t, record))) if t == target => Sample::Record(record),
+                (Interpolation::Linear, Some((t0, r0)), Some((t1, r1))) if within(t1 - t0) => {
+                    Sample::Between(r0, r1, (target - t0) as f64 / (t1 - t0) as f64)
+                }
+                (Interpolation::Linear, _, _) => Sample::Missing,
+                (Interpolation::Nearest, before, after) => {
+                    let nearest = match (before, after) {
+                        (Some((t0, r0)), Some((t1, _))) if target - t0 <= t1 - target => {
+                            Some((target - t0, r0))
+                        }
+                        (_, Some((t1, r1))) => Some((t1 - target, r1)),
+                        (Some((t0, r0)), None) => Some((target - t0, r0)),
+                        (None, None) => None,
+                    };
+                    match nearest {
+                        Some((gap, record)) if within(gap) => Sample::Record(record),
+                        _ => Sample::Missing,
+                    }
+                }
+            }
+        })
+        .collect();
+
+    let used = samples.iter().flat_map(|sample| match *sample {
+        Sample::Missing => None,
+        Sample::Record(record) => Some(record..record + 1),
+        Sample::Between(r0, r1, _) => Some(r0..r1 + 1),
+    });
+    let Some(range) = used.reduce(|a, b| a.start.min(b.start)..a.end.max(b.end)) else {
+        return Ok(samples.iter().map(|_| None).collect());
+    };
+    let read = variable.read_records(range.clone())?;
+    let fill = variable.attribute("FILLVAL").and_then(|f| f.first());
+    Ok(samples
+        .iter()
+        .map(|sample| match *sample {
+            Sample::Missing => None,
+            Sample::Record(record) => Some(read[record - range.start].clone()),
+            Sample::Between(r0, r1, weight) => {
+                let (a, b) = (&read[r0 - range.start], &read[r1 - range.start]);
+                let data = a
+                    .data
+                    .iter()
+                    .zip(&b.data)
+                    .map(|(x, y)| match fill {
+                        Some(fill) if is_fill(x, fill) || is_fill(y, fill) => fill.clone(),
+                        _ => interpolate(x, y, weight),
+                    })
+                    .collect();
+                Some(VariableRecord {
+                    data_type: a.data_type.clone(),
+                    data_len: a.data_len.clone(),
+                    data,
+                })
+            }
+        })
+        .collect())
+}
+
+/// Match the records of two epoch variables, such as those of two instruments in different files,
+/// whose times are within `tolerance` of each other. Each record is matched at most once, to the
+/// closest record of the other epoch, and the pairs of record numbers are returned in increasing
+/// order of both. Both epochs must increase monotonically but may be of different time types.
+/// Records whose time is a fill value are not matched.
+/// # Errors
+/// Returns a [`CdfError::Other`] if either variable does not hold times, or if values cannot be
+/// read.
+pub fn join(
+    left: &Variable,
+    right: &Variable,
+    tolerance: Duration,
+) -> Result<Vec<(usize, usize)>, CdfError> {
+    let times = |epoch: &Variable| -> Result<Vec<(i128, usize)>, CdfError> {
+        let mut times = Vec::with_capacity(epoch.n_records());
+        for (record, time) in epoch_values(epoch, 0..epoch.n_records())?
+            .iter()
+            .enumerate()
+        {
+            if let Some(time) = unix_nanos(time)? {
+                times.push((time, record));
+            }
+        }
+        Ok(times)
+    };
+    let (left, right) = (times(left)?, times(right)?);
+    let tolerance = i128::try_from(tolerance.as_nanos()).unwrap_or(i128::MAX);
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while let (Some(&(l, left_record)), Some(&(r, right_record))) = (left.get(i), right.get(j)) {
+        if r < l.saturating_sub(tolerance) {
+            j += 1;
+        } else if l < r.saturating_sub(tolerance) {
+            i += 1;
+        } else if right
+            .get(j + 1)
+            .is_some_and(|(next, _)| (next - l).abs() < (r - l).abs())
+        {
+            // The next record of the right epoch is a closer match.
+            j += 1;
+        } else if left
+            .get(i + 1)
+            .is_some_and(|(next, _)| (next - r).abs() < (l - r).abs())
+        {
+            i += 1;
+        } else {
+            pairs.push((left_record, right_record));
+            i += 1;
+            j += 1;
+        }
+    }
+    Ok(pairs)
+}
+
+// Where the value of a resampled record comes from.
+#[derive(Clone, Copy)]
+enum Sample {
+    Missing,
+    Record(usize),
+    // The records before and after, and how far the time lies between them.
+    Between(usize, usize, f64),
+}
+
+// Value at `weight` of the way from `a` to `b`, or the closest of both if they are not numbers.
+fn interpolate(a: &CdfType, b: &CdfType, weight: f64) -> CdfType {
+    macro_rules! lerp {
+        (
+            integers: $($int:ident($int_type:ident, $int_rust:ty)),*;
+            reals: $($real:ident($real_type:ident, $real_rust:ty)),*
+        ) => {
+            match (a, b) {
+                $((CdfType::$int(x), CdfType::$int(y)) => {
+                    let (x, y) = (**x as i128, **y as i128);
+                    let step = (y - x) as f64 * weight;
+                    // Round half away from zero, which `as` alone would truncate.
+                    let step = if step < 0.0 { step - 0.5 } else { step + 0.5 } as i128;
+                    CdfType::$int($int_type::from((x + step) as $int_rust))
+                })*
+                $((CdfType::$real(x), CdfType::$real(y)) => {
+                    let (x, y) = (**x as f64, **y as f64);
+                    CdfType::$real($real_type::from((x + (y - x) * weight) as $real_rust))
+                })*
+                _ if weight <= 0.5 => a.clone(),
+                _ => b.clone(),
+            }
+        };
+    }
+    lerp!(
+        integers: Int1(CdfInt1, i8), Int2(CdfInt2, i16), Int4(CdfInt4, i32), Int8(CdfInt8, i64),
+            Uint1(CdfUint1, u8), Uint2(CdfUint2, u16), Uint4(CdfUint4, u32), Byte(CdfByte, i8),
+            TimeTt2000(CdfTimeTt2000, i64);
+        reals: Real4(CdfReal4, f32), Real8(CdfReal8, f64), Epoch(CdfEpoch, f64)
+    )
+}
+
+fn is_fill(value: &CdfType, fill: &CdfType) -> bool {
+    format!("{value:?}") == format!("{fill:?}")
+}
+
+// First value of each record of `epoch` in `records`.
+fn epoch_values(epoch: &Variable, records: Range<usize>) -> Result<Vec<CdfType>, CdfError> {
+    epoch
+        .read_records(records)?
+        .into_iter()
+        .map(|r| {
+            r.data
+                .into_iter()
+                .next()
+                .ok_or_else(|| CdfError::Other(format!("Epoch {} has no values.", epoch.name())))
+        })
+        .collect()
+}
+
+// Nanoseconds since the Unix epoch of a time value, or `None` for fill values.
+pub(crate) fn unix_nanos(time: &CdfType) -> Result<Option<i128>, CdfError> {
+    match time {
+        CdfType::Epoch(t) => Ok(t.to_unix_nanos()),
+        CdfType::Epoch16(t) => Ok(t.to_unix_nanos()),
+        CdfType::TimeTt2000(t) => Ok(t.to_unix_nanos()),
+        other => Err(CdfError::Other(format!("Value {other:?} is not a time."))),
+    }
+}
+
+// Read the values of `variable` in `records`. Records past the last one of the variable are
+// filled with its pad record, so that all columns are aligned on the epoch.
+fn column(variable: &Variable, records: Range<usize>) -> Result<Column, CdfError> {
+    let stored = records.start.min(variable.n_records())..records.end.min(variable.n_records());
+    let missing = records.len() - stored.len();
+    let mut values = Vec::new();
+    for record in variable.read_records(stored)? {
+        values.extend(record.data);
+    }
+    if missing > 0 {
+        let pad = variable.pad_record()?;
+        for _ in 0..missing {
+            values.extend(pad.data.iter().cloned());
+        }
+    }
+    Ok(Column {
+        name: variable.name().to_string(),
+        shape: variable.record_shape()?,
+        values,
+        coordinates: Vec::new(),
+    })
+}
+
+// Read the coordinates named by `DEPEND_i` for each dimension `i` of `variable` that varies, and
+// repeat those that do not vary from record to record for every record in `records`.
+fn coordinates(variable: &Variable, records: Range<usize>) -> Result<Vec<Coordinate>, CdfError> {
+    let mut coordinates = Vec::new();
+    for (dim, size) in variable.shape()?.into_iter().enumerate() {
+        let Some(coordinate) = variable.companion(&format!("DEPEND_{}", dim + 1)) else {
+            continue;
+        };
+        let record_varies = coordinate.record_varies();
+        let values = if record_varies {
+            column(&coordinate, records.clone())?.values
+        } else {
+            let first = match coordinate.n_records() {
+                0 => Vec::new(),
+                _ => coordinate.read_records(0..1)?.swap_remove(0).data,
+            };
+            if first.len() != size {
+                return Err(coordinate_error(variable, &coordinate, dim, size));
+            }
+            records
+                .clone()
+                .flat_map(|_| first.iter().cloned())
+                .collect()
+        };
+        if values.len() != records.len() * size {
+            return Err(coordinate_error(variable, &coordinate, dim, size));
+        }
+        coordinates.push(Coordinate {
+            name: coordinate.name().to_string(),
+            dim,
+            record_varies,
+            values,
+        });
+    }
+    Ok(coordinates)
+}
+
+fn coordinate_error(
+    variable: &Variable,
+    coordinate: &Variable,
+    dim: usize,
+    size: usize,
+) -> CdfError {
+    CdfError::Other(format!(
+        "Coordinate {} does not hold {size} values for dimension {} of {}.",
+        coordinate.name(),
+        dim + 1,
+        variable.name()
+    ))
+}
+
+// Index of the first time that is not before `bound`.
+fn partition(times: &[CdfType], bound: &CdfType) -> Result<usize, CdfError> {
+    let (mut low, mut high) = (0, times.len());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if compare_time(&times[mid], bound)? == Ordering::Less {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(low)
+}
+
+fn compare_time(a: &CdfType, b: &CdfType) -> Result<Ordering, CdfError> {
+    let ordering = match (a, b) {
+        (CdfType::Epoch(a), CdfType::Epoch(b)) => a.compare(b),
+        (CdfType::Epoch16(a), CdfType::Epoch16(b)) => a.compare(b),
+        (CdfType::TimeTt2000(a), CdfType::TimeTt2000(b)) => a.compare(b),
+        (a, b) => {
+            return Err(CdfError::Other(format!(
+                "Cannot compare time {a:?} with {b:?} - the types differ or are not times."
+            )))
+        }
+    };
+    ordering.ok_or_else(|| CdfError::Other(format!("Cannot compare time {a:?} with {b:?}.")))
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+
+    use crate::cdf::Cdf;
+    use crate::error::CdfError;
+    use crate::io;
+    use crate::types::{CdfEpoch, CdfInt4, DataType};
+    use crate::writer::{CdfWriter, VariableSpec};
+    use std::path::PathBuf;
+    use std::string::ToString;
+    use std::vec;
+
+    use super::*;
+
+    fn _ulysses() -> Result<Cdf, CdfError> {
+        let path: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "ulysses.cdf",
+        ]
+        .iter()
+        .collect();
+        Cdf::read_cdf_file(&path)
+    }
+
+    #[test]
+    fn test_datasets() -> Result<(), CdfError> {
+        let cdf = _ulysses()?;
+        let datasets = cdf.datasets();
+        assert_eq!(datasets.len(), 1);
+        assert_eq!(datasets[0].epoch().name(), "Epoch");
+        assert_eq!(datasets[0].variables().len(), 11);
+        assert!(cdf.dataset("Time_PB5").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sel_time() -> Result<(), CdfError> {
+        let cdf = _ulysses()?;
+        let dataset = cdf.dataset("Epoch")?;
+        let times = dataset.sel_records(10..20)?.time;
+
+        let selection = dataset.sel_time(times[0].clone()..times[9].clone())?;
+        assert_eq!(selection.records, 10..19);
+        assert_eq!(selection.len(), 9);
+        for column in &selection.columns {
+            let values_per_record = cdf.variable(&column.name)?.values_per_record()?;
+            assert_eq!(column.values.len(), 9 * values_per_record);
+        }
+
+        let expected = cdf.variable("B_scalar")?.read_records(10..19)?;
+        let column = selection
+            .column("B_scalar")
+            .expect("B_scalar is in the dataset");
+        for (record, value) in expected.iter().zip(column.values.iter()) {
+            assert_eq!(format!("{:?}", record.data[0]), format!("{value:?}"));
+        }
+
+        let before = CdfType::Epoch(CdfEpoch::from(0.0));
+        assert!(dataset.sel_time(before.clone()..before)?.is_empty());
+        let wrong = CdfType::Int4(CdfInt4::from(0));
+        assert!(dataset.sel_time(wrong.clone()..wrong).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_resample() -> Result<(), CdfError> {
+        let mut cdf = _ulysses()?;
+        // The epoch shifted by half an hour and by six minutes, between its hourly records.
+        for (name, offset) in [("Epoch_half", 0.5), ("Epoch_tenth", 0.1)] {
+            cdf.register_virtual(name, &["Epoch"], move |inputs| {
+                let CdfType::Epoch(epoch) = &inputs[0][0] else {
+                    return Err(CdfError::Other("Epoch is not an Epoch.".to_string()));
+                };
+                let shifted = **epoch + offset * 3_600_000.0;
+                Ok(vec![CdfType::Epoch(CdfEpoch::from(shifted))])
+            })?;
+        }
+        let b_scalar = cdf.variable("B_scalar")?;
+        let times = cdf.variable("Epoch")?.read_records(0..100)?;
+        let values = b_scalar.read_records(0..100)?;
+        let hour = |i: usize| match (&times[i].data[0], &times[i + 1].data[0]) {
+            (CdfType::Epoch(a), CdfType::Epoch(b)) => **b - **a,
+            _ => unreachable!(),
+        };
+        let real = |v: &CdfType| match v {
+            CdfType::Real4(v) => **v,
+            _ => unreachable!(),
+        };
+
+        let tenth = cdf.variable("Epoch_tenth")?;
+        let nearest = ResampleOptions {
+            method: Interpolation::Nearest,
+            max_gap: None,
+        };
+        let resampled = resample(&b_scalar, &tenth, &nearest)?;
+        assert_eq!(resampled.len(), b_scalar.n_records());
+        for i in 0..99 {
+            let record = resampled[i].as_ref().expect("a record is close");
+            assert_eq!(
+                format!("{:?}", record.data),
+                format!("{:?}", values[i].data)
+            );
+        }
+        let close = ResampleOptions {
+            max_gap: Some(Duration::from_secs(60)),
+            ..nearest
+        };
+        assert!(resample(&b_scalar, &tenth, &close)?[..99]
+            .iter()
+            .all(Option::is_none));
+
+        let fill = real(
+            &b_scalar
+                .attribute("FILLVAL")
+                .expect("B_scalar has a FILLVAL")[0],
+        );
+        let half = cdf.variable("Epoch_half")?;
+        let resampled = resample(&b_scalar, &half, &ResampleOptions::default())?;
+        let mut interpolated = 0;
+        for i in 0..99 {
+            let (a, b) = (real(&values[i].data[0]), real(&values[i + 1].data[0]));
+            let weight = 1_800_000.0 / hour(i);
+            let value = real(&resampled[i].as_ref().expect("within the records").data[0]);
+            if a == fill || b == fill {
+                assert_eq!(value, fill);
+            } else if weight <= 1.0 {
+                assert!((value - (a + (b - a) * weight as f32)).abs() < 1e-3);
+                interpolated += 1;
+            }
+        }
+        assert!(interpolated > 0);
+        assert!(resampled.last().is_some_and(Option::is_none));
+
+        let gaps = ResampleOptions {
+            max_gap: Some(Duration::from_secs(1800)),
+            ..Default::default()
+        };
+        let resampled = resample(&b_scalar, &half, &gaps)?;
+        for (i, record) in resampled.iter().take(99).enumerate() {
+            assert_eq!(record.is_some(), hour(i) <= 1_800_000.0);
+        }
+
+        assert!(resample(&cdf.variable("Epoch")?, &half, &nearest).is_err());
+        assert!(resample(&b_scalar, &b_scalar, &nearest).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_join() -> Result<(), CdfError> {
+        let mut cdf = _ulysses()?;
+        for (name, offset) in [("Epoch_tenth", 0.1), ("Epoch_late", 0.9)] {
+            cdf.register_virtual(name, &["Epoch"], move |inputs| {
+                let CdfType::Epoch(epoch) = &inputs[0][0] else {
+                    return Err(CdfError::Other("Epoch is not an Epoch.".to_string()));
+                };
+                let shifted = **epoch + offset * 3_600_000.0;
+                Ok(vec![CdfType::Epoch(CdfEpoch::from(shifted))])
+            })?;
+        }
+        let epoch = cdf.variable("Epoch")?;
+        let n_records = epoch.n_records();
+
+        let tenth = cdf.variable("Epoch_tenth")?;
+        let pairs = join(&epoch, &tenth, Duration::from_secs(600))?;
+        assert_eq!(pairs, (0..n_records).map(|i| (i, i)).collect::<Vec<_>>());
+        assert!(join(&epoch, &tenth, Duration::from_secs(60))?.is_empty());
+        let dataset = cdf.dataset("Epoch")?;
+        assert_eq!(dataset.join(&dataset, Duration::ZERO)?.len(), n_records);
+
+        // Shifted by most of an hour, each record is closer to the next record of the epoch.
+        let late = cdf.variable("Epoch_late")?;
+        let pairs = join(&epoch, &late, Duration::from_secs(7200))?;
+        assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1));
+        assert!(pairs.iter().filter(|(l, r)| *l == r + 1).count() > n_records / 2);
+        assert!(join(&epoch, &cdf.variable("B_scalar")?, Duration::ZERO).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_selection_serde() -> Result<(), CdfError> {
+        let cdf = _ulysses()?;
+        let selection = cdf.dataset("Epoch")?.sel_records(0..100)?;
+        let expected = format!("{selection:?}");
+
+        let cbor = serde_cbor::to_vec(&selection).map_err(|e| CdfError::Other(e.to_string()))?;
+        let from_cbor: Selection =
+            serde_cbor::from_slice(&cbor).map_err(|e| CdfError::Other(e.to_string()))?;
+        assert_eq!(format!("{from_cbor:?}"), expected);
+
+        let msgpack = rmp_serde::to_vec(&selection).map_err(|e| CdfError::Other(e.to_string()))?;
+        let from_msgpack: Selection =
+            rmp_serde::from_slice(&msgpack).map_err(|e| CdfError::Other(e.to_string()))?;
+        assert_eq!(format!("{from_msgpack:?}"), expected);
+
+        let json =
+            serde_json::to_vec_pretty(&selection).map_err(|e| CdfError::Other(e.to_string()))?;
+        assert!(cbor.len() < json.len() && msgpack.len() < json.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dataset_coordinates() -> Result<(), CdfError> {
+        let real = |v: f32| CdfType::Real4(CdfReal4::from(v));
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("Epoch", DataType::Epoch))
+            .variable(VariableSpec::new("flux", DataType::Real4).dims(&[3, 2]))
+            .variable(VariableSpec::new("energy", DataType::Real4).dims(&[3]))
+            .variable(VariableSpec::new("pitch", DataType::Real4).dims(&[2]))
+            .build(io::Cursor::new(Vec::new()))?;
+        let times: Vec<_> = (0..3)
+            .map(|s| vec![CdfType::Epoch(CdfEpoch::from(6.3e13 + f64::from(s)))])
+            .collect();
+        writer.write_records("Epoch", &times)?;
+        let flux: Vec<_> = (0..3)
+            .map(|r| (0..6).map(|i| real((r * 6 + i) as f32)).collect())
+            .collect();
+        writer.write_records("flux", &flux)?;
+        writer.write_records("energy", &[vec![real(10.0), real(20.0), real(40.0)]])?;
+        let pitch: Vec<_> = (0..2)
+            .map(|r| vec![real(45.0 * r as f32), real(90.0 + 45.0 * r as f32)])
+            .collect();
+        writer.write_records("pitch", &pitch)?;
+        writer.var_attr("flux", "DEPEND_0", "Epoch")?;
+        writer.var_attr("flux", "DEPEND_1", "energy")?;
+        writer.var_attr("flux", "DEPEND_2", "pitch")?;
+        writer.var_attr("energy", "DEPEND_1", "pitch")?;
+        let bytes = writer.close()?.into_inner();
+        let mut cdf = Cdf::from_bytes(&bytes)?;
+        cdf.cdr.gdr.zvdr_vec[2].flags.variance = false;
+
+        let dataset = cdf.dataset("Epoch")?;
+        assert!(dataset.sel_records(0..3)?.columns[0].coordinates.is_empty());
+        let selection = dataset.with_coordinates(true).sel_records(1..3)?;
+        let column = selection.column("flux").expect("flux is in the dataset");
+        assert_eq!(column.shape, [3, 2]);
+        let [energy, pitch] = column.coordinates.as_slice() else {
+            panic!("Expected two coordinates, got {:?}", column.coordinates);
+        };
+        assert_eq!((energy.name.as_str(), energy.dim), ("energy", 0));
+        assert!(!energy.record_varies);
+        assert_eq!(
+            format!("{:?}", energy.values),
+            format!("{:?}", [10.0, 20.0, 40.0, 10.0, 20.0, 40.0].map(real))
+        );
+        assert_eq!((pitch.name.as_str(), pitch.dim), ("pitch", 1));
+        assert!(pitch.record_varies);
+        // Records past the last one of the coordinate are padded like the data.
+        let pad = cdf.variable("pitch")?.pad_record()?.data;
+        assert_eq!(
+            format!("{:?}", pitch.values),
+            format!("{:?}", [vec![real(45.0), real(135.0)], pad].concat())
+        );
+
+        // Coordinates must hold one value per value along their dimension.
+        let energy = cdf.variable("energy")?;
+        let dataset = Dataset::new(cdf.variable("Epoch")?, vec![energy]).with_coordinates(true);
+        assert!(dataset.sel_records(0..1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dataset_without_records() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("Epoch", DataType::Epoch))
+            .variable(VariableSpec::new("empty_epoch", DataType::Epoch))
+            .variable(VariableSpec::new("B", DataType::Real8))
+            .build(io::Cursor::new(Vec::new()))?;
+        let times: Vec<_> = (0..3)
+            .map(|s| {
+                vec![CdfType::Epoch(CdfEpoch::from(
+                    6.3e13 + 1000.0 * f64::from(s),
+                ))]
+            })
+            .collect();
+        writer.write_records("Epoch", &times)?;
+        writer.var_attr("B", "DEPEND_0", "Epoch")?;
+        let bytes = writer.close()?.into_inner();
+        let mut cdf = Cdf::from_bytes(&bytes)?;
+
+        let empty = Dataset::new(cdf.variable("empty_epoch")?, vec![cdf.variable("B")?]);
+        assert_eq!(empty.n_records(), 0);
+        assert!(empty.times()?.is_empty());
+        assert!(empty.sel_records(0..0)?.is_empty());
+        let selection = empty.sel_time(times[0][0].clone()..times[2][0].clone())?;
+        assert!(selection.is_empty());
+        assert_eq!(selection.column("B").map(|c| c.values.len()), Some(0));
+
+        // Variables without records have no values to resample, whether they vary or not.
+        for varies in [true, false] {
+            cdf.cdr.gdr.zvdr_vec[2].flags.variance = varies;
+            let resampled = resample(
+                &cdf.variable("B")?,
+                &cdf.variable("Epoch")?,
+                &ResampleOptions::default(),
+            )?;
+            assert_eq!(resampled.len(), 3);
+            assert!(resampled.iter().all(Option::is_none));
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,14 @@
-use std::io;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-use crate::error::CdfError;
+use alloc::{collections::BTreeMap, format, string::String, sync::Arc, vec, vec::Vec};
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::io::{self, SeekFrom};
+
+use crate::error::{CdfError, ShapeError};
+use crate::record::cpr::CdfCompressionKind;
 use crate::repr::{CdfEncoding, CdfVersion, Endian};
 use crate::types::{CdfInt4, CdfInt8};
 
@@ -30,7 +38,8 @@ pub trait Decodable: Sized {
     where
         R: io::Read + io::Seek,
     {
-        let n = usize::try_from(**num_elements)?;
+        let n = checked_usize("num_elements", **num_elements)?;
+        decoder.check_alloc(n.saturating_mul(core::mem::size_of::<Self>()))?;
         let mut result: Vec<Self> = Vec::with_capacity(n);
         for _ in 0..n {
             result.push(Self::decode_be(decoder)?);
@@ -46,7 +55,8 @@ pub trait Decodable: Sized {
     where
         R: io::Read + io::Seek,
     {
-        let n = usize::try_from(**num_elements)?;
+        let n = checked_usize("num_elements", **num_elements)?;
+        decoder.check_alloc(n.saturating_mul(core::mem::size_of::<Self>()))?;
         let mut result: Vec<Self> = Vec::with_capacity(n);
         for _ in 0..n {
             result.push(Self::decode_le(decoder)?);
@@ -64,25 +74,675 @@ where
     pub reader: R,
     /// Context keeps track of values that are needed by other records for decoding.
     pub context: DecodeContext,
+    /// User-supplied options that control how the CDF is decoded.
+    pub options: DecodeOptions,
 }
 
 impl<R> Decoder<R>
 where
     R: io::Read + io::Seek,
 {
-    /// Create a new decoder based on some reader than implements [`io::Read`] and a CDF encoding.
+    /// Create a new decoder based on some reader than implements [`io::Read`]. The CDF version and
+    /// encoding do not need to be known beforehand, they are bootstrapped from the magic numbers
+    /// and the CDR while decoding.
     /// # Errors
     /// Returns a [`CdfError`] if the decoder cannot be constructed.
     pub fn new(reader: R) -> Result<Self, CdfError> {
+        Decoder::builder().build(reader)
+    }
+
+    /// Create a new decoder that decodes values using `encoding`, ignoring the encoding announced
+    /// in the CDR. This is only useful for files that were written with an incorrect encoding.
+    /// # Errors
+    /// Returns a [`CdfError`] if the decoder cannot be constructed.
+    pub fn with_encoding(reader: R, encoding: CdfEncoding) -> Result<Self, CdfError> {
+        Decoder::builder().encoding(encoding).build(reader)
+    }
+
+    /// Create a decoder over another reader that shares the context and options of this decoder.
+    /// This is used to decode data that has been decompressed into memory.
+    pub fn nested<S>(&self, reader: S) -> Decoder<S>
+    where
+        S: io::Read + io::Seek,
+    {
+        // Offsets within the other reader are not checked against the size of this file.
+        let mut context = self.context.clone();
+        context.file_size = None;
+        context.eof = None;
+        Decoder {
+            reader,
+            context,
+            options: self.options.clone(),
+        }
+    }
+
+    /// Check that an allocation of `num_bytes` requested while decoding is within the limit set
+    /// in [`DecodeOptions::max_alloc`]. Sizes read from a corrupt file can otherwise lead to
+    /// absurdly large allocations.
+    /// # Errors
+    /// Returns a [`CdfError::Decode`] if the allocation exceeds the limit.
+    pub fn check_alloc(&self, num_bytes: usize) -> Result<(), CdfError> {
+        match self.options.max_alloc {
+            Some(max) if num_bytes > max => Err(CdfError::Decode(format!(
+                "Refusing to allocate {num_bytes} bytes, the limit is {max} bytes."
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Keep the exact bytes of the record that starts at the current position of the reader in
+    /// [`DecodeContext::raw_records`], if [`DecodeOptions::keep_raw`] is set. The position of the
+    /// reader is unchanged.
+    /// # Errors
+    /// Returns a [`CdfError`] if the record cannot be read.
+    pub fn keep_raw_record(&mut self) -> Result<(), CdfError> {
+        if !self.options.keep_raw {
+            return Ok(());
+        }
+        let offset = self.reader.stream_position()?;
+        let record_size = checked_usize("record_size", *decode_version3_int4_int8(self)?)?;
+        self.check_alloc(record_size)?;
+        let mut bytes = vec![0; record_size];
+        _ = self.reader.seek(SeekFrom::Start(offset))?;
+        self.reader.read_exact(&mut bytes)?;
+        _ = self.reader.seek(SeekFrom::Start(offset))?;
+        _ = self.context.raw_records.insert(offset, bytes);
+        Ok(())
+    }
+
+    /// Report progress to the callback set in [`DecodeOptions::progress`], if any, after a record
+    /// of type `record_type` has been decoded.
+    /// # Errors
+    /// Returns a [`CdfError::Io`] if the position of the reader cannot be determined, or a
+    /// [`CdfError::Cancelled`] if the decode was cancelled.
+    pub fn report_progress(&mut self, record_type: &CdfInt4) -> Result<(), CdfError> {
+        self.check_cancelled()?;
+        if let Some(progress) = &self.options.progress {
+            progress(DecodeProgress {
+                bytes_processed: self.reader.stream_position()?,
+                record_type: **record_type,
+                variable: self.context.variable_name.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Check whether the token set in [`DecodeOptions::cancel`], if any, was cancelled. This is
+    /// called after each record, and after each variable record of the values being read.
+    /// # Errors
+    /// Returns a [`CdfError::Cancelled`] if the token was cancelled.
+    pub fn check_cancelled(&self) -> Result<(), CdfError> {
+        match &self.options.cancel {
+            Some(cancel) if cancel.is_cancelled() => Err(CdfError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// Start decoding the record at the current position of the reader, whose offset is returned
+    /// and kept in [`DecodeContext::record_start`].
+    /// # Errors
+    /// Returns a [`CdfError::Io`] if the position of the reader cannot be determined.
+    pub fn begin_record(&mut self) -> Result<u64, CdfError> {
+        let start = self.reader.stream_position()?;
+        self.context.record_start = Some(start);
+        Ok(start)
+    }
+
+    /// Seek to `offset`, a file offset read from the file, such as the location of the next record
+    /// of a linked list or of a child of a VXR, after checking that it points within the file.
+    /// # Errors
+    /// Returns a [`CdfError::OffsetOutOfBounds`] if `offset` is negative or past the end of the
+    /// file, or past its end according to the GDR, or a [`CdfError::Truncated`] if it is past the
+    /// end of the file but not past the end according to the GDR.
+    pub fn seek_offset(&mut self, offset: i64) -> Result<u64, CdfError> {
+        let file_size = match self.context.file_size {
+            Some(file_size) => file_size,
+            None => self.reader.seek(SeekFrom::End(0))?,
+        };
+        self.context.file_size = Some(file_size);
+        let end = self.context.eof.unwrap_or(file_size);
+        match u64::try_from(offset) {
+            Ok(start) if start < end.min(file_size) => {
+                _ = self.reader.seek(SeekFrom::Start(start))?;
+                Ok(start)
+            }
+            Ok(start) if start < end => Err(CdfError::Truncated {
+                file_size,
+                offset: start,
+                record_type: None,
+                missing: None,
+            }),
+            _ => Err(CdfError::OffsetOutOfBounds {
+                offset,
+                end: end.min(file_size),
+            }),
+        }
+    }
+
+    /// Turn an error caused by the file ending in the middle of the record that was being decoded
+    /// into a [`CdfError::Truncated`], which tells where the file ends and how many bytes of the
+    /// record are missing. Other errors are returned unchanged.
+    pub fn explain_truncation(&mut self, error: CdfError) -> CdfError {
+        let (CdfError::Io(e), Some(offset)) = (&error, self.context.record_start) else {
+            return error;
+        };
+        if e.kind() != io::ErrorKind::UnexpectedEof {
+            return error;
+        }
+        let Ok(file_size) = self.reader.seek(SeekFrom::End(0)) else {
+            return error;
+        };
+        // The size and type of the record are only known if its header is in the file.
+        let header = self
+            .reader
+            .seek(SeekFrom::Start(offset))
+            .ok()
+            .and_then(|_| decode_version3_int4_int8(self).ok())
+            .zip(CdfInt4::decode_be(self).ok());
+        let (missing, record_type) = match header {
+            Some((size, record_type)) => (
+                u64::try_from(*size)
+                    .ok()
+                    .map(|size| (offset + size).saturating_sub(file_size)),
+                Some(*record_type),
+            ),
+            None => (None, None),
+        };
+        CdfError::Truncated {
+            file_size,
+            offset,
+            record_type,
+            missing,
+        }
+    }
+
+    /// Check that the record that starts at offset `start` was decoded up to the end given by its
+    /// `record_size`, and move the reader to that end. Bytes left at the end of the record are
+    /// reported as a [`DecodeWarning::RecordSizeMismatch`] and skipped, so that decoding carries
+    /// on at the next record instead of misaligned within this one.
+    /// # Errors
+    /// Returns a [`CdfError::Decode`] if decoding read past the end of the record and the decoder
+    /// is strict, since the values read are then wrong.
+    pub fn finish_record(
+        &mut self,
+        start: u64,
+        record_size: &CdfInt8,
+        record_type: &CdfInt4,
+    ) -> Result<(), CdfError> {
+        let record_size = u64::try_from(**record_size)?;
+        let end = start.checked_add(record_size).ok_or_else(|| {
+            CdfError::Decode(format!(
+                "Record of {record_size} bytes at offset {start} is too large."
+            ))
+        })?;
+        let consumed = self.reader.stream_position()?.saturating_sub(start);
+        if consumed == record_size {
+            return Ok(());
+        }
+        if consumed > record_size && self.options.strict {
+            return Err(CdfError::Decode(format!(
+                "Record of type {} at offset {start} was read for {consumed} bytes, past its size \
+                 of {record_size} bytes.",
+                **record_type
+            )));
+        }
+        self.report_warning(DecodeWarning::RecordSizeMismatch {
+            offset: start,
+            record_type: **record_type,
+            record_size,
+            consumed,
+        });
+        _ = self.reader.seek(SeekFrom::Start(end))?;
+        Ok(())
+    }
+
+    /// Enter a lower level of nested records, such as the child VXR of a VXR.
+    /// # Errors
+    /// Returns a [`CdfError::MaxDepthExceeded`] if this goes past [`DecodeOptions::max_depth`].
+    pub fn descend(&mut self) -> Result<(), CdfError> {
+        if self.context.depth >= self.options.max_depth {
+            return Err(CdfError::MaxDepthExceeded {
+                max_depth: self.options.max_depth,
+            });
+        }
+        self.context.depth += 1;
+        Ok(())
+    }
+
+    /// Leave a level of nested records entered with [`Decoder::descend`].
+    pub fn ascend(&mut self) {
+        self.context.depth = self.context.depth.saturating_sub(1);
+    }
+
+    /// Collect a warning in [`DecodeContext::warnings`], and report it to the callback set in
+    /// [`DecodeOptions::warning`], if any.
+    pub fn report_warning(&mut self, warning: DecodeWarning) {
+        if let Some(callback) = &self.options.warning {
+            callback(warning.clone());
+        }
+        self.context.warnings.push(warning);
+    }
+}
+
+/// Problem found while decoding that does not prevent the file from being read. Warnings are
+/// collected in [`crate::cdf::Cdf::warnings`], and passed to the callback set in
+/// [`DecodeOptions::warning`] as they are found.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecodeWarning {
+    /// The file was written with a leap second table that is newer than the table loaded by this
+    /// library, so TT2000 values after the last known leap second may be converted incorrectly.
+    /// Dates are stored as YYYYMMDD.
+    OutdatedLeapSeconds {
+        /// Date of the last leap second update stored in the GDR of the file.
+        file: i32,
+        /// Date of the last entry of the leap second table of this library.
+        library: i32,
+    },
+    /// The index of the values of a variable is inconsistent, so that some of its records may not
+    /// be read. See [`crate::record::vxr::check_index`].
+    InconsistentIndex {
+        /// Name of the variable.
+        variable: String,
+        /// Description of the problem.
+        problem: String,
+    },
+    /// A name or text stored in a fixed-size field has bytes other than NUL after its terminating
+    /// NUL. The text is read up to the first NUL.
+    EmbeddedNul {
+        /// Text up to the first NUL.
+        text: String,
+    },
+    /// The encoding stored in the CDR is unknown, and the encoding set in
+    /// [`DecodeOptions::encoding`] is used instead.
+    UnknownEncoding {
+        /// Encoding stored in the CDR.
+        encoding: i32,
+    },
+    /// The file has bytes after the end of file recorded in the GDR, and after its checksum if it
+    /// has one. These bytes are ignored.
+    TrailingBytes {
+        /// Number of bytes after the end of the file.
+        bytes: u64,
+    },
+    /// A record was decoded from a different number of bytes than its record size. Bytes left
+    /// at its end are skipped, see [`Decoder::finish_record`].
+    RecordSizeMismatch {
+        /// File offset of the record.
+        offset: u64,
+        /// Type of the record, as defined in the CDF specification.
+        record_type: i32,
+        /// Size of the record stored in the file.
+        record_size: u64,
+        /// Number of bytes that were decoded.
+        consumed: u64,
+    },
+}
+
+impl fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeWarning::OutdatedLeapSeconds { file, library } => write!(
+                f,
+                "The file was written with leap seconds up to {file}, but the leap second table \
+                 only goes up to {library}."
+            ),
+            DecodeWarning::InconsistentIndex { variable, problem } => {
+                write!(f, "Index of variable {variable} is inconsistent: {problem}")
+            }
+            DecodeWarning::EmbeddedNul { text } => {
+                write!(f, "Text {text} is followed by bytes other than NUL.")
+            }
+            DecodeWarning::UnknownEncoding { encoding } => {
+                write!(
+                    f,
+                    "Encoding {encoding} is unknown, the requested encoding is used."
+                )
+            }
+            DecodeWarning::TrailingBytes { bytes } => {
+                write!(
+                    f,
+                    "The file has {bytes} bytes after its end, which are ignored."
+                )
+            }
+            DecodeWarning::RecordSizeMismatch {
+                offset,
+                record_type,
+                record_size,
+                consumed,
+            } => write!(
+                f,
+                "Record of type {record_type} at offset {offset} has {record_size} bytes, but \
+                 {consumed} bytes were decoded."
+            ),
+        }
+    }
+}
+
+/// Callback that receives the progress of a decode, see [`DecodeOptions::progress`].
+pub type ProgressFn = dyn Fn(DecodeProgress) + Send + Sync;
+
+/// Callback that receives the warnings found while decoding, see [`DecodeOptions::warning`].
+pub type WarningFn = dyn Fn(DecodeWarning) + Send + Sync;
+
+/// Progress of a decode, passed to the callback set in [`DecodeOptions::progress`].
+#[derive(Debug, Clone)]
+pub struct DecodeProgress {
+    /// Position of the reader in bytes. Records are not stored in file order, so this does not
+    /// always increase.
+    pub bytes_processed: u64,
+    /// Type of the record that was just decoded, as defined in the CDF specification.
+    pub record_type: i32,
+    /// Name of the variable that is being read, if any.
+    pub variable: Option<String>,
+}
+
+/// Token that cancels the decodes and reads it was given to, set in [`DecodeOptions::cancel`].
+/// Clones of a token share its state, so that a user interface or a server can keep one clone and
+/// cancel a long read running on another thread, which then fails with [`CdfError::Cancelled`]
+/// at the next record instead of running to completion.
+///
+/// ```
+/// use cdf::decode::CancelToken;
+///
+/// let token = CancelToken::new();
+/// let shared = token.clone();
+/// shared.cancel();
+/// assert!(token.is_cancelled());
+/// token.reset();
+/// assert!(!shared.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Create a token that is not cancelled.
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Cancel the decodes and reads that use this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether this token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clear the cancellation, so that values of a file that was read with this token can be read
+    /// again on demand.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+impl Decoder<io::Empty> {
+    /// Returns a [`DecoderBuilder`] to configure a decoder before constructing it.
+    ///
+    /// ```
+    /// use cdf::decode::Decoder;
+    /// use std::io::Cursor;
+    ///
+    /// let decoder = Decoder::builder()
+    ///     .strict(false)
+    ///     .max_alloc(1 << 30)
+    ///     .verify_checksum(true)
+    ///     .build(Cursor::new(vec![0u8; 8]))
+    ///     .unwrap();
+    /// assert!(!decoder.options.strict);
+    /// ```
+    pub fn builder() -> DecoderBuilder {
+        DecoderBuilder::new()
+    }
+}
+
+/// Default of [`DecodeOptions::max_depth`].
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// Options that override or tune the default decoding behavior.
+#[derive(Clone)]
+pub struct DecodeOptions {
+    /// Encoding used for values stored inside records. When `None`, the encoding stored in the
+    /// CDR is used.
+    pub encoding: Option<CdfEncoding>,
+    /// Whether values that are reserved for future use must match the values expected by the
+    /// specification. Some writers do not respect this, so it can be relaxed.
+    pub strict: bool,
+    /// Maximum number of bytes that may be allocated for any single collection read from the
+    /// file. `None` means no limit.
+    pub max_alloc: Option<usize>,
+    /// Maximum number of levels of VXRs below the top-level VXRs of a variable. Real files rarely
+    /// need more than a few levels, and corrupt files can otherwise nest records endlessly.
+    pub max_depth: usize,
+    /// Whether to verify the MD5 checksum of the file, if the file has one.
+    pub verify_checksum: bool,
+    /// Whether to skip reading the values of all variables. Only the index of where the values
+    /// are stored is read, so that values can be read on demand later.
+    pub lazy: bool,
+    /// Names of the variables whose values are read. `None` means all variables. The values of
+    /// the other variables are treated as if `lazy` was set.
+    pub variables: Option<Vec<String>>,
+    /// Whether to decompress compressed variable values and compressed CDF files while decoding.
+    pub decompress: bool,
+    /// Callback invoked after each descriptor, index and values record is decoded, and while
+    /// reading variable values on demand.
+    pub progress: Option<Arc<ProgressFn>>,
+    /// Callback invoked for each [`DecodeWarning`] found while decoding.
+    pub warning: Option<Arc<WarningFn>>,
+    /// Whether to keep the exact bytes of each record that is decoded, including reserved fields
+    /// and padding, see [`crate::cdf::Cdf::raw_records`].
+    pub keep_raw: bool,
+    /// Token checked while decoding and while reading variable values on demand, which stops
+    /// them with [`CdfError::Cancelled`] once cancelled.
+    pub cancel: Option<CancelToken>,
+    /// Size of the buffer of the reader built by [`DecoderBuilder::build_buffered`], see
+    /// [`io::BufferedReader`].
+    pub buffer_size: usize,
+    /// Number of bytes read at once from the start of each VVR whose records are read on demand,
+    /// so that the following VVRs are read along with it if they are next in the file. Zero
+    /// reads each VVR separately.
+    pub prefetch: usize,
+    /// Whether to read the records of the file in file order before decoding them, instead of
+    /// seeking back and forth along the linked lists of records. The value records are only read
+    /// ahead if the values of all variables are loaded, see [`crate::cdf::Cdf`].
+    pub sequential: bool,
+}
+
+impl fmt::Debug for DecodeOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DecodeOptions")
+            .field("encoding", &self.encoding)
+            .field("strict", &self.strict)
+            .field("max_alloc", &self.max_alloc)
+            .field("max_depth", &self.max_depth)
+            .field("verify_checksum", &self.verify_checksum)
+            .field("lazy", &self.lazy)
+            .field("variables", &self.variables)
+            .field("decompress", &self.decompress)
+            .field("progress", &self.progress.is_some())
+            .field("warning", &self.warning.is_some())
+            .field("keep_raw", &self.keep_raw)
+            .field("cancel", &self.cancel)
+            .field("buffer_size", &self.buffer_size)
+            .field("prefetch", &self.prefetch)
+            .field("sequential", &self.sequential)
+            .finish()
+    }
+}
+
+impl DecodeOptions {
+    /// Returns whether the values of the variable `name` should be read while decoding.
+    pub fn loads_variable(&self, name: &str) -> bool {
+        !self.lazy
+            && self
+                .variables
+                .as_ref()
+                .is_none_or(|names| names.iter().any(|n| n == name))
+    }
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        DecodeOptions {
+            encoding: None,
+            strict: true,
+            max_alloc: None,
+            max_depth: DEFAULT_MAX_DEPTH,
+            verify_checksum: false,
+            lazy: false,
+            variables: None,
+            decompress: false,
+            progress: None,
+            warning: None,
+            keep_raw: false,
+            cancel: None,
+            buffer_size: io::DEFAULT_BUFFER_SIZE,
+            prefetch: 0,
+            sequential: false,
+        }
+    }
+}
+
+/// Builder for a [`Decoder`] with non-default [`DecodeOptions`].
+#[derive(Debug, Clone, Default)]
+pub struct DecoderBuilder {
+    options: DecodeOptions,
+}
+
+impl DecoderBuilder {
+    /// Create a new builder with default options.
+    pub fn new() -> Self {
+        DecoderBuilder::default()
+    }
+
+    /// Decode values using `encoding` instead of the encoding stored in the CDR.
+    pub fn encoding(mut self, encoding: CdfEncoding) -> Self {
+        self.options.encoding = Some(encoding);
+        self
+    }
+
+    /// Set whether reserved values are checked against the specification.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.strict = strict;
+        self
+    }
+
+    /// Set the maximum number of bytes allocated for any single collection.
+    pub fn max_alloc(mut self, max_alloc: usize) -> Self {
+        self.options.max_alloc = Some(max_alloc);
+        self
+    }
+
+    /// Set the maximum number of levels of nested VXRs.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.options.max_depth = max_depth;
+        self
+    }
+
+    /// Set whether to verify the MD5 checksum of the file.
+    pub fn verify_checksum(mut self, verify_checksum: bool) -> Self {
+        self.options.verify_checksum = verify_checksum;
+        self
+    }
+
+    /// Set whether to skip reading variable values while decoding.
+    pub fn lazy(mut self, lazy: bool) -> Self {
+        self.options.lazy = lazy;
+        self
+    }
+
+    /// Only read the values of the named variables while decoding.
+    pub fn variables<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.variables = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set whether to decompress compressed data while decoding.
+    pub fn decompress(mut self, decompress: bool) -> Self {
+        self.options.decompress = decompress;
+        self
+    }
+
+    /// Set whether to keep the exact bytes of each record that is decoded.
+    pub fn keep_raw(mut self, keep_raw: bool) -> Self {
+        self.options.keep_raw = keep_raw;
+        self
+    }
+
+    /// Set a callback that reports the progress of decoding. The callback may capture state,
+    /// such as a progress bar.
+    pub fn progress(mut self, progress: impl Fn(DecodeProgress) + Send + Sync + 'static) -> Self {
+        self.options.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Set a callback that reports warnings found while decoding.
+    pub fn warning(mut self, warning: impl Fn(DecodeWarning) + Send + Sync + 'static) -> Self {
+        self.options.warning = Some(Arc::new(warning));
+        self
+    }
+
+    /// Set a token that cancels decoding and reading values on demand.
+    pub fn cancel(mut self, cancel: CancelToken) -> Self {
+        self.options.cancel = Some(cancel);
+        self
+    }
+
+    /// Set the size of the buffer of the reader built by [`DecoderBuilder::build_buffered`].
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.options.buffer_size = buffer_size;
+        self
+    }
+
+    /// Set the number of bytes read at once when reading the records of a variable on demand.
+    pub fn prefetch(mut self, prefetch: usize) -> Self {
+        self.options.prefetch = prefetch;
+        self
+    }
+
+    /// Set whether to read the records of the file in file order before decoding them.
+    pub fn sequential(mut self, sequential: bool) -> Self {
+        self.options.sequential = sequential;
+        self
+    }
+
+    /// Construct the decoder using the reader and the configured options, buffering the reader
+    /// with a [`io::BufferedReader`] of [`DecodeOptions::buffer_size`] bytes. The reader should
+    /// not be buffered already, such as a file.
+    /// # Errors
+    /// Returns a [`CdfError`] if the decoder cannot be constructed.
+    pub fn build_buffered<R>(self, reader: R) -> Result<Decoder<io::BufferedReader<R>>, CdfError>
+    where
+        R: io::Read + io::Seek,
+    {
+        let buffer_size = self.options.buffer_size;
+        self.build(io::BufferedReader::with_capacity(buffer_size, reader))
+    }
+
+    /// Construct the decoder using the reader and the configured options.
+    /// # Errors
+    /// Returns a [`CdfError`] if the decoder cannot be constructed.
+    pub fn build<R>(self, reader: R) -> Result<Decoder<R>, CdfError>
+    where
+        R: io::Read + io::Seek,
+    {
         Ok(Decoder {
             reader,
             context: DecodeContext::default(),
+            options: self.options,
         })
     }
 }
 
 /// Stores various contextual values read in the CDF that other records depend on for their decoding.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct DecodeContext {
     /// The "encoding" of the values in the CDF. This has to be read in or specified for every
     /// CDF file and is contained in the CDR.
@@ -111,6 +771,25 @@ pub struct DecodeContext {
     pub num_records: Option<usize>,
     /// Whether variable records are stored in row-major (true) or column-major (false) format.
     pub row_major: Option<bool>,
+    /// Compression used for the values of the currently read variable.
+    pub compression: Option<CdfCompressionKind>,
+    /// Whether the values of the currently read variable are skipped.
+    pub skip_values: bool,
+    /// Name of the variable that is currently being read.
+    pub variable_name: Option<String>,
+    /// Number of levels of VXRs below the top-level VXR that is currently being read.
+    pub depth: usize,
+    /// Warnings reported so far, in the order in which they were found.
+    pub warnings: Vec<DecodeWarning>,
+    /// Exact bytes of the records decoded so far by file offset, if [`DecodeOptions::keep_raw`]
+    /// is set.
+    pub raw_records: BTreeMap<u64, Vec<u8>>,
+    /// File offset of the record that is being decoded, used to report truncated files.
+    pub record_start: Option<u64>,
+    /// Size of the file, known once the first offset is checked by [`Decoder::seek_offset`].
+    pub file_size: Option<u64>,
+    /// End of the file according to the GDR, after which no record starts.
+    pub eof: Option<u64>,
 }
 
 macro_rules! impl_getter {
@@ -140,6 +819,56 @@ impl DecodeContext {
     impl_getter!(var_data_len, CdfInt4);
     impl_getter!(num_records, usize);
     impl_getter!(row_major, bool);
+    impl_getter!(compression, CdfCompressionKind);
+}
+
+/// Convert `value`, the count, size or index held in `field` of a record, to a `usize`.
+/// # Errors
+/// Returns a [`CdfError::InvalidCount`] if `value` is negative or does not fit in a `usize`.
+pub fn checked_usize(field: &'static str, value: impl Into<i64>) -> Result<usize, CdfError> {
+    let value = value.into();
+    usize::try_from(value).map_err(|_| CdfError::InvalidCount { field, value })
+}
+
+/// Maximum number of dimensions of a variable, as in the CDF library.
+pub const CDF_MAX_DIMS: usize = 10;
+
+/// Convert `value`, the number of dimensions held in `field` of a record, to a `usize`.
+/// # Errors
+/// Returns a [`CdfError::InvalidCount`] if `value` is negative, or a [`ShapeError`] if it is more
+/// than [`CDF_MAX_DIMS`].
+pub fn checked_num_dims(field: &'static str, value: i32) -> Result<usize, CdfError> {
+    let num_dims = checked_usize(field, value)?;
+    if num_dims > CDF_MAX_DIMS {
+        return Err(ShapeError::TooManyDimensions { field, num_dims }.into());
+    }
+    Ok(num_dims)
+}
+
+/// Convert `value`, the size of dimension `dim` held in `field` of a record, to a `usize`.
+/// # Errors
+/// Returns a [`ShapeError`] if `value` is negative.
+pub fn checked_dim_size(field: &'static str, dim: usize, value: i32) -> Result<usize, CdfError> {
+    usize::try_from(value).map_err(|_| {
+        ShapeError::InvalidDimensionSize {
+            field,
+            dim,
+            size: value.into(),
+        }
+        .into()
+    })
+}
+
+/// Size of the data that follows a header of `header` bytes in a record of `record_size` bytes.
+/// # Errors
+/// Returns a [`CdfError::InvalidCount`] if `record_size` is negative or smaller than the header.
+pub fn record_data_size(record_size: &CdfInt8, header: usize) -> Result<usize, CdfError> {
+    checked_usize("record_size", **record_size)?
+        .checked_sub(header)
+        .ok_or(CdfError::InvalidCount {
+            field: "record_size",
+            value: **record_size,
+        })
 }
 
 /// CDF versions prior to 3.0 use 4-byte signed integer to store file-offsets pointing to various
@@ -0,0 +1,522 @@
+use alloc::{collections::BTreeMap, format, string::ToString, vec::Vec};
+
+use crate::cdf::Cdf;
+use crate::checksum::md5_prefix;
+use crate::decode::{Decodable, Decoder};
+use crate::encode::{encode_to_vec, Encodable, Encoder};
+use crate::error::CdfError;
+use crate::io::{self, SeekFrom};
+use crate::types::{CdfInt4, CdfInt8, CdfString, CdfType};
+use crate::variable::is_reference_attribute;
+
+/// Rename the variable called `from` to `to` in the CDF file `file`, without copying the file.
+///
+/// The name is stored in a field of fixed size in the descriptor of the variable, which is
+/// overwritten where it is. With `update_references`, entries of variable attributes that refer
+/// to the variable by name, such as `DEPEND_0`, are changed too, see [`is_reference_attribute`].
+/// Entries whose size does not change are overwritten where they are, and others are appended
+/// to the end of the file and linked in place of the old ones. The checksum of the file is
+/// updated if it has one.
+///
+/// Only the records that change are written, after checking that they encode to the bytes read
+/// from the file, so that nothing else is changed. Use [`crate::transcode::RenameVariable`] to
+/// rename a variable while copying a file instead, such as a compressed one.
+///
+/// ```
+/// use cdf::cdf::Cdf;
+/// use cdf::edit::rename_variable;
+/// use std::io::Cursor;
+///
+/// # let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/data/ulysses.cdf");
+/// let mut file = Cursor::new(std::fs::read(path).unwrap());
+/// rename_variable(&mut file, "Epoch", "Epoch_UT", true).unwrap();
+///
+/// let cdf = Cdf::from_bytes(file.get_ref()).unwrap();
+/// let b = cdf.variable("B_scalar").unwrap();
+/// assert_eq!(b.attribute_str("DEPEND_0"), Some("Epoch_UT"));
+/// ```
+/// # Errors
+/// Returns a [`CdfError::Other`] if the file is compressed, if there is no variable called `from`,
+/// if there is already a variable called `to`, if `to` does not fit in the name field, or if a
+/// record to change does not encode to the bytes read from the file. Nothing is written then.
+pub fn rename_variable<F>(
+    file: &mut F,
+    from: &str,
+    to: &str,
+    update_references: bool,
+) -> Result<(), CdfError>
+where
+    F: io::Read + io::Write + io::Seek,
+{
+    let mut decoder = Decoder::builder()
+        .lazy(true)
+        .keep_raw(true)
+        .build(&mut *file)?;
+    let mut cdf = Cdf::decode_be(&mut decoder)?;
+    if cdf.is_compressed {
+        return Err(CdfError::Other(
+            "Compressed files cannot be edited in place.".to_string(),
+        ));
+    }
+    if cdf.variable(from).is_err() {
+        return Err(CdfError::Other(format!("Variable {from} does not exist.")));
+    }
+    if cdf.variable(to).is_ok() {
+        return Err(CdfError::Other(format!("Variable {to} already exists.")));
+    }
+    let version = cdf.cdr.cdf_version.clone();
+    let name_bytes = if version.major < 3 { 64 } else { 256 };
+    if to.is_empty() || to.len() > name_bytes {
+        return Err(CdfError::Other(format!(
+            "Name {to} does not fit in the {name_bytes} bytes of a variable name."
+        )));
+    }
+
+    let gdr = &mut cdf.cdr.gdr;
+    let eof = u64::try_from(gdr.eof.as_ref().map_or(0, |eof| **eof))?;
+    let mut edit = InPlace {
+        encoder: Encoder::new(
+            io::Cursor::new(Vec::new()),
+            version,
+            cdf.cdr.encoding.clone(),
+        )?,
+        raw: core::mem::take(&mut cdf.raw_records),
+        writes: BTreeMap::new(),
+        eof,
+    };
+
+    let new_name = || CdfString::from(to.to_string());
+    let mut offset = gdr.rvdr_head.clone();
+    for vdr in &mut gdr.rvdr_vec {
+        let at = edit.offset(&offset)?;
+        offset = vdr.rvdr_next.clone();
+        if *vdr.name == from {
+            edit.rewrite(at, vdr, |vdr| vdr.name = new_name())?;
+        }
+    }
+    let mut offset = gdr.zvdr_head.clone();
+    for vdr in &mut gdr.zvdr_vec {
+        let at = edit.offset(&offset)?;
+        offset = vdr.zvdr_next.clone();
+        if *vdr.name == from {
+            edit.rewrite(at, vdr, |vdr| vdr.name = new_name())?;
+        }
+    }
+
+    if update_references {
+        let mut adr_offset = gdr.adr_head.clone();
+        for adr in &mut gdr.adr_vec {
+            let adr_at = edit.offset(&adr_offset)?;
+            adr_offset = adr.adr_next.clone();
+            if matches!(*adr.scope, 1 | 3) || !is_reference_attribute(&adr.name) {
+                continue;
+            }
+            // Follow the list of entries, linking each entry that moves from the ADR or from the
+            // entry before it.
+            macro_rules! update_entries {
+                ($entries:ident, $head:ident, $next:ident) => {
+                    let mut offset = adr.$head.clone();
+                    let mut previous: Option<u64> = None;
+                    for i in 0..adr.$entries.len() {
+                        let at = edit.offset(&offset)?;
+                        offset = adr.$entries[i].$next.clone();
+                        let entry = &mut adr.$entries[i];
+                        if !matches!(entry.value.as_slice(), [CdfType::String(s)] if **s == from) {
+                            previous = Some(at);
+                            continue;
+                        }
+                        let moved = edit.replace(at, entry, |entry| {
+                            entry.num_elements = CdfInt4::from(i32::try_from(to.len())?);
+                            entry.num_strings = CdfInt4::from(1);
+                            entry.value = alloc::vec![CdfType::String(new_name())];
+                            Ok(())
+                        })?;
+                        let link = Some(CdfInt8::from(i64::try_from(moved)?));
+                        match previous {
+                            _ if moved == at => {}
+                            None => edit.rewrite(adr_at, adr, |adr| adr.$head = link)?,
+                            Some(previous) => {
+                                edit.rewrite(previous, &mut adr.$entries[i - 1], |entry| {
+                                    entry.$next = link
+                                })?
+                            }
+                        }
+                        previous = Some(moved);
+                    }
+                };
+            }
+            update_entries!(agredr_vec, agredr_head, agredr_next);
+            update_entries!(azedr_vec, azedr_head, azedr_next);
+        }
+    }
+
+    if edit.eof != eof {
+        let gdr_at = u64::try_from(*cdf.cdr.gdr_offset)?;
+        let new_eof = Some(CdfInt8::from(i64::try_from(edit.eof)?));
+        edit.rewrite(gdr_at, gdr, |gdr| gdr.eof = new_eof)?;
+    }
+    edit.write(file, cdf.cdr.flags.has_checksum)
+}
+
+/// Rename the variable called `from` to `to` in the CDF file at `path`, see [`rename_variable`].
+/// # Errors
+/// Returns a [`CdfError`] if the file cannot be opened, or if the variable cannot be renamed.
+#[cfg(feature = "std-fs")]
+pub fn rename_variable_file<P>(
+    path: P,
+    from: &str,
+    to: &str,
+    update_references: bool,
+) -> Result<(), CdfError>
+where
+    P: AsRef<std::path::Path>,
+{
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)?;
+    rename_variable(&mut file, from, to, update_references)
+}
+
+/// Change the record of type `T` stored at `offset` in the CDF file `file` with `change`, without
+/// copying the file, for example to correct a field of the GDR or a flag of a variable.
+///
+/// The record is decoded, changed, and encoded where it is, after checking that it encodes to the
+/// bytes read from the file, so that nothing else is changed. The records it points to, such as
+/// the entries of an ADR, are not written. The checksum of the file is updated if it has one.
+///
+/// ```
+/// use cdf::cdf::Cdf;
+/// use cdf::edit::patch_record;
+/// use cdf::record::gdr::GlobalDescriptorRecord;
+/// use cdf::types::CdfInt4;
+/// use std::io::Cursor;
+///
+/// # let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/data/ulysses.cdf");
+/// let mut file = Cursor::new(std::fs::read(path).unwrap());
+/// let gdr_offset = *Cdf::from_bytes(file.get_ref()).unwrap().cdr.gdr_offset as u64;
+/// patch_record(&mut file, gdr_offset, |gdr: &mut GlobalDescriptorRecord| {
+///     gdr.date_last_leapsecond_update = CdfInt4::from(20_170_101);
+/// })
+/// .unwrap();
+///
+/// let cdf = Cdf::from_bytes(file.get_ref()).unwrap();
+/// assert_eq!(*cdf.cdr.gdr.date_last_leapsecond_update, 20_170_101);
+/// ```
+/// # Errors
+/// Returns a [`CdfError::Other`] if the file is compressed, if no record starts at `offset`, if
+/// the record does not encode to the bytes read from the file, or if its size changes, or a
+/// [`CdfError::Decode`] if the record is not of type `T`. Nothing is written then.
+pub fn patch_record<F, T, C>(file: &mut F, offset: u64, change: C) -> Result<(), CdfError>
+where
+    F: io::Read + io::Write + io::Seek,
+    T: Decodable + Encodable,
+    C: FnOnce(&mut T),
+{
+    _ = file.seek(SeekFrom::Start(0))?;
+    let mut decoder = Decoder::builder()
+        .lazy(true)
+        .keep_raw(true)
+        .build(&mut *file)?;
+    let mut cdf = Cdf::decode_be(&mut decoder)?;
+    if cdf.is_compressed {
+        return Err(CdfError::Other(
+            "Compressed files cannot be edited in place.".to_string(),
+        ));
+    }
+    // The magic numbers are kept at offset zero, and the bytes after the last record after it.
+    let eof = u64::try_from(cdf.cdr.gdr.eof.as_ref().map_or(0, |eof| **eof))?;
+    if offset == 0 || (eof > 0 && offset >= eof) || !cdf.raw_records.contains_key(&offset) {
+        return Err(CdfError::Other(format!(
+            "No record starts at offset {offset}."
+        )));
+    }
+
+    // Records that point to others are decoded along with them, without their values.
+    _ = decoder.reader.seek(SeekFrom::Start(offset))?;
+    decoder.options.keep_raw = false;
+    decoder.context.skip_values = true;
+    let mut record = T::decode_be(&mut decoder)?;
+
+    let mut edit = InPlace {
+        encoder: Encoder::new(
+            io::Cursor::new(Vec::new()),
+            cdf.cdr.cdf_version.clone(),
+            cdf.cdr.encoding.clone(),
+        )?,
+        raw: core::mem::take(&mut cdf.raw_records),
+        writes: BTreeMap::new(),
+        eof,
+    };
+    edit.rewrite(offset, &mut record, change)?;
+    edit.write(file, cdf.cdr.flags.has_checksum)
+}
+
+/// Change the record of type `T` stored at `offset` in the CDF file at `path`, see
+/// [`patch_record`].
+/// # Errors
+/// Returns a [`CdfError`] if the file cannot be opened, or if the record cannot be changed.
+#[cfg(feature = "std-fs")]
+pub fn patch_record_file<P, T, C>(path: P, offset: u64, change: C) -> Result<(), CdfError>
+where
+    P: AsRef<std::path::Path>,
+    T: Decodable + Encodable,
+    C: FnOnce(&mut T),
+{
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)?;
+    patch_record(&mut file, offset, change)
+}
+
+// Records to write to a file, encoded with the version and encoding of the file.
+struct InPlace {
+    encoder: Encoder<io::Cursor<Vec<u8>>>,
+    // Bytes of the records read from the file, by offset.
+    raw: BTreeMap<u64, Vec<u8>>,
+    // Bytes to write to the file, by offset.
+    writes: BTreeMap<u64, Vec<u8>>,
+    // End of the file, where records that grow are appended.
+    eof: u64,
+}
+
+impl InPlace {
+    fn offset(&self, offset: &Option<CdfInt8>) -> Result<u64, CdfError> {
+        let offset = offset
+            .as_ref()
+            .ok_or_else(|| CdfError::Other("A list of records ends early.".to_string()))?;
+        Ok(u64::try_from(**offset)?)
+    }
+
+    // Check that `record` encodes to the bytes stored at `offset`, which are those read from the
+    // file or those written before, and return them.
+    fn current<T: Encodable>(&self, offset: u64, record: &T) -> Result<Vec<u8>, CdfError> {
+        let before = encode_to_vec(&self.encoder, record)?;
+        match self.writes.get(&offset).or_else(|| self.raw.get(&offset)) {
+            Some(bytes) if *bytes == before => Ok(before),
+            _ => Err(CdfError::Other(format!(
+                "Record at offset {offset} does not encode to the bytes of the file, so it cannot \
+                 be edited in place."
+            ))),
+        }
+    }
+
+    // Apply `change` to `record`, stored at `offset`, which keeps its size.
+    fn rewrite<T, C>(&mut self, offset: u64, record: &mut T, change: C) -> Result<(), CdfError>
+    where
+        T: Encodable,
+        C: FnOnce(&mut T),
+    {
+        let before = self.current(offset, record)?;
+        change(record);
+        let after = encode_to_vec(&self.encoder, record)?;
+        if after.len() != before.len() {
+            return Err(CdfError::Other(format!(
+                "Record at offset {offset} changes size."
+            )));
+        }
+        _ = self.writes.insert(offset, after);
+        Ok(())
+    }
+
+    // Write the records to `file`, followed by the checksum of the file if it has one.
+    fn write<F>(&self, file: &mut F, has_checksum: bool) -> Result<(), CdfError>
+    where
+        F: io::Read + io::Write + io::Seek,
+    {
+        for (offset, bytes) in &self.writes {
+            _ = file.seek(SeekFrom::Start(*offset))?;
+            file.write_all(bytes)?;
+        }
+        if has_checksum {
+            let checksum = md5_prefix(file, self.eof)?;
+            file.write_all(&checksum)?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+
+    // Apply `change` to the entry `record`, stored at `offset`, and return where it is written:
+    // at the same offset if its size is unchanged, or else at the end of the file.
+    fn replace<T, C>(&mut self, offset: u64, record: &mut T, change: C) -> Result<u64, CdfError>
+    where
+        T: Encodable + Sized,
+        T: HasRecordSize,
+        C: FnOnce(&mut T) -> Result<(), CdfError>,
+    {
+        let before = self.current(offset, record)?;
+        change(record)?;
+        let size = encode_to_vec(&self.encoder, record)?.len();
+        *record.record_size() = CdfInt8::from(i64::try_from(size)?);
+        let after = encode_to_vec(&self.encoder, record)?;
+        let at = if after.len() == before.len() {
+            offset
+        } else {
+            let at = self.eof;
+            self.eof += after.len() as u64;
+            at
+        };
+        _ = self.writes.insert(at, after);
+        Ok(at)
+    }
+}
+
+// Records whose size is stored in their first field.
+trait HasRecordSize {
+    fn record_size(&mut self) -> &mut CdfInt8;
+}
+
+impl HasRecordSize for crate::record::agredr::AttributeGREntryDescriptorRecord {
+    fn record_size(&mut self) -> &mut CdfInt8 {
+        &mut self.record_size
+    }
+}
+
+impl HasRecordSize for crate::record::azedr::AttributeZEntryDescriptorRecord {
+    fn record_size(&mut self) -> &mut CdfInt8 {
+        &mut self.record_size
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use super::*;
+    use crate::checksum::verify_md5;
+    use crate::record::{
+        agredr::AttributeGREntryDescriptorRecord, gdr::GlobalDescriptorRecord,
+        zvdr::ZVariableDescriptorRecord,
+    };
+    use crate::transcode::{transcode, RenameVariable};
+    use alloc::vec;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    fn _bytes(name: &str) -> Result<Vec<u8>, CdfError> {
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "examples", "data", name]
+            .iter()
+            .collect();
+        Ok(std::fs::read(path)?)
+    }
+
+    #[test]
+    fn test_rename_variable() -> Result<(), CdfError> {
+        // Same length, so that all changes are made in place, and a longer name whose references
+        // are appended to the file.
+        for to in ["EPOCH", "Epoch_TT2000"] {
+            let original = _bytes("ulysses.cdf")?;
+            let mut file = Cursor::new(original.clone());
+            rename_variable(&mut file, "Epoch", to, true)?;
+            let bytes = file.into_inner();
+            assert_eq!(bytes.len() == original.len(), to.len() == "Epoch".len());
+
+            let before = Cdf::from_bytes(&original)?;
+            let after = Cdf::from_bytes(&bytes)?;
+            assert!(after.variable("Epoch").is_err());
+            assert_eq!(
+                format!("{:?}", after.variable(to)?.read_records(0..10)?),
+                format!("{:?}", before.variable("Epoch")?.read_records(0..10)?)
+            );
+            for (variable, renamed) in before.variables().iter().zip(after.variables()) {
+                for (name, value) in variable.attributes().iter() {
+                    let expected = match variable.attribute_str(name) {
+                        Some("Epoch") if is_reference_attribute(name) => {
+                            format!("{:?}", [CdfType::String(CdfString::from(to.to_string()))])
+                        }
+                        _ => format!("{value:?}"),
+                    };
+                    let got = format!("{:?}", renamed.attribute(name).unwrap_or_default());
+                    assert_eq!(got, expected, "{} {name}", variable.name());
+                }
+            }
+        }
+
+        // Without references, only the name changes.
+        let mut file = Cursor::new(_bytes("ulysses.cdf")?);
+        rename_variable(&mut file, "Epoch", "Epoch_TT2000", false)?;
+        let cdf = Cdf::from_bytes(file.get_ref())?;
+        let b = cdf.variable("B_scalar")?;
+        assert_eq!(b.attribute_str("DEPEND_0"), Some("Epoch"));
+
+        // The checksum is kept up to date, and bad names are refused.
+        let mut file = Cursor::new(_bytes("test_alltypes.cdf")?);
+        rename_variable(&mut file, "Latitude", "Latitude_renamed", true)?;
+        verify_md5(&mut Cursor::new(file.get_ref()))?;
+        assert!(Cdf::from_bytes(file.get_ref())?
+            .variable("Latitude_renamed")
+            .is_ok());
+        assert!(rename_variable(&mut file, "Latitude", "x", true).is_err());
+        assert!(rename_variable(&mut file, "Longitude", "Latitude1", true).is_err());
+        assert!(rename_variable(&mut file, "Longitude", &"x".repeat(300), true).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_patch_record() -> Result<(), CdfError> {
+        let original = _bytes("test_alltypes.cdf")?;
+        let cdf = Cdf::from_bytes(&original)?;
+        let gdr_offset = u64::try_from(*cdf.cdr.gdr_offset)?;
+        let zvdr_offset = u64::try_from(**cdf.cdr.gdr.zvdr_head.as_ref().unwrap())?;
+        let name = cdf.cdr.gdr.zvdr_vec[0].name.to_string();
+
+        // Fields of fixed size are changed in place, and the checksum is kept up to date.
+        let mut file = Cursor::new(original.clone());
+        patch_record(&mut file, gdr_offset, |gdr: &mut GlobalDescriptorRecord| {
+            gdr.date_last_leapsecond_update = CdfInt4::from(20_150_701);
+        })?;
+        patch_record(
+            &mut file,
+            zvdr_offset,
+            |zvdr: &mut ZVariableDescriptorRecord| {
+                zvdr.name = CdfString::from(format!("{name}_patched"));
+            },
+        )?;
+        assert_eq!(file.get_ref().len(), original.len());
+        verify_md5(&mut Cursor::new(file.get_ref()))?;
+        let patched = Cdf::from_bytes(file.get_ref())?;
+        assert_eq!(*patched.cdr.gdr.date_last_leapsecond_update, 20_150_701);
+        assert_eq!(
+            format!(
+                "{:?}",
+                patched.variable(&format!("{name}_patched"))?.read_all()?
+            ),
+            format!("{:?}", cdf.variable(&name)?.read_all()?)
+        );
+
+        // Records of another type, offsets that are not records and changes of size are refused,
+        // and nothing is written.
+        let mut file = Cursor::new(original.clone());
+        let unchanged = |_: &mut ZVariableDescriptorRecord| {};
+        assert!(patch_record(&mut file, gdr_offset, unchanged).is_err());
+        assert!(patch_record(&mut file, zvdr_offset + 1, unchanged).is_err());
+        assert!(patch_record(&mut file, 0, unchanged).is_err());
+        let agredr_head = cdf.cdr.gdr.adr_vec[0].agredr_head.as_ref().unwrap();
+        let agredr_offset = u64::try_from(**agredr_head)?;
+        let result = patch_record(
+            &mut file,
+            agredr_offset,
+            |entry: &mut AttributeGREntryDescriptorRecord| {
+                entry.num_elements = CdfInt4::from(*entry.num_elements + 1);
+                entry.value.push(entry.value[0].clone());
+            },
+        );
+        assert!(result.is_err());
+        assert_eq!(*file.get_ref(), original);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_variable_transcode() -> Result<(), CdfError> {
+        let rename = RenameVariable::new("Epoch", "Epoch_TT2000").update_references(true);
+        let writer = transcode(
+            Cursor::new(_bytes("ulysses.cdf")?),
+            Cursor::new(vec![]),
+            rename,
+        )?;
+        let cdf = Cdf::from_bytes(writer.get_ref())?;
+        assert!(cdf.variable("Epoch_TT2000").is_ok());
+        let b = cdf.variable("B_scalar")?;
+        assert_eq!(b.attribute_str("DEPEND_0"), Some("Epoch_TT2000"));
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use alloc::{format, vec::Vec};
+
+use crate::io;
+
+use crate::error::CdfError;
+use crate::repr::{CdfEncoding, CdfVersion, Endian};
+use crate::types::{CdfInt4, CdfInt8};
+
+/// Trait for encoding a CDF value or record into a writer.
+pub trait Encodable {
+    /// Encode this value into the output that implements `io::Write` and `io::Seek` using
+    /// Big-Endian encoding.
+    /// # Errors
+    /// Returns a [`CdfError::Encode`] if the encoding fails for any reason.
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek;
+
+    /// Encode this value into the output that implements `io::Write` and `io::Seek` using
+    /// Little-Endian encoding.
+    /// # Errors
+    /// Returns a [`CdfError::Encode`] if the encoding fails for any reason.
+    fn encode_le<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek;
+
+    /// Encode this value using the endianness of the values of the file, as set in the encoder
+    /// context.
+    /// # Errors
+    /// Returns a [`CdfError::Encode`] if the encoding fails for any reason.
+    fn encode_value<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        match encoder.context.endianness {
+            Endian::Big => self.encode_be(encoder),
+            Endian::Little => self.encode_le(encoder),
+        }
+    }
+}
+
+/// Struct containing the writer and the properties of the file being encoded.
+pub struct Encoder<W>
+where
+    W: io::Write + io::Seek,
+{
+    /// A writer is some object that implements [`io::Write`] and [`io::Seek`].
+    pub writer: W,
+    /// Context keeps track of file properties that are needed to encode records and values.
+    pub context: EncodeContext,
+}
+
+impl<W> Encoder<W>
+where
+    W: io::Write + io::Seek,
+{
+    /// Create a new encoder that writes a file of the given version, with values stored using
+    /// `encoding`.
+    /// # Errors
+    /// Returns a [`CdfError::Encode`] if the encoding is not supported.
+    pub fn new(writer: W, version: CdfVersion, encoding: CdfEncoding) -> Result<Self, CdfError> {
+        let endianness = encoding
+            .get_endian()
+            .map_err(|e| CdfError::Encode(format!("{e}")))?;
+        Ok(Encoder {
+            writer,
+            context: EncodeContext {
+                version,
+                encoding,
+                endianness,
+            },
+        })
+    }
+
+    /// Create an encoder over another writer that shares the context of this encoder. This is
+    /// used to encode data that is compressed before it is written.
+    pub fn nested<S>(&self, writer: S) -> Encoder<S>
+    where
+        S: io::Write + io::Seek,
+    {
+        Encoder {
+            writer,
+            context: self.context.clone(),
+        }
+    }
+
+    /// Current position of the writer.
+    /// # Errors
+    /// Returns a [`CdfError::Io`] if the position cannot be determined.
+    pub fn position(&mut self) -> Result<u64, CdfError> {
+        Ok(self.writer.stream_position()?)
+    }
+}
+
+/// Properties of the file being encoded.
+#[derive(Debug, Clone)]
+pub struct EncodeContext {
+    /// Version of the CDF format that is written.
+    pub version: CdfVersion,
+    /// Encoding of the values stored inside records.
+    pub encoding: CdfEncoding,
+    /// Endianness of the values stored inside records, following from `encoding`.
+    pub endianness: Endian,
+}
+
+/// Encode `value` into a file offset or a record size, which is stored in an Int8 for CDF
+/// versions 3 and above, and in an Int4 for older versions.
+/// # Errors
+/// Returns a [`CdfError::Encode`] if the value does not fit in an Int4 for older versions.
+pub fn encode_version3_int4_int8<W>(encoder: &mut Encoder<W>, value: i64) -> Result<(), CdfError>
+where
+    W: io::Write + io::Seek,
+{
+    if encoder.context.version.major >= 3 {
+        CdfInt8::from(value).encode_be(encoder)
+    } else {
+        let value = i32::try_from(value).map_err(|_| {
+            CdfError::Encode(format!(
+                "Value {value} does not fit in the 4-byte offsets of CDF versions before 3."
+            ))
+        })?;
+        CdfInt4::from(value).encode_be(encoder)
+    }
+}
+
+/// Encode a sequence of values using the endianness of the values of the file.
+/// # Errors
+/// Returns a [`CdfError::Encode`] if the encoding fails for any value.
+pub fn encode_values<W, T>(encoder: &mut Encoder<W>, values: &[T]) -> Result<(), CdfError>
+where
+    W: io::Write + io::Seek,
+    T: Encodable,
+{
+    for value in values {
+        value.encode_value(encoder)?;
+    }
+    Ok(())
+}
+
+/// Encode `value` into a buffer in memory, using the context of `encoder`.
+/// # Errors
+/// Returns a [`CdfError::Encode`] if the encoding fails.
+pub fn encode_to_vec<W, T>(encoder: &Encoder<W>, value: &T) -> Result<Vec<u8>, CdfError>
+where
+    W: io::Write + io::Seek,
+    T: Encodable,
+{
+    let mut inner = encoder.nested(io::Cursor::new(Vec::new()));
+    value.encode_be(&mut inner)?;
+    Ok(inner.writer.into_inner())
+}
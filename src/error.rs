@@ -1,4 +1,7 @@
-use std::{fmt::Display, io, num::TryFromIntError};
+use alloc::string::{String, ToString};
+use core::{fmt::Display, num::TryFromIntError};
+
+use crate::io;
 
 /// Top-level error to handle all kinds of errors associated with this library.
 #[derive(Debug)]
@@ -7,10 +10,95 @@ pub enum CdfError {
     Decode(String),
     /// Errors related to encoding / serializing.
     Encode(String),
-    /// IO errors passed from [`std::io`]
+    /// IO errors passed from the reader, see [`crate::io`].
     Io(io::Error),
     /// Other errors that do not belong in any other category.
     Other(String),
+    /// A linked list of records loops back on itself.
+    CyclicRecordList {
+        /// File offset of the record that is reached a second time.
+        offset: i64,
+    },
+    /// Records are nested deeper than [`crate::decode::DecodeOptions::max_depth`].
+    MaxDepthExceeded {
+        /// The limit that was exceeded.
+        max_depth: usize,
+    },
+    /// A count, size or index read from a file is negative, or too large to be held in memory.
+    InvalidCount {
+        /// Name of the field that holds the value, as in the CDF specification.
+        field: &'static str,
+        /// The value read from the file.
+        value: i64,
+    },
+    /// An offset read from the file points outside of it, see
+    /// [`crate::decode::Decoder::seek_offset`].
+    OffsetOutOfBounds {
+        /// The offset read from the file.
+        offset: i64,
+        /// End of the file, which is its size or the end of the file according to the GDR,
+        /// whichever is smaller.
+        end: u64,
+    },
+    /// Decoding or reading was stopped by a [`crate::decode::CancelToken`].
+    Cancelled,
+    /// The file ends in the middle of a record, see [`crate::decode::Decoder::explain_truncation`].
+    Truncated {
+        /// Size of the file in bytes, which is where it is cut off.
+        file_size: u64,
+        /// File offset of the record that is cut off.
+        offset: u64,
+        /// Type of the record, if its header is in the file.
+        record_type: Option<i32>,
+        /// Number of bytes of the record that are missing, if its header is in the file.
+        missing: Option<u64>,
+    },
+    /// The number of dimensions or a dimension size of a variable is out of bounds.
+    Shape(ShapeError),
+}
+
+/// Number of dimensions or dimension size that no valid CDF file holds, usually read from a
+/// corrupt file, which is reported before anything is allocated for the dimensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShapeError {
+    /// More dimensions than [`crate::decode::CDF_MAX_DIMS`].
+    TooManyDimensions {
+        /// Name of the field that holds the number of dimensions, as in the CDF specification.
+        field: &'static str,
+        /// The number of dimensions.
+        num_dims: usize,
+    },
+    /// A dimension size is negative.
+    InvalidDimensionSize {
+        /// Name of the field that holds the dimension sizes, as in the CDF specification.
+        field: &'static str,
+        /// Index of the dimension.
+        dim: usize,
+        /// The dimension size.
+        size: i64,
+    },
+}
+
+impl Display for ShapeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ShapeError::TooManyDimensions { field, num_dims } => write!(
+                f,
+                "Invalid {field} of {num_dims}, which is more than the {} dimensions of a CDF \
+                 variable.",
+                crate::decode::CDF_MAX_DIMS
+            ),
+            ShapeError::InvalidDimensionSize { field, dim, size } => {
+                write!(f, "Invalid size {size} of dimension {dim} in {field}.")
+            }
+        }
+    }
+}
+
+impl From<ShapeError> for CdfError {
+    fn from(value: ShapeError) -> Self {
+        CdfError::Shape(value)
+    }
 }
 
 impl From<io::Error> for CdfError {
@@ -26,14 +114,51 @@ impl From<TryFromIntError> for CdfError {
 }
 
 impl Display for CdfError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             CdfError::Decode(err) => write!(f, "{err}"),
             CdfError::Encode(_) => write!(f, "encoding error."),
             CdfError::Io(err) => err.fmt(f),
             CdfError::Other(err) => write!(f, "{err}"),
+            CdfError::CyclicRecordList { offset } => {
+                write!(f, "Linked-list of records loops back to offset {offset}.")
+            }
+            CdfError::MaxDepthExceeded { max_depth } => {
+                write!(f, "Records are nested deeper than {max_depth} levels.")
+            }
+            CdfError::InvalidCount { field, value } => {
+                write!(
+                    f,
+                    "Invalid {field} of {value}, which is negative or too large."
+                )
+            }
+            CdfError::OffsetOutOfBounds { offset, end } => {
+                write!(
+                    f,
+                    "Offset {offset} is outside of the file, which ends at byte {end}."
+                )
+            }
+            CdfError::Cancelled => write!(f, "Decoding was cancelled."),
+            CdfError::Truncated {
+                file_size,
+                offset,
+                record_type,
+                missing,
+            } => {
+                write!(f, "File truncated at byte {file_size}: record")?;
+                if let Some(record_type) = record_type {
+                    write!(f, " of type {record_type}")?;
+                }
+                write!(f, " at offset {offset}")?;
+                match missing {
+                    Some(missing) => write!(f, " needs {missing} more bytes."),
+                    None if offset >= file_size => write!(f, " is past the end of the file."),
+                    None => write!(f, " is cut off in its header."),
+                }
+            }
+            CdfError::Shape(err) => err.fmt(f),
         }
     }
 }
 
-impl std::error::Error for CdfError {}
+impl core::error::Error for CdfError {}
@@ -0,0 +1,422 @@
+use alloc::{format, string::String, vec, vec::Vec};
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::repr::Endian;
+use crate::types::{
+    CdfByte, CdfEpoch, CdfEpoch16, CdfInt1, CdfInt2, CdfInt4, CdfInt8, CdfReal4, CdfReal8,
+    CdfString, CdfTimeTt2000, CdfType, CdfUint1, CdfUint2, CdfUint4,
+};
+
+// Data types that can be stored in a generated variable.
+const DATA_TYPES: [i32; 17] = [
+    1, 2, 4, 8, 11, 12, 14, 21, 22, 31, 32, 33, 41, 44, 45, 51, 52,
+];
+
+// Sizes in bytes of the records written by the generator (CDF v3 layout).
+const CDR_SIZE: u64 = 312;
+const GDR_SIZE: u64 = 84;
+const ZVDR_BASE_SIZE: u64 = 344;
+const VXR_SIZE: u64 = 44;
+const VVR_BASE_SIZE: u64 = 12;
+
+/// One zVariable of an [`ArbitraryCdfFile`], along with the values stored for it.
+#[derive(Debug, Clone)]
+pub struct ArbitraryVariable {
+    /// Name of the variable.
+    pub name: String,
+    /// Type of data stored in the variable.
+    pub data_type: CdfInt4,
+    /// Number of elements of the data type in each value.
+    pub num_elements: CdfInt4,
+    /// Sizes of the dimensions of the variable. All dimensions vary.
+    pub dim_sizes: Vec<CdfInt4>,
+    /// Values of each record, as they are expected to be decoded.
+    pub records: Vec<Vec<CdfType>>,
+}
+
+/// A structurally valid CDF file with random contents, together with the values it stores. Use
+/// it to property-test the decoder, or mutate its bytes to fuzz the decoder with hostile input.
+#[derive(Debug, Clone)]
+pub struct ArbitraryCdfFile {
+    /// Endianness of the values stored in the file.
+    pub endianness: Endian,
+    /// Variables stored in the file, in order.
+    pub variables: Vec<ArbitraryVariable>,
+    /// The encoded file.
+    pub bytes: Vec<u8>,
+}
+
+impl<'a> Arbitrary<'a> for ArbitraryCdfFile {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let endianness = if u.arbitrary()? {
+            Endian::Big
+        } else {
+            Endian::Little
+        };
+        let num_variables = u.int_in_range(0..=4)?;
+        let variables = (0..num_variables)
+            .map(|i| ArbitraryVariable::arbitrary_numbered(u, i))
+            .collect::<Result<Vec<_>>>()?;
+        let bytes = encode_file(&endianness, &variables);
+        Ok(ArbitraryCdfFile {
+            endianness,
+            variables,
+            bytes,
+        })
+    }
+}
+
+impl ArbitraryVariable {
+    fn arbitrary_numbered(u: &mut Unstructured, num: usize) -> Result<Self> {
+        let data_type = *u.choose(&DATA_TYPES)?;
+        let num_elements = if matches!(data_type, 51 | 52) {
+            u.int_in_range(1..=8)?
+        } else {
+            1
+        };
+        let num_dims = u.int_in_range(0..=2)?;
+        let dim_sizes = (0..num_dims)
+            .map(|_| u.int_in_range(1..=3).map(CdfInt4::from))
+            .collect::<Result<Vec<_>>>()?;
+        let num_values: i32 = dim_sizes.iter().map(|s| **s).product();
+
+        let num_records = u.int_in_range(0..=5)?;
+        let records = (0..num_records)
+            .map(|_| random_record(u, data_type, num_elements, num_values))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ArbitraryVariable {
+            name: format!("var{num}_{}", u.int_in_range(0..=999)?),
+            data_type: CdfInt4::from(data_type),
+            num_elements: CdfInt4::from(num_elements),
+            dim_sizes,
+            records,
+        })
+    }
+
+    // Number of values of the data type in each record, as stored in the file.
+    fn values_per_record(&self) -> usize {
+        let num_values: i32 = self.dim_sizes.iter().map(|s| **s).product();
+        usize::try_from(*self.num_elements * num_values).unwrap_or(0)
+    }
+}
+
+fn random_record(
+    u: &mut Unstructured,
+    data_type: i32,
+    num_elements: i32,
+    num_values: i32,
+) -> Result<Vec<CdfType>> {
+    macro_rules! random_values {
+        ($cdf_type:ty, $enum_variant:ident) => {{
+            (0..num_values)
+                .map(|_| {
+                    let bytes: [u8; <$cdf_type>::size()] = u.arbitrary()?;
+                    Ok(CdfType::$enum_variant(<$cdf_type>::from_be_bytes(bytes)))
+                })
+                .collect()
+        }};
+    }
+    match data_type {
+        1 => random_values!(CdfInt1, Int1),
+        2 => random_values!(CdfInt2, Int2),
+        4 => random_values!(CdfInt4, Int4),
+        8 => random_values!(CdfInt8, Int8),
+        11 => random_values!(CdfUint1, Uint1),
+        12 => random_values!(CdfUint2, Uint2),
+        14 => random_values!(CdfUint4, Uint4),
+        21 | 44 => random_values!(CdfReal4, Real4),
+        22 | 45 => random_values!(CdfReal8, Real8),
+        31 => random_values!(CdfEpoch, Epoch),
+        32 => random_values!(CdfEpoch16, Epoch16),
+        33 => random_values!(CdfTimeTt2000, TimeTt2000),
+        41 => random_values!(CdfByte, Byte),
+        // Characters of all values of a record are decoded as one string.
+        _ => {
+            let len = usize::try_from(num_elements * num_values).unwrap_or(0);
+            let chars = (0..len)
+                .map(|_| u.int_in_range(b' '..=b'~').map(char::from))
+                .collect::<Result<String>>()?;
+            Ok(vec![CdfType::String(CdfString::from(chars))])
+        }
+    }
+}
+
+/// Bytes of `value` as stored in a file with the given endianness.
+pub fn value_bytes(value: &CdfType, endianness: &Endian) -> Vec<u8> {
+    macro_rules! bytes {
+        ($v:expr) => {
+            match endianness {
+                Endian::Big => $v.clone().to_be_bytes().to_vec(),
+                Endian::Little => $v.clone().to_le_bytes().to_vec(),
+            }
+        };
+    }
+    match value {
+        CdfType::Int1(v) => bytes!(v),
+        CdfType::Int2(v) => bytes!(v),
+        CdfType::Int4(v) => bytes!(v),
+        CdfType::Int8(v) => bytes!(v),
+        CdfType::Uint1(v) => bytes!(v),
+        CdfType::Uint2(v) => bytes!(v),
+        CdfType::Uint4(v) => bytes!(v),
+        CdfType::Real4(v) => bytes!(v),
+        CdfType::Real8(v) => bytes!(v),
+        CdfType::Epoch(v) => bytes!(v),
+        CdfType::Epoch16(v) => bytes!(v),
+        CdfType::TimeTt2000(v) => bytes!(v),
+        CdfType::Byte(v) => bytes!(v),
+        CdfType::Char(v) | CdfType::Uchar(v) => bytes!(v),
+        CdfType::String(s) => s.as_bytes().to_vec(),
+    }
+}
+
+fn put_i4(bytes: &mut Vec<u8>, value: i32) {
+    bytes.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_i8(bytes: &mut Vec<u8>, value: u64) {
+    bytes.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_name(bytes: &mut Vec<u8>, name: &str, len: usize) {
+    let start = bytes.len();
+    bytes.extend_from_slice(name.as_bytes());
+    bytes.resize(start + len, 0);
+}
+
+// Encode a single-file, uncompressed CDF v3.9.0 file holding `variables` as zVariables.
+fn encode_file(endianness: &Endian, variables: &[ArbitraryVariable]) -> Vec<u8> {
+    let encoding = match endianness {
+        Endian::Big => 1,
+        Endian::Little => 6,
+    };
+
+    // Records are laid out in order - CDR, GDR, then each zVDR followed by its VXR and VVR.
+    let gdr_offset = 8 + CDR_SIZE;
+    let mut offset = gdr_offset + GDR_SIZE;
+    let mut layout = Vec::with_capacity(variables.len());
+    for v in variables {
+        let zvdr = offset;
+        offset += ZVDR_BASE_SIZE + 8 * v.dim_sizes.len() as u64;
+        let vxr = offset;
+        let vvr_size = if v.records.is_empty() {
+            0
+        } else {
+            let record_size: usize = v.records[0]
+                .iter()
+                .map(|x| value_bytes(x, endianness).len())
+                .sum();
+            offset += VXR_SIZE;
+            VVR_BASE_SIZE + (record_size * v.records.len()) as u64
+        };
+        let vvr = offset;
+        offset += vvr_size;
+        layout.push((zvdr, vxr, vvr, vvr_size));
+    }
+    let eof = offset;
+
+    let mut bytes = Vec::with_capacity(usize::try_from(eof).unwrap_or(0));
+    bytes.extend_from_slice(&0xcdf30001u32.to_be_bytes());
+    bytes.extend_from_slice(&0x0000ffffu32.to_be_bytes());
+
+    // CDR
+    put_i8(&mut bytes, CDR_SIZE);
+    put_i4(&mut bytes, 1);
+    put_i8(&mut bytes, gdr_offset);
+    put_i4(&mut bytes, 3);
+    put_i4(&mut bytes, 9);
+    put_i4(&mut bytes, encoding);
+    put_i4(&mut bytes, 3); // row major, single file
+    put_i4(&mut bytes, 0);
+    put_i4(&mut bytes, 0);
+    put_i4(&mut bytes, 0);
+    put_i4(&mut bytes, -1);
+    put_i4(&mut bytes, -1);
+    put_name(&mut bytes, "Common Data Format (CDF)", 256);
+
+    // GDR
+    put_i8(&mut bytes, GDR_SIZE);
+    put_i4(&mut bytes, 2);
+    put_i8(&mut bytes, 0);
+    put_i8(&mut bytes, layout.first().map_or(0, |l| l.0));
+    put_i8(&mut bytes, 0);
+    put_i8(&mut bytes, eof);
+    put_i4(&mut bytes, 0);
+    put_i4(&mut bytes, 0);
+    put_i4(&mut bytes, -1);
+    put_i4(&mut bytes, 0);
+    put_i4(&mut bytes, variables.len() as i32);
+    put_i8(&mut bytes, 0);
+    put_i4(&mut bytes, 0);
+    put_i4(&mut bytes, 20170101);
+    put_i4(&mut bytes, -1);
+
+    for (i, (v, &(zvdr, vxr, vvr, vvr_size))) in variables.iter().zip(layout.iter()).enumerate() {
+        let has_records = !v.records.is_empty();
+        let next = layout.get(i + 1).map_or(0, |l| l.0);
+
+        put_i8(&mut bytes, vxr - zvdr);
+        put_i4(&mut bytes, 8);
+        put_i8(&mut bytes, next);
+        put_i4(&mut bytes, *v.data_type);
+        put_i4(&mut bytes, v.records.len() as i32 - 1);
+        put_i8(&mut bytes, if has_records { vxr } else { 0 });
+        put_i8(&mut bytes, if has_records { vxr } else { 0 });
+        put_i4(&mut bytes, 1); // record variance
+        put_i4(&mut bytes, 0);
+        put_i4(&mut bytes, 0);
+        put_i4(&mut bytes, -1);
+        put_i4(&mut bytes, -1);
+        put_i4(&mut bytes, *v.num_elements);
+        put_i4(&mut bytes, i as i32);
+        bytes.extend_from_slice(&(-1i64).to_be_bytes());
+        put_i4(&mut bytes, 0);
+        put_name(&mut bytes, &v.name, 256);
+        put_i4(&mut bytes, v.dim_sizes.len() as i32);
+        for size in &v.dim_sizes {
+            put_i4(&mut bytes, **size);
+        }
+        for _ in &v.dim_sizes {
+            put_i4(&mut bytes, -1);
+        }
+
+        if has_records {
+            put_i8(&mut bytes, VXR_SIZE);
+            put_i4(&mut bytes, 6);
+            put_i8(&mut bytes, 0);
+            put_i4(&mut bytes, 1);
+            put_i4(&mut bytes, 1);
+            put_i4(&mut bytes, 0);
+            put_i4(&mut bytes, v.records.len() as i32 - 1);
+            put_i8(&mut bytes, vvr);
+
+            put_i8(&mut bytes, vvr_size);
+            put_i4(&mut bytes, 7);
+            for record in &v.records {
+                for value in record {
+                    bytes.extend(value_bytes(value, endianness));
+                }
+            }
+            debug_assert_eq!(
+                v.values_per_record(),
+                v.records[0]
+                    .iter()
+                    .map(|x| match x {
+                        CdfType::String(s) => s.len(),
+                        _ => 1,
+                    })
+                    .sum::<usize>()
+            );
+        }
+    }
+    debug_assert_eq!(bytes.len() as u64, eof);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::cdf::Cdf;
+    use crate::decode::{Decodable, Decoder};
+    use crate::error::CdfError;
+    use std::io;
+
+    // Deterministic pseudo-random bytes to drive `Unstructured`.
+    fn _random_bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(0x9e3779b97f4a7c15) | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 32) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_generated_files_round_trip() -> Result<(), CdfError> {
+        for seed in 0..64 {
+            let data = _random_bytes(seed, 2048);
+            let file = ArbitraryCdfFile::arbitrary(&mut Unstructured::new(&data))
+                .map_err(|e| CdfError::Other(format!("{e}")))?;
+
+            let cdf = Cdf::from_bytes(&file.bytes)?;
+            let variables = cdf.variables();
+            assert_eq!(variables.len(), file.variables.len());
+            for (variable, expected) in variables.iter().zip(file.variables.iter()) {
+                assert_eq!(variable.name(), expected.name);
+                let records = variable.read_all()?;
+                assert_eq!(records.len(), expected.records.len());
+                for (record, values) in records.iter().zip(expected.records.iter()) {
+                    let decoded: Vec<u8> = record
+                        .data
+                        .iter()
+                        .flat_map(|v| value_bytes(v, &file.endianness))
+                        .collect();
+                    let stored: Vec<u8> = values
+                        .iter()
+                        .flat_map(|v| value_bytes(v, &file.endianness))
+                        .collect();
+                    assert_eq!(decoded, stored);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_hostile_files_do_not_panic() -> Result<(), CdfError> {
+        for seed in 0..256 {
+            let data = _random_bytes(seed, 2048);
+            let Ok(file) = ArbitraryCdfFile::arbitrary(&mut Unstructured::new(&data)) else {
+                continue;
+            };
+
+            // Corrupt a few bytes of an otherwise valid file.
+            let mut bytes = file.bytes;
+            let noise = _random_bytes(seed + 1000, 16);
+            for pair in noise.chunks(2) {
+                let i = (usize::from(pair[0]) * 131 + usize::from(pair[1])) % bytes.len();
+                bytes[i] ^= pair[1] | 1;
+            }
+
+            let mut decoder = Decoder::builder()
+                .max_alloc(1 << 20)
+                .build(io::Cursor::new(bytes.as_slice()))?;
+            _ = Cdf::decode_be(&mut decoder);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_looping_records() -> Result<(), CdfError> {
+        let data = _random_bytes(7, 2048);
+        let mut u = Unstructured::new(&data);
+        let variable = ArbitraryVariable::arbitrary_numbered(&mut u, 0)
+            .map_err(|e| CdfError::Other(format!("{e}")))?;
+        let mut bytes = encode_file(&Endian::Big, &[variable]);
+
+        // Point the first zVDR back to itself.
+        let zvdr = 8 + CDR_SIZE + GDR_SIZE;
+        let next = usize::try_from(zvdr + 12)?;
+        bytes[next..next + 8].copy_from_slice(&zvdr.to_be_bytes());
+        assert!(matches!(
+            Cdf::from_bytes(&bytes),
+            Err(CdfError::CyclicRecordList { offset }) if u64::try_from(offset) == Ok(zvdr)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_arbitrary_records() {
+        for seed in 0..64 {
+            let data = _random_bytes(seed, 4096);
+            let mut u = Unstructured::new(&data);
+            _ = Cdf::arbitrary(&mut u);
+        }
+    }
+}
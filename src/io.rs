@@ -0,0 +1,616 @@
+#[cfg(feature = "std")]
+pub use std::io::{empty, Cursor, Empty, Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+
+#[cfg(not(feature = "std"))]
+pub use self::core_io::*;
+
+#[cfg(not(feature = "std"))]
+mod core_io {
+    use alloc::{boxed::Box, vec::Vec};
+    use core::fmt;
+
+    /// Result type of the I/O operations in this module.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// Kinds of I/O errors that can occur while decoding.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ErrorKind {
+        /// The reader ended before the requested number of bytes could be read.
+        UnexpectedEof,
+        /// An invalid argument was passed, for example a seek to a negative position.
+        InvalidInput,
+        /// The writer could not accept any more bytes.
+        WriteZero,
+        /// Any other error reported by a reader.
+        Other,
+    }
+
+    /// Error returned by [`Read`], [`Write`] and [`Seek`] implementations.
+    #[derive(Debug)]
+    pub struct Error {
+        kind: ErrorKind,
+        message: &'static str,
+    }
+
+    impl Error {
+        /// Create a new error of the given kind.
+        pub const fn new(kind: ErrorKind, message: &'static str) -> Self {
+            Error { kind, message }
+        }
+
+        /// Kind of this error.
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.message)
+        }
+    }
+
+    impl core::error::Error for Error {}
+
+    /// Position to seek to, relative to the start, the end or the current position of a reader.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SeekFrom {
+        /// Offset from the start.
+        Start(u64),
+        /// Offset from the end.
+        End(i64),
+        /// Offset from the current position.
+        Current(i64),
+    }
+
+    /// Source of bytes, mirroring `std::io::Read`.
+    pub trait Read {
+        /// Read some bytes into `buf`, returning how many bytes were read.
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+        /// Read exactly enough bytes to fill `buf`.
+        fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.read(buf)? {
+                    0 => {
+                        return Err(Error::new(
+                            ErrorKind::UnexpectedEof,
+                            "failed to fill whole buffer",
+                        ))
+                    }
+                    n => buf = &mut buf[n..],
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Sink of bytes, mirroring `std::io::Write`.
+    pub trait Write {
+        /// Write some bytes from `buf`, returning how many bytes were written.
+        fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+        /// Flush any buffered bytes to the sink.
+        fn flush(&mut self) -> Result<()>;
+
+        /// Write all bytes of `buf`.
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.write(buf)? {
+                    0 => {
+                        return Err(Error::new(
+                            ErrorKind::WriteZero,
+                            "failed to write whole buffer",
+                        ))
+                    }
+                    n => buf = &buf[n..],
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Reader with a position that can be moved, mirroring `std::io::Seek`.
+    pub trait Seek {
+        /// Seek to `pos`, returning the new position from the start.
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64>;
+
+        /// Current position from the start.
+        fn stream_position(&mut self) -> Result<u64> {
+            self.seek(SeekFrom::Current(0))
+        }
+
+        /// Seek relative to the current position.
+        fn seek_relative(&mut self, offset: i64) -> Result<()> {
+            self.seek(SeekFrom::Current(offset)).map(|_| ())
+        }
+    }
+
+    impl<R: Read + ?Sized> Read for &mut R {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+    }
+
+    impl<S: Seek + ?Sized> Seek for &mut S {
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+            (**self).seek(pos)
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            (**self).write(buf)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            (**self).flush()
+        }
+    }
+
+    impl<R: Read + ?Sized> Read for Box<R> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+    }
+
+    impl<S: Seek + ?Sized> Seek for Box<S> {
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+            (**self).seek(pos)
+        }
+    }
+
+    /// In-memory reader over a byte buffer, mirroring `std::io::Cursor`.
+    #[derive(Debug, Clone, Default)]
+    pub struct Cursor<T> {
+        inner: T,
+        pos: u64,
+    }
+
+    impl<T> Cursor<T> {
+        /// Create a cursor at the start of `inner`.
+        pub const fn new(inner: T) -> Self {
+            Cursor { inner, pos: 0 }
+        }
+
+        /// Consume the cursor and return the buffer.
+        pub fn into_inner(self) -> T {
+            self.inner
+        }
+
+        /// Reference to the buffer.
+        pub const fn get_ref(&self) -> &T {
+            &self.inner
+        }
+
+        /// Current position of the cursor.
+        pub const fn position(&self) -> u64 {
+            self.pos
+        }
+
+        /// Move the cursor to `pos`.
+        pub fn set_position(&mut self, pos: u64) {
+            self.pos = pos;
+        }
+    }
+
+    impl<T: AsRef<[u8]>> Read for Cursor<T> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let data = self.inner.as_ref();
+            let start = usize::try_from(self.pos).map_or(data.len(), |p| p.min(data.len()));
+            let n = buf.len().min(data.len() - start);
+            buf[..n].copy_from_slice(&data[start..start + n]);
+            self.pos += n as u64;
+            Ok(n)
+        }
+    }
+
+    impl<T: AsRef<[u8]>> Seek for Cursor<T> {
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+            let (base, offset) = match pos {
+                SeekFrom::Start(n) => {
+                    self.pos = n;
+                    return Ok(n);
+                }
+                SeekFrom::End(n) => (self.inner.as_ref().len() as u64, n),
+                SeekFrom::Current(n) => (self.pos, n),
+            };
+            match base.checked_add_signed(offset) {
+                Some(n) => {
+                    self.pos = n;
+                    Ok(n)
+                }
+                None => Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "invalid seek to a negative or overflowing position",
+                )),
+            }
+        }
+    }
+
+    impl Write for Cursor<Vec<u8>> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            let start = usize::try_from(self.pos).map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    "cursor position does not fit in memory",
+                )
+            })?;
+            let end = start + buf.len();
+            if self.inner.len() < end {
+                self.inner.resize(end, 0);
+            }
+            self.inner[start..end].copy_from_slice(buf);
+            self.pos = end as u64;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Write for Cursor<&mut [u8]> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            let len = self.inner.len();
+            let start = usize::try_from(self.pos).map_or(len, |p| p.min(len));
+            let n = buf.len().min(len - start);
+            self.inner[start..start + n].copy_from_slice(&buf[..n]);
+            self.pos += n as u64;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Reader that is always at its end, mirroring `std::io::Empty`.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct Empty;
+
+    /// Create an [`Empty`] reader.
+    pub const fn empty() -> Empty {
+        Empty
+    }
+
+    impl Read for Empty {
+        fn read(&mut self, _: &mut [u8]) -> Result<usize> {
+            Ok(0)
+        }
+    }
+
+    impl Seek for Empty {
+        fn seek(&mut self, _: SeekFrom) -> Result<u64> {
+            Ok(0)
+        }
+    }
+
+    impl Write for Empty {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+}
+
+/// Default capacity of a [`BufferedReader`], which holds many small records or a good part of a
+/// large VVR, so that sequential reads of values need few reads of the underlying reader.
+pub const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Reader that buffers the bytes of another reader, like `std::io::BufReader`, but keeps its
+/// buffer when seeking to a position that is already buffered. The decoder seeks to every record
+/// it follows and back after peeking at record headers, which would otherwise empty the buffer.
+pub struct BufferedReader<R> {
+    inner: R,
+    buf: alloc::vec::Vec<u8>,
+    pos: usize,
+    filled: usize,
+    // Position of the inner reader, which is just after the buffered bytes, once it is known.
+    end: Option<u64>,
+}
+
+impl<R> BufferedReader<R> {
+    /// Create a reader over `inner` with a buffer of [`DEFAULT_BUFFER_SIZE`] bytes.
+    pub fn new(inner: R) -> Self {
+        BufferedReader::with_capacity(DEFAULT_BUFFER_SIZE, inner)
+    }
+
+    /// Create a reader over `inner` with a buffer of `capacity` bytes. Reads of at least
+    /// `capacity` bytes go directly to `inner`.
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        BufferedReader {
+            inner,
+            buf: alloc::vec![0; capacity],
+            pos: 0,
+            filled: 0,
+            end: None,
+        }
+    }
+
+    /// Size of the buffer in bytes.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Bytes that are buffered and not read yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+
+    /// Reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Consume this reader and return the underlying reader, whose position is after the bytes
+    /// that were buffered.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> BufferedReader<R> {
+    // Bytes that are buffered and not read yet, reading more from the inner reader if there are
+    // none.
+    fn fill_buffer(&mut self) -> Result<&[u8]> {
+        if self.pos == self.filled {
+            let n = self.inner.read(&mut self.buf)?;
+            self.pos = 0;
+            self.filled = n;
+            self.end = self.end.map(|end| end + n as u64);
+        }
+        Ok(&self.buf[self.pos..self.filled])
+    }
+}
+
+impl<R: Read> Read for BufferedReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> Result<usize> {
+        if self.pos == self.filled && out.len() >= self.buf.len() {
+            let n = self.inner.read(out)?;
+            self.end = self.end.map(|end| end + n as u64);
+            return Ok(n);
+        }
+        let buffered = self.fill_buffer()?;
+        let n = out.len().min(buffered.len());
+        out[..n].copy_from_slice(&buffered[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> std::io::BufRead for BufferedReader<R> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        self.fill_buffer()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.pos = (self.pos + amount).min(self.filled);
+    }
+}
+
+impl<R: Seek> Seek for BufferedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(n) => {
+                let end = match self.end {
+                    Some(end) => end,
+                    None => self.inner.stream_position()?,
+                };
+                self.end = Some(end);
+                let target = (end - (self.filled - self.pos) as u64).checked_add_signed(n);
+                if target.is_none() {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "invalid seek to a negative or overflowing position",
+                    ));
+                }
+                target
+            }
+            SeekFrom::End(_) => None,
+        };
+        if let (Some(target), Some(end)) = (target, self.end) {
+            let start = end - self.filled as u64;
+            if (start..=end).contains(&target) {
+                self.pos = (target - start) as usize;
+                return Ok(target);
+            }
+        }
+        self.pos = 0;
+        self.filled = 0;
+        let end = self.inner.seek(target.map_or(pos, SeekFrom::Start))?;
+        self.end = Some(end);
+        Ok(end)
+    }
+}
+
+/// Reader that serves reads from records read ahead of time, keyed by their offset, and reads
+/// everything else from another reader. Records can then be read in file order once, and decoded
+/// in the order in which the decoder follows them without going back to the underlying reader.
+pub struct PrefetchedReader<R> {
+    inner: R,
+    records: alloc::collections::BTreeMap<u64, alloc::vec::Vec<u8>>,
+    pos: u64,
+    // Position of the inner reader, if it is known.
+    inner_pos: Option<u64>,
+}
+
+impl<R> PrefetchedReader<R> {
+    /// Create a reader over `inner` that serves the bytes of `records`, which are keyed by their
+    /// offset, from memory. The reader starts at offset zero.
+    pub fn new(inner: R, records: alloc::collections::BTreeMap<u64, alloc::vec::Vec<u8>>) -> Self {
+        PrefetchedReader {
+            inner,
+            records,
+            pos: 0,
+            inner_pos: None,
+        }
+    }
+
+    /// Reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Consume this reader and return the underlying reader, whose position is unspecified.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read + Seek> Read for PrefetchedReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> Result<usize> {
+        let pos = self.pos;
+        let prefetched = self
+            .records
+            .range(..=pos)
+            .next_back()
+            .and_then(|(start, bytes)| bytes.get(usize::try_from(pos - start).ok()?..))
+            .filter(|rest| !rest.is_empty());
+        let n = match prefetched {
+            Some(rest) => {
+                let n = out.len().min(rest.len());
+                out[..n].copy_from_slice(&rest[..n]);
+                n
+            }
+            None => {
+                if self.inner_pos != Some(pos) {
+                    _ = self.inner.seek(SeekFrom::Start(pos))?;
+                }
+                let n = self.inner.read(out)?;
+                self.inner_pos = Some(pos + n as u64);
+                n
+            }
+        };
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for PrefetchedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.pos = match pos {
+            SeekFrom::Start(n) => n,
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n).ok_or(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            ))?,
+            SeekFrom::End(_) => {
+                let end = self.inner.seek(pos)?;
+                self.inner_pos = Some(end);
+                end
+            }
+        };
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    // Reader that counts the reads and seeks of the underlying cursor.
+    struct Counting {
+        cursor: Cursor<Vec<u8>>,
+        reads: usize,
+        seeks: usize,
+    }
+
+    impl Read for Counting {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            self.reads += 1;
+            self.cursor.read(buf)
+        }
+    }
+
+    impl Seek for Counting {
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+            self.seeks += 1;
+            self.cursor.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_buffered_reader() -> Result<()> {
+        let bytes: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut reader = BufferedReader::with_capacity(
+            100,
+            Counting {
+                cursor: Cursor::new(bytes.clone()),
+                reads: 0,
+                seeks: 0,
+            },
+        );
+        let mut buf = [0; 10];
+        reader.read_exact(&mut buf)?;
+        assert_eq!(buf[..], bytes[..10]);
+
+        // Seeks within the buffer keep it.
+        assert_eq!(reader.seek(SeekFrom::Current(-4))?, 6);
+        reader.read_exact(&mut buf)?;
+        assert_eq!(buf[..], bytes[6..16]);
+        assert_eq!(reader.seek(SeekFrom::Start(90))?, 90);
+        assert_eq!(reader.stream_position()?, 90);
+        reader.read_exact(&mut buf)?;
+        assert_eq!(buf[..], bytes[90..100]);
+        assert_eq!((reader.get_ref().reads, reader.get_ref().seeks), (1, 1));
+
+        // Other seeks go to the underlying reader, and large reads bypass the buffer.
+        assert_eq!(reader.seek(SeekFrom::Start(500))?, 500);
+        let mut large = [0; 200];
+        reader.read_exact(&mut large)?;
+        assert_eq!(large[..], bytes[500..700]);
+        assert_eq!(reader.seek(SeekFrom::End(-5))?, 995);
+        assert_eq!(reader.read(&mut buf)?, 5);
+        assert_eq!(buf[..5], bytes[995..]);
+        assert_eq!(reader.read(&mut buf)?, 0);
+        assert!(reader.seek(SeekFrom::Current(-2000)).is_err());
+        assert_eq!(reader.stream_position()?, 1000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefetched_reader() -> Result<()> {
+        let bytes: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let records = [
+            (100, bytes[100..200].to_vec()),
+            (300, bytes[300..310].to_vec()),
+        ];
+        let mut reader = PrefetchedReader::new(
+            Counting {
+                cursor: Cursor::new(bytes.clone()),
+                reads: 0,
+                seeks: 0,
+            },
+            records.into_iter().collect(),
+        );
+
+        // Reads within the records do not touch the underlying reader.
+        let mut buf = [0; 10];
+        assert_eq!(reader.seek(SeekFrom::Start(300))?, 300);
+        reader.read_exact(&mut buf)?;
+        assert_eq!(buf[..], bytes[300..310]);
+        assert_eq!(reader.seek(SeekFrom::Current(-205))?, 105);
+        reader.read_exact(&mut buf)?;
+        assert_eq!(buf[..], bytes[105..115]);
+        assert_eq!((reader.get_ref().reads, reader.get_ref().seeks), (0, 0));
+
+        // Other reads go to the underlying reader, also across the end of a record.
+        assert_eq!(reader.seek(SeekFrom::Start(195))?, 195);
+        let mut large = [0; 20];
+        reader.read_exact(&mut large)?;
+        assert_eq!(large[..], bytes[195..215]);
+        assert_eq!(reader.get_ref().seeks, 1);
+        assert_eq!(reader.seek(SeekFrom::End(-5))?, 995);
+        assert_eq!(reader.read(&mut buf)?, 5);
+        assert_eq!(buf[..5], bytes[995..]);
+        assert!(reader.seek(SeekFrom::Current(-2000)).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,360 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::error::CdfError;
+use crate::io;
+use crate::time::CdfTime;
+use crate::types::{
+    CdfByte, CdfEpoch, CdfEpoch16, CdfInt1, CdfInt2, CdfInt4, CdfInt8, CdfReal4, CdfReal8,
+    CdfTimeTt2000, CdfType, CdfUint1, CdfUint2, CdfUint4, DataType,
+};
+use crate::variable::Variable;
+use crate::writer::{AttributeValue, CdfWriter};
+
+/// Global attributes that the ISTP guidelines require in every file.
+pub const REQUIRED_GLOBAL_ATTRIBUTES: [&str; 14] = [
+    "Project",
+    "Source_name",
+    "Discipline",
+    "Data_type",
+    "Descriptor",
+    "Data_version",
+    "Logical_file_id",
+    "PI_name",
+    "PI_affiliation",
+    "TEXT",
+    "Instrument_type",
+    "Mission_group",
+    "Logical_source",
+    "Logical_source_description",
+];
+
+// Range of times accepted by VALIDMIN and VALIDMAX of time variables, 1990-01-01 to 2100-01-01,
+// as nanoseconds since the Unix epoch.
+const VALID_TIMES: (i128, i128) = (631_152_000_000_000_000, 4_102_444_800_000_000_000);
+
+/// Kind of plot that the data variables of a file are meant for, written to their `DISPLAY_TYPE`
+/// attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IstpKind {
+    /// Values plotted against time, one line per component.
+    TimeSeries,
+    /// Values along a second axis, such as energy, shown as colors against time.
+    Spectrogram,
+    /// Several components stacked above each other against time.
+    StackPlot,
+    /// Two-dimensional values shown as images.
+    Image,
+}
+
+impl IstpKind {
+    /// Value of the `DISPLAY_TYPE` attribute for this kind.
+    pub fn display_type(&self) -> &'static str {
+        match self {
+            IstpKind::TimeSeries => "time_series",
+            IstpKind::Spectrogram => "spectrogram",
+            IstpKind::StackPlot => "stack_plot",
+            IstpKind::Image => "image",
+        }
+    }
+}
+
+/// Create the attributes that the ISTP guidelines require for the file written by `writer` and
+/// for each of its variables, so that a new file starts out compliant.
+///
+/// - Each of [`REQUIRED_GLOBAL_ATTRIBUTES`] gets an entry holding a single space, to be filled in.
+/// - Variables holding times become `support_data`, and the first of them is the `DEPEND_0` of
+///   the other variables. Variables holding strings become `metadata`, and the others become
+///   `data` displayed as `kind`.
+/// - `FILLVAL`, `VALIDMIN` and `VALIDMAX` are of the data type of their variable, with the ISTP
+///   fill value of the type and the range of the type, or 1990 to 2100 for times. `FIELDNAM`,
+///   `CATDESC` and `LABLAXIS` are the name of the variable, and `UNITS` is a single space.
+///
+/// Attributes and entries that already exist are kept, so attributes set before this call take
+/// precedence. Entries of variable attributes can also be replaced afterwards with
+/// [`CdfWriter::var_attr`].
+///
+/// ```
+/// use cdf::cdf::Cdf;
+/// use cdf::istp::{apply_template, IstpKind};
+/// use cdf::types::DataType;
+/// use cdf::writer::{CdfWriter, VariableSpec};
+/// use std::io::Cursor;
+///
+/// let mut writer = CdfWriter::builder()
+///     .variable(VariableSpec::new("Epoch", DataType::TimeTt2000))
+///     .variable(VariableSpec::new("B_mag", DataType::Real4))
+///     .build(Cursor::new(Vec::new()))
+///     .unwrap();
+/// writer.global_attr("Project").unwrap().entry("ISTP>International Solar-Terrestrial Physics");
+/// apply_template(&mut writer, IstpKind::TimeSeries).unwrap();
+/// writer.var_attr("B_mag", "UNITS", "nT").unwrap();
+/// let bytes = writer.close().unwrap().into_inner();
+///
+/// let cdf = Cdf::from_bytes(&bytes).unwrap();
+/// let b_mag = cdf.variable("B_mag").unwrap();
+/// assert_eq!(b_mag.attribute_str("DEPEND_0"), Some("Epoch"));
+/// assert_eq!(b_mag.attribute_str("UNITS"), Some("nT"));
+/// ```
+/// # Errors
+/// Returns a [`CdfError::Encode`] if one of the attributes is already declared with the other
+/// scope.
+pub fn apply_template<W>(writer: &mut CdfWriter<W>, kind: IstpKind) -> Result<(), CdfError>
+where
+    W: io::Write + io::Seek,
+{
+    for name in REQUIRED_GLOBAL_ATTRIBUTES {
+        if !writer.has_entry(name, None) {
+            writer.global_attr(name)?.entry(" ");
+        }
+    }
+
+    let variables: Vec<(String, DataType)> = writer
+        .specs()
+        .map(|spec| (spec.name().to_string(), spec.data_type()))
+        .collect();
+    let epoch = variables
+        .iter()
+        .find(|(_, data_type)| is_time(*data_type))
+        .map(|(name, _)| name.clone());
+    for (name, data_type) in &variables {
+        let mut attributes: Vec<(&str, AttributeValue)> = vec![
+            ("FIELDNAM", name.as_str().into()),
+            ("CATDESC", name.as_str().into()),
+        ];
+        if data_type.is_string() {
+            attributes.push(("VAR_TYPE", "metadata".into()));
+        } else {
+            let (fill, min, max) = fill_and_range(*data_type)?;
+            attributes.extend([
+                ("FILLVAL", AttributeValue::new(*data_type, vec![fill])?),
+                ("VALIDMIN", AttributeValue::new(*data_type, vec![min])?),
+                ("VALIDMAX", AttributeValue::new(*data_type, vec![max])?),
+                ("UNITS", " ".into()),
+            ]);
+            if is_time(*data_type) {
+                attributes.push(("VAR_TYPE", "support_data".into()));
+            } else {
+                attributes.extend([
+                    ("VAR_TYPE", "data".into()),
+                    ("DISPLAY_TYPE", kind.display_type().into()),
+                    ("LABLAXIS", name.as_str().into()),
+                ]);
+            }
+        }
+        if let Some(epoch) = epoch.as_ref().filter(|epoch| *epoch != name) {
+            attributes.push(("DEPEND_0", epoch.as_str().into()));
+        }
+        for (attribute, value) in attributes {
+            if !writer.has_entry(attribute, Some(name)) {
+                writer.var_attr(name, attribute, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Attributes of the CF conventions that translate the ISTP attributes of `variable`, so that it
+/// can be exported to netCDF or Zarr files that open cleanly in tools such as xarray or Panoply.
+///
+/// - `UNITS` becomes `units` and `CATDESC` becomes `long_name`, unless they are blank.
+/// - `FILLVAL`, `VALIDMIN` and `VALIDMAX` become `_FillValue`, `valid_min` and `valid_max` if they
+///   have the data type of the variable, as the CF conventions require.
+/// - `DEPEND_0`, `DEPEND_1`, ... become `coordinates`, the names of the variables they point to
+///   separated by spaces.
+///
+/// Attributes that `variable` does not have are left out.
+/// # Errors
+/// Returns a [`CdfError`] if the data type or the shape of `variable` is invalid.
+pub fn cf_attributes(variable: &Variable) -> Result<Vec<(&'static str, Vec<CdfType>)>, CdfError> {
+    let data_type = DataType::try_from(variable.data_type().clone())?;
+    let mut attributes = Vec::new();
+    for (istp, cf) in [("UNITS", "units"), ("CATDESC", "long_name")] {
+        if let Some(value) = variable.attribute_str(istp).map(str::trim) {
+            if !value.is_empty() {
+                attributes.push((cf, vec![CdfType::String(value.to_string().into())]));
+            }
+        }
+    }
+    for (istp, cf) in [
+        ("FILLVAL", "_FillValue"),
+        ("VALIDMIN", "valid_min"),
+        ("VALIDMAX", "valid_max"),
+    ] {
+        if let Some(value @ [v]) = variable.attribute(istp) {
+            if v.is_of_type(&data_type) {
+                attributes.push((cf, value.to_vec()));
+            }
+        }
+    }
+    let coordinates: Vec<&str> = (0..=variable.shape()?.len())
+        .filter_map(|i| Some(variable.companion(&format!("DEPEND_{i}"))?.name()))
+        .collect();
+    if !coordinates.is_empty() {
+        let coordinates = coordinates.join(" ");
+        attributes.push(("coordinates", vec![CdfType::String(coordinates.into())]));
+    }
+    Ok(attributes)
+}
+
+fn is_time(data_type: DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Epoch | DataType::Epoch16 | DataType::TimeTt2000
+    )
+}
+
+// The ISTP fill value of `data_type`, and the smallest and largest valid values.
+fn fill_and_range(data_type: DataType) -> Result<(CdfType, CdfType, CdfType), CdfError> {
+    macro_rules! values {
+        ($variant:ident, $cdf_type:ident, $fill:expr, $min:expr, $max:expr) => {
+            (
+                CdfType::$variant($cdf_type::from($fill)),
+                CdfType::$variant($cdf_type::from($min)),
+                CdfType::$variant($cdf_type::from($max)),
+            )
+        };
+    }
+    macro_rules! time {
+        ($variant:ident, $cdf_type:ident, $fill:expr) => {
+            (
+                CdfType::$variant($fill),
+                CdfType::$variant($cdf_type::from_unix_nanos(VALID_TIMES.0)?),
+                CdfType::$variant($cdf_type::from_unix_nanos(VALID_TIMES.1)?),
+            )
+        };
+    }
+    Ok(match data_type {
+        DataType::Int1 => values!(Int1, CdfInt1, i8::MIN, i8::MIN + 1, i8::MAX),
+        DataType::Byte => values!(Byte, CdfByte, i8::MIN, i8::MIN + 1, i8::MAX),
+        DataType::Int2 => values!(Int2, CdfInt2, i16::MIN, i16::MIN + 1, i16::MAX),
+        DataType::Int4 => values!(Int4, CdfInt4, i32::MIN, i32::MIN + 1, i32::MAX),
+        DataType::Int8 => values!(Int8, CdfInt8, i64::MIN, i64::MIN + 1, i64::MAX),
+        DataType::Uint1 => values!(Uint1, CdfUint1, u8::MAX, 0, u8::MAX - 1),
+        DataType::Uint2 => values!(Uint2, CdfUint2, u16::MAX, 0, u16::MAX - 1),
+        DataType::Uint4 => values!(Uint4, CdfUint4, u32::MAX, 0, u32::MAX - 1),
+        DataType::Real4 | DataType::Float => values!(Real4, CdfReal4, -1e31, -1e30, 1e30),
+        DataType::Real8 | DataType::Double => values!(Real8, CdfReal8, -1e31, -1e30, 1e30),
+        DataType::Epoch => time!(Epoch, CdfEpoch, CdfEpoch::from(CdfEpoch::FILL)),
+        DataType::Epoch16 => time!(
+            Epoch16,
+            CdfEpoch16,
+            CdfEpoch16::new(CdfEpoch::FILL, CdfEpoch::FILL)
+        ),
+        DataType::TimeTt2000 => time!(
+            TimeTt2000,
+            CdfTimeTt2000,
+            CdfTimeTt2000::from(CdfTimeTt2000::FILL)
+        ),
+        DataType::Char | DataType::Uchar => {
+            return Err(CdfError::Encode(
+                "Strings have no ISTP fill value.".to_string(),
+            ))
+        }
+    })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::cdf::Cdf;
+    use crate::writer::VariableSpec;
+    use alloc::format;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_apply_template() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("Epoch", DataType::Epoch))
+            .variable(VariableSpec::new("counts", DataType::Uint2).dims(&[4]))
+            .variable(VariableSpec::new("label", DataType::Char).num_elements(8))
+            .build(Cursor::new(Vec::new()))?;
+        writer.global_attr("PI_name")?.entry("A. Researcher");
+        writer.var_attr("counts", "UNITS", "counts/s")?;
+        apply_template(&mut writer, IstpKind::Spectrogram)?;
+        apply_template(&mut writer, IstpKind::Spectrogram)?;
+        let cdf = Cdf::from_bytes(writer.close()?.get_ref())?;
+
+        let global = |name: &str| {
+            let adr = cdf.cdr.gdr.adr_vec.iter().find(|adr| *adr.name == name);
+            adr.map(|adr| adr.agredr_vec.len())
+        };
+        for name in REQUIRED_GLOBAL_ATTRIBUTES {
+            assert_eq!(global(name), Some(1), "{name}");
+        }
+
+        let epoch = cdf.variable("Epoch")?;
+        assert_eq!(epoch.attribute_str("VAR_TYPE"), Some("support_data"));
+        assert_eq!(epoch.attribute_str("DEPEND_0"), None);
+        assert_eq!(
+            format!("{:?}", epoch.attribute("FILLVAL")),
+            format!("{:?}", Some([CdfType::Epoch(CdfEpoch::from(-1e31))]))
+        );
+
+        let counts = cdf.variable("counts")?;
+        assert_eq!(counts.attribute_str("VAR_TYPE"), Some("data"));
+        assert_eq!(counts.attribute_str("DISPLAY_TYPE"), Some("spectrogram"));
+        assert_eq!(counts.attribute_str("DEPEND_0"), Some("Epoch"));
+        assert_eq!(counts.attribute_str("UNITS"), Some("counts/s"));
+        assert_eq!(counts.attribute_str("FIELDNAM"), Some("counts"));
+        assert_eq!(
+            format!("{:?}", counts.attribute("VALIDMAX")),
+            format!("{:?}", Some([CdfType::Uint2(CdfUint2::from(65534))]))
+        );
+
+        let label = cdf.variable("label")?;
+        assert_eq!(label.attribute_str("VAR_TYPE"), Some("metadata"));
+        assert!(label.attribute("FILLVAL").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cf_attributes() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("Epoch", DataType::TimeTt2000))
+            .variable(VariableSpec::new("flux", DataType::Real4).dims(&[3]))
+            .variable(VariableSpec::new("energy", DataType::Real4).dims(&[3]))
+            .build(Cursor::new(Vec::new()))?;
+        apply_template(&mut writer, IstpKind::Spectrogram)?;
+        writer.var_attr("flux", "UNITS", "1/(cm^2 s sr keV)")?;
+        writer.var_attr("flux", "CATDESC", "Differential particle flux")?;
+        writer.var_attr("flux", "DEPEND_1", "energy")?;
+        writer.var_attr("flux", "VALIDMAX", 1e30_f64)?;
+        let cdf = Cdf::from_bytes(writer.close()?.get_ref())?;
+
+        let attributes = cf_attributes(&cdf.variable("flux")?)?;
+        let names: Vec<&str> = attributes.iter().map(|(name, _)| *name).collect();
+        // The Real8 VALIDMAX does not have the type of the variable.
+        assert_eq!(
+            names,
+            [
+                "units",
+                "long_name",
+                "_FillValue",
+                "valid_min",
+                "coordinates"
+            ]
+        );
+        let value = |name: &str| {
+            let (_, value) = attributes.iter().find(|(n, _)| *n == name).unwrap();
+            format!("{value:?}")
+        };
+        assert_eq!(value("units"), "[String(1/(cm^2 s sr keV))]");
+        assert_eq!(value("long_name"), "[String(Differential particle flux)]");
+        assert_eq!(
+            value("_FillValue"),
+            format!("{:?}", [CdfType::Real4(CdfReal4::from(-1e31))])
+        );
+        assert_eq!(value("coordinates"), "[String(Epoch energy)]");
+
+        // The blank UNITS of the template are left out.
+        let attributes = cf_attributes(&cdf.variable("energy")?)?;
+        assert!(attributes.iter().all(|(name, _)| *name != "units"));
+        Ok(())
+    }
+}
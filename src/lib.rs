@@ -1,12 +1,24 @@
 #![doc = include_str!("../README.md")]
 #![warn(missing_docs)]
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 /// Module containing error definitions.
 pub mod error;
 
+/// I/O traits used by the decoder and the encoder. With the `std` feature these are the traits of
+/// [`std::io`], otherwise minimal replacements that work without the standard library.
+pub mod io;
+
 /// General definitions for structures and traits for decoding CDF data.
 pub mod decode;
 
+/// General definitions for structures and traits for encoding CDF data.
+pub mod encode;
+
 /// Representation for different CDF types.
 pub mod repr;
 
@@ -20,3 +32,72 @@ pub mod record;
 /// The structure of the CDF file.
 pub mod cdf;
 
+/// Checksums stored in CDF files.
+pub mod checksum;
+
+/// Decompression of compressed CDF files and compressed variable values.
+pub mod compression;
+
+/// Handles to variables stored in a CDF file, used to read variable values.
+pub mod variable;
+
+/// Zero-copy views of CDF files held in memory, which borrow names and values from their bytes.
+pub mod view;
+
+/// Variables grouped by their epoch, with time-based selection of aligned values and resampling
+/// onto other epochs.
+pub mod dataset;
+
+/// Writing of CDF files, streaming variable records to the file as they are pushed.
+pub mod writer;
+
+/// Leap seconds used to convert between CDF time types and UTC.
+pub mod time;
+
+/// Application of the metadata of SPDF master CDFs to data files.
+pub mod master;
+
+/// Attribute templates that follow the ISTP guidelines, for files authored with the writer.
+pub mod istp;
+
+/// In-place edits of existing CDF files, which only overwrite the records they change.
+pub mod edit;
+
+/// Traversal of the records of a decoded CDF file, with their offsets and what they belong to.
+pub mod walk;
+
+/// Streaming copy of CDF files that applies transforms to their variables and records.
+pub mod transcode;
+
+/// Conversion of CSV and TSV records into CDF files, using an existing CDF file as a skeleton.
+pub mod csv;
+
+/// Conversion of netCDF classic files into CDF files.
+#[cfg(feature = "netcdf")]
+pub mod netcdf;
+
+/// Client of the CDAWeb web services, which opens the CDF files of a dataset over HTTP.
+#[cfg(feature = "cdaweb")]
+pub mod cdaweb;
+
+/// Histograms and approximate percentiles of the values of variables.
+#[cfg(feature = "summary")]
+pub mod summary;
+
+/// Generators of structurally valid random CDF files, for property testing and fuzzing.
+#[cfg(feature = "arbitrary")]
+pub mod generate;
+
+/// Re-exports of the most commonly used types, to be imported with `use cdf::prelude::*;`.
+///
+/// ```
+/// use cdf::prelude::*;
+///
+/// fn data_types(cdf: &Cdf) -> Result<Vec<DataType>, CdfError> {
+///     cdf.variables()
+///         .iter()
+///         .map(|v| DataType::try_from(v.data_type().clone()))
+///         .collect()
+/// }
+/// ```
+pub mod prelude;
@@ -0,0 +1,388 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::cdf::Cdf;
+use crate::error::CdfError;
+use crate::io;
+use crate::record::adr::AttributeDescriptorRecord;
+use crate::record::agredr::AttributeGREntryDescriptorRecord;
+use crate::record::azedr::AttributeZEntryDescriptorRecord;
+#[cfg(feature = "std-fs")]
+use crate::transcode::transcode_file;
+use crate::transcode::{transcode, RecordTransform};
+use crate::types::{CdfInt4, CdfInt8, CdfType};
+use crate::variable::Variable;
+
+/// Overlay the attributes of the SPDF master CDF `master` onto the data file `data`, which is how
+/// CDAWeb normalizes the files of data providers.
+///
+/// - Global attributes of the master replace all entries of the same global attribute in `data`.
+/// - Variable attributes of the master replace the entries of the same attribute for the
+///   variables of `data` that have the same name. Variables of the master that are not in `data`
+///   are ignored.
+/// - Attributes of `data` that are not in the master are kept, and attributes of the master that
+///   are not in `data` are added.
+///
+/// Only the metadata of `data` is changed, in memory. Variable values are untouched.
+/// # Errors
+/// Returns a [`CdfError::Other`] if an attribute is global in one file and a variable attribute
+/// in the other.
+pub fn apply_master(data: &mut Cdf, master: &Cdf) -> Result<(), CdfError> {
+    // Name, kind and number of the variables of the data file, so that its attributes can be
+    // changed while they are matched.
+    let variables: Vec<(String, bool, i32)> = data
+        .variables()
+        .iter()
+        .filter(|v| !v.is_virtual())
+        .map(|v| (v.name().to_string(), v.is_zvariable(), **v.num()))
+        .collect();
+    let master_variables = master.variables();
+
+    for master_adr in &master.cdr.gdr.adr_vec {
+        let global = is_global(&master_adr.scope);
+        let adr_vec = &mut data.cdr.gdr.adr_vec;
+        let index = match adr_vec.iter().position(|adr| *adr.name == *master_adr.name) {
+            Some(index) if is_global(&adr_vec[index].scope) != global => {
+                return Err(CdfError::Other(format!(
+                    "Attribute {} is global in one file and a variable attribute in the other.",
+                    *master_adr.name
+                )));
+            }
+            Some(index) => index,
+            None => {
+                let num = i32::try_from(adr_vec.len())?;
+                adr_vec.push(empty_adr(master_adr, num));
+                data.cdr.gdr.num_attributes = CdfInt4::from(num + 1);
+                adr_vec.len() - 1
+            }
+        };
+        let adr = &mut data.cdr.gdr.adr_vec[index];
+
+        if global {
+            adr.agredr_vec = master_adr
+                .agredr_vec
+                .iter()
+                .map(|e| Entry::from_gr(e).to_gr(&adr.num, *e.num))
+                .collect();
+        } else {
+            for master_variable in &master_variables {
+                let Some(entry) = variable_entry(master_adr, master_variable) else {
+                    continue;
+                };
+                let Some((_, is_z, num)) = variables
+                    .iter()
+                    .find(|(name, _, _)| name == master_variable.name())
+                else {
+                    continue;
+                };
+                if *is_z {
+                    adr.azedr_vec.retain(|e| *e.num != *num);
+                    adr.azedr_vec.push(entry.to_z(&adr.num, *num));
+                    adr.azedr_vec.sort_by_key(|e| *e.num);
+                } else {
+                    adr.agredr_vec.retain(|e| *e.num != *num);
+                    adr.agredr_vec.push(entry.to_gr(&adr.num, *num));
+                    adr.agredr_vec.sort_by_key(|e| *e.num);
+                }
+            }
+        }
+        update_counts(adr)?;
+    }
+    Ok(())
+}
+
+/// Derive a master CDF from the data file read from `reader` and write it to `writer`, which is
+/// then returned. The master keeps all attributes and variables of the data file, along with the
+/// records of the variables that do not vary from record to record, such as labels and energy
+/// bins, while variables that vary from record to record have no records.
+///
+/// ```
+/// use cdf::cdf::Cdf;
+/// use cdf::master::make_master;
+/// use std::io::Cursor;
+///
+/// # let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/data/test_alltypes.cdf");
+/// let reader = Cursor::new(std::fs::read(path).unwrap());
+/// let writer = make_master(reader, Cursor::new(Vec::new())).unwrap();
+///
+/// let master = Cdf::from_bytes(writer.get_ref()).unwrap();
+/// assert_eq!(master.variable("Longitude").unwrap().n_records(), 0);
+/// ```
+/// # Errors
+/// Returns a [`CdfError`] if the data file cannot be decoded or the master cannot be written.
+pub fn make_master<R, W>(reader: R, writer: W) -> Result<W, CdfError>
+where
+    R: io::Read + io::Seek + Send + 'static,
+    W: io::Write + io::Seek,
+{
+    transcode(reader, writer, NonRecordVarying)
+}
+
+/// Derive a master CDF from the data file at `input_path` and create it at `output_path`, see
+/// [`make_master`].
+/// # Errors
+/// Returns a [`CdfError`] if a file cannot be opened, or if the master cannot be derived.
+#[cfg(feature = "std-fs")]
+pub fn make_master_file<P, Q>(input_path: P, output_path: Q) -> Result<(), CdfError>
+where
+    P: AsRef<std::path::Path>,
+    Q: AsRef<std::path::Path>,
+{
+    transcode_file(input_path, output_path, NonRecordVarying)
+}
+
+// Keeps the records of the variables that do not vary from record to record only.
+struct NonRecordVarying;
+
+impl RecordTransform for NonRecordVarying {
+    fn n_records(&mut self, variable: &Variable, n_records: usize) -> usize {
+        if variable.record_varies() {
+            0
+        } else {
+            n_records
+        }
+    }
+}
+
+fn is_global(scope: &CdfInt4) -> bool {
+    matches!(**scope, 1 | 3)
+}
+
+// A new attribute with the name and scope of `adr` and no entries.
+fn empty_adr(adr: &AttributeDescriptorRecord, num: i32) -> AttributeDescriptorRecord {
+    AttributeDescriptorRecord {
+        record_size: adr.record_size.clone(),
+        record_type: adr.record_type.clone(),
+        adr_next: None,
+        agredr_head: None,
+        scope: adr.scope.clone(),
+        num: CdfInt4::from(num),
+        num_gr_entries: CdfInt4::from(0),
+        max_gr_entry: CdfInt4::from(-1),
+        rfu_a: CdfInt4::from(0),
+        azedr_head: None,
+        num_z_entries: CdfInt4::from(0),
+        max_z_entry: CdfInt4::from(-1),
+        rfu_e: CdfInt4::from(-1),
+        name: adr.name.clone(),
+        agredr_vec: Vec::new(),
+        azedr_vec: Vec::new(),
+    }
+}
+
+fn update_counts(adr: &mut AttributeDescriptorRecord) -> Result<(), CdfError> {
+    adr.num_gr_entries = CdfInt4::from(i32::try_from(adr.agredr_vec.len())?);
+    adr.max_gr_entry = CdfInt4::from(adr.agredr_vec.iter().map(|e| *e.num).max().unwrap_or(-1));
+    adr.num_z_entries = CdfInt4::from(i32::try_from(adr.azedr_vec.len())?);
+    adr.max_z_entry = CdfInt4::from(adr.azedr_vec.iter().map(|e| *e.num).max().unwrap_or(-1));
+    Ok(())
+}
+
+// The entry of the variable attribute `adr` for `variable`, which is stored with the entries of
+// rVariables or of zVariables depending on the kind of variable.
+fn variable_entry<'a>(
+    adr: &'a AttributeDescriptorRecord,
+    variable: &Variable,
+) -> Option<Entry<'a>> {
+    let num = **variable.num();
+    if variable.is_zvariable() {
+        adr.azedr_vec
+            .iter()
+            .find(|e| *e.num == num)
+            .map(Entry::from_z)
+    } else {
+        adr.agredr_vec
+            .iter()
+            .find(|e| *e.num == num)
+            .map(Entry::from_gr)
+    }
+}
+
+// The contents of an attribute entry, which are the same for both kinds of entry records.
+struct Entry<'a> {
+    record_size: &'a CdfInt8,
+    data_type: &'a CdfInt4,
+    num_elements: &'a CdfInt4,
+    num_strings: &'a CdfInt4,
+    value: &'a [CdfType],
+}
+
+impl<'a> Entry<'a> {
+    fn from_gr(e: &'a AttributeGREntryDescriptorRecord) -> Self {
+        Entry {
+            record_size: &e.record_size,
+            data_type: &e.data_type,
+            num_elements: &e.num_elements,
+            num_strings: &e.num_strings,
+            value: &e.value,
+        }
+    }
+
+    fn from_z(e: &'a AttributeZEntryDescriptorRecord) -> Self {
+        Entry {
+            record_size: &e.record_size,
+            data_type: &e.data_type,
+            num_elements: &e.num_elements,
+            num_strings: &e.num_strings,
+            value: &e.value,
+        }
+    }
+
+    fn to_gr(&self, attr_num: &CdfInt4, num: i32) -> AttributeGREntryDescriptorRecord {
+        AttributeGREntryDescriptorRecord {
+            record_size: self.record_size.clone(),
+            record_type: CdfInt4::from(5),
+            agredr_next: None,
+            attr_num: attr_num.clone(),
+            data_type: self.data_type.clone(),
+            num: CdfInt4::from(num),
+            num_elements: self.num_elements.clone(),
+            num_strings: self.num_strings.clone(),
+            rfu_b: CdfInt4::from(0),
+            rfu_c: CdfInt4::from(0),
+            rfu_d: CdfInt4::from(-1),
+            rfu_e: CdfInt4::from(-1),
+            value: self.value.to_vec(),
+        }
+    }
+
+    fn to_z(&self, attr_num: &CdfInt4, num: i32) -> AttributeZEntryDescriptorRecord {
+        AttributeZEntryDescriptorRecord {
+            record_size: self.record_size.clone(),
+            record_type: CdfInt4::from(9),
+            azedr_next: None,
+            attr_num: attr_num.clone(),
+            data_type: self.data_type.clone(),
+            num: CdfInt4::from(num),
+            num_elements: self.num_elements.clone(),
+            num_strings: self.num_strings.clone(),
+            rfu_b: CdfInt4::from(0),
+            rfu_c: CdfInt4::from(0),
+            rfu_d: CdfInt4::from(-1),
+            rfu_e: CdfInt4::from(-1),
+            value: self.value.to_vec(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use std::path::PathBuf;
+
+    fn _read(name: &str) -> Result<Cdf, CdfError> {
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "examples", "data", name]
+            .iter()
+            .collect();
+        Cdf::read_cdf_file(path)
+    }
+
+    fn _global<'a>(cdf: &'a Cdf, name: &str) -> Option<&'a [CdfType]> {
+        let adr = cdf.cdr.gdr.adr_vec.iter().find(|adr| *adr.name == name)?;
+        adr.agredr_vec.first().map(|e| e.value.as_slice())
+    }
+
+    #[test]
+    fn test_make_master() -> Result<(), CdfError> {
+        let path: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let data = _read("test_alltypes.cdf")?;
+        let reader = io::Cursor::new(std::fs::read(path)?);
+        let writer = make_master(reader, io::Cursor::new(vec![]))?;
+        let master = Cdf::from_bytes(writer.get_ref())?;
+
+        assert_eq!(master.variables().len(), data.variables().len());
+        for variable in data.variables() {
+            let copied = master.variable(variable.name())?;
+            let expected = if variable.record_varies() {
+                0
+            } else {
+                variable.n_records()
+            };
+            assert_eq!(copied.n_records(), expected, "{}", variable.name());
+            assert_eq!(
+                copied.attributes().iter().count(),
+                variable.attributes().iter().count()
+            );
+        }
+        assert_eq!(
+            format!("{:?}", master.variable("Latitude")?.read_all()?),
+            format!("{:?}", data.variable("Latitude")?.read_all()?)
+        );
+        assert_eq!(
+            format!("{:?}", _global(&master, "Project")),
+            format!("{:?}", _global(&data, "Project"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_master() -> Result<(), CdfError> {
+        let mut master = _read("ulysses.cdf")?;
+        let mut data = _read("ulysses.cdf")?;
+
+        // The master renames the units of B_scalar and the project of the file, and the data file
+        // lacks the DEPEND_0 attribute.
+        let adr_vec = &mut master.cdr.gdr.adr_vec;
+        let units = adr_vec.iter_mut().find(|adr| *adr.name == "UNITS").unwrap();
+        let num = **data.variable("B_scalar")?.num();
+        let entry = units.agredr_vec.iter_mut().find(|e| *e.num == num).unwrap();
+        entry.value = vec![CdfType::String(String::from("nanotesla").into())];
+        let project = adr_vec
+            .iter_mut()
+            .find(|adr| *adr.name == "Project")
+            .unwrap();
+        project.agredr_vec.truncate(1);
+        project.agredr_vec[0].value = vec![CdfType::String(String::from("Master project").into())];
+        data.cdr.gdr.adr_vec.retain(|adr| *adr.name != "DEPEND_0");
+        assert!(data.variable("B_scalar")?.attribute("DEPEND_0").is_none());
+
+        apply_master(&mut data, &master)?;
+        let variable = data.variable("B_scalar")?;
+        assert_eq!(variable.attribute_str("UNITS"), Some("nanotesla"));
+        assert_eq!(variable.attribute_str("DEPEND_0"), Some("Epoch"));
+        assert!(matches!(
+            _global(&data, "Project"),
+            Some([CdfType::String(s)]) if **s == "Master project"
+        ));
+        let project = data
+            .cdr
+            .gdr
+            .adr_vec
+            .iter()
+            .find(|adr| *adr.name == "Project")
+            .unwrap();
+        assert_eq!(*project.num_gr_entries, 1);
+        assert_eq!(
+            *data.cdr.gdr.num_attributes,
+            master.cdr.gdr.adr_vec.len() as i32
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_master_scope_mismatch() -> Result<(), CdfError> {
+        let mut master = _read("ulysses.cdf")?;
+        let mut data = _read("ulysses.cdf")?;
+        let units = master
+            .cdr
+            .gdr
+            .adr_vec
+            .iter_mut()
+            .find(|adr| *adr.name == "UNITS")
+            .unwrap();
+        units.scope = CdfInt4::from(1);
+        assert!(apply_master(&mut data, &master).is_err());
+        Ok(())
+    }
+}
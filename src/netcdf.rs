@@ -0,0 +1,564 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::error::CdfError;
+use crate::io::{self, SeekFrom};
+use crate::types::{
+    CdfInt1, CdfInt2, CdfInt4, CdfInt8, CdfReal4, CdfReal8, CdfString, CdfType, CdfUint1, CdfUint2,
+    CdfUint4, DataType,
+};
+use crate::writer::{CdfWriter, VariableSpec};
+
+/// Tag of the list of dimensions in the header.
+const NC_DIMENSION: u32 = 0x0A;
+/// Tag of the list of variables in the header.
+const NC_VARIABLE: u32 = 0x0B;
+/// Tag of a list of attributes in the header.
+const NC_ATTRIBUTE: u32 = 0x0C;
+
+/// Name of the attribute that holds the value of data that was not written.
+const FILL_VALUE: &str = "_FillValue";
+
+/// Types of the values of a netCDF classic file. The unsigned types and `Int64` are only found in
+/// files of the 64-bit data format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NcType {
+    Byte,
+    Char,
+    Short,
+    Int,
+    Float,
+    Double,
+    Ubyte,
+    Ushort,
+    Uint,
+    Int64,
+    Uint64,
+}
+
+impl NcType {
+    fn from_tag(tag: u32) -> Result<Self, CdfError> {
+        Ok(match tag {
+            1 => NcType::Byte,
+            2 => NcType::Char,
+            3 => NcType::Short,
+            4 => NcType::Int,
+            5 => NcType::Float,
+            6 => NcType::Double,
+            7 => NcType::Ubyte,
+            8 => NcType::Ushort,
+            9 => NcType::Uint,
+            10 => NcType::Int64,
+            11 => NcType::Uint64,
+            t => return Err(CdfError::Decode(format!("Invalid netCDF type {t}."))),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            NcType::Byte | NcType::Char | NcType::Ubyte => 1,
+            NcType::Short | NcType::Ushort => 2,
+            NcType::Int | NcType::Float | NcType::Uint => 4,
+            NcType::Double | NcType::Int64 | NcType::Uint64 => 8,
+        }
+    }
+
+    fn data_type(self) -> Result<DataType, CdfError> {
+        Ok(match self {
+            NcType::Byte => DataType::Int1,
+            NcType::Char => DataType::Char,
+            NcType::Short => DataType::Int2,
+            NcType::Int => DataType::Int4,
+            NcType::Float => DataType::Real4,
+            NcType::Double => DataType::Real8,
+            NcType::Ubyte => DataType::Uint1,
+            NcType::Ushort => DataType::Uint2,
+            NcType::Uint => DataType::Uint4,
+            NcType::Int64 => DataType::Int8,
+            NcType::Uint64 => {
+                return Err(CdfError::Encode(
+                    "CDF has no data type for unsigned 64-bit integers.".to_string(),
+                ))
+            }
+        })
+    }
+
+    // Convert big-endian `bytes` into values, or into a single string for character data.
+    fn values(self, bytes: &[u8]) -> Result<Vec<CdfType>, CdfError> {
+        if self == NcType::Char {
+            let s = String::from_utf8(bytes.to_vec())
+                .map_err(|e| CdfError::Decode(format!("Invalid netCDF characters - {e}.")))?;
+            return Ok(vec![CdfType::String(CdfString::from(s))]);
+        }
+        bytes
+            .chunks_exact(self.size())
+            .map(|b| {
+                Ok(match self {
+                    NcType::Byte => CdfType::Int1(CdfInt1::from(i8::from_be_bytes(array(b)))),
+                    NcType::Short => CdfType::Int2(CdfInt2::from(i16::from_be_bytes(array(b)))),
+                    NcType::Int => CdfType::Int4(CdfInt4::from(i32::from_be_bytes(array(b)))),
+                    NcType::Float => CdfType::Real4(CdfReal4::from(f32::from_be_bytes(array(b)))),
+                    NcType::Double => CdfType::Real8(CdfReal8::from(f64::from_be_bytes(array(b)))),
+                    NcType::Ubyte => CdfType::Uint1(CdfUint1::from(u8::from_be_bytes(array(b)))),
+                    NcType::Ushort => CdfType::Uint2(CdfUint2::from(u16::from_be_bytes(array(b)))),
+                    NcType::Uint => CdfType::Uint4(CdfUint4::from(u32::from_be_bytes(array(b)))),
+                    NcType::Int64 => CdfType::Int8(CdfInt8::from(i64::from_be_bytes(array(b)))),
+                    NcType::Char | NcType::Uint64 => {
+                        return Err(CdfError::Encode(format!(
+                            "Cannot convert netCDF values of type {self:?}."
+                        )))
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+// Copy a chunk of exactly `N` bytes into an array.
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut array = [0; N];
+    array.copy_from_slice(bytes);
+    array
+}
+
+// Round `n` up to a multiple of 4 bytes, the alignment of everything in a netCDF classic file.
+fn padded(n: usize) -> usize {
+    n.next_multiple_of(4)
+}
+
+#[derive(Debug, Clone)]
+struct Dimension {
+    name: String,
+    // Zero for the unlimited dimension, along which records are stored.
+    size: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Attribute {
+    name: String,
+    nc_type: NcType,
+    values: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+struct NcVariable {
+    name: String,
+    dim_ids: Vec<usize>,
+    attributes: Vec<Attribute>,
+    nc_type: NcType,
+    begin: u64,
+}
+
+/// The header of a netCDF classic file, which describes its dimensions and variables.
+#[derive(Debug, Clone)]
+struct Header {
+    num_records: u64,
+    dimensions: Vec<Dimension>,
+    variables: Vec<NcVariable>,
+}
+
+// Reads the header of a file of format `version`, 1 for the classic format, 2 for the 64-bit
+// offset format and 5 for the 64-bit data format.
+struct HeaderReader<'r, R> {
+    reader: &'r mut R,
+    version: u8,
+}
+
+impl<R> HeaderReader<'_, R>
+where
+    R: io::Read + io::Seek,
+{
+    fn read_bytes(&mut self, n: usize) -> Result<Vec<u8>, CdfError> {
+        // Check the length against the size of the file before allocating.
+        let position = self.reader.stream_position()?;
+        let end = self.reader.seek(SeekFrom::End(0))?;
+        _ = self.reader.seek(SeekFrom::Start(position))?;
+        if u64::try_from(n)? > end - position {
+            return Err(CdfError::Decode(format!(
+                "Invalid netCDF header - {n} bytes are past the end of the file."
+            )));
+        }
+        let mut bytes = vec![0; n];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, CdfError> {
+        let mut bytes = [0; 4];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(u32::from_be_bytes(bytes))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, CdfError> {
+        let mut bytes = [0; 8];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    // Counts and sizes are 8 bytes in the 64-bit data format and 4 bytes otherwise.
+    fn read_size(&mut self) -> Result<usize, CdfError> {
+        let size = match self.version {
+            5 => self.read_u64()?,
+            _ => u64::from(self.read_u32()?),
+        };
+        Ok(usize::try_from(size)?)
+    }
+
+    // Offsets are 4 bytes in the classic format and 8 bytes otherwise.
+    fn read_offset(&mut self) -> Result<u64, CdfError> {
+        match self.version {
+            1 => Ok(u64::from(self.read_u32()?)),
+            _ => self.read_u64(),
+        }
+    }
+
+    fn read_name(&mut self) -> Result<String, CdfError> {
+        let len = self.read_size()?;
+        let mut bytes = self.read_bytes(padded(len))?;
+        bytes.truncate(len);
+        String::from_utf8(bytes)
+            .map_err(|e| CdfError::Decode(format!("Invalid netCDF name - {e}.")))
+    }
+
+    // Read the tag and the number of elements of a list, which are both zero for an empty list.
+    fn read_list(&mut self, tag: u32) -> Result<usize, CdfError> {
+        let found = self.read_u32()?;
+        let len = self.read_size()?;
+        if found != tag && (found, len) != (0, 0) {
+            return Err(CdfError::Decode(format!(
+                "Invalid netCDF header - expected tag {tag:#x}, found {found:#x}."
+            )));
+        }
+        Ok(len)
+    }
+
+    fn read_attributes(&mut self) -> Result<Vec<Attribute>, CdfError> {
+        (0..self.read_list(NC_ATTRIBUTE)?)
+            .map(|_| {
+                let name = self.read_name()?;
+                let nc_type = NcType::from_tag(self.read_u32()?)?;
+                let len = self.read_size()? * nc_type.size();
+                let mut values = self.read_bytes(padded(len))?;
+                values.truncate(len);
+                Ok(Attribute {
+                    name,
+                    nc_type,
+                    values,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Header {
+    fn decode<R>(reader: &mut R) -> Result<Self, CdfError>
+    where
+        R: io::Read + io::Seek,
+    {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        let version = match magic {
+            [b'C', b'D', b'F', v @ (1 | 2 | 5)] => v,
+            [b'\x89', b'H', b'D', b'F'] => {
+                return Err(CdfError::Decode(
+                    "netCDF-4 files are HDF5 files, only netCDF classic files can be read."
+                        .to_string(),
+                ))
+            }
+            _ => {
+                return Err(CdfError::Decode(
+                    "Not a netCDF classic file - invalid magic number.".to_string(),
+                ))
+            }
+        };
+        let mut header = HeaderReader { reader, version };
+        let num_records = match version {
+            5 => header.read_u64()?,
+            _ => u64::from(header.read_u32()?),
+        };
+        let dimensions = (0..header.read_list(NC_DIMENSION)?)
+            .map(|_| {
+                Ok(Dimension {
+                    name: header.read_name()?,
+                    size: header.read_size()?,
+                })
+            })
+            .collect::<Result<Vec<_>, CdfError>>()?;
+        // Global attributes have no counterpart in the written file.
+        _ = header.read_attributes()?;
+        let variables = (0..header.read_list(NC_VARIABLE)?)
+            .map(|_| {
+                let name = header.read_name()?;
+                let dim_ids = (0..header.read_size()?)
+                    .map(|_| header.read_size())
+                    .collect::<Result<Vec<_>, _>>()?;
+                let attributes = header.read_attributes()?;
+                let nc_type = NcType::from_tag(header.read_u32()?)?;
+                _ = header.read_size()?;
+                let begin = header.read_offset()?;
+                Ok(NcVariable {
+                    name,
+                    dim_ids,
+                    attributes,
+                    nc_type,
+                    begin,
+                })
+            })
+            .collect::<Result<Vec<_>, CdfError>>()?;
+        Ok(Header {
+            num_records,
+            dimensions,
+            variables,
+        })
+    }
+
+    fn dimension(&self, id: usize) -> Result<&Dimension, CdfError> {
+        self.dimensions
+            .get(id)
+            .ok_or_else(|| CdfError::Decode(format!("Invalid netCDF dimension {id}.")))
+    }
+
+    // Whether the first dimension of `variable` is the unlimited dimension.
+    fn is_record_variable(&self, variable: &NcVariable) -> Result<bool, CdfError> {
+        match variable.dim_ids.first() {
+            Some(id) => Ok(self.dimension(*id)?.size == 0),
+            None => Ok(false),
+        }
+    }
+}
+
+// Where and how the values of a netCDF variable are written to the CDF file.
+struct Layout {
+    spec: VariableSpec,
+    num_records: u64,
+    record_bytes: usize,
+}
+
+impl Layout {
+    fn new(header: &Header, variable: &NcVariable) -> Result<Self, CdfError> {
+        let is_record = header.is_record_variable(variable)?;
+        let mut dims = variable.dim_ids[usize::from(is_record)..]
+            .iter()
+            .map(|id| {
+                let dimension = header.dimension(*id)?;
+                if dimension.size == 0 {
+                    return Err(CdfError::Decode(format!(
+                        "Unlimited dimension {} of variable {} is not its first dimension.",
+                        dimension.name, variable.name
+                    )));
+                }
+                Ok(dimension.size)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // The last dimension of character data is the length of the strings.
+        let num_elements = match variable.nc_type {
+            NcType::Char => dims.pop().unwrap_or(1),
+            _ => 1,
+        };
+        let record_bytes = dims.iter().product::<usize>() * num_elements * variable.nc_type.size();
+
+        let mut spec = VariableSpec::new(&variable.name, variable.nc_type.data_type()?)
+            .num_elements(num_elements)
+            .dims(&dims);
+        let fill_value = variable.attributes.iter().find(|a| a.name == FILL_VALUE);
+        if let Some(fill_value) = fill_value {
+            if fill_value.nc_type == variable.nc_type && fill_value.nc_type != NcType::Char {
+                if let [pad_value] = fill_value.nc_type.values(&fill_value.values)?.as_slice() {
+                    spec = spec.pad_value(pad_value.clone());
+                }
+            }
+        }
+        Ok(Layout {
+            spec,
+            num_records: if is_record { header.num_records } else { 1 },
+            record_bytes,
+        })
+    }
+}
+
+/// Convert the netCDF classic file read from `reader` into a CDF file written to `writer`, and
+/// return the writer. Each netCDF variable becomes a zVariable of the same name whose dimensions
+/// are the netCDF dimensions, except for the unlimited dimension along which records are stored.
+/// Variables without the unlimited dimension are written as a single record, and the last
+/// dimension of character data is the length of its strings. The `_FillValue` attribute of a
+/// variable becomes its pad value, while other attributes are not converted.
+///
+/// Files of the classic, 64-bit offset and 64-bit data formats are supported. netCDF-4 files,
+/// which are HDF5 files, are not.
+/// # Errors
+/// Returns a [`CdfError::Decode`] if the netCDF file is invalid, a [`CdfError::Encode`] if a
+/// variable has no CDF counterpart, such as unsigned 64-bit integers, or a [`CdfError::Io`] if
+/// reading or writing fails.
+pub fn import<R, W>(mut reader: R, writer: W) -> Result<W, CdfError>
+where
+    R: io::Read + io::Seek,
+    W: io::Write + io::Seek,
+{
+    let header = Header::decode(&mut reader)?;
+    let layouts = header
+        .variables
+        .iter()
+        .map(|v| Layout::new(&header, v))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Records hold the values of all record variables one after the other, each padded to 4
+    // bytes, unless there is a single record variable whose records are not padded.
+    let mut record_size = 0;
+    let mut num_record_variables = 0;
+    for (variable, layout) in header.variables.iter().zip(&layouts) {
+        if header.is_record_variable(variable)? {
+            record_size += padded(layout.record_bytes);
+            num_record_variables += 1;
+        }
+    }
+
+    let mut builder = CdfWriter::builder();
+    for layout in &layouts {
+        builder = builder.variable(layout.spec.clone());
+    }
+    let mut writer = builder.build(writer)?;
+    for (variable, layout) in header.variables.iter().zip(&layouts) {
+        let stride = match num_record_variables {
+            1 => layout.record_bytes,
+            _ => record_size,
+        };
+        for r in 0..layout.num_records {
+            let position = variable.begin + r * u64::try_from(stride)?;
+            _ = reader.seek(SeekFrom::Start(position))?;
+            let mut bytes = vec![0; layout.record_bytes];
+            reader.read_exact(&mut bytes)?;
+            writer.write_records(&variable.name, &[variable.nc_type.values(&bytes)?])?;
+        }
+    }
+    writer.close()
+}
+
+/// Convert the netCDF classic file at `netcdf_path` into a CDF file created at `cdf_path`, see
+/// [`import`].
+/// # Errors
+/// Returns a [`CdfError`] if a file cannot be opened, or if the conversion fails.
+#[cfg(feature = "std-fs")]
+pub fn import_file<P, Q>(netcdf_path: P, cdf_path: Q) -> Result<(), CdfError>
+where
+    P: AsRef<std::path::Path>,
+    Q: AsRef<std::path::Path>,
+{
+    use std::{fs::File, io::BufReader, io::BufWriter};
+
+    let reader = BufReader::new(File::open(netcdf_path)?);
+    let writer = BufWriter::new(File::create(cdf_path)?);
+    _ = import(reader, writer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cdf::Cdf;
+
+    // Encode a name, padded to 4 bytes, as found in a netCDF header.
+    fn _name(bytes: &mut Vec<u8>, name: &str) {
+        bytes.extend(u32::try_from(name.len()).unwrap().to_be_bytes());
+        bytes.extend(name.as_bytes());
+        bytes.resize(padded(bytes.len()), 0);
+    }
+
+    // A netCDF classic file with two record variables, `time(time)` and `temp(time, x)` which has
+    // a fill value, and two other variables, `label(x, len)` and `count`.
+    fn _netcdf() -> Vec<u8> {
+        let mut h = b"CDF\x01".to_vec();
+        h.extend(3u32.to_be_bytes());
+        h.extend([NC_DIMENSION, 3].iter().flat_map(|v| v.to_be_bytes()));
+        for (name, size) in [("time", 0u32), ("x", 3), ("len", 4)] {
+            _name(&mut h, name);
+            h.extend(size.to_be_bytes());
+        }
+        h.extend([0u32, 0].iter().flat_map(|v| v.to_be_bytes()));
+        h.extend([NC_VARIABLE, 4].iter().flat_map(|v| v.to_be_bytes()));
+        // Name, dimensions, type, size of the values, and a placeholder for their offset.
+        let variables: [(&str, &[u32], u32, u32); 4] = [
+            ("time", &[0], 6, 8),
+            ("temp", &[0, 1], 5, 12),
+            ("label", &[1, 2], 2, 12),
+            ("count", &[], 4, 4),
+        ];
+        let mut begins = vec![];
+        for (name, dims, nc_type, vsize) in variables {
+            _name(&mut h, name);
+            h.extend(u32::try_from(dims.len()).unwrap().to_be_bytes());
+            h.extend(dims.iter().flat_map(|d| d.to_be_bytes()));
+            if name == "temp" {
+                h.extend([NC_ATTRIBUTE, 1].iter().flat_map(|v| v.to_be_bytes()));
+                _name(&mut h, FILL_VALUE);
+                h.extend([5u32, 1].iter().flat_map(|v| v.to_be_bytes()));
+                h.extend((-1e31f32).to_be_bytes());
+            } else {
+                h.extend([0u32, 0].iter().flat_map(|v| v.to_be_bytes()));
+            }
+            h.extend([nc_type, vsize].iter().flat_map(|v| v.to_be_bytes()));
+            begins.push(h.len());
+            h.extend(0u32.to_be_bytes());
+        }
+
+        // Values of the other variables come first, followed by the records.
+        let set_begin = |h: &mut Vec<u8>, i: usize| {
+            let begin = u32::try_from(h.len()).unwrap().to_be_bytes();
+            h[begins[i]..begins[i] + 4].copy_from_slice(&begin);
+        };
+        set_begin(&mut h, 2);
+        h.extend(b"abc\0de\0\0fghi");
+        set_begin(&mut h, 3);
+        h.extend(42i32.to_be_bytes());
+        set_begin(&mut h, 0);
+        let time_begin = h.len();
+        for r in 0..3u8 {
+            h.extend(f64::from(r).to_be_bytes());
+            h.extend(
+                [1.0f32, 2.0, 3.0]
+                    .iter()
+                    .flat_map(|v| (v * f32::from(r)).to_be_bytes()),
+            );
+        }
+        let temp_begin = u32::try_from(time_begin + 8).unwrap().to_be_bytes();
+        h[begins[1]..begins[1] + 4].copy_from_slice(&temp_begin);
+        h
+    }
+
+    #[test]
+    fn test_import() -> Result<(), CdfError> {
+        let bytes = import(io::Cursor::new(_netcdf()), io::Cursor::new(Vec::new()))?;
+        let cdf = Cdf::from_bytes(&bytes.into_inner())?;
+
+        let time = cdf.variable("time")?;
+        assert_eq!(time.n_records(), 3);
+        assert_eq!(format!("{:?}", time.read_all()?[2].data), "[Real8(2)]");
+
+        let temp = cdf.variable("temp")?;
+        assert_eq!(temp.dim_sizes()?, [3]);
+        assert_eq!(
+            format!("{:?}", temp.read_all()?[2].data),
+            "[Real4(2), Real4(4), Real4(6)]"
+        );
+        assert!(matches!(&temp.pad_record()?.data[0], CdfType::Real4(v) if **v == -1e31));
+
+        let label = cdf.variable("label")?;
+        assert_eq!(label.num_elements()?, 4);
+        assert_eq!(label.n_records(), 1);
+        let count = cdf.variable("count")?;
+        assert_eq!(format!("{:?}", count.read_all()?[0].data), "[Int4(42)]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_invalid() {
+        let hdf5 = b"\x89HDF\r\n\x1a\n".to_vec();
+        assert!(import(io::Cursor::new(hdf5), io::Cursor::new(Vec::new())).is_err());
+        let mut truncated = _netcdf();
+        truncated.truncate(100);
+        assert!(import(io::Cursor::new(truncated), io::Cursor::new(Vec::new())).is_err());
+    }
+}
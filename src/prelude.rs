@@ -0,0 +1,12 @@
+pub use crate::cdf::{Cdf, CdfInfo, FormatInfo, Majority, ReadOptions};
+pub use crate::dataset::{Dataset, Interpolation, ResampleOptions, Selection};
+pub use crate::decode::{
+    CancelToken, Decodable, DecodeOptions, DecodeProgress, DecodeWarning, Decoder, DecoderBuilder,
+};
+pub use crate::encode::{Encodable, Encoder};
+pub use crate::error::CdfError;
+pub use crate::repr::{CdfEncoding, CdfVersion};
+pub use crate::time::{CdfTime, EpochFormat};
+pub use crate::types::{CdfString, CdfType, DataType};
+pub use crate::variable::{Aggregation, Promotion, RecordMatch, Variable};
+pub use crate::writer::{AttributeValue, CdfWriter, VariableSpec, VxrLayout};
@@ -22,6 +22,8 @@ pub mod rvdr;
 pub mod spr;
 /// Unused Internal Record
 pub mod uir;
+/// Records of unknown types
+pub mod unknown;
 /// Variable Values Record
 pub mod vvr;
 /// Variable Index Record
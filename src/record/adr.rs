@@ -1,20 +1,27 @@
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use alloc::{format, vec, vec::Vec};
+
+use crate::io;
 use crate::{
     decode::{decode_version3_int4_int8, Decodable, Decoder},
+    encode::{encode_version3_int4_int8, Encodable, Encoder},
     error::CdfError,
     record::{
         agredr::AttributeGREntryDescriptorRecord,
         azedr::AttributeZEntryDescriptorRecord,
         collection::{get_record_vec, RecordList},
     },
+    repr::CdfVersion,
     types::{CdfInt4, CdfInt8, CdfString},
 };
-use std::io;
 
 /// The Attribute Descriptor Record contains information on each attribute in the CDF.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct AttributeDescriptorRecord {
     /// The size in bytes of this record.
@@ -56,6 +63,8 @@ impl Decodable for AttributeDescriptorRecord {
     where
         R: io::Read + io::Seek,
     {
+        let start = decoder.begin_record()?;
+        decoder.keep_raw_record()?;
         let cdf_version = decoder.context.version()?;
 
         let record_size = decode_version3_int4_int8(decoder)?;
@@ -76,20 +85,27 @@ impl Decodable for AttributeDescriptorRecord {
         let max_gr_entry = CdfInt4::decode_be(decoder)?;
 
         let rfu_a = CdfInt4::decode_be(decoder)?;
-        if *rfu_a != 0 {
+        if decoder.options.strict && *rfu_a != 0 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_a read from file in ADR - expected 0, received {}",
                 *rfu_a
             )));
         }
 
-        let azedr_head = decode_version3_int4_int8(decoder).map(|v| (*v != 0).then_some(v))?;
+        // Entries for zVariables were introduced in CDF v2.2, these fields are undefined before.
+        let has_z_entries = cdf_version >= CdfVersion::new(2, 2, 0);
+        let azedr_head =
+            decode_version3_int4_int8(decoder).map(|v| (*v != 0 && has_z_entries).then_some(v))?;
 
-        let num_z_entries = CdfInt4::decode_be(decoder)?;
-        let max_z_entry = CdfInt4::decode_be(decoder)?;
+        let num_z_entries =
+            CdfInt4::decode_be(decoder)
+                .map(|v| if has_z_entries { v } else { CdfInt4::from(0) })?;
+        let max_z_entry =
+            CdfInt4::decode_be(decoder)
+                .map(|v| if has_z_entries { v } else { CdfInt4::from(-1) })?;
 
         let rfu_e = CdfInt4::decode_be(decoder)?;
-        if *rfu_e != -1 {
+        if decoder.options.strict && *rfu_e != -1 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_e read from file in ADR - expected -1, received {}",
                 *rfu_e
@@ -102,6 +118,8 @@ impl Decodable for AttributeDescriptorRecord {
             CdfString::decode_string_from_numbytes(decoder, 256)?
         };
 
+        decoder.finish_record(start, &record_size, &record_type)?;
+
         let agredr_vec = match &agredr_head {
             Some(head) => get_record_vec::<R, AttributeGREntryDescriptorRecord>(decoder, head)?,
             None => vec![],
@@ -112,6 +130,7 @@ impl Decodable for AttributeDescriptorRecord {
             None => vec![],
         };
 
+        decoder.report_progress(&record_type)?;
         Ok(AttributeDescriptorRecord {
             record_size,
             record_type,
@@ -142,13 +161,52 @@ impl Decodable for AttributeDescriptorRecord {
     }
 }
 
+impl Encodable for AttributeDescriptorRecord {
+    /// Encode the Attribute Descriptor Record. The entries it points to are encoded separately.
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        let offset = |v: &Option<CdfInt8>| v.as_ref().map_or(0, |v| **v);
+
+        encode_version3_int4_int8(encoder, *self.record_size)?;
+        self.record_type.encode_be(encoder)?;
+        encode_version3_int4_int8(encoder, offset(&self.adr_next))?;
+        encode_version3_int4_int8(encoder, offset(&self.agredr_head))?;
+        self.scope.encode_be(encoder)?;
+        self.num.encode_be(encoder)?;
+        self.num_gr_entries.encode_be(encoder)?;
+        self.max_gr_entry.encode_be(encoder)?;
+        self.rfu_a.encode_be(encoder)?;
+        encode_version3_int4_int8(encoder, offset(&self.azedr_head))?;
+        self.num_z_entries.encode_be(encoder)?;
+        self.max_z_entry.encode_be(encoder)?;
+        self.rfu_e.encode_be(encoder)?;
+        let name_bytes = if encoder.context.version.major < 3 {
+            64
+        } else {
+            256
+        };
+        self.name.encode_string_to_numbytes(encoder, name_bytes)
+    }
+
+    fn encode_le<W>(&self, _: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        unreachable!(
+            "Little-endian encoding is not supported for records, only for values within records."
+        )
+    }
+}
+
 impl RecordList for AttributeDescriptorRecord {
     fn next_record(&self) -> Option<CdfInt8> {
         self.adr_next.clone()
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std-fs"))]
 mod tests {
 
     use crate::cdf;
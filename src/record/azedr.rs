@@ -1,16 +1,22 @@
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use alloc::{format, string::String, vec::Vec};
+
 use crate::decode::{decode_version3_int4_int8, Decodable, Decoder};
+use crate::encode::{encode_values, encode_version3_int4_int8, Encodable, Encoder};
 use crate::error::CdfError;
+use crate::io;
 use crate::record::collection::RecordList;
 use crate::repr::Endian;
 use crate::types::{CdfInt4, CdfInt8, CdfType};
-use std::io;
 
 /// Struct to store contents of an Attribute Entry Descriptor Record that stores information on
 /// zVariable attributes.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct AttributeZEntryDescriptorRecord {
     /// The size of this record in bytes.
@@ -42,12 +48,25 @@ pub struct AttributeZEntryDescriptorRecord {
     pub value: Vec<CdfType>,
 }
 
+impl AttributeZEntryDescriptorRecord {
+    /// The strings stored in this entry if it holds characters, split into the number of strings
+    /// recorded in the entry, see [`crate::types::CdfString::split_strings`].
+    pub fn strings(&self) -> Option<Vec<String>> {
+        match self.value.as_slice() {
+            [CdfType::String(s)] => Some(s.split_strings(usize::try_from(*self.num_strings).ok()?)),
+            _ => None,
+        }
+    }
+}
+
 impl Decodable for AttributeZEntryDescriptorRecord {
     /// Decode a value from the input that implements `io::Read`.
     fn decode_be<R>(decoder: &mut Decoder<R>) -> Result<Self, CdfError>
     where
         R: io::Read + io::Seek,
     {
+        let start = decoder.begin_record()?;
+        decoder.keep_raw_record()?;
         let record_size = decode_version3_int4_int8(decoder)?;
         let record_type = CdfInt4::decode_be(decoder)?;
         if *record_type != 9 {
@@ -66,28 +85,28 @@ impl Decodable for AttributeZEntryDescriptorRecord {
         let num_strings = CdfInt4::decode_be(decoder)?;
 
         let rfu_b = CdfInt4::decode_be(decoder)?;
-        if *rfu_b != 0 {
+        if decoder.options.strict && *rfu_b != 0 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_b read from file in AZEDR - expected 0, received {}",
                 *rfu_b
             )));
         }
         let rfu_c = CdfInt4::decode_be(decoder)?;
-        if *rfu_c != 0 {
+        if decoder.options.strict && *rfu_c != 0 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_c read from file in AZEDR - expected 0, received {}",
                 *rfu_c
             )));
         }
         let rfu_d = CdfInt4::decode_be(decoder)?;
-        if *rfu_d != -1 {
+        if decoder.options.strict && *rfu_d != -1 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_d read from file in AZEDR - expected -1, received {}",
                 *rfu_d
             )));
         }
         let rfu_e = CdfInt4::decode_be(decoder)?;
-        if *rfu_e != -1 {
+        if decoder.options.strict && *rfu_e != -1 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_e read from file in AZEDR - expected -1, received {}",
                 *rfu_e
@@ -101,6 +120,8 @@ impl Decodable for AttributeZEntryDescriptorRecord {
             Endian::Little => CdfType::decode_vec_le(decoder, &data_type, &num_elements)?,
         };
 
+        decoder.finish_record(start, &record_size, &record_type)?;
+
         Ok(AttributeZEntryDescriptorRecord {
             record_size,
             record_type,
@@ -128,13 +149,44 @@ impl Decodable for AttributeZEntryDescriptorRecord {
     }
 }
 
+impl Encodable for AttributeZEntryDescriptorRecord {
+    /// Encode the entry along with its values, which use the encoding of the file.
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        encode_version3_int4_int8(encoder, *self.record_size)?;
+        self.record_type.encode_be(encoder)?;
+        encode_version3_int4_int8(encoder, self.azedr_next.as_ref().map_or(0, |v| **v))?;
+        self.attr_num.encode_be(encoder)?;
+        self.data_type.encode_be(encoder)?;
+        self.num.encode_be(encoder)?;
+        self.num_elements.encode_be(encoder)?;
+        self.num_strings.encode_be(encoder)?;
+        self.rfu_b.encode_be(encoder)?;
+        self.rfu_c.encode_be(encoder)?;
+        self.rfu_d.encode_be(encoder)?;
+        self.rfu_e.encode_be(encoder)?;
+        encode_values(encoder, &self.value)
+    }
+
+    fn encode_le<W>(&self, _: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        unreachable!(
+            "Little-endian encoding is not supported for records, only for values within records."
+        )
+    }
+}
+
 impl RecordList for AttributeZEntryDescriptorRecord {
     fn next_record(&self) -> Option<CdfInt8> {
         self.azedr_next.clone()
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std-fs"))]
 mod tests {
 
     use crate::cdf;
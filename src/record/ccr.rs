@@ -1,16 +1,21 @@
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use alloc::{format, vec, vec::Vec};
+
+use crate::io;
 use crate::{
-    decode::{decode_version3_int4_int8, Decodable, Decoder},
+    decode::{decode_version3_int4_int8, record_data_size, Decodable, Decoder},
     error::CdfError,
     types::{CdfInt4, CdfInt8},
 };
-use std::io;
 
 /// Stores compressed values in the case of full-file compression (as opposed to individual
 /// variable data compression).
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct CompressedCdfRecord {
     /// Size of this record in bytes.
@@ -32,6 +37,8 @@ impl Decodable for CompressedCdfRecord {
     where
         R: io::Read + io::Seek,
     {
+        _ = decoder.begin_record()?;
+        decoder.keep_raw_record()?;
         let record_size = decode_version3_int4_int8(decoder)?;
         let record_type = CdfInt4::decode_be(decoder)?;
         if *record_type != 10 {
@@ -44,7 +51,7 @@ impl Decodable for CompressedCdfRecord {
         let uncompressed_size = decode_version3_int4_int8(decoder)?;
 
         let rfu_a = CdfInt4::decode_be(decoder)?;
-        if *rfu_a != 0 {
+        if decoder.options.strict && *rfu_a != 0 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_a read from file in CCR - expected 0, received {}",
                 *rfu_a
@@ -54,10 +61,11 @@ impl Decodable for CompressedCdfRecord {
         // Read the compressed data.
         // prior to v3.0 there were no 8-byte ints.
         let num_data = if decoder.context.version()?.major < 3 {
-            usize::try_from(*record_size)? - 20
+            record_data_size(&record_size, 20)?
         } else {
-            usize::try_from(*record_size)? - 32
+            record_data_size(&record_size, 32)?
         };
+        decoder.check_alloc(num_data)?;
         let mut data = vec![0u8; num_data];
         decoder.reader.read_exact(&mut data)?;
 
@@ -80,7 +88,7 @@ impl Decodable for CompressedCdfRecord {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std-fs"))]
 mod tests {
 
     use crate::cdf;
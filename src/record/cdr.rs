@@ -1,17 +1,35 @@
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use alloc::format;
+
+use crate::io;
 use crate::{
-    decode::{decode_version3_int4_int8, Decodable, Decoder},
+    decode::{decode_version3_int4_int8, Decodable, DecodeWarning, Decoder},
+    encode::{encode_version3_int4_int8, Encodable, Encoder},
     error::CdfError,
     record::gdr::GlobalDescriptorRecord,
     repr::{CdfEncoding, CdfVersion},
     types::{CdfInt4, CdfInt8, CdfString},
 };
-use std::io;
+
+/// The copyright notice stored in the CDR of files written by the CDF library.
+pub const COPYRIGHT: &str = "\nCommon Data Format (CDF)\nhttps://cdf.gsfc.nasa.gov\nSpace Physics Data Facility\nNASA/Goddard Space Flight Center\nGreenbelt, Maryland 20771 USA\n";
+
+// Size in bytes of the copyright field, which is longer in files older than version 2.5.
+pub(crate) fn copyright_len(version: &CdfVersion) -> usize {
+    if *version < CdfVersion::new(2, 5, 0) {
+        1945
+    } else {
+        256
+    }
+}
 
 /// Flags pertaining to this CDF file.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug, PartialEq)]
 pub struct CdrFlags {
     /// Whether this is row_major (true) or column-major (false)
@@ -27,6 +45,7 @@ pub struct CdrFlags {
 /// The CDF Descriptor Record is present in all CDF files at a file offset of 8-bytes and contains
 /// general information about the CDF.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct CdfDescriptorRecord {
     /// The size of this record in bytes.
@@ -49,7 +68,7 @@ pub struct CdfDescriptorRecord {
     pub identifier: CdfInt4,
     /// A value reserved for future use.
     pub rfu_e: CdfInt4,
-    /// The copyright string.
+    /// The copyright string, without the NUL bytes that pad it to the size of its field.
     pub copyright: CdfString,
     /// Contents of the global descriptor record.
     pub gdr: GlobalDescriptorRecord,
@@ -61,6 +80,8 @@ impl Decodable for CdfDescriptorRecord {
     where
         R: io::Read + io::Seek,
     {
+        let start = decoder.begin_record()?;
+        decoder.keep_raw_record()?;
         let record_size = decode_version3_int4_int8(decoder)?;
         let record_type = CdfInt4::decode_be(decoder)?;
         if *record_type != 1 {
@@ -73,11 +94,29 @@ impl Decodable for CdfDescriptorRecord {
         let gdr_offset = decode_version3_int4_int8(decoder)?;
         let version: i32 = CdfInt4::decode_be(decoder)?.into();
         let release: i32 = CdfInt4::decode_be(decoder)?.into();
-        let encoding: CdfEncoding = CdfInt4::decode_be(decoder)?.try_into()?;
+        let stored_encoding = CdfInt4::decode_be(decoder)?;
+        let encoding: CdfEncoding = match stored_encoding.clone().try_into() {
+            Ok(encoding) => encoding,
+            // A file with an unknown encoding can still be read with the encoding requested by
+            // the user.
+            Err(e) => {
+                let encoding = decoder.options.encoding.clone().ok_or(e)?;
+                decoder.report_warning(DecodeWarning::UnknownEncoding {
+                    encoding: *stored_encoding,
+                });
+                encoding
+            }
+        };
 
-        // Set the encoding of the decoder using the value read from the CDR.
-        decoder.context.encoding = Some(encoding.clone());
-        decoder.context.endianness = Some(encoding.get_endian()?);
+        // Set the encoding of the decoder using the value read from the CDR, unless the user has
+        // asked for a specific encoding.
+        let value_encoding = decoder
+            .options
+            .encoding
+            .clone()
+            .unwrap_or_else(|| encoding.clone());
+        decoder.context.endianness = Some(value_encoding.get_endian()?);
+        decoder.context.encoding = Some(value_encoding);
 
         let flags = CdfInt4::decode_be(decoder)?;
         let flags = CdrFlags {
@@ -90,14 +129,14 @@ impl Decodable for CdfDescriptorRecord {
         decoder.context.row_major = Some(flags.row_major);
 
         let rfu_a = CdfInt4::decode_be(decoder)?;
-        if *rfu_a != 0 {
+        if decoder.options.strict && *rfu_a != 0 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_a read from file in CDR - expected 0, received {}",
                 *rfu_a
             )));
         }
         let rfu_b = CdfInt4::decode_be(decoder)?;
-        if *rfu_b != 0 {
+        if decoder.options.strict && *rfu_b != 0 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_b read from file in CDR - expected 0, received {}",
                 *rfu_b
@@ -117,14 +156,14 @@ impl Decodable for CdfDescriptorRecord {
 
         let identifier = CdfInt4::decode_be(decoder)?;
         let rfu_e = CdfInt4::decode_be(decoder)?;
-        let copyright = if cdf_version < CdfVersion::new(2, 5, 0) {
-            CdfString::decode_string_from_numbytes(decoder, 1945)?
-        } else {
-            CdfString::decode_string_from_numbytes(decoder, 256)?
-        };
+        let copyright =
+            CdfString::decode_string_from_numbytes(decoder, copyright_len(&cdf_version))?;
 
+        decoder.finish_record(start, &record_size, &record_type)?;
+        _ = decoder.seek_offset(*gdr_offset)?;
         let gdr = GlobalDescriptorRecord::decode_be(decoder)?;
 
+        decoder.report_progress(&record_type)?;
         Ok(CdfDescriptorRecord {
             record_size,
             record_type,
@@ -151,7 +190,45 @@ impl Decodable for CdfDescriptorRecord {
     }
 }
 
-#[cfg(test)]
+impl Encodable for CdfDescriptorRecord {
+    /// Encode the CDF Descriptor Record. The GDR is encoded separately, at `gdr_offset`.
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        encode_version3_int4_int8(encoder, *self.record_size)?;
+        self.record_type.encode_be(encoder)?;
+        encode_version3_int4_int8(encoder, *self.gdr_offset)?;
+        CdfInt4::from(i32::from(self.cdf_version.major)).encode_be(encoder)?;
+        CdfInt4::from(i32::from(self.cdf_version.minor)).encode_be(encoder)?;
+        CdfInt4::from(self.encoding.clone() as i32).encode_be(encoder)?;
+
+        let flags = i32::from(self.flags.row_major)
+            | i32::from(self.flags.single_file) << 1
+            | i32::from(self.flags.has_checksum) << 2
+            | i32::from(self.flags.md5_checksum) << 3;
+        CdfInt4::from(flags).encode_be(encoder)?;
+
+        self.rfu_a.encode_be(encoder)?;
+        self.rfu_b.encode_be(encoder)?;
+        CdfInt4::from(i32::from(self.cdf_version.patch)).encode_be(encoder)?;
+        self.identifier.encode_be(encoder)?;
+        self.rfu_e.encode_be(encoder)?;
+        let num_bytes = copyright_len(&encoder.context.version);
+        self.copyright.encode_string_to_numbytes(encoder, num_bytes)
+    }
+
+    fn encode_le<W>(&self, _: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        unreachable!(
+            "Little-endian encoding is not supported for records, only for values within records."
+        )
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
 mod tests {
 
     use crate::cdf;
@@ -159,6 +236,8 @@ mod tests {
     use std::fs::File;
     use std::io::BufReader;
     use std::path::PathBuf;
+    use std::string::ToString;
+    use std::vec::Vec;
 
     use super::*;
 
@@ -230,4 +309,87 @@ mod tests {
         assert_eq!(cdr.copyright.len(), len_copyright);
         Ok(())
     }
+
+    #[test]
+    fn test_cdr_encoding_override() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+
+        let f = File::open(path_test_file)?;
+        let reader = BufReader::new(f);
+        let mut decoder = Decoder::with_encoding(reader, CdfEncoding::Network)?;
+        let cdf = cdf::Cdf::decode_be(&mut decoder)?;
+
+        // The CDR still reports the encoding stored in the file, but values are decoded using
+        // the requested encoding.
+        assert_eq!(cdf.cdr.encoding, CdfEncoding::IbmPc);
+        assert_eq!(decoder.context.encoding()?, CdfEncoding::Network);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cdr_copyright() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "ulysses.cdf",
+        ]
+        .iter()
+        .collect();
+
+        let f = File::open(path_test_file)?;
+        let mut decoder = Decoder::new(BufReader::new(f))?;
+        let cdf = cdf::Cdf::decode_be(&mut decoder)?;
+        let copyright = cdf.cdr.copyright.to_string();
+        assert!(copyright.starts_with("\nNSSDC Common Data Format (CDF)\n"));
+        assert!(copyright.ends_with("NSSDCA.GSFC.NASA.GOV)\n"));
+
+        // The copyright is padded to the size of its field for the version being written.
+        for (version, len) in [
+            (CdfVersion::new(2, 4, 0), 1945),
+            (CdfVersion::new(2, 7, 0), 256),
+        ] {
+            let mut encoder =
+                Encoder::new(io::Cursor::new(Vec::new()), version, CdfEncoding::Network)?;
+            cdf.cdr.encode_be(&mut encoder)?;
+            let bytes = encoder.writer.into_inner();
+            assert_eq!(bytes.len(), 48 + len);
+            assert_eq!(&bytes[48..48 + copyright.len()], copyright.as_bytes());
+            assert!(bytes[48 + copyright.len()..].iter().all(|b| *b == 0));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cdr_strict_reserved() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+
+        // Put a non-zero value in rfu_a of the CDR.
+        let mut bytes = std::fs::read(path_test_file)?;
+        bytes[47] = 1;
+
+        let mut decoder = Decoder::new(io::Cursor::new(bytes.as_slice()))?;
+        assert!(cdf::Cdf::decode_be(&mut decoder).is_err());
+
+        let mut decoder = Decoder::builder()
+            .strict(false)
+            .build(io::Cursor::new(bytes.as_slice()))?;
+        let cdf = cdf::Cdf::decode_be(&mut decoder)?;
+        assert_eq!(*cdf.cdr.rfu_a, 1);
+        Ok(())
+    }
 }
@@ -1,4 +1,6 @@
-use std::io::{self, SeekFrom};
+use alloc::{collections::BTreeSet, vec, vec::Vec};
+
+use crate::io;
 
 use crate::{
     decode::{Decodable, Decoder},
@@ -17,18 +19,21 @@ pub trait RecordList {
 /// calls this must be [`Decodable`] and [`RecordList`].
 ///
 /// # Errors
-/// Will return a [`CdfError::Decode`] if the decoding fails for any reason.
+/// Will return a [`CdfError::Decode`] if the decoding fails for any reason, or a
+/// [`CdfError::CyclicRecordList`] if the linked-list loops back on itself.
 pub fn get_record_vec<R, T>(decoder: &mut Decoder<R>, head: &CdfInt8) -> Result<Vec<T>, CdfError>
 where
     R: io::Read + io::Seek,
     T: Decodable + RecordList,
 {
     let mut result_vec = vec![];
+    let mut visited = BTreeSet::new();
     let mut next = head.clone();
     loop {
-        _ = decoder
-            .reader
-            .seek(SeekFrom::Start(u64::try_from(*next)?))?;
+        if !visited.insert(*next) {
+            return Err(CdfError::CyclicRecordList { offset: *next });
+        }
+        _ = decoder.seek_offset(*next)?;
         let record = T::decode_be(decoder)?;
         if let Some(n) = record.next_record() {
             result_vec.push(record);
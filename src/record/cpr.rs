@@ -1,17 +1,24 @@
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use alloc::{format, string::ToString};
+
+use crate::io;
 use crate::{
     decode::{decode_version3_int4_int8, Decodable, Decoder},
+    encode::{encode_version3_int4_int8, Encodable, Encoder},
     error::CdfError,
     types::{CdfInt4, CdfInt8},
 };
-use std::{fmt, io};
+use core::fmt;
 
 /// Stores the different possible compressions that CDF files could make use of.
 #[repr(i32)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CdfCompressionKind {
     /// No compression
     None = 0,
@@ -57,6 +64,7 @@ impl TryFrom<i32> for CdfCompressionKind {
 /// (in case of full compression of the CDF file) or the VDR (in case of compression on individual
 /// variables).
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct CompressedParametersRecord {
     /// Size of this record in bytes.
@@ -78,6 +86,8 @@ impl Decodable for CompressedParametersRecord {
     where
         R: io::Read + io::Seek,
     {
+        let start = decoder.begin_record()?;
+        decoder.keep_raw_record()?;
         let record_size = decode_version3_int4_int8(decoder)?;
         let record_type = CdfInt4::decode_be(decoder)?;
         if *record_type != 11 {
@@ -90,16 +100,16 @@ impl Decodable for CompressedParametersRecord {
         let compression_type: i32 = CdfInt4::decode_be(decoder)?.into();
         let compression_type: CdfCompressionKind = compression_type.try_into()?;
 
-        let compressed_parameter_count = CdfInt4::decode_be(decoder)?;
-
         let rfu_a = CdfInt4::decode_be(decoder)?;
-        if *rfu_a != 0 {
+        if decoder.options.strict && *rfu_a != 0 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_a read from file in CPR - expected 0, received {}",
                 *rfu_a
             )));
         }
 
+        let compressed_parameter_count = CdfInt4::decode_be(decoder)?;
+
         let compression_level = CdfInt4::decode_be(decoder)?;
 
         match &compression_type {
@@ -120,6 +130,8 @@ impl Decodable for CompressedParametersRecord {
             }
         }
 
+        decoder.finish_record(start, &record_size, &record_type)?;
+
         Ok(CompressedParametersRecord {
             record_size,
             record_type,
@@ -140,7 +152,30 @@ impl Decodable for CompressedParametersRecord {
     }
 }
 
-#[cfg(test)]
+impl Encodable for CompressedParametersRecord {
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        encode_version3_int4_int8(encoder, *self.record_size)?;
+        self.record_type.encode_be(encoder)?;
+        CdfInt4::from(self.compression_type.clone() as i32).encode_be(encoder)?;
+        self.rfu_a.encode_be(encoder)?;
+        self.compressed_parameter_count.encode_be(encoder)?;
+        self.compression_level.encode_be(encoder)
+    }
+
+    fn encode_le<W>(&self, _: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        unreachable!(
+            "Little-endian encoding is not supported for records, only for values within records."
+        )
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
 mod tests {
 
     use crate::cdf;
@@ -1,16 +1,24 @@
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use alloc::{format, vec, vec::Vec};
+
+use crate::io;
 use crate::{
-    decode::{decode_version3_int4_int8, Decodable, Decoder},
+    compression::decompress_limited,
+    decode::{checked_usize, decode_version3_int4_int8, Decodable, Decoder},
+    encode::{encode_version3_int4_int8, Encodable, Encoder},
     error::CdfError,
+    record::vvr::VariableRecord,
     types::{CdfInt4, CdfInt8},
 };
-use std::io;
 
 /// Stores the contents of a Compressed Variable Values record, which stores one section of
 /// compressed variable value records (VVR).
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct CompressedVariableValuesRecord {
     /// The size of this record in bytes.
@@ -23,6 +31,29 @@ pub struct CompressedVariableValuesRecord {
     pub compressed_size: CdfInt8,
     /// Compressed data
     pub data: Vec<u8>,
+    /// Variable records stored in the compressed data. This is empty unless the data was
+    /// decompressed while decoding.
+    pub records: Vec<VariableRecord>,
+}
+
+impl CompressedVariableValuesRecord {
+    /// Decompress the data stored in this record and decode the variable records within. The
+    /// compression, data type and number of records are taken from the decoder context.
+    /// # Errors
+    /// Returns a [`CdfError`] if decompression or decoding fails.
+    pub fn decompress_records<R>(
+        &self,
+        decoder: &Decoder<R>,
+    ) -> Result<Vec<VariableRecord>, CdfError>
+    where
+        R: io::Read + io::Seek,
+    {
+        let limit = decoder.options.max_alloc.unwrap_or(usize::MAX);
+        let bytes = decompress_limited(&decoder.context.compression()?, &self.data, limit)?;
+        let num_records = i32::try_from(decoder.context.num_records()?)?;
+        let mut inner = decoder.nested(io::Cursor::new(bytes));
+        VariableRecord::decode_vec_be(&mut inner, &CdfInt4::from(num_records))
+    }
 }
 
 impl Decodable for CompressedVariableValuesRecord {
@@ -30,6 +61,8 @@ impl Decodable for CompressedVariableValuesRecord {
     where
         R: io::Read + io::Seek,
     {
+        let start = decoder.begin_record()?;
+        decoder.keep_raw_record()?;
         let record_size = decode_version3_int4_int8(decoder)?;
         let record_type = CdfInt4::decode_be(decoder)?;
         if *record_type != 13 {
@@ -40,7 +73,7 @@ impl Decodable for CompressedVariableValuesRecord {
         }
 
         let rfu_a = CdfInt4::decode_be(decoder)?;
-        if *rfu_a != 0 {
+        if decoder.options.strict && *rfu_a != 0 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_a read from file in CVVR - expected 0, received {}",
                 *rfu_a
@@ -51,16 +84,25 @@ impl Decodable for CompressedVariableValuesRecord {
 
         // Read the compressed data.
         // prior to v3.0 there were no 8-byte ints.
-        let mut data = vec![0u8; usize::try_from(*compressed_size)?];
+        let num_data = checked_usize("compressed_size", *compressed_size)?;
+        decoder.check_alloc(num_data)?;
+        let mut data = vec![0u8; num_data];
         decoder.reader.read_exact(&mut data)?;
+        decoder.finish_record(start, &record_size, &record_type)?;
 
-        Ok(Self {
+        let mut cvvr = Self {
             record_size,
             record_type,
             rfu_a,
             compressed_size,
             data,
-        })
+            records: vec![],
+        };
+        if decoder.options.decompress {
+            cvvr.records = cvvr.decompress_records(decoder)?;
+        }
+        decoder.report_progress(&cvvr.record_type)?;
+        Ok(cvvr)
     }
     fn decode_le<R>(_: &mut Decoder<R>) -> Result<Self, CdfError>
     where
@@ -72,7 +114,30 @@ impl Decodable for CompressedVariableValuesRecord {
     }
 }
 
-#[cfg(test)]
+impl Encodable for CompressedVariableValuesRecord {
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        encode_version3_int4_int8(encoder, *self.record_size)?;
+        self.record_type.encode_be(encoder)?;
+        self.rfu_a.encode_be(encoder)?;
+        encode_version3_int4_int8(encoder, *self.compressed_size)?;
+        encoder.writer.write_all(&self.data)?;
+        Ok(())
+    }
+
+    fn encode_le<W>(&self, _: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        unreachable!(
+            "Little-endian encoding is not supported for records, only for values within records."
+        )
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
 mod tests {
 
     use crate::cdf;
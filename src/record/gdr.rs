@@ -1,8 +1,17 @@
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use alloc::{format, vec, vec::Vec};
+
+use crate::io;
 use crate::{
-    decode::{decode_version3_int4_int8, Decodable, Decoder},
+    decode::{
+        checked_dim_size, checked_num_dims, decode_version3_int4_int8, Decodable, DecodeWarning,
+        Decoder,
+    },
+    encode::{encode_version3_int4_int8, Encodable, Encoder},
     error::CdfError,
     record::{
         adr::AttributeDescriptorRecord, collection::get_record_vec,
@@ -10,13 +19,14 @@ use crate::{
         zvdr::ZVariableDescriptorRecord,
     },
     repr::CdfVersion,
+    time::leap_seconds,
     types::{CdfInt4, CdfInt8},
 };
-use std::io;
 
 /// The Global Descriptor Record is present in all uncompressed CDF files after the CDF Descriptor
 /// Record, at the file offset noted in the CDR `gdr_offset` attribute.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct GlobalDescriptorRecord {
     /// The size of this record in bytes.
@@ -66,6 +76,8 @@ impl Decodable for GlobalDescriptorRecord {
     where
         R: io::Read + io::Seek,
     {
+        let start = decoder.begin_record()?;
+        decoder.keep_raw_record()?;
         let cdf_version = decoder.context.version()?;
 
         let record_size = decode_version3_int4_int8(decoder)?;
@@ -86,19 +98,34 @@ impl Decodable for GlobalDescriptorRecord {
         // eof is undefined for CDF < v2.1
         let eof = decode_version3_int4_int8(decoder)
             .map(|eof| (cdf_version >= CdfVersion::new(2, 1, 0)).then_some(eof))?;
+        decoder.context.eof = eof
+            .as_ref()
+            .and_then(|eof| u64::try_from(**eof).ok())
+            .filter(|eof| *eof > 0);
 
         let num_rvars = CdfInt4::decode_be(decoder)?;
         let num_attributes = CdfInt4::decode_be(decoder)?;
         let max_rvar = CdfInt4::decode_be(decoder)?;
 
         let num_r_dims = CdfInt4::decode_be(decoder)?;
+        // Checked here so that a negative or absurd number of dimensions is reported as such,
+        // rather than as a number of elements of `size_r_dims`.
+        _ = checked_num_dims("num_r_dims", *num_r_dims)?;
         decoder.context.num_r_dims = Some(num_r_dims.clone());
 
-        let num_zvars = CdfInt4::decode_be(decoder)?;
-        let uir_head = decode_version3_int4_int8(decoder).map(|v| (*v != 0).then_some(v))?;
+        // zVariables were introduced in CDF v2.2, and UIRs along with eof in CDF v2.1.
+        let num_zvars = CdfInt4::decode_be(decoder).map(|v| {
+            if cdf_version >= CdfVersion::new(2, 2, 0) {
+                v
+            } else {
+                CdfInt4::from(0)
+            }
+        })?;
+        let uir_head = decode_version3_int4_int8(decoder)
+            .map(|v| (*v != 0 && cdf_version >= CdfVersion::new(2, 1, 0)).then_some(v))?;
 
         let rfu_c = CdfInt4::decode_be(decoder)?;
-        if *rfu_c != 0 {
+        if decoder.options.strict && *rfu_c != 0 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_c read from file - expected 0, received {}",
                 *rfu_c
@@ -106,9 +133,16 @@ impl Decodable for GlobalDescriptorRecord {
         }
 
         let date_last_leapsecond_update = CdfInt4::decode_be(decoder)?;
+        let library = leap_seconds().last_update();
+        if *date_last_leapsecond_update > library {
+            decoder.report_warning(DecodeWarning::OutdatedLeapSeconds {
+                file: *date_last_leapsecond_update,
+                library,
+            });
+        }
 
         let rfu_e = CdfInt4::decode_be(decoder)?;
-        if *rfu_e != -1 {
+        if decoder.options.strict && *rfu_e != -1 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_e read from file - expected -1, received {}",
                 *rfu_e
@@ -116,8 +150,13 @@ impl Decodable for GlobalDescriptorRecord {
         }
 
         let size_r_dims = CdfInt4::decode_vec_be(decoder, &num_r_dims)?;
+        for (dim, size) in size_r_dims.iter().enumerate() {
+            _ = checked_dim_size("size_r_dims", dim, **size)?;
+        }
         decoder.context.size_r_dims = Some(size_r_dims.clone());
 
+        decoder.finish_record(start, &record_size, &record_type)?;
+
         let rvdr_vec = match &rvdr_head {
             Some(head) => get_record_vec::<R, RVariableDescriptorRecord>(decoder, head)?,
             None => vec![],
@@ -138,6 +177,7 @@ impl Decodable for GlobalDescriptorRecord {
             None => vec![],
         };
 
+        decoder.report_progress(&record_type)?;
         Ok(Self {
             record_size,
             record_type,
@@ -172,7 +212,46 @@ impl Decodable for GlobalDescriptorRecord {
     }
 }
 
-#[cfg(test)]
+impl Encodable for GlobalDescriptorRecord {
+    /// Encode the Global Descriptor Record. The records it points to are encoded separately.
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        let offset = |v: &Option<CdfInt8>| v.as_ref().map_or(0, |v| **v);
+
+        encode_version3_int4_int8(encoder, *self.record_size)?;
+        self.record_type.encode_be(encoder)?;
+        encode_version3_int4_int8(encoder, offset(&self.rvdr_head))?;
+        encode_version3_int4_int8(encoder, offset(&self.zvdr_head))?;
+        encode_version3_int4_int8(encoder, offset(&self.adr_head))?;
+        encode_version3_int4_int8(encoder, offset(&self.eof))?;
+        self.num_rvars.encode_be(encoder)?;
+        self.num_attributes.encode_be(encoder)?;
+        self.max_rvar.encode_be(encoder)?;
+        self.num_r_dims.encode_be(encoder)?;
+        self.num_zvars.encode_be(encoder)?;
+        encode_version3_int4_int8(encoder, offset(&self.uir_head))?;
+        self.rfu_c.encode_be(encoder)?;
+        self.date_last_leapsecond_update.encode_be(encoder)?;
+        self.rfu_e.encode_be(encoder)?;
+        for size in &self.size_r_dims {
+            size.encode_be(encoder)?;
+        }
+        Ok(())
+    }
+
+    fn encode_le<W>(&self, _: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        unreachable!(
+            "Little-endian encoding is not supported for records, only for values within records."
+        )
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
 mod tests {
 
     use crate::cdf;
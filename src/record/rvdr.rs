@@ -1,20 +1,27 @@
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use alloc::{format, string::ToString, vec, vec::Vec};
+
+use crate::io;
 use crate::{
-    decode::{decode_version3_int4_int8, Decodable, Decoder},
+    decode::{checked_usize, decode_version3_int4_int8, Decodable, DecodeWarning, Decoder},
+    encode::{encode_version3_int4_int8, Encodable, Encoder},
     error::CdfError,
     record::{
         collection::{get_record_vec, RecordList},
-        vxr::VariableIndexRecord,
+        cpr::CompressedParametersRecord,
+        vxr::{check_index, VariableIndexRecord},
     },
-    repr::Endian,
+    repr::{CdfVersion, Endian},
     types::{CdfInt4, CdfInt8, CdfString, CdfType},
 };
-use std::io;
 
 /// Various options for rVariables.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct RVariableFlags {
     /// Whether this rVariable has variance.
@@ -27,6 +34,7 @@ pub struct RVariableFlags {
 
 /// Describes one rVariable stored in the CDF file.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct RVariableDescriptorRecord {
     /// Size of this record in bytes.
@@ -64,10 +72,15 @@ pub struct RVariableDescriptorRecord {
     pub blocking_factor: CdfInt4,
     /// Name of this variable
     pub name: CdfString,
+    /// Sizes of the dimensions of this rVariable. All rVariables have the dimensions stored in the
+    /// GDR, which are copied here when decoding rather than stored in the rVDR.
+    pub size_r_dims: Vec<CdfInt4>,
     /// Dimension variances for this variable.
     pub dim_variances: Vec<bool>,
-    /// Pad value of this variable.
+    /// Pad value of this variable. Empty if the variable does not have a pad value.
     pub pad_value: Vec<CdfType>,
+    /// Compressed Parameters Record describing the compression of this variable, if compressed.
+    pub cpr: Option<CompressedParametersRecord>,
     /// Vector of Variable Index Records.
     pub vxr_vec: Vec<VariableIndexRecord>,
 }
@@ -77,6 +90,8 @@ impl Decodable for RVariableDescriptorRecord {
     where
         R: io::Read + io::Seek,
     {
+        let start = decoder.begin_record()?;
+        decoder.keep_raw_record()?;
         let record_size = decode_version3_int4_int8(decoder)?;
         let record_type = CdfInt4::decode_be(decoder)?;
         if *record_type != 3 {
@@ -90,34 +105,45 @@ impl Decodable for RVariableDescriptorRecord {
 
         let data_type = CdfInt4::decode_be(decoder)?;
         let max_record = CdfInt4::decode_be(decoder)?;
+        // The last record is -1 for variables without records, and is never lower.
+        if decoder.options.strict && *max_record < -1 {
+            return Err(CdfError::InvalidCount {
+                field: "max_record",
+                value: i64::from(*max_record),
+            });
+        }
         let vxr_head = decode_version3_int4_int8(decoder).map(|v| (*v != 0).then_some(v))?;
         let vxr_tail = decode_version3_int4_int8(decoder).map(|v| (*v != 0).then_some(v))?;
 
+        // Compression and sparse records were introduced in CDF v2.6, their fields are undefined
+        // before.
+        let compressible = decoder.context.version()? >= CdfVersion::new(2, 6, 0);
         let flags = CdfInt4::decode_be(decoder)?;
         let flags = RVariableFlags {
             variance: *flags & 1i32 == 1,
             has_padding: *flags & 2i32 == 2,
-            is_compressed: *flags & 4i32 == 4,
+            is_compressed: compressible && *flags & 4i32 == 4,
         };
 
-        let sparse_records = CdfInt4::decode_be(decoder)?;
+        let sparse_records =
+            CdfInt4::decode_be(decoder).map(|v| if compressible { v } else { CdfInt4::from(0) })?;
 
         let rfu_b = CdfInt4::decode_be(decoder)?;
-        if *rfu_b != 0 {
+        if decoder.options.strict && *rfu_b != 0 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_b read from file in RVDR - expected 0, received {}",
                 *rfu_b
             )));
         }
         let rfu_c = CdfInt4::decode_be(decoder)?;
-        if *rfu_c != -1 {
+        if decoder.options.strict && *rfu_c != -1 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_c read from file in RVDR - expected -1, received {}",
                 *rfu_c
             )));
         }
         let rfu_f = CdfInt4::decode_be(decoder)?;
-        if *rfu_f != -1 {
+        if decoder.options.strict && *rfu_f != -1 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_f read from file in RVDR - expected -1, received {}",
                 *rfu_f
@@ -130,14 +156,21 @@ impl Decodable for RVariableDescriptorRecord {
         // According to spec, this check should be with 0xFFFF_FFFF_FFFF_FFFF. But Rust
         // throws a compilation error because this does not fit inside a Int8. So we are
         // checking with -1 instead, which should lead to the same behavior.
-        let cpr_spr_offset = decode_version3_int4_int8(decoder).map(|v| (*v != -1).then_some(v))?;
+        let cpr_spr_offset =
+            decode_version3_int4_int8(decoder).map(|v| (*v != -1 && compressible).then_some(v))?;
 
         let blocking_factor = CdfInt4::decode_be(decoder)?;
 
-        let name = CdfString::decode_string_from_numbytes(decoder, 256)?;
+        let name = if decoder.context.version()?.major < 3 {
+            CdfString::decode_string_from_numbytes(decoder, 64)?
+        } else {
+            CdfString::decode_string_from_numbytes(decoder, 256)?
+        };
+        decoder.context.variable_name = Some(name.to_string());
+        decoder.report_progress(&record_type)?;
 
         let num_r_dims = *decoder.context.num_r_dims()?;
-        let mut dim_variances: Vec<bool> = vec![false; usize::try_from(num_r_dims)?];
+        let mut dim_variances: Vec<bool> = vec![false; checked_usize("num_r_dims", num_r_dims)?];
         for d in dim_variances.iter_mut() {
             if *CdfInt4::decode_be(decoder)? == -1 {
                 *d = true;
@@ -145,9 +178,10 @@ impl Decodable for RVariableDescriptorRecord {
         }
 
         let endianness = decoder.context.endianness()?;
-        let pad_value = match endianness {
-            Endian::Big => CdfType::decode_vec_be(decoder, &data_type, &num_elements)?,
-            Endian::Little => CdfType::decode_vec_le(decoder, &data_type, &num_elements)?,
+        let pad_value = match (flags.has_padding, endianness) {
+            (false, _) => vec![],
+            (true, Endian::Big) => CdfType::decode_vec_be(decoder, &data_type, &num_elements)?,
+            (true, Endian::Little) => CdfType::decode_vec_le(decoder, &data_type, &num_elements)?,
         };
 
         // Before reading in the VXRs, we need to know the variable data type and the number of such
@@ -157,25 +191,51 @@ impl Decodable for RVariableDescriptorRecord {
         // The size of all actively stored dimensions is known from the GDR. While decoding the
         // GDR, this relevant information was also stored in the decoder context.
 
-        let size_r_dims = decoder.context.size_r_dims()?;
-        let size_active_dims: i32 = dim_variances
+        let size_r_dims = decoder.context.size_r_dims()?.clone();
+        let var_data_len = dim_variances
             .iter()
             .zip(size_r_dims.iter())
             .filter(|(v, _)| **v)
-            .map(|(_, s)| **s)
-            .product();
-
-        let var_data_len = (*num_elements) * (size_active_dims);
+            .try_fold(*num_elements, |len, (_, s)| {
+                (**s > 0).then(|| len.checked_mul(**s)).flatten()
+            })
+            .filter(|len| *len > 0)
+            .ok_or_else(|| {
+                CdfError::Decode(format!(
+                    "Invalid number of elements or dimension sizes for variable {name}."
+                ))
+            })?;
 
         decoder.context.var_data_type = Some(data_type.clone());
         decoder.context.var_data_len = Some(CdfInt4::from(var_data_len));
 
+        decoder.finish_record(start, &record_size, &record_type)?;
+
+        // The CPR is only present for compressed variables. Otherwise, this offset points to a
+        // Sparseness Parameters Record, which is not implemented.
+        let cpr = match (&flags.is_compressed, &cpr_spr_offset) {
+            (true, Some(offset)) => {
+                _ = decoder.seek_offset(**offset)?;
+                Some(CompressedParametersRecord::decode_be(decoder)?)
+            }
+            _ => None,
+        };
+        decoder.context.compression = cpr.as_ref().map(|c| c.compression_type.clone());
+        decoder.context.skip_values = !decoder.options.loads_variable(&name);
+
         let vxr_vec = if let Some(head) = &vxr_head {
             get_record_vec::<R, VariableIndexRecord>(decoder, head)?
         } else {
             vec![]
         };
+        for problem in check_index(&vxr_vec, *max_record, *sparse_records != 0) {
+            decoder.report_warning(DecodeWarning::InconsistentIndex {
+                variable: name.to_string(),
+                problem,
+            });
+        }
 
+        decoder.context.variable_name = None;
         Ok(RVariableDescriptorRecord {
             record_size,
             record_type,
@@ -194,15 +254,17 @@ impl Decodable for RVariableDescriptorRecord {
             cpr_spr_offset,
             blocking_factor,
             name,
+            size_r_dims,
             dim_variances,
             pad_value,
+            cpr,
             vxr_vec,
         })
     }
 
     fn decode_le<R>(_: &mut Decoder<R>) -> Result<Self, CdfError>
     where
-        R: std::io::Read + std::io::Seek,
+        R: crate::io::Read + crate::io::Seek,
     {
         unreachable!(
             "Little-endian decoding is not supported for records, only for values within records."
@@ -210,13 +272,70 @@ impl Decodable for RVariableDescriptorRecord {
     }
 }
 
+impl Encodable for RVariableDescriptorRecord {
+    /// Encode the rVariable Descriptor Record. The CPR and VXRs it points to are encoded
+    /// separately.
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        let offset = |v: &Option<CdfInt8>, none: i64| v.as_ref().map_or(none, |v| **v);
+
+        encode_version3_int4_int8(encoder, *self.record_size)?;
+        self.record_type.encode_be(encoder)?;
+        encode_version3_int4_int8(encoder, offset(&self.rvdr_next, 0))?;
+        self.data_type.encode_be(encoder)?;
+        self.max_record.encode_be(encoder)?;
+        encode_version3_int4_int8(encoder, offset(&self.vxr_head, 0))?;
+        encode_version3_int4_int8(encoder, offset(&self.vxr_tail, 0))?;
+
+        let flags = i32::from(self.flags.variance)
+            | i32::from(self.flags.has_padding) << 1
+            | i32::from(self.flags.is_compressed) << 2;
+        CdfInt4::from(flags).encode_be(encoder)?;
+
+        self.sparse_records.encode_be(encoder)?;
+        self.rfu_b.encode_be(encoder)?;
+        self.rfu_c.encode_be(encoder)?;
+        self.rfu_f.encode_be(encoder)?;
+        self.num_elements.encode_be(encoder)?;
+        self.num.encode_be(encoder)?;
+        encode_version3_int4_int8(encoder, offset(&self.cpr_spr_offset, -1))?;
+        self.blocking_factor.encode_be(encoder)?;
+        let name_bytes = if encoder.context.version.major < 3 {
+            64
+        } else {
+            256
+        };
+        self.name.encode_string_to_numbytes(encoder, name_bytes)?;
+        for variance in &self.dim_variances {
+            CdfInt4::from(if *variance { -1 } else { 0 }).encode_be(encoder)?;
+        }
+        if self.flags.has_padding {
+            for value in &self.pad_value {
+                value.encode_value(encoder)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_le<W>(&self, _: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        unreachable!(
+            "Little-endian encoding is not supported for records, only for values within records."
+        )
+    }
+}
+
 impl RecordList for RVariableDescriptorRecord {
     fn next_record(&self) -> Option<CdfInt8> {
         self.rvdr_next.clone()
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std-fs"))]
 mod tests {
 
     use crate::cdf;
@@ -253,4 +372,30 @@ mod tests {
         // }
         Ok(())
     }
+
+    #[test]
+    fn test_rvdr_shape() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "ulysses.cdf",
+        ]
+        .iter()
+        .collect();
+        let cdf = cdf::Cdf::read_cdf_file(path_test_file)?;
+        let gdr = &cdf.cdr.gdr;
+        for rvdr in &gdr.rvdr_vec {
+            assert_eq!(rvdr.size_r_dims, gdr.size_r_dims);
+        }
+
+        // Records of rVariables hold the values of the dimensions that vary.
+        let time = cdf.variable("Time_PB5")?;
+        assert_eq!((time.dim_sizes()?, time.shape()?), (vec![3], vec![3]));
+        assert_eq!(time.read_records(0..1)?[0].data.len(), 3);
+        let epoch = cdf.variable("Epoch")?;
+        assert_eq!((epoch.dim_sizes()?, epoch.shape()?), (vec![3], vec![]));
+        assert_eq!(epoch.read_records(0..1)?[0].data.len(), 1);
+        Ok(())
+    }
 }
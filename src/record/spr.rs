@@ -1,4 +1,8 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 /// Stores the contents of a Sparseness Parameters Record, which is used to store sparse array
 /// information used by a variable record. This record is not being implemented here because it is
 /// not implemented in the official C library.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SparsenessParametersRecord {}
@@ -1,16 +1,21 @@
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use alloc::{format, vec, vec::Vec};
+
+use crate::io;
 use crate::{
-    decode::{decode_version3_int4_int8, Decodable, Decoder},
+    decode::{decode_version3_int4_int8, record_data_size, Decodable, Decoder},
     error::CdfError,
     record::collection::RecordList,
     types::{CdfInt4, CdfInt8},
 };
-use std::io;
 
 /// Stores the contents of an Unused Internal Record.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct UnusedInternalRecord {
     /// The size of this record in bytes.
@@ -30,6 +35,8 @@ impl Decodable for UnusedInternalRecord {
     where
         R: io::Read + io::Seek,
     {
+        _ = decoder.begin_record()?;
+        decoder.keep_raw_record()?;
         let record_size = decode_version3_int4_int8(decoder)?;
         let record_type = CdfInt4::decode_be(decoder)?;
         if *record_type != -1 {
@@ -45,10 +52,11 @@ impl Decodable for UnusedInternalRecord {
         // Read the remainder data.
         // prior to v3.0 there were no 8-byte ints.
         let num_data = if decoder.context.version()?.major < 3 {
-            usize::try_from(*record_size)? - 16
+            record_data_size(&record_size, 16)?
         } else {
-            usize::try_from(*record_size)? - 28
+            record_data_size(&record_size, 28)?
         };
+        decoder.check_alloc(num_data)?;
         let mut remainder = vec![0u8; num_data];
         decoder.reader.read_exact(&mut remainder)?;
 
@@ -80,6 +88,7 @@ impl RecordList for UnusedInternalRecord {
 /// Stores the contents of an Unsociable Unused Internal Record. (yes, that is the official name)
 /// There are isolated unused records that are not stored on the unused linked-list.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct UnsociableUnusedInternalRecord {
     /// The size of this record in bytes.
@@ -95,6 +104,8 @@ impl Decodable for UnsociableUnusedInternalRecord {
     where
         R: io::Read + io::Seek,
     {
+        _ = decoder.begin_record()?;
+        decoder.keep_raw_record()?;
         let record_size = decode_version3_int4_int8(decoder)?;
         let record_type = CdfInt4::decode_be(decoder)?;
         if *record_type != -1 {
@@ -107,10 +118,11 @@ impl Decodable for UnsociableUnusedInternalRecord {
         // Read the remainder data.
         // prior to v3.0 there were no 8-byte ints.
         let num_data = if decoder.context.version()?.major < 3 {
-            usize::try_from(*record_size)? - 8
+            record_data_size(&record_size, 8)?
         } else {
-            usize::try_from(*record_size)? - 12
+            record_data_size(&record_size, 12)?
         };
+        decoder.check_alloc(num_data)?;
         let mut remainder = vec![0u8; num_data];
         decoder.reader.read_exact(&mut remainder)?;
 
@@ -131,7 +143,7 @@ impl Decodable for UnsociableUnusedInternalRecord {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std-fs"))]
 mod tests {
 
     use crate::cdf;
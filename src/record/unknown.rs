@@ -0,0 +1,128 @@
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use alloc::{format, vec, vec::Vec};
+
+use crate::io;
+use crate::{
+    decode::{decode_version3_int4_int8, record_data_size, Decodable, Decoder},
+    encode::{encode_version3_int4_int8, Encodable, Encoder},
+    error::CdfError,
+    types::{CdfInt4, CdfInt8},
+};
+
+/// Stores a record of a type that this crate does not know, such as a record added by a later
+/// version of the CDF specification. Its fields are kept as they are so that it can be written
+/// back unchanged.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[derive(Debug)]
+pub struct UnknownRecord {
+    /// The size of this record in bytes.
+    pub record_size: CdfInt8,
+    /// The type of record as an integer, which is not one of the types this crate decodes.
+    pub record_type: CdfInt4,
+    /// File offset of this record.
+    pub offset: u64,
+    /// Bytes of the record after its size and type.
+    pub bytes: Vec<u8>,
+}
+
+/// Whether `record_type` is one of the types of records defined by the CDF specification, which
+/// are decoded by this crate.
+pub fn is_known_record_type(record_type: i32) -> bool {
+    matches!(record_type, -1 | 1..=13)
+}
+
+impl UnknownRecord {
+    /// Build the record found at `offset` from its bytes `raw`, header included, where the header
+    /// holding the record size and type is `header` bytes long.
+    /// # Errors
+    /// Returns a [`CdfError::Decode`] if `raw` is shorter than the header.
+    pub(crate) fn from_raw(offset: u64, raw: &[u8], header: usize) -> Result<Self, CdfError> {
+        if raw.len() < header || !matches!(header, 8 | 12) {
+            return Err(CdfError::Decode(format!(
+                "Record at offset {offset} is shorter than its header."
+            )));
+        }
+        // The record size takes 8 bytes from v3 on and 4 bytes before, followed by the type.
+        let (size, record_type) = raw[..header].split_at(header - 4);
+        let mut padded = [0u8; 8];
+        padded[8 - size.len()..].copy_from_slice(size);
+        let record_size = i64::from_be_bytes(padded);
+        let record_type = i32::from_be_bytes([
+            record_type[0],
+            record_type[1],
+            record_type[2],
+            record_type[3],
+        ]);
+        Ok(UnknownRecord {
+            record_size: CdfInt8::from(record_size),
+            record_type: CdfInt4::from(record_type),
+            offset,
+            bytes: raw[header..].to_vec(),
+        })
+    }
+}
+
+impl Decodable for UnknownRecord {
+    fn decode_be<R>(decoder: &mut Decoder<R>) -> Result<Self, CdfError>
+    where
+        R: io::Read + io::Seek,
+    {
+        let offset = decoder.begin_record()?;
+        decoder.keep_raw_record()?;
+        let record_size = decode_version3_int4_int8(decoder)?;
+        let record_type = CdfInt4::decode_be(decoder)?;
+
+        // prior to v3.0 there were no 8-byte ints.
+        let num_data = if decoder.context.version()?.major < 3 {
+            record_data_size(&record_size, 8)?
+        } else {
+            record_data_size(&record_size, 12)?
+        };
+        decoder.check_alloc(num_data)?;
+        let mut bytes = vec![0u8; num_data];
+        decoder.reader.read_exact(&mut bytes)?;
+        decoder.report_progress(&record_type)?;
+
+        Ok(UnknownRecord {
+            record_size,
+            record_type,
+            offset,
+            bytes,
+        })
+    }
+
+    fn decode_le<R>(_: &mut Decoder<R>) -> Result<Self, CdfError>
+    where
+        R: io::Read + io::Seek,
+    {
+        unreachable!(
+            "Little-endian decoding is not supported for records, only for values within records."
+        )
+    }
+}
+
+impl Encodable for UnknownRecord {
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        encode_version3_int4_int8(encoder, *self.record_size)?;
+        self.record_type.encode_be(encoder)?;
+        encoder.writer.write_all(&self.bytes)?;
+        Ok(())
+    }
+
+    fn encode_le<W>(&self, _: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        unreachable!(
+            "Little-endian encoding is not supported for records, only for values within records."
+        )
+    }
+}
@@ -1,13 +1,18 @@
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use alloc::{format, vec::Vec};
+
+use crate::io;
 use crate::{
     decode::{decode_version3_int4_int8, Decodable, Decoder},
+    encode::{encode_version3_int4_int8, Encodable, Encoder},
     error::CdfError,
     repr::Endian,
     types::{CdfInt4, CdfInt8, CdfType},
 };
-use std::io;
 
 /// A Variable Record contains an array of variables. Each variable may have multiple elements. For
 /// example, a variable record may contain many strings. Each string is an element of the variable
@@ -35,7 +40,8 @@ use std::io;
 /// - `dim_variances` is stored in the rVDR as `dim_variances`
 /// - `data` is stored in the VariableValuesRecord that we need to read in.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[derive(Debug, Clone)]
 pub struct VariableRecord {
     /// Integer identifier for the data type stored in this variable record as per the spec.
     pub data_type: CdfInt4,
@@ -69,7 +75,7 @@ impl Decodable for VariableRecord {
 
     fn decode_le<R>(_: &mut Decoder<R>) -> Result<Self, CdfError>
     where
-        R: std::io::Read + std::io::Seek,
+        R: crate::io::Read + crate::io::Seek,
     {
         unreachable!(
             "Little-endian decoding is not supported for records, only for values within records."
@@ -77,8 +83,31 @@ impl Decodable for VariableRecord {
     }
 }
 
+impl Encodable for VariableRecord {
+    /// Encode the values of this record using the endianness of the values of the file.
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        for value in &self.data {
+            value.encode_value(encoder)?;
+        }
+        Ok(())
+    }
+
+    fn encode_le<W>(&self, _: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        unreachable!(
+            "Little-endian encoding is not supported for records, only for values within records."
+        )
+    }
+}
+
 /// Stores the contents of a Variable Values Record.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct VariableValuesRecord {
     /// The size of this record in bytes.
@@ -101,6 +130,8 @@ impl Decodable for VariableValuesRecord {
     where
         R: io::Read + io::Seek,
     {
+        let start = decoder.begin_record()?;
+        decoder.keep_raw_record()?;
         let record_size = decode_version3_int4_int8(decoder)?;
         let record_type = CdfInt4::decode_be(decoder)?;
         if *record_type != 7 {
@@ -112,11 +143,15 @@ impl Decodable for VariableValuesRecord {
 
         let num_records = decoder.context.num_records()?;
 
+        decoder.check_alloc(num_records.saturating_mul(core::mem::size_of::<VariableRecord>()))?;
         let mut records = Vec::with_capacity(num_records);
         for _ in 0..num_records {
+            decoder.check_cancelled()?;
             records.push(VariableRecord::decode_be(decoder)?);
         }
+        decoder.finish_record(start, &record_size, &record_type)?;
 
+        decoder.report_progress(&record_type)?;
         Ok(VariableValuesRecord {
             record_size,
             record_type,
@@ -126,10 +161,33 @@ impl Decodable for VariableValuesRecord {
 
     fn decode_le<R>(_: &mut Decoder<R>) -> Result<Self, CdfError>
     where
-        R: std::io::Read + std::io::Seek,
+        R: crate::io::Read + crate::io::Seek,
     {
         unreachable!(
             "Little-endian decoding is not supported for records, only for values within records."
         )
     }
 }
+
+impl Encodable for VariableValuesRecord {
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        encode_version3_int4_int8(encoder, *self.record_size)?;
+        self.record_type.encode_be(encoder)?;
+        for record in &self.records {
+            record.encode_be(encoder)?;
+        }
+        Ok(())
+    }
+
+    fn encode_le<W>(&self, _: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        unreachable!(
+            "Little-endian encoding is not supported for records, only for values within records."
+        )
+    }
+}
@@ -1,13 +1,24 @@
-use std::io::SeekFrom;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::io::{self, SeekFrom};
 
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    decode::{decode_version3_int4_int8, Decodable, Decoder},
+    decode::{checked_usize, decode_version3_int4_int8, Decodable, Decoder},
+    encode::{encode_version3_int4_int8, Encodable, Encoder},
     error::CdfError,
     record::{
-        collection::RecordList, cvvr::CompressedVariableValuesRecord, vvr::VariableValuesRecord,
+        collection::RecordList, cvvr::CompressedVariableValuesRecord, unknown::UnknownRecord,
+        vvr::VariableValuesRecord,
     },
     types::{CdfInt4, CdfInt8},
 };
@@ -16,6 +27,7 @@ use crate::{
 /// Variable Index Records are used in single-file CDFs to store the file offsets of any
 /// lower level of VXRs, Variable Values Records, or Compressed Variable Value Records.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct VariableIndexRecord {
     /// Size of this record in bytes.
@@ -34,15 +46,48 @@ pub struct VariableIndexRecord {
     pub last_vec: Vec<Option<CdfInt4>>,
     /// File offset of the VVR, CVVR or lower level VXR.
     pub offset_vec: Vec<Option<CdfInt8>>,
-    /// Child record that are pointed to by this VXR.
+    /// Child record that are pointed to by this VXR. Children are `None` if the values of the
+    /// variable were skipped while decoding.
     pub children: Vec<Option<VariableIndexRecordChild>>,
 }
 
 impl Decodable for VariableIndexRecord {
+    /// Decode the entries of this VXR and, unless the values of the variable are skipped, its
+    /// children. See [`VariableIndexRecord::decode_index`] to only decode the entries.
     fn decode_be<R>(decoder: &mut Decoder<R>) -> Result<Self, CdfError>
     where
-        R: std::io::Read + std::io::Seek,
+        R: crate::io::Read + crate::io::Seek,
+    {
+        let mut vxr = Self::decode_index(decoder)?;
+        if !decoder.context.skip_values {
+            for i in 0..vxr.offset_vec.len() {
+                vxr.children[i] = vxr.read_child(decoder, i)?;
+            }
+        }
+        Ok(vxr)
+    }
+
+    fn decode_le<R>(_: &mut Decoder<R>) -> Result<Self, crate::error::CdfError>
+    where
+        R: crate::io::Read + crate::io::Seek,
     {
+        unreachable!(
+            "Little-endian decoding is not supported for records, only for values within records."
+        )
+    }
+}
+
+impl VariableIndexRecord {
+    /// Decode the entries of this VXR without seeking to its children, which are all `None`.
+    /// Children are read on demand with [`VariableIndexRecord::read_child`].
+    /// # Errors
+    /// Returns a [`CdfError::Decode`] if the record is invalid.
+    pub fn decode_index<R>(decoder: &mut Decoder<R>) -> Result<Self, CdfError>
+    where
+        R: io::Read + io::Seek,
+    {
+        let start = decoder.begin_record()?;
+        decoder.keep_raw_record()?;
         let record_size = decode_version3_int4_int8(decoder)?;
         let record_type = CdfInt4::decode_be(decoder)?;
         if *record_type != 6 {
@@ -55,7 +100,8 @@ impl Decodable for VariableIndexRecord {
 
         let num_entries = CdfInt4::decode_be(decoder)?;
         let num_used_entries = CdfInt4::decode_be(decoder)?;
-        let n = usize::try_from(*num_entries)?;
+        let n = checked_usize("num_entries", *num_entries)?;
+        decoder.check_alloc(n.saturating_mul(core::mem::size_of::<Option<CdfInt8>>()))?;
 
         let mut first_vec: Vec<Option<CdfInt4>> = vec![None; n];
         for val in first_vec.iter_mut() {
@@ -84,31 +130,8 @@ impl Decodable for VariableIndexRecord {
             }
         }
 
-        let mut children: Vec<Option<VariableIndexRecordChild>> = Vec::with_capacity(n);
-        for i in 0..n {
-            if let Some(next) = &offset_vec[i] {
-                _ = decoder
-                    .reader
-                    .seek(SeekFrom::Start(u64::try_from(**next)?))?;
-
-                // Each first and last vec combination gives the number of variable records stored
-                // in this group of this VXR.
-                let num_records = match (&first_vec[i], &last_vec[i]) {
-                    (Some(first), Some(last)) => usize::try_from(**last - **first)
-                        .map_err(|e| CdfError::Decode(e.to_string())),
-                    _ => Err(CdfError::Decode(
-                        "first and last in VXR do not have matching Some value.".to_string(),
-                    )),
-                }?;
-
-                decoder.context.num_records = Some(num_records);
-
-                children.push(Some(VariableIndexRecordChild::decode_be(decoder)?));
-            } else {
-                children.push(None)
-            }
-        }
-
+        decoder.finish_record(start, &record_size, &record_type)?;
+        decoder.report_progress(&record_type)?;
         Ok(VariableIndexRecord {
             record_size,
             record_type,
@@ -118,17 +141,82 @@ impl Decodable for VariableIndexRecord {
             first_vec,
             last_vec,
             offset_vec,
-            children,
+            children: (0..n).map(|_| None).collect(),
         })
     }
 
-    fn decode_le<R>(_: &mut Decoder<R>) -> Result<Self, crate::error::CdfError>
+    /// Read the child of entry `i` of this VXR from the file. The values of a VVR or CVVR child
+    /// are read unless `decoder.context.skip_values` is set, in which case a lower-level VXR is
+    /// only indexed as by [`VariableIndexRecord::decode_index`]. Returns `None` if the entry is
+    /// unused.
+    /// # Errors
+    /// Returns a [`CdfError`] if the entry is invalid or the child cannot be decoded.
+    pub fn read_child<R>(
+        &self,
+        decoder: &mut Decoder<R>,
+        i: usize,
+    ) -> Result<Option<VariableIndexRecordChild>, CdfError>
     where
-        R: std::io::Read + std::io::Seek,
+        R: io::Read + io::Seek,
     {
-        unreachable!(
-            "Little-endian decoding is not supported for records, only for values within records."
-        )
+        let Some(Some(offset)) = self.offset_vec.get(i) else {
+            return Ok(None);
+        };
+        decoder.context.num_records = Some(self.num_records(i)?);
+        _ = decoder.seek_offset(**offset)?;
+        VariableIndexRecordChild::decode_be(decoder).map(Some)
+    }
+
+    /// Returns the number of variable records stored in the child of entry `i` of this VXR.
+    /// # Errors
+    /// Returns a [`CdfError::Decode`] if the entry is unused or invalid.
+    pub fn num_records(&self, i: usize) -> Result<usize, CdfError> {
+        match (self.first_vec.get(i), self.last_vec.get(i)) {
+            (Some(first), Some(last)) => entry_num_records(first, last),
+            _ => Err(CdfError::Decode(format!("VXR does not have an entry {i}."))),
+        }
+    }
+
+    /// Number of entries that are in use, which come before the unused ones.
+    fn used_entries(&self) -> usize {
+        usize::try_from(*self.num_used_entries)
+            .unwrap_or(0)
+            .min(self.first_vec.len())
+            .min(self.last_vec.len())
+    }
+
+    /// Last record stored under this VXR.
+    fn last_record(&self) -> Option<i32> {
+        let used = self.used_entries();
+        self.last_vec[..used]
+            .iter()
+            .rev()
+            .find_map(|l| l.as_ref().map(|l| **l))
+    }
+
+    /// Binary search for the entry whose range of records contains `record`.
+    fn find_entry(&self, record: i32) -> Option<usize> {
+        let used = self.used_entries();
+        let i = self.last_vec[..used].partition_point(|l| l.as_ref().is_some_and(|l| **l < record));
+        match (self.first_vec.get(i)?, self.last_vec.get(i)?) {
+            (Some(first), Some(last)) if **first <= record && record <= **last && i < used => {
+                Some(i)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Each first and last vec combination gives the (inclusive) range of variable records stored in
+/// one entry of a VXR.
+fn entry_num_records(first: &Option<CdfInt4>, last: &Option<CdfInt4>) -> Result<usize, CdfError> {
+    match (first, last) {
+        (Some(first), Some(last)) => {
+            checked_usize("num_records", i64::from(**last) - i64::from(**first) + 1)
+        }
+        _ => Err(CdfError::Decode(
+            "first and last in VXR do not have matching Some value.".to_string(),
+        )),
     }
 }
 
@@ -138,9 +226,181 @@ impl RecordList for VariableIndexRecord {
     }
 }
 
+/// Check the index of the values of a variable: the entries of its top-level VXRs `vxrs` and of
+/// their decoded children must each hold an inclusive range of records, the ranges must increase,
+/// and entries of lower-level VXRs must lie within the range of their parent entry. Unless the
+/// variable has sparse records, every record up to `max_record` must also be stored. Returns a
+/// description of each problem, which would otherwise lead to records that are silently replaced
+/// by pad values when reading.
+pub fn check_index(vxrs: &[VariableIndexRecord], max_record: i32, sparse: bool) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut previous = None;
+    let mut gaps = Vec::new();
+    check_vxrs(vxrs, None, &mut previous, &mut gaps, &mut problems);
+    if !sparse {
+        let stored = previous.unwrap_or(-1);
+        if stored < max_record {
+            gaps.push((stored + 1, max_record));
+        }
+        for (first, last) in gaps.into_iter().filter(|(first, _)| *first <= max_record) {
+            problems.push(format!(
+                "Records {first} to {} are not stored, but the variable has no sparse records.",
+                last.min(max_record)
+            ));
+        }
+    }
+    problems
+}
+
+fn check_vxrs(
+    vxrs: &[VariableIndexRecord],
+    parent: Option<(i32, i32)>,
+    previous: &mut Option<i32>,
+    gaps: &mut Vec<(i32, i32)>,
+    problems: &mut Vec<String>,
+) {
+    for vxr in vxrs {
+        let num_entries = vxr.offset_vec.len();
+        let num_used = usize::try_from(*vxr.num_used_entries).unwrap_or(usize::MAX);
+        if num_used > num_entries {
+            problems.push(format!(
+                "VXR uses {} of its {num_entries} entries.",
+                *vxr.num_used_entries
+            ));
+        }
+        for i in 0..num_entries {
+            let entry = (&vxr.first_vec[i], &vxr.last_vec[i], &vxr.offset_vec[i]);
+            let (first, last) = match entry {
+                (None, None, None) if i < num_used => {
+                    problems.push(format!("VXR entry {i} is in use but empty."));
+                    continue;
+                }
+                (None, None, None) => continue,
+                (Some(first), Some(last), Some(_)) => (**first, **last),
+                _ => {
+                    problems.push(format!(
+                        "VXR entry {i} is missing its first record, last record or offset."
+                    ));
+                    continue;
+                }
+            };
+            if last < first {
+                problems.push(format!(
+                    "VXR entry {i} holds records {first} to {last}, which is an empty range."
+                ));
+                continue;
+            }
+            let next = previous.map_or(0, |previous| previous + 1);
+            if parent.is_none() && first > next {
+                gaps.push((next, first - 1));
+            }
+            if let Some(previous) = previous.filter(|previous| first <= *previous) {
+                problems.push(format!(
+                    "VXR entry {i} holds records {first} to {last}, which do not follow record \
+                     {previous} of the entry before it."
+                ));
+            }
+            if let Some((low, high)) = parent.filter(|(low, high)| first < *low || last > *high) {
+                problems.push(format!(
+                    "VXR entry {i} holds records {first} to {last}, outside of records {low} to \
+                     {high} of its parent entry."
+                ));
+            }
+            if let Some(Some(VariableIndexRecordChild::VXR(child))) = vxr.children.get(i) {
+                check_vxrs(
+                    core::slice::from_ref(child),
+                    Some((first, last)),
+                    previous,
+                    gaps,
+                    problems,
+                );
+            }
+            *previous = Some(last);
+        }
+    }
+}
+
+/// Index over the chain of top-level VXRs of one variable, used to find the VVR or CVVR that
+/// stores a record without reading the other blocks of the variable.
+#[derive(Debug, Clone, Copy)]
+pub struct VxrIndex<'a> {
+    vxrs: &'a [VariableIndexRecord],
+}
+
+impl<'a> VxrIndex<'a> {
+    /// Create an index over the top-level VXRs of a variable, e.g. the `vxr_vec` of its VDR.
+    pub fn new(vxrs: &'a [VariableIndexRecord]) -> Self {
+        VxrIndex { vxrs }
+    }
+
+    /// Find the block that stores record `record`. Returns the file offset of the VVR or CVVR and
+    /// the position of the record within that block, or `None` if the record is not stored in
+    /// the file. Entries are binary searched at every level of the VXR tree, and lower-level VXRs
+    /// that were not decoded are indexed from the file as they are reached.
+    /// # Errors
+    /// Returns a [`CdfError`] if a lower-level VXR cannot be read.
+    pub fn locate<R>(
+        &self,
+        decoder: &mut Decoder<R>,
+        record: usize,
+    ) -> Result<Option<(u64, usize)>, CdfError>
+    where
+        R: io::Read + io::Seek,
+    {
+        let Ok(record) = i32::try_from(record) else {
+            return Ok(None);
+        };
+        let i = self
+            .vxrs
+            .partition_point(|vxr| vxr.last_record().is_some_and(|last| last < record));
+        let Some(mut vxr) = self.vxrs.get(i) else {
+            return Ok(None);
+        };
+        let mut decoded;
+        let mut depth = 0;
+        loop {
+            let Some(i) = vxr.find_entry(record) else {
+                return Ok(None);
+            };
+            let (Some(Some(first)), Some(Some(offset))) =
+                (vxr.first_vec.get(i), vxr.offset_vec.get(i))
+            else {
+                return Ok(None);
+            };
+            let within = checked_usize("first", i64::from(record) - i64::from(**first))?;
+            if let Some(Some(VariableIndexRecordChild::VXR(child))) = vxr.children.get(i) {
+                vxr = child;
+                continue;
+            }
+            if vxr.children.get(i).is_some_and(Option::is_some) {
+                return Ok(Some((u64::try_from(**offset)?, within)));
+            }
+
+            let offset = decoder.seek_offset(**offset)?;
+            _ = decode_version3_int4_int8(decoder)?;
+            if *CdfInt4::decode_be(decoder)? != 6 {
+                return Ok(Some((offset, within)));
+            }
+            // Lower-level VXRs that point back up the tree would otherwise loop forever.
+            depth += 1;
+            if depth > decoder.options.max_depth {
+                return Err(CdfError::MaxDepthExceeded {
+                    max_depth: decoder.options.max_depth,
+                });
+            }
+            _ = decoder.reader.seek(SeekFrom::Start(offset))?;
+            decoded = VariableIndexRecord::decode_index(decoder)?;
+            vxr = &decoded;
+        }
+    }
+}
+
 /// Possible child records of the Variable Index Record. A VXR may contain either (1) a variable
 /// values record, (2) a compressed variable values record, or (3) another variable index record.
+/// Children of other types, such as records of a later version of the CDF specification, are kept
+/// as (4) unknown records.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub enum VariableIndexRecordChild {
     /// Contains a Variable Values record.
@@ -149,12 +409,15 @@ pub enum VariableIndexRecordChild {
     CVVR(CompressedVariableValuesRecord),
     /// Contains a lower-level Variable Index record.
     VXR(VariableIndexRecord),
+    /// Contains a record of a type that is not known, which holds no records of the variable
+    /// that can be read.
+    Unknown(UnknownRecord),
 }
 
 impl Decodable for VariableIndexRecordChild {
     fn decode_be<R>(decoder: &mut Decoder<R>) -> Result<Self, CdfError>
     where
-        R: std::io::Read + std::io::Seek,
+        R: crate::io::Read + crate::io::Seek,
     {
         // Need to know the record type before decoding.
         let _ = decode_version3_int4_int8(decoder)?;
@@ -168,23 +431,26 @@ impl Decodable for VariableIndexRecordChild {
         }
 
         match *record_type {
-            6 => Ok(VariableIndexRecordChild::VXR(
-                VariableIndexRecord::decode_be(decoder)?,
-            )),
+            6 => {
+                decoder.descend()?;
+                let vxr = VariableIndexRecord::decode_be(decoder);
+                decoder.ascend();
+                Ok(VariableIndexRecordChild::VXR(vxr?))
+            }
             7 => Ok(VariableIndexRecordChild::VVR(
                 VariableValuesRecord::decode_be(decoder)?,
             )),
             13 => Ok(VariableIndexRecordChild::CVVR(
                 CompressedVariableValuesRecord::decode_be(decoder)?,
             )),
-            e => Err(CdfError::Decode(format!(
-                "Invalid record_type for VXR - expected 6, received {e}",
-            ))),
+            _ => Ok(VariableIndexRecordChild::Unknown(UnknownRecord::decode_be(
+                decoder,
+            )?)),
         }
     }
     fn decode_le<R>(_: &mut Decoder<R>) -> Result<Self, CdfError>
     where
-        R: std::io::Read + std::io::Seek,
+        R: crate::io::Read + crate::io::Seek,
     {
         unreachable!(
             "Little-endian decoding is not supported for records, only for values within records."
@@ -192,7 +458,42 @@ impl Decodable for VariableIndexRecordChild {
     }
 }
 
-#[cfg(test)]
+impl Encodable for VariableIndexRecord {
+    /// Encode the Variable Index Record. The children it points to are encoded separately.
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        encode_version3_int4_int8(encoder, *self.record_size)?;
+        self.record_type.encode_be(encoder)?;
+        encode_version3_int4_int8(encoder, self.vxr_next.as_ref().map_or(0, |v| **v))?;
+        self.num_entries.encode_be(encoder)?;
+        self.num_used_entries.encode_be(encoder)?;
+
+        // Unused entries are stored as 0xFFFF_FFFF.
+        for first in &self.first_vec {
+            CdfInt4::from(first.as_ref().map_or(-1, |v| **v)).encode_be(encoder)?;
+        }
+        for last in &self.last_vec {
+            CdfInt4::from(last.as_ref().map_or(-1, |v| **v)).encode_be(encoder)?;
+        }
+        for offset in &self.offset_vec {
+            encode_version3_int4_int8(encoder, offset.as_ref().map_or(-1, |v| **v))?;
+        }
+        Ok(())
+    }
+
+    fn encode_le<W>(&self, _: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        unreachable!(
+            "Little-endian encoding is not supported for records, only for values within records."
+        )
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
 mod tests {
 
     use crate::cdf;
@@ -238,4 +539,256 @@ mod tests {
         // }
         Ok(())
     }
+
+    #[test]
+    fn test_vxr_read_child() -> Result<(), CdfError> {
+        use crate::types::{CdfReal8, CdfType, DataType};
+        use crate::writer::{CdfWriter, VariableSpec, VxrLayout};
+
+        let mut writer = CdfWriter::builder()
+            .vxr_entries(3)
+            .vxr_layout(VxrLayout::Tree)
+            .variable(VariableSpec::new("x", DataType::Real8).blocking_factor(5))
+            .build(io::Cursor::new(Vec::new()))?;
+        let records: Vec<_> = (0..100)
+            .map(|r| vec![CdfType::Real8(CdfReal8::from(f64::from(r)))])
+            .collect();
+        writer.write_records("x", &records)?;
+        let bytes = writer.close()?.into_inner();
+
+        let mut decoder = Decoder::builder()
+            .lazy(true)
+            .build(io::Cursor::new(bytes))?;
+        let cdf = cdf::Cdf::decode_be(&mut decoder)?;
+        let top = &cdf.cdr.gdr.zvdr_vec[0].vxr_vec[0];
+        assert!(top.children.iter().all(Option::is_none));
+
+        // Lower-level VXRs are only indexed while values are skipped.
+        let Some(VariableIndexRecordChild::VXR(middle)) = top.read_child(&mut decoder, 0)? else {
+            panic!("Expected a lower-level VXR.");
+        };
+        assert!(middle.children.iter().all(Option::is_none));
+        let Some(VariableIndexRecordChild::VXR(leaf)) = middle.read_child(&mut decoder, 1)? else {
+            panic!("Expected a lower-level VXR.");
+        };
+        assert_eq!(leaf.first_vec[0], Some(CdfInt4::from(15)));
+
+        decoder.context.var_data_type = Some(CdfInt4::from(DataType::Real8));
+        decoder.context.var_data_len = Some(CdfInt4::from(1));
+        let Some(VariableIndexRecordChild::VVR(vvr)) = leaf.read_child(&mut decoder, 0)? else {
+            panic!("Expected a VVR.");
+        };
+        assert_eq!(vvr.records.len(), 5);
+        assert_eq!(format!("{:?}", vvr.records[0].data), "[Real8(15)]");
+        assert!(leaf.read_child(&mut decoder, 3)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_vxr_index_locate() -> Result<(), CdfError> {
+        use crate::io::Seek;
+        use crate::types::{CdfReal8, CdfType, DataType};
+        use crate::writer::{CdfWriter, VariableSpec, VxrLayout};
+
+        for layout in [VxrLayout::Chain, VxrLayout::Tree] {
+            let mut writer = CdfWriter::builder()
+                .vxr_entries(3)
+                .vxr_layout(layout)
+                .variable(VariableSpec::new("x", DataType::Real8).blocking_factor(5))
+                .build(io::Cursor::new(Vec::new()))?;
+            let records: Vec<_> = (0..100)
+                .map(|r| vec![CdfType::Real8(CdfReal8::from(f64::from(r)))])
+                .collect();
+            writer.write_records("x", &records)?;
+            let bytes = writer.close()?.into_inner();
+
+            for lazy in [true, false] {
+                let reader = io::Cursor::new(bytes.clone());
+                let mut decoder = Decoder::builder().lazy(lazy).build(reader)?;
+                let cdf = cdf::Cdf::decode_be(&mut decoder)?;
+                let index = VxrIndex::new(&cdf.cdr.gdr.zvdr_vec[0].vxr_vec);
+                assert_eq!(index.locate(&mut decoder, 100)?, None);
+                let (offset, within) = index
+                    .locate(&mut decoder, 37)?
+                    .ok_or_else(|| CdfError::Other("Record 37 not found.".to_string()))?;
+                assert_eq!(within, 2);
+
+                decoder.context.var_data_type = Some(CdfInt4::from(DataType::Real8));
+                decoder.context.var_data_len = Some(CdfInt4::from(1));
+                decoder.context.num_records = Some(5);
+                _ = decoder.reader.seek(SeekFrom::Start(offset))?;
+                let vvr = VariableValuesRecord::decode_be(&mut decoder)?;
+                assert_eq!(format!("{:?}", vvr.records[within].data), "[Real8(37)]");
+
+                for record in [0, 4, 5, 59, 60, 99] {
+                    let (_, within) = index
+                        .locate(&mut decoder, record)?
+                        .ok_or_else(|| CdfError::Other(format!("Record {record} not found.")))?;
+                    assert_eq!(within, record % 5);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn _vxr(entries: &[(i32, i32)], num_used: i32) -> VariableIndexRecord {
+        let n = entries.len();
+        VariableIndexRecord {
+            record_size: CdfInt8::from(0),
+            record_type: CdfInt4::from(6),
+            vxr_next: None,
+            num_entries: CdfInt4::from(i32::try_from(n).unwrap()),
+            num_used_entries: CdfInt4::from(num_used),
+            first_vec: entries.iter().map(|e| Some(CdfInt4::from(e.0))).collect(),
+            last_vec: entries.iter().map(|e| Some(CdfInt4::from(e.1))).collect(),
+            offset_vec: (0..n)
+                .map(|i| Some(CdfInt8::from(i as i64 * 100)))
+                .collect(),
+            children: (0..n).map(|_| None).collect(),
+        }
+    }
+
+    #[test]
+    fn test_check_index() {
+        // Blocks may hold more records than the variable has.
+        let vxrs = [_vxr(&[(0, 4), (5, 9)], 2), _vxr(&[(10, 19)], 1)];
+        assert!(check_index(&vxrs, 19, false).is_empty());
+        assert!(check_index(&vxrs, 15, false).is_empty());
+        assert_eq!(
+            check_index(&vxrs, 25, false),
+            ["Records 20 to 25 are not stored, but the variable has no sparse records."]
+        );
+        assert!(check_index(&vxrs, 25, true).is_empty());
+
+        let gap = [_vxr(&[(2, 4), (8, 9)], 2)];
+        assert_eq!(check_index(&gap, 9, false).len(), 2);
+        assert_eq!(check_index(&gap, 3, false).len(), 1);
+        assert!(check_index(&gap, 9, true).is_empty());
+
+        assert_eq!(check_index(&[_vxr(&[(0, 4), (3, 9)], 2)], 9, true).len(), 1);
+        assert_eq!(check_index(&[_vxr(&[(5, 4)], 1)], 9, true).len(), 1);
+        assert_eq!(check_index(&[_vxr(&[(0, 4)], 2)], 9, true).len(), 1);
+
+        let mut parent = _vxr(&[(0, 9)], 1);
+        parent.children[0] = Some(VariableIndexRecordChild::VXR(_vxr(&[(0, 4), (5, 12)], 2)));
+        assert_eq!(check_index(&[parent], 9, false).len(), 1);
+
+        let mut missing = _vxr(&[(0, 4), (5, 9)], 2);
+        missing.offset_vec[1] = None;
+        assert_eq!(check_index(&[missing], 4, true).len(), 1);
+    }
+
+    #[test]
+    fn test_vxr_max_depth() -> Result<(), CdfError> {
+        use crate::types::{CdfReal8, CdfType, DataType};
+        use crate::writer::{CdfWriter, VariableSpec, VxrLayout};
+
+        let mut writer = CdfWriter::builder()
+            .vxr_entries(3)
+            .vxr_layout(VxrLayout::Tree)
+            .variable(VariableSpec::new("x", DataType::Real8).blocking_factor(5))
+            .build(io::Cursor::new(Vec::new()))?;
+        let records: Vec<_> = (0..100)
+            .map(|r| vec![CdfType::Real8(CdfReal8::from(f64::from(r)))])
+            .collect();
+        writer.write_records("x", &records)?;
+        let mut bytes = writer.close()?.into_inner();
+
+        // The tree has two levels of VXRs below the top-level VXR.
+        let decode = |bytes: &[u8], max_depth, lazy| {
+            let reader = io::Cursor::new(bytes.to_vec());
+            let mut decoder = Decoder::builder()
+                .max_depth(max_depth)
+                .lazy(lazy)
+                .build(reader)?;
+            cdf::Cdf::decode_be(&mut decoder).map(|cdf| (cdf, decoder))
+        };
+        assert!(decode(&bytes, 2, false).is_ok());
+        assert!(matches!(
+            decode(&bytes, 1, false),
+            Err(CdfError::MaxDepthExceeded { max_depth: 1 })
+        ));
+        let (cdf, mut decoder) = decode(&bytes, 1, true)?;
+        let index = VxrIndex::new(&cdf.cdr.gdr.zvdr_vec[0].vxr_vec);
+        assert!(matches!(
+            index.locate(&mut decoder, 50),
+            Err(CdfError::MaxDepthExceeded { max_depth: 1 })
+        ));
+
+        // Point the first entry of the top-level VXR back to itself.
+        let (cdf, _) = decode(&bytes, 2, true)?;
+        let head = cdf.cdr.gdr.zvdr_vec[0]
+            .vxr_head
+            .clone()
+            .ok_or_else(|| CdfError::Other("Variable has no VXR.".to_string()))?;
+        let entry = usize::try_from(*head)? + 28 + 8 * 3;
+        bytes[entry..entry + 8].copy_from_slice(&head.to_be_bytes());
+        assert!(matches!(
+            decode(&bytes, 64, false),
+            Err(CdfError::MaxDepthExceeded { max_depth: 64 })
+        ));
+        let options = cdf::ReadOptions {
+            lazy: true,
+            ..Default::default()
+        };
+        let cdf = cdf::Cdf::from_reader(io::Cursor::new(bytes), options)?;
+        assert!(matches!(
+            cdf.variable("x")?.read_all(),
+            Err(CdfError::MaxDepthExceeded { max_depth: 64 })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_vxr_unknown_child() -> Result<(), CdfError> {
+        use crate::types::{CdfReal8, CdfType, DataType};
+        use crate::writer::{CdfWriter, VariableSpec};
+
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("x", DataType::Real8))
+            .build(io::Cursor::new(Vec::new()))?;
+        let records: Vec<_> = (0..4)
+            .map(|r| vec![CdfType::Real8(CdfReal8::from(f64::from(r)))])
+            .collect();
+        writer.write_records("x", &records)?;
+        let mut bytes = writer.close()?.into_inner();
+
+        // Give the VVR a record type that no version of the specification defines.
+        let cdf = cdf::Cdf::from_bytes(&bytes)?;
+        let offset = cdf.cdr.gdr.zvdr_vec[0].vxr_vec[0].offset_vec[0]
+            .clone()
+            .ok_or_else(|| CdfError::Other("VXR has no entry.".to_string()))?;
+        let offset = usize::try_from(*offset)?;
+        bytes[offset + 8..offset + 12].copy_from_slice(&99_i32.to_be_bytes());
+
+        let options = cdf::ReadOptions {
+            keep_raw: true,
+            ..Default::default()
+        };
+        let cdf = cdf::Cdf::from_reader(io::Cursor::new(bytes.clone()), options)?;
+        let Some(VariableIndexRecordChild::Unknown(record)) =
+            &cdf.cdr.gdr.zvdr_vec[0].vxr_vec[0].children[0]
+        else {
+            panic!("Expected an unknown record.");
+        };
+        assert_eq!((*record.record_type, record.offset), (99, offset as u64));
+        assert_eq!(record.bytes.len(), 4 * 8);
+        assert!(matches!(
+            cdf.variable("x")?.read_all(),
+            Err(CdfError::Decode(_))
+        ));
+
+        // The unknown record is written back unchanged.
+        let mut rewritten = Vec::new();
+        cdf.write_raw(&mut rewritten)?;
+        assert_eq!(rewritten, bytes);
+        let encoder = Encoder::new(
+            io::Cursor::new(Vec::new()),
+            cdf.cdr.cdf_version.clone(),
+            cdf.cdr.encoding.clone(),
+        )?;
+        let encoded = crate::encode::encode_to_vec(&encoder, record)?;
+        assert_eq!(encoded, bytes[offset..offset + encoded.len()]);
+        Ok(())
+    }
 }
@@ -1,20 +1,30 @@
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use alloc::{format, string::ToString, vec, vec::Vec};
+
+use crate::io;
 use crate::{
-    decode::{decode_version3_int4_int8, Decodable, Decoder},
+    decode::{
+        checked_dim_size, checked_num_dims, decode_version3_int4_int8, Decodable, DecodeWarning,
+        Decoder,
+    },
+    encode::{encode_version3_int4_int8, Encodable, Encoder},
     error::CdfError,
     record::{
         collection::{get_record_vec, RecordList},
-        vxr::VariableIndexRecord,
+        cpr::CompressedParametersRecord,
+        vxr::{check_index, VariableIndexRecord},
     },
-    repr::Endian,
+    repr::{CdfVersion, Endian},
     types::{CdfInt4, CdfInt8, CdfString, CdfType},
 };
-use std::io;
 
 /// Various options for rVariables.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct ZVariableFlags {
     /// Whether this rVariable has variance.
@@ -27,6 +37,7 @@ pub struct ZVariableFlags {
 
 /// Describes one zVariable stored in the CDF file.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug)]
 pub struct ZVariableDescriptorRecord {
     /// Size of this record in bytes.
@@ -69,8 +80,10 @@ pub struct ZVariableDescriptorRecord {
     pub size_z_dims: Vec<CdfInt4>,
     /// Dimension variances for this variable.
     pub dim_variances: Vec<bool>,
-    /// Pad value of this variable.
+    /// Pad value of this variable. Empty if the variable does not have a pad value.
     pub pad_value: Vec<CdfType>,
+    /// Compressed Parameters Record describing the compression of this variable, if compressed.
+    pub cpr: Option<CompressedParametersRecord>,
     /// Vector of Variable Index Records.
     pub vxr_vec: Vec<VariableIndexRecord>,
 }
@@ -80,6 +93,8 @@ impl Decodable for ZVariableDescriptorRecord {
     where
         R: io::Read + io::Seek,
     {
+        let start = decoder.begin_record()?;
+        decoder.keep_raw_record()?;
         let record_size = decode_version3_int4_int8(decoder)?;
         let record_type = CdfInt4::decode_be(decoder)?;
         if *record_type != 8 {
@@ -93,34 +108,45 @@ impl Decodable for ZVariableDescriptorRecord {
 
         let data_type = CdfInt4::decode_be(decoder)?;
         let max_record = CdfInt4::decode_be(decoder)?;
+        // The last record is -1 for variables without records, and is never lower.
+        if decoder.options.strict && *max_record < -1 {
+            return Err(CdfError::InvalidCount {
+                field: "max_record",
+                value: i64::from(*max_record),
+            });
+        }
         let vxr_head = decode_version3_int4_int8(decoder).map(|v| (*v != 0).then_some(v))?;
         let vxr_tail = decode_version3_int4_int8(decoder).map(|v| (*v != 0).then_some(v))?;
 
+        // Compression and sparse records were introduced in CDF v2.6, their fields are undefined
+        // before.
+        let compressible = decoder.context.version()? >= CdfVersion::new(2, 6, 0);
         let flags = CdfInt4::decode_be(decoder)?;
         let flags = ZVariableFlags {
             variance: *flags & 1i32 == 1,
             has_padding: *flags & 2i32 == 2,
-            is_compressed: *flags & 4i32 == 4,
+            is_compressed: compressible && *flags & 4i32 == 4,
         };
 
-        let sparse_records = CdfInt4::decode_be(decoder)?;
+        let sparse_records =
+            CdfInt4::decode_be(decoder).map(|v| if compressible { v } else { CdfInt4::from(0) })?;
 
         let rfu_b = CdfInt4::decode_be(decoder)?;
-        if *rfu_b != 0 {
+        if decoder.options.strict && *rfu_b != 0 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_b read from file in ZVDR - expected 0, received {}",
                 *rfu_b
             )));
         }
         let rfu_c = CdfInt4::decode_be(decoder)?;
-        if *rfu_c != -1 {
+        if decoder.options.strict && *rfu_c != -1 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_c read from file in ZVDR - expected -1, received {}",
                 *rfu_c
             )));
         }
         let rfu_f = CdfInt4::decode_be(decoder)?;
-        if *rfu_f != -1 {
+        if decoder.options.strict && *rfu_f != -1 {
             return Err(CdfError::Decode(format!(
                 "Invalid rfu_f read from file in ZVDR - expected -1, received {}",
                 *rfu_f
@@ -133,19 +159,30 @@ impl Decodable for ZVariableDescriptorRecord {
         // According to spec, this check should be with 0xFFFF_FFFF_FFFF_FFFF. But Rust
         // throws a compilation error because this does not fit inside a Int8. So we are
         // checking with -1 instead, which should lead to the same behavior.
-        let cpr_spr_offset = decode_version3_int4_int8(decoder).map(|v| (*v != -1).then_some(v))?;
+        let cpr_spr_offset =
+            decode_version3_int4_int8(decoder).map(|v| (*v != -1 && compressible).then_some(v))?;
 
         let blocking_factor = CdfInt4::decode_be(decoder)?;
 
-        let name = CdfString::decode_string_from_numbytes(decoder, 256)?;
+        let name = if decoder.context.version()?.major < 3 {
+            CdfString::decode_string_from_numbytes(decoder, 64)?
+        } else {
+            CdfString::decode_string_from_numbytes(decoder, 256)?
+        };
+        decoder.context.variable_name = Some(name.to_string());
+        decoder.report_progress(&record_type)?;
 
         let num_z_dims = CdfInt4::decode_be(decoder)?;
+        let n_dims = checked_num_dims("num_z_dims", *num_z_dims)?;
         decoder.context.num_z_dims = Some(num_z_dims.clone());
 
         let size_z_dims = CdfInt4::decode_vec_be(decoder, &num_z_dims)?;
+        for (dim, size) in size_z_dims.iter().enumerate() {
+            _ = checked_dim_size("size_z_dims", dim, **size)?;
+        }
         decoder.context.size_z_dims = Some(size_z_dims.clone());
 
-        let mut dim_variances: Vec<bool> = vec![false; usize::try_from(*num_z_dims)?];
+        let mut dim_variances: Vec<bool> = vec![false; n_dims];
         for d in dim_variances.iter_mut() {
             if *CdfInt4::decode_be(decoder)? == -1 {
                 *d = true;
@@ -153,32 +190,60 @@ impl Decodable for ZVariableDescriptorRecord {
         }
 
         let endianness = decoder.context.endianness()?;
-        let pad_value = match endianness {
-            Endian::Big => CdfType::decode_vec_be(decoder, &data_type, &num_elements)?,
-            Endian::Little => CdfType::decode_vec_le(decoder, &data_type, &num_elements)?,
+        let pad_value = match (flags.has_padding, endianness) {
+            (false, _) => vec![],
+            (true, Endian::Big) => CdfType::decode_vec_be(decoder, &data_type, &num_elements)?,
+            (true, Endian::Little) => CdfType::decode_vec_le(decoder, &data_type, &num_elements)?,
         };
 
         // Before reading in the VXRs, we need to know the variable data type and the number of such
         // types in each variable record. For zVariables, this is all stored in the zVDR, which is
-        // in scope.
-        let size_active_dims: i32 = dim_variances
+        // in scope. Scalar variables, which vary along no dimension, have `num_elements` values in
+        // each record. Records without values are invalid.
+        let var_data_len = dim_variances
             .iter()
             .zip(size_z_dims.iter())
             .filter(|(v, _)| **v)
-            .map(|(_, s)| **s)
-            .product();
-
-        let var_data_len = (*num_elements) * (size_active_dims);
+            .try_fold(*num_elements, |len, (_, s)| {
+                (**s > 0).then(|| len.checked_mul(**s)).flatten()
+            })
+            .filter(|len| *len > 0)
+            .ok_or_else(|| {
+                CdfError::Decode(format!(
+                    "Invalid number of elements or dimension sizes for variable {name}."
+                ))
+            })?;
 
         decoder.context.var_data_type = Some(data_type.clone());
         decoder.context.var_data_len = Some(CdfInt4::from(var_data_len));
 
+        decoder.finish_record(start, &record_size, &record_type)?;
+
+        // The CPR is only present for compressed variables. Otherwise, this offset points to a
+        // Sparseness Parameters Record, which is not implemented.
+        let cpr = match (&flags.is_compressed, &cpr_spr_offset) {
+            (true, Some(offset)) => {
+                _ = decoder.seek_offset(**offset)?;
+                Some(CompressedParametersRecord::decode_be(decoder)?)
+            }
+            _ => None,
+        };
+        decoder.context.compression = cpr.as_ref().map(|c| c.compression_type.clone());
+        decoder.context.skip_values = !decoder.options.loads_variable(&name);
+
         let vxr_vec = if let Some(head) = &vxr_head {
             get_record_vec::<R, VariableIndexRecord>(decoder, head)?
         } else {
             vec![]
         };
+        for problem in check_index(&vxr_vec, *max_record, *sparse_records != 0) {
+            decoder.report_warning(DecodeWarning::InconsistentIndex {
+                variable: name.to_string(),
+                problem,
+            });
+        }
 
+        decoder.context.variable_name = None;
         Ok(ZVariableDescriptorRecord {
             record_size,
             record_type,
@@ -201,13 +266,14 @@ impl Decodable for ZVariableDescriptorRecord {
             size_z_dims,
             dim_variances,
             pad_value,
+            cpr,
             vxr_vec,
         })
     }
 
     fn decode_le<R>(_: &mut Decoder<R>) -> Result<Self, CdfError>
     where
-        R: std::io::Read + std::io::Seek,
+        R: crate::io::Read + crate::io::Seek,
     {
         unreachable!(
             "Little-endian decoding is not supported for records, only for values within records."
@@ -221,11 +287,73 @@ impl RecordList for ZVariableDescriptorRecord {
     }
 }
 
-#[cfg(test)]
+impl Encodable for ZVariableDescriptorRecord {
+    /// Encode the zVariable Descriptor Record. The CPR and VXRs it points to are encoded
+    /// separately.
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        let offset = |v: &Option<CdfInt8>, none: i64| v.as_ref().map_or(none, |v| **v);
+
+        encode_version3_int4_int8(encoder, *self.record_size)?;
+        self.record_type.encode_be(encoder)?;
+        encode_version3_int4_int8(encoder, offset(&self.zvdr_next, 0))?;
+        self.data_type.encode_be(encoder)?;
+        self.max_record.encode_be(encoder)?;
+        encode_version3_int4_int8(encoder, offset(&self.vxr_head, 0))?;
+        encode_version3_int4_int8(encoder, offset(&self.vxr_tail, 0))?;
+
+        let flags = i32::from(self.flags.variance)
+            | i32::from(self.flags.has_padding) << 1
+            | i32::from(self.flags.is_compressed) << 2;
+        CdfInt4::from(flags).encode_be(encoder)?;
+
+        self.sparse_records.encode_be(encoder)?;
+        self.rfu_b.encode_be(encoder)?;
+        self.rfu_c.encode_be(encoder)?;
+        self.rfu_f.encode_be(encoder)?;
+        self.num_elements.encode_be(encoder)?;
+        self.num.encode_be(encoder)?;
+        encode_version3_int4_int8(encoder, offset(&self.cpr_spr_offset, -1))?;
+        self.blocking_factor.encode_be(encoder)?;
+        let name_bytes = if encoder.context.version.major < 3 {
+            64
+        } else {
+            256
+        };
+        self.name.encode_string_to_numbytes(encoder, name_bytes)?;
+        self.num_z_dims.encode_be(encoder)?;
+        for size in &self.size_z_dims {
+            size.encode_be(encoder)?;
+        }
+        for variance in &self.dim_variances {
+            CdfInt4::from(if *variance { -1 } else { 0 }).encode_be(encoder)?;
+        }
+        if self.flags.has_padding {
+            for value in &self.pad_value {
+                value.encode_value(encoder)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_le<W>(&self, _: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        unreachable!(
+            "Little-endian encoding is not supported for records, only for values within records."
+        )
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
 mod tests {
 
     use crate::cdf;
-    use crate::error::CdfError;
+    use crate::error::{CdfError, ShapeError};
+    use crate::view::CdfView;
     use std::fs::File;
     use std::io::BufReader;
     use std::path::PathBuf;
@@ -255,4 +383,170 @@ mod tests {
         // dbg!(cdf.zvdr_vec.len());
         Ok(())
     }
+
+    #[test]
+    fn test_zvdr_invalid_counts() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let bytes = std::fs::read(path_test_file)?;
+        let cdf = cdf::Cdf::from_bytes(&bytes)?;
+        let zvdr = usize::try_from(cdf.cdr.gdr.zvdr_head.map_or(0, |head| *head))?;
+
+        // Offsets of max_record, num_elements and num_z_dims within the first zVDR.
+        for (field, position, value) in [
+            ("max_record", 24, -5),
+            ("num_elements", 64, -3),
+            ("num_z_dims", 340, -1),
+        ] {
+            let mut corrupt = bytes.clone();
+            corrupt[zvdr + position..zvdr + position + 4].copy_from_slice(&i32::to_be_bytes(value));
+            let mut decoder = Decoder::new(io::Cursor::new(corrupt.as_slice()))?;
+            match cdf::Cdf::decode_be(&mut decoder) {
+                Err(CdfError::InvalidCount { field: f, value: v }) => {
+                    assert_eq!((f, v), (field, i64::from(value)))
+                }
+                other => panic!("{field}: {other:?}"),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_zvdr_invalid_shape() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let bytes = std::fs::read(path_test_file)?;
+        let cdf = cdf::Cdf::from_bytes(&bytes)?;
+        let zvdr = usize::try_from(cdf.cdr.gdr.zvdr_head.map_or(0, |head| *head))?;
+
+        // Offsets of num_z_dims and of the first dimension size within the first zVDR.
+        for (position, value, expected) in [
+            (
+                340,
+                2_000_000_000,
+                ShapeError::TooManyDimensions {
+                    field: "num_z_dims",
+                    num_dims: 2_000_000_000,
+                },
+            ),
+            (
+                344,
+                -3,
+                ShapeError::InvalidDimensionSize {
+                    field: "size_z_dims",
+                    dim: 0,
+                    size: -3,
+                },
+            ),
+        ] {
+            let mut corrupt = bytes.clone();
+            corrupt[zvdr + position..zvdr + position + 4].copy_from_slice(&i32::to_be_bytes(value));
+            let mut decoder = Decoder::new(io::Cursor::new(corrupt.as_slice()))?;
+            match cdf::Cdf::decode_be(&mut decoder) {
+                Err(CdfError::Shape(error)) => assert_eq!(error, expected),
+                other => panic!("{value}: {other:?}"),
+            }
+            match CdfView::parse(&corrupt) {
+                Err(CdfError::Shape(error)) => assert_eq!(error, expected),
+                other => panic!("{value}: {other:?}"),
+            }
+        }
+        assert_eq!(
+            CdfError::Shape(ShapeError::TooManyDimensions {
+                field: "num_z_dims",
+                num_dims: 11
+            })
+            .to_string(),
+            "Invalid num_z_dims of 11, which is more than the 10 dimensions of a CDF variable."
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zvdr_offset_out_of_bounds() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let bytes = std::fs::read(path_test_file)?;
+        let cdf = cdf::Cdf::from_bytes(&bytes)?;
+        let zvdr = usize::try_from(cdf.cdr.gdr.zvdr_head.map_or(0, |head| *head))?;
+        let eof = cdf.cdr.gdr.eof.map_or(0, |eof| *eof);
+        let end = u64::try_from(bytes.len())?;
+        assert_eq!(end - u64::try_from(eof)?, 16);
+
+        // The offset of the next zVDR is checked before following it, against the end of the
+        // file according to the GDR, which is before the MD5 checksum, or against the end of the
+        // bytes of a view.
+        for offset in [-8, 1 << 40, eof, i64::try_from(end)?] {
+            let mut corrupt = bytes.clone();
+            corrupt[zvdr + 12..zvdr + 20].copy_from_slice(&i64::to_be_bytes(offset));
+            let mut decoder = Decoder::new(io::Cursor::new(corrupt.as_slice()))?;
+            match cdf::Cdf::decode_be(&mut decoder) {
+                Err(CdfError::OffsetOutOfBounds { offset: o, end: e }) => {
+                    assert_eq!((o, e), (offset, u64::try_from(eof)?))
+                }
+                other => panic!("{offset}: {other:?}"),
+            }
+            if offset != eof {
+                match CdfView::parse(&corrupt) {
+                    Err(CdfError::OffsetOutOfBounds { offset: o, end: e }) => {
+                        assert_eq!((o, e), (offset, end))
+                    }
+                    other => panic!("{offset}: {other:?}"),
+                }
+            }
+        }
+        let error = CdfError::OffsetOutOfBounds { offset: -8, end };
+        assert_eq!(
+            error.to_string(),
+            format!("Offset -8 is outside of the file, which ends at byte {end}.")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zvdr_zero_size() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "test_alltypes.cdf",
+        ]
+        .iter()
+        .collect();
+        let bytes = std::fs::read(path_test_file)?;
+        let cdf = cdf::Cdf::from_bytes(&bytes)?;
+        let zvdr = usize::try_from(cdf.cdr.gdr.zvdr_head.map_or(0, |head| *head))?;
+        assert_eq!(cdf.cdr.gdr.zvdr_vec[0].size_z_dims, [CdfInt4::from(3)]);
+
+        // Records without values, from a dimension or a number of elements of zero, are invalid.
+        for position in [64, 344] {
+            let mut corrupt = bytes.clone();
+            corrupt[zvdr + position..zvdr + position + 4].copy_from_slice(&[0; 4]);
+            let mut decoder = Decoder::new(io::Cursor::new(corrupt.as_slice()))?;
+            assert!(matches!(
+                cdf::Cdf::decode_be(&mut decoder),
+                Err(CdfError::Decode(e)) if e.contains("Invalid number of elements")
+            ));
+            assert!(crate::view::CdfView::parse(&corrupt).is_err());
+        }
+        Ok(())
+    }
 }
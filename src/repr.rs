@@ -1,11 +1,16 @@
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use alloc::{format, string::ToString};
+
 use crate::{error::CdfError, types::CdfInt4};
 
 /// Data Encodings used in CDF (from CDF specification Table 5.11). Floating-point representations
 /// other than IEEE754 are not implemented and will raise an error.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug, PartialEq, Clone)]
 pub enum CdfEncoding {
     /// In case the encoding is unspecified.  This will raise an error.
@@ -43,6 +48,17 @@ pub enum CdfEncoding {
 }
 
 impl CdfEncoding {
+    /// The encoding that stores values in the natural byte order of the host, as the CDF library
+    /// does by default: [`CdfEncoding::IbmPc`] on little-endian hosts and
+    /// [`CdfEncoding::Network`] on big-endian hosts.
+    pub fn host() -> Self {
+        if cfg!(target_endian = "little") {
+            CdfEncoding::IbmPc
+        } else {
+            CdfEncoding::Network
+        }
+    }
+
     /// Returns the endianness associated with this CDF data encoding.
     /// # Errors
     /// Returns a [`CdfError`] if the CDF encoding is invalid.
@@ -101,6 +117,7 @@ impl TryFrom<CdfInt4> for CdfEncoding {
 }
 
 /// Enum to handle different endianess.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone)]
 pub enum Endian {
     /// Big-Endian
@@ -111,6 +128,7 @@ pub enum Endian {
 
 /// Stores the version of the CDF in a simple implementation of semantic versioning.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct CdfVersion {
     /// Major version
@@ -0,0 +1,384 @@
+use alloc::{format, vec, vec::Vec};
+
+use crate::error::CdfError;
+use crate::variable::{Variable, VariableStats};
+
+/// Compression of the digests built by [`percentiles`] and [`summarize`]. Larger values keep more
+/// centroids, which makes percentiles more accurate at the cost of memory.
+pub const DEFAULT_COMPRESSION: f64 = 100.0;
+
+/// Counts of values in bins of equal width between a minimum and a maximum.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    /// Lower edge of the first bin.
+    pub min: f64,
+    /// Upper edge of the last bin, which includes values equal to it.
+    pub max: f64,
+    /// Number of values in each bin.
+    pub counts: Vec<u64>,
+    /// Number of values smaller than `min`.
+    pub below: u64,
+    /// Number of values larger than `max`.
+    pub above: u64,
+}
+
+impl Histogram {
+    /// An empty histogram of `bins` bins of equal width between `min` and `max`.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if there are no bins, or if `min` is not smaller than `max`.
+    pub fn new(min: f64, max: f64, bins: usize) -> Result<Self, CdfError> {
+        if bins == 0 || max <= min || !(max - min).is_finite() {
+            return Err(CdfError::Other(format!(
+                "Cannot make a histogram of {bins} bins between {min} and {max}."
+            )));
+        }
+        Ok(Histogram {
+            min,
+            max,
+            counts: vec![0; bins],
+            below: 0,
+            above: 0,
+        })
+    }
+
+    /// Count `x` in the bin that holds it.
+    pub fn add(&mut self, x: f64) {
+        if x < self.min {
+            self.below += 1;
+        } else if x > self.max {
+            self.above += 1;
+        } else {
+            let bins = self.counts.len();
+            let bin = ((x - self.min) / self.width()) as usize;
+            self.counts[bin.min(bins - 1)] += 1;
+        }
+    }
+
+    /// Width of each bin.
+    pub fn width(&self) -> f64 {
+        (self.max - self.min) / self.counts.len() as f64
+    }
+
+    /// Edges of the bins, from `min` to `max`, one more than the number of bins.
+    pub fn edges(&self) -> Vec<f64> {
+        let bins = self.counts.len();
+        (0..=bins)
+            .map(|i| {
+                if i == bins {
+                    self.max
+                } else {
+                    self.min + self.width() * i as f64
+                }
+            })
+            .collect()
+    }
+}
+
+/// A group of nearby values of a [`TDigest`], summarized by their mean and their number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// A t-digest, which estimates the percentiles of a stream of values in little memory. Values are
+/// grouped in centroids that are kept small near the tails, so that extreme percentiles are the
+/// most accurate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TDigest {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    // Values added since the centroids were last merged.
+    buffer: Vec<f64>,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    /// An empty digest. `compression` bounds the number of centroids, see
+    /// [`DEFAULT_COMPRESSION`].
+    pub fn new(compression: f64) -> Self {
+        TDigest {
+            compression: compression.max(1.0),
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Add `x` to the values of the digest. NaN values are ignored.
+    pub fn add(&mut self, x: f64) {
+        if x.is_nan() {
+            return;
+        }
+        self.buffer.push(x);
+        self.count += 1;
+        self.min = self.min.min(x);
+        self.max = self.max.max(x);
+        if self.buffer.len() >= (self.compression * 5.0) as usize {
+            self.merge();
+        }
+    }
+
+    /// Add the values of `other` to this digest.
+    pub fn merge_digest(&mut self, other: &TDigest) {
+        if other.count == 0 {
+            return;
+        }
+        self.merge();
+        self.centroids.extend(other.centroids.iter().copied());
+        self.buffer.extend(other.buffer.iter().copied());
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.merge();
+    }
+
+    /// Number of values added to the digest.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Estimate of the `q`-quantile of the values, for `q` between 0 and 1, so that 0.5 is the
+    /// median. Returns `None` if the digest is empty or `q` is out of range.
+    pub fn quantile(&mut self, q: f64) -> Option<f64> {
+        if self.count == 0 || !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+        self.merge();
+        let total = self.count as f64;
+        let target = q * total;
+
+        // Each centroid stands for the values around its center, in the order of the values. The
+        // quantile is interpolated between the centers on either side of the target, and between
+        // the extreme values and the first and last centers.
+        let mut previous = (0.0, self.min);
+        let mut cumulative = 0.0;
+        for centroid in &self.centroids {
+            let center = cumulative + centroid.weight / 2.0;
+            if target < center {
+                return Some(interpolate(previous, (center, centroid.mean), target));
+            }
+            previous = (center, centroid.mean);
+            cumulative += centroid.weight;
+        }
+        Some(interpolate(previous, (total, self.max), target))
+    }
+
+    // Merge the buffered values into the centroids. Neighbouring centroids are combined while
+    // their weight stays under the bound of the t-digest at their quantile, 4 n q (1 - q) / δ.
+    fn merge(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut all = core::mem::take(&mut self.centroids);
+        all.extend(
+            self.buffer
+                .drain(..)
+                .map(|mean| Centroid { mean, weight: 1.0 }),
+        );
+        all.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+
+        let total = self.count as f64;
+        let mut merged = Vec::with_capacity(all.len());
+        let mut current = all[0];
+        let mut cumulative = 0.0;
+        for next in all.into_iter().skip(1) {
+            let weight = current.weight + next.weight;
+            let q = (cumulative + weight / 2.0) / total;
+            if weight <= 4.0 * total * q * (1.0 - q) / self.compression {
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                cumulative += current.weight;
+                merged.push(current);
+                current = next;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+}
+
+// Value at `target` on the line through the points `(position, value)` `from` and `to`.
+fn interpolate(from: (f64, f64), to: (f64, f64), target: f64) -> f64 {
+    if to.0 <= from.0 {
+        return to.1;
+    }
+    from.1 + (to.1 - from.1) * (target - from.0) / (to.0 - from.0)
+}
+
+/// Summary of the values of a variable: its statistics, a histogram over the range of its values
+/// and a digest of their percentiles. See [`summarize`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// Count, range, mean and standard deviation of the values.
+    pub stats: VariableStats,
+    /// Histogram of the values between their minimum and maximum, or `None` if the variable has
+    /// fewer than two distinct values.
+    pub histogram: Option<Histogram>,
+    /// Digest of the values, to estimate any percentile with [`TDigest::quantile`].
+    pub digest: TDigest,
+}
+
+/// Histogram of the values of `variable` in `bins` bins between `min` and `max`, computed in a
+/// single pass over its records. Fill values are left out, as for [`Variable::stats`].
+/// # Errors
+/// Returns a [`CdfError::Other`] if the bins are invalid or the variable does not hold numbers,
+/// or a [`CdfError`] if its records cannot be read.
+pub fn histogram(
+    variable: &Variable,
+    min: f64,
+    max: f64,
+    bins: usize,
+) -> Result<Histogram, CdfError> {
+    let mut histogram = Histogram::new(min, max, bins)?;
+    variable.visit_numbers(|x| {
+        if let Some(x) = x {
+            histogram.add(x);
+        }
+    })?;
+    Ok(histogram)
+}
+
+/// Approximate `quantiles` of the values of `variable`, each between 0 and 1, computed in a single
+/// pass over its records with a [`TDigest`]. Fill values are left out, as for
+/// [`Variable::stats`]. Quantiles are `None` if all values are fill values.
+/// # Errors
+/// Returns a [`CdfError::Other`] if a quantile is out of range or the variable does not hold
+/// numbers, or a [`CdfError`] if its records cannot be read.
+pub fn percentiles(variable: &Variable, quantiles: &[f64]) -> Result<Vec<Option<f64>>, CdfError> {
+    if let Some(q) = quantiles.iter().find(|q| !(0.0..=1.0).contains(*q)) {
+        return Err(CdfError::Other(format!(
+            "Quantile {q} is not between 0 and 1."
+        )));
+    }
+    let mut digest = digest(variable)?;
+    Ok(quantiles.iter().map(|q| digest.quantile(*q)).collect())
+}
+
+/// Statistics, histogram in `bins` bins and digest of the values of `variable`. The histogram
+/// spans the range of the values, which is found by a first pass over the records before the
+/// second pass fills the histogram and the digest.
+/// # Errors
+/// Returns a [`CdfError::Other`] if there are no bins or the variable does not hold numbers, or a
+/// [`CdfError`] if its records cannot be read.
+pub fn summarize(variable: &Variable, bins: usize) -> Result<Summary, CdfError> {
+    let stats = variable.stats()?;
+    let mut histogram = match (stats.min, stats.max) {
+        (Some(min), Some(max)) if min < max => Some(Histogram::new(min, max, bins)?),
+        _ if bins == 0 => return Err(CdfError::Other("Cannot make a histogram of 0 bins.".into())),
+        _ => None,
+    };
+    let mut digest = TDigest::new(DEFAULT_COMPRESSION);
+    variable.visit_numbers(|x| {
+        if let Some(x) = x {
+            digest.add(x);
+            if let Some(histogram) = &mut histogram {
+                histogram.add(x);
+            }
+        }
+    })?;
+    Ok(Summary {
+        stats,
+        histogram,
+        digest,
+    })
+}
+
+// Digest of the values of `variable` that are not fill values.
+fn digest(variable: &Variable) -> Result<TDigest, CdfError> {
+    let mut digest = TDigest::new(DEFAULT_COMPRESSION);
+    variable.visit_numbers(|x| {
+        if let Some(x) = x {
+            digest.add(x);
+        }
+    })?;
+    Ok(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cdf::Cdf;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_histogram() -> Result<(), CdfError> {
+        let mut histogram = Histogram::new(0.0, 10.0, 4)?;
+        for x in [-1.0, 0.0, 2.4, 2.5, 7.5, 9.9, 10.0, 10.5] {
+            histogram.add(x);
+        }
+        assert_eq!(histogram.counts, vec![2, 1, 0, 3]);
+        assert_eq!((histogram.below, histogram.above), (1, 1));
+        assert_eq!(histogram.edges(), vec![0.0, 2.5, 5.0, 7.5, 10.0]);
+        assert!(Histogram::new(0.0, 10.0, 0).is_err());
+        assert!(Histogram::new(1.0, 1.0, 4).is_err());
+        assert!(Histogram::new(f64::NAN, 1.0, 4).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tdigest() {
+        let mut digest = TDigest::new(DEFAULT_COMPRESSION);
+        assert_eq!(digest.quantile(0.5), None);
+        // Values in a scrambled order, which the digest must not depend on.
+        for i in 0..100_000u64 {
+            digest.add(((i * 7919) % 100_000) as f64);
+        }
+        assert_eq!(digest.count(), 100_000);
+        assert_eq!(digest.quantile(0.0), Some(0.0));
+        assert_eq!(digest.quantile(1.0), Some(99_999.0));
+        for q in [0.001, 0.01, 0.25, 0.5, 0.75, 0.99, 0.999] {
+            let estimate = digest.quantile(q).unwrap_or_default();
+            assert!((estimate - q * 100_000.0).abs() < 100.0, "{q}: {estimate}");
+        }
+        assert!(digest.centroids.len() < 1000);
+
+        let mut halves = (TDigest::new(50.0), TDigest::new(50.0));
+        for i in 0..1000 {
+            halves.0.add(f64::from(i));
+            halves.1.add(f64::from(i + 1000));
+        }
+        halves.0.merge_digest(&halves.1);
+        let median = halves.0.quantile(0.5).unwrap_or_default();
+        assert!((median - 1000.0).abs() < 20.0, "{median}");
+    }
+
+    #[test]
+    fn test_summarize() -> Result<(), CdfError> {
+        let path_test_file: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "examples",
+            "data",
+            "ulysses.cdf",
+        ]
+        .iter()
+        .collect();
+        let cdf = Cdf::read_cdf_file(path_test_file)?;
+        let b = cdf.variable("B_scalar")?;
+        let mut summary = summarize(&b, 20)?;
+        let bins = summary.histogram.clone().expect("B_scalar varies");
+        assert_eq!(bins.counts.iter().sum::<u64>(), summary.stats.count as u64);
+        assert_eq!((bins.below, bins.above), (0, 0));
+        assert_eq!(summary.digest.count(), summary.stats.count as u64);
+        assert_eq!(summary.digest.quantile(0.0), summary.stats.min);
+        assert_eq!(summary.digest.quantile(1.0), summary.stats.max);
+
+        let quartiles = percentiles(&b, &[0.25, 0.5, 0.75])?;
+        assert!(quartiles.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(quartiles[1], summary.digest.quantile(0.5));
+        assert!(percentiles(&b, &[1.5]).is_err());
+
+        let clipped = histogram(&b, 0.0, 1.0, 2)?;
+        assert_eq!(
+            clipped.counts.iter().sum::<u64>() + clipped.below + clipped.above,
+            summary.stats.count as u64
+        );
+        assert!(summarize(&cdf.variable("Epoch")?, 20).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,1343 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::cmp::Ordering;
+use core::fmt;
+
+use crate::error::CdfError;
+use crate::types::{CdfEpoch, CdfEpoch16, CdfTimeTt2000};
+
+/// Environment variable that points to a leap second table, as read by the CDF C library.
+pub const LEAP_SECONDS_TABLE_ENV: &str = "CDF_LEAPSECONDSTABLE";
+
+/// Leap seconds known when this library was released, as in `CDFLeapSeconds.txt` of the CDF C
+/// library: year, month, day, TAI-UTC in seconds and the two drift coefficients used before 1972.
+const BUILTIN_LEAP_SECONDS: [(i32, u32, u32, f64, f64, f64); 42] = [
+    (1960, 1, 1, 1.417818, 37300.0, 0.001296),
+    (1961, 1, 1, 1.422818, 37300.0, 0.001296),
+    (1961, 8, 1, 1.372818, 37300.0, 0.001296),
+    (1962, 1, 1, 1.845858, 37665.0, 0.0011232),
+    (1963, 11, 1, 1.945858, 37665.0, 0.0011232),
+    (1964, 1, 1, 3.24013, 38761.0, 0.001296),
+    (1964, 4, 1, 3.34013, 38761.0, 0.001296),
+    (1964, 9, 1, 3.44013, 38761.0, 0.001296),
+    (1965, 1, 1, 3.54013, 38761.0, 0.001296),
+    (1965, 3, 1, 3.64013, 38761.0, 0.001296),
+    (1965, 7, 1, 3.74013, 38761.0, 0.001296),
+    (1965, 9, 1, 3.84013, 38761.0, 0.001296),
+    (1966, 1, 1, 4.31317, 39126.0, 0.002592),
+    (1968, 2, 1, 4.21317, 39126.0, 0.002592),
+    (1972, 1, 1, 10.0, 0.0, 0.0),
+    (1972, 7, 1, 11.0, 0.0, 0.0),
+    (1973, 1, 1, 12.0, 0.0, 0.0),
+    (1974, 1, 1, 13.0, 0.0, 0.0),
+    (1975, 1, 1, 14.0, 0.0, 0.0),
+    (1976, 1, 1, 15.0, 0.0, 0.0),
+    (1977, 1, 1, 16.0, 0.0, 0.0),
+    (1978, 1, 1, 17.0, 0.0, 0.0),
+    (1979, 1, 1, 18.0, 0.0, 0.0),
+    (1980, 1, 1, 19.0, 0.0, 0.0),
+    (1981, 7, 1, 20.0, 0.0, 0.0),
+    (1982, 7, 1, 21.0, 0.0, 0.0),
+    (1983, 7, 1, 22.0, 0.0, 0.0),
+    (1985, 7, 1, 23.0, 0.0, 0.0),
+    (1988, 1, 1, 24.0, 0.0, 0.0),
+    (1990, 1, 1, 25.0, 0.0, 0.0),
+    (1991, 1, 1, 26.0, 0.0, 0.0),
+    (1992, 7, 1, 27.0, 0.0, 0.0),
+    (1993, 7, 1, 28.0, 0.0, 0.0),
+    (1994, 7, 1, 29.0, 0.0, 0.0),
+    (1996, 1, 1, 30.0, 0.0, 0.0),
+    (1997, 7, 1, 31.0, 0.0, 0.0),
+    (1999, 1, 1, 32.0, 0.0, 0.0),
+    (2006, 1, 1, 33.0, 0.0, 0.0),
+    (2009, 1, 1, 34.0, 0.0, 0.0),
+    (2012, 7, 1, 35.0, 0.0, 0.0),
+    (2015, 7, 1, 36.0, 0.0, 0.0),
+    (2017, 1, 1, 37.0, 0.0, 0.0),
+];
+
+/// One entry of a leap second table: from the given date, TAI-UTC is `offset` seconds. Before
+/// 1972, TAI-UTC also drifts by `drift_rate` seconds per day since the Modified Julian Date
+/// `drift_epoch`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LeapSecond {
+    /// Year from which this entry applies.
+    pub year: i32,
+    /// Month from which this entry applies, from 1 to 12.
+    pub month: u32,
+    /// Day from which this entry applies, from 1 to 31.
+    pub day: u32,
+    /// TAI-UTC in seconds.
+    pub offset: f64,
+    /// Modified Julian Date from which the drift is counted.
+    pub drift_epoch: f64,
+    /// Drift of TAI-UTC in seconds per day.
+    pub drift_rate: f64,
+}
+
+/// Table of the leap seconds used to convert between TT2000 and UTC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeapSecondTable {
+    entries: Vec<LeapSecond>,
+}
+
+impl Default for LeapSecondTable {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl LeapSecondTable {
+    /// The table built into this library.
+    pub fn builtin() -> Self {
+        LeapSecondTable {
+            entries: BUILTIN_LEAP_SECONDS
+                .iter()
+                .map(
+                    |&(year, month, day, offset, drift_epoch, drift_rate)| LeapSecond {
+                        year,
+                        month,
+                        day,
+                        offset,
+                        drift_epoch,
+                        drift_rate,
+                    },
+                )
+                .collect(),
+        }
+    }
+
+    /// Create a table from its entries, which must be in chronological order.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if there are no entries or they are not in order.
+    pub fn new(entries: Vec<LeapSecond>) -> Result<Self, CdfError> {
+        if entries.is_empty() {
+            return Err(CdfError::Other(
+                "A leap second table needs at least one entry.".to_string(),
+            ));
+        }
+        let date = |e: &LeapSecond| (e.year, e.month, e.day);
+        if entries.windows(2).any(|w| date(&w[0]) >= date(&w[1])) {
+            return Err(CdfError::Other(
+                "Entries of a leap second table must be in chronological order.".to_string(),
+            ));
+        }
+        Ok(LeapSecondTable { entries })
+    }
+
+    /// Parse a table in the format of `CDFLeapSeconds.txt`. Lines starting with `;` are comments,
+    /// and every other line holds the year, month, day and TAI-UTC, optionally followed by the
+    /// two drift coefficients.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if a line cannot be parsed or the entries are not in order.
+    pub fn parse(text: &str) -> Result<Self, CdfError> {
+        let mut entries = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let invalid =
+                || CdfError::Other(format!("Invalid leap second table at line {}.", i + 1));
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 4 && fields.len() != 6 {
+                return Err(invalid());
+            }
+            let float = |k: usize| -> Result<f64, CdfError> {
+                fields
+                    .get(k)
+                    .map_or(Ok(0.0), |f| f.parse().map_err(|_| invalid()))
+            };
+            entries.push(LeapSecond {
+                year: fields[0].parse().map_err(|_| invalid())?,
+                month: fields[1].parse().map_err(|_| invalid())?,
+                day: fields[2].parse().map_err(|_| invalid())?,
+                offset: float(3)?,
+                drift_epoch: float(4)?,
+                drift_rate: float(5)?,
+            });
+        }
+        Self::new(entries)
+    }
+
+    /// Read a table from the file at `path`.
+    /// # Errors
+    /// Returns a [`CdfError`] if the file cannot be read or parsed.
+    #[cfg(feature = "std-fs")]
+    pub fn from_path<P: AsRef<std::path::Path>>(path: P) -> Result<Self, CdfError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Read the table pointed to by the `CDF_LEAPSECONDSTABLE` environment variable, if it is set.
+    /// # Errors
+    /// Returns a [`CdfError`] if the variable is set but the file cannot be read or parsed.
+    #[cfg(feature = "std-fs")]
+    pub fn from_env() -> Result<Option<Self>, CdfError> {
+        match std::env::var_os(LEAP_SECONDS_TABLE_ENV) {
+            Some(path) => Ok(Some(Self::from_path(path)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Entries of this table, in chronological order.
+    pub fn entries(&self) -> &[LeapSecond] {
+        &self.entries
+    }
+
+    /// Date of the last entry, as YYYYMMDD. This is what the GDR of a file stores as the date of
+    /// the last leap second update.
+    pub fn last_update(&self) -> i32 {
+        let last = self.entries[self.entries.len() - 1];
+        last.year * 10000 + last.month as i32 * 100 + last.day as i32
+    }
+
+    /// TAI-UTC in seconds at the start of the given day. Dates before the first entry have no
+    /// leap seconds.
+    pub fn tai_minus_utc(&self, year: i32, month: u32, day: u32) -> f64 {
+        let index = self
+            .entries
+            .partition_point(|e| (e.year, e.month, e.day) <= (year, month, day));
+        match index.checked_sub(1).map(|i| &self.entries[i]) {
+            Some(e) if e.drift_rate != 0.0 => {
+                let mjd = days_from_civil(year, month, day) as f64 + MJD_UNIX_EPOCH;
+                e.offset + (mjd - e.drift_epoch) * e.drift_rate
+            }
+            Some(e) => e.offset,
+            None => 0.0,
+        }
+    }
+}
+
+/// Modified Julian Date of 1970-01-01.
+const MJD_UNIX_EPOCH: f64 = 40587.0;
+
+/// Number of days from 1970-01-01 to the given date of the proleptic Gregorian calendar.
+pub(crate) fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Date of the proleptic Gregorian calendar that is the given number of days after 1970-01-01.
+pub(crate) fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year as i32, month, day)
+}
+
+/// Number of days in the given month.
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Calendar date and time of day in UTC, broken down into the parts used by the CDF C library.
+/// The second is 60 inside a leap second.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeComponents {
+    /// Year.
+    pub year: i32,
+    /// Month, from 1 to 12.
+    pub month: u32,
+    /// Day of the month, from 1 to 31.
+    pub day: u32,
+    /// Hour, from 0 to 23.
+    pub hour: u32,
+    /// Minute, from 0 to 59.
+    pub minute: u32,
+    /// Second, from 0 to 60.
+    pub second: u32,
+    /// Millisecond, from 0 to 999.
+    pub millisecond: u32,
+    /// Microsecond, from 0 to 999.
+    pub microsecond: u32,
+    /// Nanosecond, from 0 to 999.
+    pub nanosecond: u32,
+}
+
+impl TimeComponents {
+    /// Components that the CDF C library uses for the fill value of time types,
+    /// 9999-12-31T23:59:59.999999999.
+    pub const FILL: TimeComponents = TimeComponents {
+        year: 9999,
+        month: 12,
+        day: 31,
+        hour: 23,
+        minute: 59,
+        second: 59,
+        millisecond: 999,
+        microsecond: 999,
+        nanosecond: 999,
+    };
+
+    /// Components that the CDF C library uses for the default pad value of time types,
+    /// 0000-01-01T00:00:00.000000000.
+    pub const PAD: TimeComponents = TimeComponents {
+        year: 0,
+        month: 1,
+        day: 1,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        millisecond: 0,
+        microsecond: 0,
+        nanosecond: 0,
+    };
+
+    /// Components of the given date and time, with a sub-second part of zero.
+    pub fn new(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> Self {
+        TimeComponents {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            millisecond: 0,
+            microsecond: 0,
+            nanosecond: 0,
+        }
+    }
+
+    /// Parse an ISO 8601 date and time such as `2001-02-03T04:05:06.789`, as written by
+    /// [`CdfTime::to_iso8601`]. The time, its seconds, its fraction and a trailing `Z` are
+    /// optional, and a space may separate the date from the time. Digits of the fraction past
+    /// nanoseconds are ignored.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if `text` is not such a date and time, or if a component is
+    /// out of range.
+    pub fn parse_iso8601(text: &str) -> Result<Self, CdfError> {
+        let invalid = || CdfError::Other(format!("Invalid ISO 8601 date and time {text}."));
+        let number = |field: Option<&str>| -> Result<u32, CdfError> {
+            match field {
+                Some(f) if !f.is_empty() && f.bytes().all(|b| b.is_ascii_digit()) => {
+                    f.parse().map_err(|_| invalid())
+                }
+                _ => Err(invalid()),
+            }
+        };
+
+        let trimmed = text.trim();
+        let trimmed = trimmed.strip_suffix('Z').unwrap_or(trimmed);
+        let (date, time) = trimmed.split_once(['T', ' ']).unwrap_or((trimmed, "00:00"));
+        let mut date = date.split('-');
+        let year = i32::try_from(number(date.next())?).map_err(|_| invalid())?;
+        let (month, day) = (number(date.next())?, number(date.next())?);
+        let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+        let mut time = time.split(':');
+        let (hour, minute) = (number(time.next())?, number(time.next())?);
+        let second = time.next().map_or(Ok(0), |s| number(Some(s)))?;
+        if date.next().is_some() || time.next().is_some() {
+            return Err(invalid());
+        }
+        let nanos = match fraction {
+            "" => 0,
+            f => {
+                let digits = &f[..f.len().min(9)];
+                number(Some(digits))? * 10u32.pow(9 - digits.len() as u32)
+            }
+        };
+
+        let components = TimeComponents {
+            millisecond: nanos / 1_000_000,
+            microsecond: nanos / 1_000 % 1_000,
+            nanosecond: nanos % 1_000,
+            ..TimeComponents::new(year, month, day, hour, minute, second)
+        };
+        components.validate()?;
+        Ok(components)
+    }
+
+    /// Check that every component is within its range. Whether a second of 60 falls in an actual
+    /// leap second depends on the leap second table and is checked by the conversions.
+    fn validate(&self) -> Result<(), CdfError> {
+        let valid = (1..=12).contains(&self.month)
+            && (1..=days_in_month(self.year, self.month)).contains(&self.day)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second <= 60
+            && self.millisecond < 1000
+            && self.microsecond < 1000
+            && self.nanosecond < 1000;
+        if valid {
+            Ok(())
+        } else {
+            Err(CdfError::Other(format!("Invalid date and time {self:?}.")))
+        }
+    }
+
+    /// Nanoseconds since the start of the day.
+    fn nanos_of_day(&self) -> i128 {
+        let seconds = i128::from(self.hour * 3600 + self.minute * 60 + self.second);
+        seconds * NANOS_PER_SECOND
+            + i128::from(self.millisecond) * 1_000_000
+            + i128::from(self.microsecond) * 1_000
+            + i128::from(self.nanosecond)
+    }
+}
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+const NANOS_PER_DAY: i128 = 86_400 * NANOS_PER_SECOND;
+
+/// Days from 1970-01-01 to 2000-01-01.
+const J2000_DAYS: i64 = 10957;
+
+/// TT2000 counts from 2000-01-01T12:00:00 TT, and TT is ahead of TAI by 32.184 seconds.
+const TT2000_OFFSET: i128 = 32_184_000_000 - 43_200 * NANOS_PER_SECOND;
+
+/// Value of [`CdfTimeTt2000`] at the start of the UTC day that is `days` after 1970-01-01.
+fn tt2000_day_start(table: &LeapSecondTable, days: i64) -> i128 {
+    let (year, month, day) = civil_from_days(days);
+    // TAI-UTC is never negative, so this rounds to the nearest nanosecond without `f64::round`,
+    // which needs the standard library.
+    let leap_seconds = (table.tai_minus_utc(year, month, day) * 1e9 + 0.5) as i128;
+    i128::from(days - J2000_DAYS) * NANOS_PER_DAY + leap_seconds + TT2000_OFFSET
+}
+
+impl CdfTimeTt2000 {
+    /// Fill value of TT2000 variables.
+    pub const FILL: i64 = i64::MIN;
+
+    /// Default pad value of TT2000 variables.
+    pub const PAD: i64 = i64::MIN + 1;
+
+    /// Create a TT2000 value from a UTC date and time, like `CDF_TT2000_from_UTC_parts` of the
+    /// CDF C library. A `second` of 60 is only valid inside a leap second. The components of
+    /// [`TimeComponents::FILL`] and [`TimeComponents::PAD`] give the fill and pad values.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if a component is out of range, or if the time cannot be
+    /// represented as TT2000.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_components(
+        year: i32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+        millisecond: u32,
+        microsecond: u32,
+        nanosecond: u32,
+    ) -> Result<Self, CdfError> {
+        let components = TimeComponents {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            millisecond,
+            microsecond,
+            nanosecond,
+        };
+        Self::from_utc(&components)
+    }
+
+    /// Create a TT2000 value from a UTC date and time. See [`CdfTimeTt2000::from_components`].
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if a component is out of range, or if the time cannot be
+    /// represented as TT2000.
+    pub fn from_utc(components: &TimeComponents) -> Result<Self, CdfError> {
+        if *components == TimeComponents::FILL {
+            return Ok(CdfTimeTt2000::from(Self::FILL));
+        }
+        if *components == TimeComponents::PAD {
+            return Ok(CdfTimeTt2000::from(Self::PAD));
+        }
+        components.validate()?;
+
+        let table = leap_seconds();
+        let days = days_from_civil(components.year, components.month, components.day);
+        let start = tt2000_day_start(&table, days);
+        let end = tt2000_day_start(&table, days + 1);
+        let value = start + components.nanos_of_day();
+        if components.second == 60
+            && (components.hour != 23 || components.minute != 59 || value >= end)
+        {
+            return Err(CdfError::Other(format!(
+                "{components:?} is not within a leap second."
+            )));
+        }
+        match i64::try_from(value) {
+            Ok(value) if value > Self::PAD => Ok(CdfTimeTt2000::from(value)),
+            _ => Err(CdfError::Other(format!(
+                "{components:?} is out of the range of TT2000."
+            ))),
+        }
+    }
+
+    /// Break this value down into a UTC date and time, like `CDF_TT2000_to_UTC_parts` of the CDF
+    /// C library. The second is 60 inside a leap second, and the fill and pad values give the
+    /// components of [`TimeComponents::FILL`] and [`TimeComponents::PAD`].
+    pub fn to_components(&self) -> TimeComponents {
+        match **self {
+            Self::FILL => return TimeComponents::FILL,
+            Self::PAD => return TimeComponents::PAD,
+            _ => {}
+        }
+        let value = i128::from(**self);
+        let table = leap_seconds();
+        // Leap seconds are ignored in this guess, which is then off by at most one day.
+        let mut days = (value - TT2000_OFFSET).div_euclid(NANOS_PER_DAY) as i64 + J2000_DAYS;
+        while tt2000_day_start(&table, days) > value {
+            days -= 1;
+        }
+        while tt2000_day_start(&table, days + 1) <= value {
+            days += 1;
+        }
+        let nanos = value - tt2000_day_start(&table, days);
+        let seconds = (nanos / NANOS_PER_SECOND) as u32;
+        let (hour, minute, second) = if seconds >= 86_400 {
+            (23, 59, seconds - 86_400 + 60)
+        } else {
+            (seconds / 3600, seconds / 60 % 60, seconds % 60)
+        };
+        let subsecond = (nanos % NANOS_PER_SECOND) as u32;
+        let (year, month, day) = civil_from_days(days);
+        TimeComponents {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            millisecond: subsecond / 1_000_000,
+            microsecond: subsecond / 1_000 % 1_000,
+            nanosecond: subsecond % 1_000,
+        }
+    }
+}
+
+/// Days from 0000-01-01 to 1970-01-01.
+const EPOCH_DAYS: i64 = 719_528;
+
+/// Fill value of EPOCH and EPOCH16 variables.
+const EPOCH_FILL: f64 = -1e31;
+
+/// Check the components of an EPOCH or EPOCH16 value, which only cover years 0 to 9999 and have no
+/// leap seconds, and return the number of seconds since 0000-01-01.
+fn epoch_seconds(components: &TimeComponents) -> Result<f64, CdfError> {
+    components.validate()?;
+    if !(0..=9999).contains(&components.year) || components.second == 60 {
+        return Err(CdfError::Other(format!(
+            "{components:?} is out of the range of EPOCH."
+        )));
+    }
+    let days = days_from_civil(components.year, components.month, components.day) + EPOCH_DAYS;
+    let seconds = components.hour * 3600 + components.minute * 60 + components.second;
+    Ok((days * 86_400 + i64::from(seconds)) as f64)
+}
+
+impl CdfEpoch {
+    /// Fill value of EPOCH variables.
+    pub const FILL: f64 = EPOCH_FILL;
+
+    /// Create an EPOCH value, the number of milliseconds since 0000-01-01, from a date and time,
+    /// like `computeEPOCH` of the CDF C library. 9999-12-31T23:59:59.999 gives the fill value.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if a component is out of range.
+    pub fn from_ymdhms(
+        year: i32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+        millisecond: u32,
+    ) -> Result<Self, CdfError> {
+        // EPOCH has no microseconds or nanoseconds, so they are set to those of the fill value.
+        let components = TimeComponents {
+            millisecond,
+            microsecond: 999,
+            nanosecond: 999,
+            ..TimeComponents::new(year, month, day, hour, minute, second)
+        };
+        if components == TimeComponents::FILL {
+            return Ok(CdfEpoch::from(Self::FILL));
+        }
+        let seconds = epoch_seconds(&components)?;
+        Ok(CdfEpoch::from(seconds * 1e3 + f64::from(millisecond)))
+    }
+}
+
+impl CdfEpoch16 {
+    /// Create an EPOCH16 value, the number of seconds since 0000-01-01 and the picoseconds within
+    /// that second, from a date and time, like `computeEPOCH16` of the CDF C library.
+    /// 9999-12-31T23:59:59.999999999999 gives the fill value.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if a component is out of range.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_parts(
+        year: i32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+        millisecond: u32,
+        microsecond: u32,
+        nanosecond: u32,
+        picosecond: u32,
+    ) -> Result<Self, CdfError> {
+        let components = TimeComponents {
+            millisecond,
+            microsecond,
+            nanosecond,
+            ..TimeComponents::new(year, month, day, hour, minute, second)
+        };
+        if components == TimeComponents::FILL && picosecond == 999 {
+            return Ok(CdfEpoch16::new(EPOCH_FILL, EPOCH_FILL));
+        }
+        if picosecond >= 1000 {
+            return Err(CdfError::Other(format!("Invalid picosecond {picosecond}.")));
+        }
+        let seconds = epoch_seconds(&components)?;
+        let picoseconds = ((u64::from(millisecond) * 1000 + u64::from(microsecond)) * 1000
+            + u64::from(nanosecond))
+            * 1000
+            + u64::from(picosecond);
+        Ok(CdfEpoch16::new(seconds, picoseconds as f64))
+    }
+}
+
+/// Nanoseconds from 0000-01-01 to 1970-01-01.
+const EPOCH_UNIX_NANOS: i128 = EPOCH_DAYS as i128 * NANOS_PER_DAY;
+
+/// Components of a date and time that is `nanos` nanoseconds after 1970-01-01, ignoring leap
+/// seconds.
+fn unix_components(nanos: i128) -> TimeComponents {
+    let days = nanos.div_euclid(NANOS_PER_DAY);
+    let nanos = nanos.rem_euclid(NANOS_PER_DAY);
+    let seconds = (nanos / NANOS_PER_SECOND) as u32;
+    let subsecond = (nanos % NANOS_PER_SECOND) as u32;
+    let (year, month, day) = civil_from_days(days as i64);
+    TimeComponents {
+        millisecond: subsecond / 1_000_000,
+        microsecond: subsecond / 1_000 % 1_000,
+        nanosecond: subsecond % 1_000,
+        ..TimeComponents::new(
+            year,
+            month,
+            day,
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+        )
+    }
+}
+
+/// Nanoseconds since 1970-01-01 of the given date and time, ignoring leap seconds.
+fn unix_nanos(components: &TimeComponents) -> i128 {
+    let days = days_from_civil(components.year, components.month, components.day);
+    i128::from(days) * NANOS_PER_DAY + components.nanos_of_day()
+}
+
+/// Date and time as `yyyy-mm-ddThh:mm:ss`, without the fraction of the second.
+fn iso8601_seconds(c: &TimeComponents) -> String {
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        c.year, c.month, c.day, c.hour, c.minute, c.second
+    )
+}
+
+/// Abbreviated month names of [`EpochStyle::Classic`].
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Layout of a time formatted with [`CdfTime::format`], one for each of the styles of the
+/// `encodeEPOCH` functions of the CDF C library.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EpochStyle {
+    /// `dd-Mon-yyyy hh:mm:ss.ccc`, like `encodeEPOCH`.
+    Classic,
+    /// `yyyymmdd.ttttttt`, the date followed by the fraction of the day, like `encodeEPOCH1`.
+    /// The fraction has 7 digits at millisecond precision, and 3 more for each finer precision.
+    DayFraction,
+    /// `yyyymmddhhmmss`, without the fraction of the second, like `encodeEPOCH2`.
+    Compact,
+    /// `yyyy-mm-ddThh:mm:ss.ccc`, like `encodeEPOCH3` and `encodeEPOCH4`.
+    #[default]
+    Iso8601,
+}
+
+/// Precision of the fraction of the second of a formatted time. Digits past the precision of
+/// the time type are zeros.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EpochPrecision {
+    /// Three digits.
+    Milliseconds,
+    /// Six digits.
+    Microseconds,
+    /// Nine digits.
+    Nanoseconds,
+    /// Twelve digits.
+    Picoseconds,
+}
+
+impl EpochPrecision {
+    /// Number of digits of the fraction of the second.
+    pub fn digits(&self) -> usize {
+        match self {
+            EpochPrecision::Milliseconds => 3,
+            EpochPrecision::Microseconds => 6,
+            EpochPrecision::Nanoseconds => 9,
+            EpochPrecision::Picoseconds => 12,
+        }
+    }
+}
+
+/// How [`CdfTime::format`] writes a time. The default is the ISO 8601 format of
+/// [`CdfTime::to_iso8601`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EpochFormat {
+    /// Layout of the date and time.
+    pub style: EpochStyle,
+    /// Precision of the fraction of the second, or `None` for the precision of the time type:
+    /// milliseconds for [`CdfEpoch`], picoseconds for [`CdfEpoch16`] and nanoseconds for
+    /// [`CdfTimeTt2000`].
+    pub precision: Option<EpochPrecision>,
+    /// Character between the date and the time of [`EpochStyle::Classic`] and
+    /// [`EpochStyle::Iso8601`], or `None` for a space and `T` respectively.
+    pub separator: Option<char>,
+    /// Whether [`EpochStyle::Iso8601`] times end with `Z`, like `encodeEPOCH3`.
+    pub zulu: bool,
+}
+
+impl EpochFormat {
+    /// Display `time` in this format, such as within `format!`.
+    pub fn display<T: CdfTime>(self, time: &T) -> FormattedTime<'_, T> {
+        FormattedTime { time, format: self }
+    }
+
+    // Format the components of a time with `picoseconds` within the nanosecond, at `precision`
+    // unless this format has its own.
+    fn write(&self, c: &TimeComponents, picoseconds: u32, precision: EpochPrecision) -> String {
+        let digits = self.precision.unwrap_or(precision).digits();
+        let picos = u128::from(c.millisecond) * 1_000_000_000
+            + u128::from(c.microsecond) * 1_000_000
+            + u128::from(c.nanosecond) * 1_000
+            + u128::from(picoseconds);
+        let fraction = picos / 10u128.pow(12 - digits as u32);
+        let time = format!("{:02}:{:02}:{:02}", c.hour, c.minute, c.second);
+        match self.style {
+            EpochStyle::Classic => {
+                let month = MONTHS.get(c.month as usize - 1).unwrap_or(&"???");
+                let separator = self.separator.unwrap_or(' ');
+                format!(
+                    "{:02}-{month}-{:04}{separator}{time}.{fraction:0digits$}",
+                    c.day, c.year
+                )
+            }
+            EpochStyle::DayFraction => {
+                let digits = digits + 4;
+                let seconds = u128::from(c.hour * 3600 + c.minute * 60 + c.second);
+                let scale = 10u128.pow(digits as u32);
+                // Times within a leap second stay within the day.
+                let fraction = ((seconds * 1_000_000_000_000 + picos) * scale
+                    / (86_400 * 1_000_000_000_000))
+                    .min(scale - 1);
+                format!(
+                    "{:04}{:02}{:02}.{fraction:0digits$}",
+                    c.year, c.month, c.day
+                )
+            }
+            EpochStyle::Compact => format!(
+                "{:04}{:02}{:02}{:02}{:02}{:02}",
+                c.year, c.month, c.day, c.hour, c.minute, c.second
+            ),
+            EpochStyle::Iso8601 => {
+                let separator = self.separator.unwrap_or('T');
+                format!(
+                    "{:04}-{:02}-{:02}{separator}{time}.{fraction:0digits$}{}",
+                    c.year,
+                    c.month,
+                    c.day,
+                    if self.zulu { "Z" } else { "" }
+                )
+            }
+        }
+    }
+}
+
+/// A time that is displayed in an [`EpochFormat`], see [`EpochFormat::display`].
+#[derive(Debug, Clone, Copy)]
+pub struct FormattedTime<'a, T> {
+    time: &'a T,
+    format: EpochFormat,
+}
+
+impl<T: CdfTime> fmt::Display for FormattedTime<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.time.format(&self.format))
+    }
+}
+
+/// Operations shared by the three time types of CDF, [`CdfEpoch`], [`CdfEpoch16`] and
+/// [`CdfTimeTt2000`], so that code handling times does not depend on the type chosen by a file.
+pub trait CdfTime: Sized {
+    /// Nanoseconds since 1970-01-01T00:00:00 UTC, ignoring leap seconds as Unix time does. Times
+    /// within a leap second map to the second that follows it. Returns `None` for the fill value.
+    fn to_unix_nanos(&self) -> Option<i128>;
+
+    /// Create a value from nanoseconds since 1970-01-01T00:00:00 UTC, truncated to the precision
+    /// of this type.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if the time is out of the range of this type.
+    fn from_unix_nanos(nanos: i128) -> Result<Self, CdfError>;
+
+    /// Format this value as ISO 8601, with the fraction of the second at the precision of this
+    /// type, like `encodeEPOCH4`, `encodeEPOCH16_4` and `encodeTT2000` of the CDF C library.
+    fn to_iso8601(&self) -> String;
+
+    /// Format this value in `format`, which can also select the other styles of the CDF C
+    /// library.
+    fn format(&self, format: &EpochFormat) -> String;
+
+    /// Whether this is the fill value of this type.
+    fn is_fill(&self) -> bool;
+
+    /// Compare two values of this type. Returns `None` if either value is not a number.
+    fn compare(&self, other: &Self) -> Option<Ordering>;
+}
+
+impl CdfEpoch {
+    /// Break this value down into a date and time, like `EPOCHbreakdown` of the CDF C library.
+    /// Fractions of a millisecond are truncated, and the fill value gives the components of
+    /// [`TimeComponents::FILL`].
+    pub fn to_components(&self) -> TimeComponents {
+        if self.is_fill() {
+            return TimeComponents::FILL;
+        }
+        let nanos = (**self as i128) * 1_000_000 - EPOCH_UNIX_NANOS;
+        unix_components(nanos)
+    }
+}
+
+impl CdfTime for CdfEpoch {
+    fn to_unix_nanos(&self) -> Option<i128> {
+        if self.is_fill() {
+            return None;
+        }
+        let milliseconds = **self as i128;
+        let fraction = ((**self - milliseconds as f64) * 1e6) as i128;
+        Some(milliseconds * 1_000_000 + fraction - EPOCH_UNIX_NANOS)
+    }
+
+    fn from_unix_nanos(nanos: i128) -> Result<Self, CdfError> {
+        let c = unix_components(nanos);
+        CdfEpoch::from_ymdhms(
+            c.year,
+            c.month,
+            c.day,
+            c.hour,
+            c.minute,
+            c.second,
+            c.millisecond,
+        )
+    }
+
+    fn to_iso8601(&self) -> String {
+        let c = self.to_components();
+        format!("{}.{:03}", iso8601_seconds(&c), c.millisecond)
+    }
+
+    fn format(&self, format: &EpochFormat) -> String {
+        format.write(&self.to_components(), 0, EpochPrecision::Milliseconds)
+    }
+
+    fn is_fill(&self) -> bool {
+        **self == Self::FILL
+    }
+
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl CdfEpoch16 {
+    /// Break this value down into a date and time, like `EPOCH16breakdown` of the CDF C library.
+    /// Picoseconds are truncated, see [`CdfEpoch16::picoseconds`], and the fill value gives the
+    /// components of [`TimeComponents::FILL`].
+    pub fn to_components(&self) -> TimeComponents {
+        if self.is_fill() {
+            return TimeComponents::FILL;
+        }
+        let seconds = self.seconds() as i128;
+        let nanos = (self.picoseconds() / 1e3) as i128;
+        unix_components(seconds * NANOS_PER_SECOND + nanos - EPOCH_UNIX_NANOS)
+    }
+
+    /// Picoseconds within the nanosecond, which are not part of the components.
+    fn picoseconds_of_nanosecond(&self) -> u32 {
+        if self.is_fill() {
+            999
+        } else {
+            (self.picoseconds() as u64 % 1000) as u32
+        }
+    }
+}
+
+impl CdfTime for CdfEpoch16 {
+    fn to_unix_nanos(&self) -> Option<i128> {
+        if self.is_fill() {
+            return None;
+        }
+        let c = self.to_components();
+        Some(unix_nanos(&c))
+    }
+
+    fn from_unix_nanos(nanos: i128) -> Result<Self, CdfError> {
+        let c = unix_components(nanos);
+        CdfEpoch16::from_parts(
+            c.year,
+            c.month,
+            c.day,
+            c.hour,
+            c.minute,
+            c.second,
+            c.millisecond,
+            c.microsecond,
+            c.nanosecond,
+            0,
+        )
+    }
+
+    fn to_iso8601(&self) -> String {
+        let c = self.to_components();
+        format!(
+            "{}.{:03}{:03}{:03}{:03}",
+            iso8601_seconds(&c),
+            c.millisecond,
+            c.microsecond,
+            c.nanosecond,
+            self.picoseconds_of_nanosecond()
+        )
+    }
+
+    fn format(&self, format: &EpochFormat) -> String {
+        let c = self.to_components();
+        format.write(
+            &c,
+            self.picoseconds_of_nanosecond(),
+            EpochPrecision::Picoseconds,
+        )
+    }
+
+    fn is_fill(&self) -> bool {
+        self.seconds() == EPOCH_FILL && self.picoseconds() == EPOCH_FILL
+    }
+
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        (self.seconds(), self.picoseconds()).partial_cmp(&(other.seconds(), other.picoseconds()))
+    }
+}
+
+impl CdfTime for CdfTimeTt2000 {
+    fn to_unix_nanos(&self) -> Option<i128> {
+        if self.is_fill() {
+            return None;
+        }
+        Some(unix_nanos(&self.to_components()))
+    }
+
+    fn from_unix_nanos(nanos: i128) -> Result<Self, CdfError> {
+        CdfTimeTt2000::from_utc(&unix_components(nanos))
+    }
+
+    fn to_iso8601(&self) -> String {
+        let c = self.to_components();
+        format!(
+            "{}.{:03}{:03}{:03}",
+            iso8601_seconds(&c),
+            c.millisecond,
+            c.microsecond,
+            c.nanosecond
+        )
+    }
+
+    fn format(&self, format: &EpochFormat) -> String {
+        format.write(&self.to_components(), 0, EpochPrecision::Nanoseconds)
+    }
+
+    fn is_fill(&self) -> bool {
+        **self == Self::FILL
+    }
+
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        Some((**self).cmp(&**other))
+    }
+}
+
+#[cfg(feature = "std")]
+static LEAP_SECONDS: std::sync::RwLock<Option<Arc<LeapSecondTable>>> = std::sync::RwLock::new(None);
+
+/// The leap second table used for time conversions. Unless it was replaced with
+/// [`set_leap_seconds`], this is the table pointed to by the `CDF_LEAPSECONDSTABLE` environment
+/// variable when it is set and valid, as in the CDF C library, or else the built-in table.
+pub fn leap_seconds() -> Arc<LeapSecondTable> {
+    #[cfg(feature = "std")]
+    {
+        if let Some(table) = LEAP_SECONDS.read().ok().and_then(|t| t.clone()) {
+            return table;
+        }
+        #[cfg(feature = "std-fs")]
+        let table = LeapSecondTable::from_env()
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        #[cfg(not(feature = "std-fs"))]
+        let table = LeapSecondTable::builtin();
+        let table = Arc::new(table);
+        if let Ok(mut current) = LEAP_SECONDS.write() {
+            return current.get_or_insert(table).clone();
+        }
+        table
+    }
+    #[cfg(not(feature = "std"))]
+    Arc::new(LeapSecondTable::builtin())
+}
+
+/// Replace the leap second table used for time conversions by the whole process, for instance
+/// after a new leap second is announced.
+#[cfg(feature = "std")]
+pub fn set_leap_seconds(table: LeapSecondTable) {
+    if let Ok(mut current) = LEAP_SECONDS.write() {
+        *current = Some(Arc::new(table));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_table() {
+        let table = LeapSecondTable::builtin();
+        assert_eq!(table.last_update(), 20170101);
+        assert_eq!(table.tai_minus_utc(1950, 1, 1), 0.0);
+        assert_eq!(table.tai_minus_utc(1972, 1, 1), 10.0);
+        assert_eq!(table.tai_minus_utc(2016, 12, 31), 36.0);
+        assert_eq!(table.tai_minus_utc(2024, 3, 1), 37.0);
+        // 1966-01-01 is MJD 39126, so the drift has not started yet.
+        assert_eq!(table.tai_minus_utc(1966, 1, 1), 4.31317);
+        assert!((table.tai_minus_utc(1967, 1, 1) - (4.31317 + 365.0 * 0.002592)).abs() < 1e-9);
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 1, 1), 10957);
+        assert_eq!(days_from_civil(1, 1, 1), -719162);
+    }
+
+    #[test]
+    fn test_parse_table() -> Result<(), CdfError> {
+        let text = "; Year Month Day Leap Seconds Drift\n\
+                    1972 1 1 10.0 0.0 0.0\n\
+                    \n\
+                    2017 1 1 37.0\n\
+                    2030 7 1 38.0\n";
+        let table = LeapSecondTable::parse(text)?;
+        assert_eq!(table.entries().len(), 3);
+        assert_eq!(table.last_update(), 20300701);
+        assert_eq!(table.tai_minus_utc(2031, 1, 1), 38.0);
+
+        assert!(LeapSecondTable::parse("1972 1 1").is_err());
+        assert!(LeapSecondTable::parse("1972 1 x 10.0").is_err());
+        assert!(LeapSecondTable::parse("2017 1 1 37.0\n1972 1 1 10.0").is_err());
+        assert!(LeapSecondTable::parse("; empty").is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn test_table_from_path() -> Result<(), CdfError> {
+        let path = std::env::temp_dir().join("cdf-rs-test-leap-seconds.txt");
+        std::fs::write(&path, "1972 1 1 10.0\n2017 1 1 37.0\n")?;
+        let table = LeapSecondTable::from_path(&path)?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(table.entries().len(), 2);
+        assert!(LeapSecondTable::from_path(&path).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_set_leap_seconds() -> Result<(), CdfError> {
+        // Other tests convert times concurrently, so only add an entry after the built-in ones.
+        let mut entries = LeapSecondTable::builtin().entries().to_vec();
+        entries.push(LeapSecond {
+            year: 2030,
+            month: 7,
+            day: 1,
+            offset: 38.0,
+            drift_epoch: 0.0,
+            drift_rate: 0.0,
+        });
+        let table = LeapSecondTable::new(entries)?;
+        set_leap_seconds(table.clone());
+        assert_eq!(*leap_seconds(), table);
+        set_leap_seconds(LeapSecondTable::builtin());
+        assert_eq!(leap_seconds().last_update(), 20170101);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tt2000_components() -> Result<(), CdfError> {
+        let tt2000 = |y, mo, d, h, mi, s, ms, us, ns| {
+            CdfTimeTt2000::from_components(y, mo, d, h, mi, s, ms, us, ns).map(|t| *t)
+        };
+        assert_eq!(tt2000(2000, 1, 1, 12, 0, 0, 0, 0, 0)?, 64_184_000_000);
+        assert_eq!(tt2000(2000, 1, 1, 11, 58, 55, 816, 0, 0)?, 0);
+        assert_eq!(
+            tt2000(2017, 1, 1, 0, 0, 0, 0, 0, 0)?,
+            536_500_869_184_000_000
+        );
+        assert_eq!(
+            tt2000(2016, 12, 31, 23, 59, 60, 500, 0, 0)?,
+            536_500_868_684_000_000
+        );
+        assert_eq!(
+            tt2000(2016, 12, 31, 23, 59, 59, 0, 0, 0)?,
+            536_500_867_184_000_000
+        );
+        assert_eq!(tt2000(9999, 12, 31, 23, 59, 59, 999, 999, 999)?, i64::MIN);
+        assert_eq!(tt2000(0, 1, 1, 0, 0, 0, 0, 0, 0)?, i64::MIN + 1);
+
+        assert!(tt2000(2016, 12, 30, 23, 59, 60, 0, 0, 0).is_err());
+        assert!(tt2000(2016, 12, 31, 23, 58, 60, 0, 0, 0).is_err());
+        assert!(tt2000(2017, 2, 29, 0, 0, 0, 0, 0, 0).is_err());
+        assert!(tt2000(2017, 1, 1, 0, 0, 0, 1000, 0, 0).is_err());
+        assert!(tt2000(2300, 1, 1, 0, 0, 0, 0, 0, 0).is_err());
+
+        for (y, mo, d, h, mi, s, ms, us, ns) in [
+            (2016, 12, 31, 23, 59, 60, 999, 999, 999),
+            (2017, 1, 1, 0, 0, 0, 0, 0, 0),
+            (2016, 12, 31, 23, 59, 59, 999, 999, 999),
+            (1972, 6, 30, 23, 59, 60, 0, 0, 1),
+            (1967, 3, 14, 1, 2, 3, 4, 5, 6),
+            (1900, 2, 28, 12, 0, 0, 0, 0, 0),
+            (2024, 2, 29, 18, 30, 15, 250, 0, 0),
+            (9999, 12, 31, 23, 59, 59, 999, 999, 999),
+            (0, 1, 1, 0, 0, 0, 0, 0, 0),
+        ] {
+            let components = TimeComponents {
+                year: y,
+                month: mo,
+                day: d,
+                hour: h,
+                minute: mi,
+                second: s,
+                millisecond: ms,
+                microsecond: us,
+                nanosecond: ns,
+            };
+            let value = CdfTimeTt2000::from_components(y, mo, d, h, mi, s, ms, us, ns)?;
+            assert_eq!(value.to_components(), components);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        for days in [-719_162, -1, 0, 59, 10_957, 17_167, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        assert_eq!(civil_from_days(17_167), (2017, 1, 1));
+    }
+
+    #[test]
+    fn test_epoch_from_components() -> Result<(), CdfError> {
+        assert_eq!(*CdfEpoch::from_ymdhms(0, 1, 1, 0, 0, 0, 0)?, 0.0);
+        assert_eq!(
+            *CdfEpoch::from_ymdhms(2000, 1, 1, 0, 0, 0, 0)?,
+            63_113_904_000_000.0
+        );
+        assert_eq!(
+            *CdfEpoch::from_ymdhms(2000, 1, 1, 12, 30, 15, 250)?,
+            63_113_949_015_250.0
+        );
+        assert_eq!(
+            *CdfEpoch::from_ymdhms(9999, 12, 31, 23, 59, 59, 999)?,
+            CdfEpoch::FILL
+        );
+        assert!(CdfEpoch::from_ymdhms(2016, 12, 31, 23, 59, 60, 0).is_err());
+        assert!(CdfEpoch::from_ymdhms(10000, 1, 1, 0, 0, 0, 0).is_err());
+        assert!(CdfEpoch::from_ymdhms(2000, 13, 1, 0, 0, 0, 0).is_err());
+
+        let epoch16 = CdfEpoch16::from_parts(2000, 1, 1, 12, 30, 15, 1, 2, 3, 4)?;
+        assert_eq!(epoch16.seconds(), 63_113_949_015.0);
+        assert_eq!(epoch16.picoseconds(), 1_002_003_004.0);
+        let fill = CdfEpoch16::from_parts(9999, 12, 31, 23, 59, 59, 999, 999, 999, 999)?;
+        assert_eq!((fill.seconds(), fill.picoseconds()), (-1e31, -1e31));
+        assert!(CdfEpoch16::from_parts(2000, 1, 1, 0, 0, 0, 0, 0, 0, 1000).is_err());
+
+        let tt2000 = CdfTimeTt2000::from_utc(&TimeComponents::new(2017, 1, 1, 0, 0, 0))?;
+        assert_eq!(*tt2000, 536_500_869_184_000_000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cdf_time() -> Result<(), CdfError> {
+        let epoch = CdfEpoch::from_ymdhms(2017, 1, 1, 0, 0, 0, 5)?;
+        let epoch16 = CdfEpoch16::from_parts(2017, 1, 1, 0, 0, 0, 5, 0, 0, 7)?;
+        let tt2000 = CdfTimeTt2000::from_components(2017, 1, 1, 0, 0, 0, 5, 0, 0)?;
+        let unix = 1_483_228_800_005_000_000;
+        assert_eq!(epoch.to_unix_nanos(), Some(unix));
+        assert_eq!(epoch16.to_unix_nanos(), Some(unix));
+        assert_eq!(tt2000.to_unix_nanos(), Some(unix));
+        assert_eq!(epoch.to_iso8601(), "2017-01-01T00:00:00.005");
+        assert_eq!(epoch16.to_iso8601(), "2017-01-01T00:00:00.005000000007");
+        assert_eq!(tt2000.to_iso8601(), "2017-01-01T00:00:00.005000000");
+
+        assert_eq!(*CdfEpoch::from_unix_nanos(unix)?, *epoch);
+        assert_eq!(
+            CdfEpoch16::from_unix_nanos(unix + 3)?.to_iso8601(),
+            "2017-01-01T00:00:00.005000003000"
+        );
+        assert_eq!(*CdfTimeTt2000::from_unix_nanos(unix)?, *tt2000);
+        assert_eq!(
+            CdfEpoch::from_unix_nanos(0)?.to_iso8601(),
+            "1970-01-01T00:00:00.000"
+        );
+        assert!(CdfEpoch::from_unix_nanos(-EPOCH_UNIX_NANOS - 1).is_err());
+
+        // A time within a leap second maps to the second that follows it.
+        let leap = CdfTimeTt2000::from_components(2016, 12, 31, 23, 59, 60, 5, 0, 0)?;
+        assert_eq!(leap.to_iso8601(), "2016-12-31T23:59:60.005000000");
+        assert_eq!(leap.to_unix_nanos(), Some(unix));
+        assert_eq!(leap.compare(&tt2000), Some(Ordering::Less));
+
+        let fill = CdfTimeTt2000::from(CdfTimeTt2000::FILL);
+        assert!(fill.is_fill() && !tt2000.is_fill());
+        assert_eq!(fill.to_unix_nanos(), None);
+        assert_eq!(fill.to_iso8601(), "9999-12-31T23:59:59.999999999");
+        let fill = CdfEpoch::from(CdfEpoch::FILL);
+        assert!(fill.is_fill());
+        assert_eq!(fill.to_iso8601(), "9999-12-31T23:59:59.999");
+        let fill = CdfEpoch16::new(-1e31, -1e31);
+        assert!(fill.is_fill());
+        assert_eq!(fill.to_iso8601(), "9999-12-31T23:59:59.999999999999");
+
+        let later = CdfEpoch16::from_parts(2017, 1, 1, 0, 0, 0, 5, 0, 0, 8)?;
+        assert_eq!(epoch16.compare(&later), Some(Ordering::Less));
+        assert_eq!(epoch.compare(&CdfEpoch::from(f64::NAN)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_epoch_format() -> Result<(), CdfError> {
+        let epoch = CdfEpoch::from_ymdhms(2017, 3, 4, 5, 6, 7, 89)?;
+        let epoch16 = CdfEpoch16::from_parts(2017, 3, 4, 5, 6, 7, 89, 1, 2, 3)?;
+        let tt2000 = CdfTimeTt2000::from_components(2017, 3, 4, 5, 6, 7, 89, 1, 2)?;
+        let default = EpochFormat::default();
+        assert_eq!(epoch.format(&default), epoch.to_iso8601());
+        assert_eq!(epoch16.format(&default), epoch16.to_iso8601());
+        assert_eq!(tt2000.format(&default), tt2000.to_iso8601());
+
+        let classic = EpochFormat {
+            style: EpochStyle::Classic,
+            ..Default::default()
+        };
+        assert_eq!(epoch.format(&classic), "04-Mar-2017 05:06:07.089");
+        assert_eq!(tt2000.format(&classic), "04-Mar-2017 05:06:07.089001002");
+        let day_fraction = EpochFormat {
+            style: EpochStyle::DayFraction,
+            ..Default::default()
+        };
+        assert_eq!(epoch.format(&day_fraction), "20170304.2125820");
+        let compact = EpochFormat {
+            style: EpochStyle::Compact,
+            ..Default::default()
+        };
+        assert_eq!(epoch16.format(&compact), "20170304050607");
+
+        let iso = EpochFormat {
+            precision: Some(EpochPrecision::Microseconds),
+            separator: Some(' '),
+            zulu: true,
+            ..Default::default()
+        };
+        assert_eq!(epoch.format(&iso), "2017-03-04 05:06:07.089000Z");
+        assert_eq!(epoch16.format(&iso), "2017-03-04 05:06:07.089001Z");
+        let picoseconds = EpochFormat {
+            precision: Some(EpochPrecision::Picoseconds),
+            ..Default::default()
+        };
+        assert_eq!(
+            tt2000.format(&picoseconds),
+            "2017-03-04T05:06:07.089001002000"
+        );
+        assert_eq!(
+            format!("{}", compact.display(&tt2000)),
+            tt2000.format(&compact)
+        );
+
+        // Fill values and leap seconds keep their components in every style.
+        let fill = CdfEpoch::from(CdfEpoch::FILL);
+        assert_eq!(fill.format(&classic), "31-Dec-9999 23:59:59.999");
+        let leap = CdfTimeTt2000::from_components(2016, 12, 31, 23, 59, 60, 500, 0, 0)?;
+        assert_eq!(leap.format(&day_fraction), "20161231.9999999999999");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_iso8601() -> Result<(), CdfError> {
+        let c = TimeComponents::parse_iso8601("2016-12-31T23:59:60.123456789")?;
+        assert_eq!(
+            (c.second, c.millisecond, c.microsecond, c.nanosecond),
+            (60, 123, 456, 789)
+        );
+        let tt2000 = CdfTimeTt2000::from_utc(&c)?;
+        assert_eq!(
+            TimeComponents::parse_iso8601(&tt2000.to_iso8601())?,
+            tt2000.to_components()
+        );
+        assert_eq!(
+            TimeComponents::parse_iso8601("2001-02-03 04:05Z")?,
+            TimeComponents::new(2001, 2, 3, 4, 5, 0)
+        );
+        let c = TimeComponents::parse_iso8601("2001-02-03T04:05:06.7")?;
+        assert_eq!(c.millisecond, 700);
+        assert_eq!(
+            TimeComponents::parse_iso8601("2001-02-03")?,
+            TimeComponents::new(2001, 2, 3, 0, 0, 0)
+        );
+        for invalid in [
+            "2001-02",
+            "2001-02-30",
+            "2001-02-03T25:00",
+            "2001-02-03T04:05:06.x",
+        ] {
+            assert!(TimeComponents::parse_iso8601(invalid).is_err(), "{invalid}");
+        }
+        Ok(())
+    }
+}
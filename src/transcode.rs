@@ -0,0 +1,1003 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::cdf::{Cdf, ReadOptions};
+use crate::dataset::unix_nanos;
+use crate::decode::{checked_usize, Decodable, Decoder};
+use crate::error::CdfError;
+use crate::io;
+use crate::record::adr::AttributeDescriptorRecord;
+use crate::types::{CdfInt4, CdfType, DataType};
+use crate::variable::{is_reference_attribute, Variable};
+use crate::writer::{AttributeValue, CdfWriter, CdfWriterBuilder, VariableSpec};
+
+/// Changes applied by [`transcode`] to the variables, attributes and records of a CDF file while it
+/// is copied.
+/// All methods do nothing by default, so a transform only implements the changes it makes.
+///
+/// Transforms are chained with tuples, where the first transform is applied first.
+pub trait RecordTransform {
+    /// Describe the variable of the new file that `variable` is copied to, starting from `spec`
+    /// which describes it as it is. Returns `None` to drop the variable.
+    fn variable(&mut self, variable: &Variable, spec: VariableSpec) -> Option<VariableSpec> {
+        _ = variable;
+        Some(spec)
+    }
+
+    /// Name in the new file of the attribute called `name`.
+    fn attribute_name(&mut self, name: &str) -> String {
+        name.to_string()
+    }
+
+    /// Reorder the attributes of the new file, given by their names in the new file and in the
+    /// order of the source file, for tools that expect the attributes in a given order.
+    fn order_attributes(&mut self, names: &mut [String]) {
+        _ = names;
+    }
+
+    /// Change an entry of the attribute called `name` in the source file, which describes
+    /// `variable` for a variable attribute and the whole file for a global attribute. Returns
+    /// `None` to drop the entry.
+    fn attribute(
+        &mut self,
+        name: &str,
+        variable: Option<&Variable>,
+        value: AttributeValue,
+    ) -> Option<AttributeValue> {
+        _ = (name, variable);
+        Some(value)
+    }
+
+    /// Number of records of `variable` to copy, out of its `n_records` first records, to copy a
+    /// subset of the records.
+    fn n_records(&mut self, variable: &Variable, n_records: usize) -> usize {
+        _ = variable;
+        n_records
+    }
+
+    /// Change a chunk of `records` of `variable` before they are written, in place. The records
+    /// written must match the description returned by [`RecordTransform::variable`].
+    /// # Errors
+    /// A [`CdfError`] returned here stops the copy.
+    fn records(
+        &mut self,
+        variable: &Variable,
+        records: &mut Vec<Vec<CdfType>>,
+    ) -> Result<(), CdfError> {
+        _ = (variable, records);
+        Ok(())
+    }
+}
+
+impl RecordTransform for () {}
+
+impl<T: RecordTransform + ?Sized> RecordTransform for &mut T {
+    fn variable(&mut self, variable: &Variable, spec: VariableSpec) -> Option<VariableSpec> {
+        (**self).variable(variable, spec)
+    }
+
+    fn attribute_name(&mut self, name: &str) -> String {
+        (**self).attribute_name(name)
+    }
+
+    fn order_attributes(&mut self, names: &mut [String]) {
+        (**self).order_attributes(names);
+    }
+
+    fn attribute(
+        &mut self,
+        name: &str,
+        variable: Option<&Variable>,
+        value: AttributeValue,
+    ) -> Option<AttributeValue> {
+        (**self).attribute(name, variable, value)
+    }
+
+    fn n_records(&mut self, variable: &Variable, n_records: usize) -> usize {
+        (**self).n_records(variable, n_records)
+    }
+
+    fn records(
+        &mut self,
+        variable: &Variable,
+        records: &mut Vec<Vec<CdfType>>,
+    ) -> Result<(), CdfError> {
+        (**self).records(variable, records)
+    }
+}
+
+impl<A: RecordTransform, B: RecordTransform> RecordTransform for (A, B) {
+    fn variable(&mut self, variable: &Variable, spec: VariableSpec) -> Option<VariableSpec> {
+        let spec = self.0.variable(variable, spec)?;
+        self.1.variable(variable, spec)
+    }
+
+    fn attribute_name(&mut self, name: &str) -> String {
+        let name = self.0.attribute_name(name);
+        self.1.attribute_name(&name)
+    }
+
+    fn order_attributes(&mut self, names: &mut [String]) {
+        self.0.order_attributes(names);
+        self.1.order_attributes(names);
+    }
+
+    fn attribute(
+        &mut self,
+        name: &str,
+        variable: Option<&Variable>,
+        value: AttributeValue,
+    ) -> Option<AttributeValue> {
+        let value = self.0.attribute(name, variable, value)?;
+        self.1.attribute(name, variable, value)
+    }
+
+    fn n_records(&mut self, variable: &Variable, n_records: usize) -> usize {
+        let n_records = self.0.n_records(variable, n_records);
+        self.1.n_records(variable, n_records)
+    }
+
+    fn records(
+        &mut self,
+        variable: &Variable,
+        records: &mut Vec<Vec<CdfType>>,
+    ) -> Result<(), CdfError> {
+        self.0.records(variable, records)?;
+        self.1.records(variable, records)
+    }
+}
+
+/// Give a variable another name.
+#[derive(Debug, Clone)]
+pub struct RenameVariable {
+    from: String,
+    to: String,
+    update_references: bool,
+}
+
+impl RenameVariable {
+    /// Rename the variable called `from` to `to`.
+    pub fn new(from: &str, to: &str) -> Self {
+        RenameVariable {
+            from: from.to_string(),
+            to: to.to_string(),
+            update_references: false,
+        }
+    }
+
+    /// Also rename the variable in the entries of variable attributes that refer to it by name,
+    /// such as `DEPEND_0` or `LABL_PTR_1`, see [`is_reference_attribute`].
+    pub fn update_references(mut self, update_references: bool) -> Self {
+        self.update_references = update_references;
+        self
+    }
+}
+
+impl RecordTransform for RenameVariable {
+    fn variable(&mut self, variable: &Variable, spec: VariableSpec) -> Option<VariableSpec> {
+        if variable.name() == self.from {
+            Some(spec.rename(&self.to))
+        } else {
+            Some(spec)
+        }
+    }
+
+    fn attribute(
+        &mut self,
+        name: &str,
+        variable: Option<&Variable>,
+        value: AttributeValue,
+    ) -> Option<AttributeValue> {
+        let refers = matches!(value.values(), [CdfType::String(s)] if **s == self.from);
+        if self.update_references && variable.is_some() && refers && is_reference_attribute(name) {
+            Some(AttributeValue::from(self.to.as_str()))
+        } else {
+            Some(value)
+        }
+    }
+}
+
+/// Leave variables out of the new file.
+#[derive(Debug, Clone)]
+pub struct DropVariables {
+    names: Vec<String>,
+}
+
+impl DropVariables {
+    /// Drop the variables called `names`.
+    pub fn new<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        DropVariables {
+            names: names.into_iter().map(|s| s.as_ref().to_string()).collect(),
+        }
+    }
+}
+
+impl RecordTransform for DropVariables {
+    fn variable(&mut self, variable: &Variable, spec: VariableSpec) -> Option<VariableSpec> {
+        (!self.names.iter().any(|name| name == variable.name())).then_some(spec)
+    }
+}
+
+/// Give an attribute another name.
+#[derive(Debug, Clone)]
+pub struct RenameAttribute {
+    from: String,
+    to: String,
+}
+
+impl RenameAttribute {
+    /// Rename the attribute called `from` to `to`.
+    pub fn new(from: &str, to: &str) -> Self {
+        RenameAttribute {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+}
+
+impl RecordTransform for RenameAttribute {
+    fn attribute_name(&mut self, name: &str) -> String {
+        if name == self.from {
+            self.to.clone()
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+/// Put some attributes first in the new file, in a given order.
+#[derive(Debug, Clone)]
+pub struct OrderAttributes {
+    names: Vec<String>,
+}
+
+impl OrderAttributes {
+    /// Put the attributes called `names` in the new file first, in the order of `names`, followed
+    /// by the other attributes in their order.
+    pub fn new<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        OrderAttributes {
+            names: names.into_iter().map(|s| s.as_ref().to_string()).collect(),
+        }
+    }
+}
+
+impl RecordTransform for OrderAttributes {
+    fn order_attributes(&mut self, names: &mut [String]) {
+        names.sort_by_key(|name| {
+            self.names
+                .iter()
+                .position(|n| n == name)
+                .unwrap_or(self.names.len())
+        });
+    }
+}
+
+/// Copy the CDF file read from `reader` to `writer`, applying `transform` to its variables,
+/// attributes and records, and return the writer.
+///
+/// Only the metadata of the file is decoded. The records of each variable are then read and
+/// written one chunk at a time, as many records as the blocking factor of the new variable, so the
+/// whole file is never held in memory. Compressed files are decompressed in memory, and variables
+/// keep their compression. Entries of variable attributes follow their variable, and entries of
+/// global attributes keep their entry numbers, including gaps left by entries that are dropped.
+/// Records of types this crate does not know are not copied, since the file offsets they may hold
+/// would point into the layout of the source file; use [`copy_lossless`] to keep them.
+///
+/// ```
+/// use cdf::cdf::Cdf;
+/// use cdf::transcode::{transcode, DropVariables, RenameVariable};
+/// use std::io::Cursor;
+///
+/// # let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/data/ulysses.cdf");
+/// let reader = Cursor::new(std::fs::read(path).unwrap());
+/// let transform = (
+///     RenameVariable::new("B_scalar", "B_mag"),
+///     DropVariables::new(["BR_RTN"]),
+/// );
+/// let writer = transcode(reader, Cursor::new(Vec::new()), transform).unwrap();
+///
+/// let cdf = Cdf::from_bytes(writer.get_ref()).unwrap();
+/// assert!(cdf.variable("B_mag").is_ok());
+/// assert!(cdf.variable("BR_RTN").is_err());
+/// ```
+/// # Errors
+/// Returns a [`CdfError`] if the file cannot be decoded, if a transform fails or returns records
+/// that do not match their variable, or if the new file cannot be written.
+pub fn transcode<R, W, T>(reader: R, writer: W, mut transform: T) -> Result<W, CdfError>
+where
+    R: io::Read + io::Seek + Send + 'static,
+    W: io::Write + io::Seek,
+    T: RecordTransform,
+{
+    let options = ReadOptions {
+        lazy: true,
+        decompress: true,
+        ..Default::default()
+    };
+    let source = Cdf::from_reader(reader, options)?;
+    let mut builder = writer_builder(&source)?;
+
+    // Variables that are kept, along with their name in the new file.
+    let mut variables = Vec::new();
+    for variable in source.variables() {
+        let spec = VariableSpec::from_variable(&variable)?;
+        if let Some(spec) = transform.variable(&variable, spec) {
+            variables.push((variable, spec.name().to_string()));
+            builder = builder.variable(spec);
+        }
+    }
+    let mut writer = builder.build(writer)?;
+    copy_attributes(&source, &variables, &mut writer, &mut transform)?;
+
+    for (variable, name) in &variables {
+        let chunk = writer.blocking_factor(name).unwrap_or(1);
+        let n_records = variable.n_records();
+        let n_records = transform.n_records(variable, n_records).min(n_records);
+        for start in (0..n_records).step_by(chunk) {
+            let mut records: Vec<_> = variable
+                .read_records(start..n_records.min(start + chunk))?
+                .into_iter()
+                .map(|record| record.data)
+                .collect();
+            transform.records(variable, &mut records)?;
+            writer.write_records(name, &records)?;
+        }
+    }
+    writer.close()
+}
+
+// A builder for a file with the encoding, majority and rVariable dimensions of `source`.
+fn writer_builder(source: &Cdf) -> Result<CdfWriterBuilder, CdfError> {
+    let r_dim_sizes = source
+        .cdr
+        .gdr
+        .size_r_dims
+        .iter()
+        .map(|s| checked_usize("size_r_dims", **s))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(CdfWriter::builder()
+        .encoding(source.cdr.encoding.clone())
+        .row_major(source.cdr.flags.row_major)
+        .r_dim_sizes(&r_dim_sizes))
+}
+
+// Write the attributes of `source` through `transform`, with the entries of variable attributes
+// for the kept `variables` only.
+fn copy_attributes<W, T>(
+    source: &Cdf,
+    variables: &[(Variable, String)],
+    writer: &mut CdfWriter<W>,
+    transform: &mut T,
+) -> Result<(), CdfError>
+where
+    W: io::Write + io::Seek,
+    T: RecordTransform,
+{
+    // Attributes by their name in the new file, in the order given by the transform.
+    let mut attributes: Vec<(String, &AttributeDescriptorRecord)> = Vec::new();
+    for adr in &source.cdr.gdr.adr_vec {
+        let name = transform.attribute_name(&adr.name);
+        if attributes.iter().any(|(n, _)| *n == name) {
+            return Err(CdfError::Other(format!(
+                "Several attributes are renamed to {name}."
+            )));
+        }
+        attributes.push((name, adr));
+    }
+    let mut names: Vec<String> = attributes.iter().map(|(name, _)| name.clone()).collect();
+    transform.order_attributes(&mut names);
+
+    for name in &names {
+        let Some(k) = attributes.iter().position(|(n, _)| n == name) else {
+            return Err(CdfError::Other(format!(
+                "Attribute {name} is not in the file or is ordered twice."
+            )));
+        };
+        let (name, adr) = attributes.swap_remove(k);
+        if matches!(*adr.scope, 1 | 3) {
+            let mut attribute = writer.global_attr(&name)?;
+            for entry in &adr.agredr_vec {
+                let value = entry_value(&entry.data_type, &entry.num_strings, &entry.value)?;
+                if let Some(value) = transform.attribute(&adr.name, None, value) {
+                    attribute = attribute.entry_at(checked_usize("num", *entry.num)?, value);
+                }
+            }
+        } else {
+            for (variable, variable_name) in variables {
+                let Some(value) = variable_entry(adr, variable)? else {
+                    continue;
+                };
+                if let Some(value) = transform.attribute(&adr.name, Some(variable), value) {
+                    writer.var_attr(variable_name, &name, value)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// The entry of the variable attribute `adr` for `variable`, if it has one.
+fn variable_entry(
+    adr: &AttributeDescriptorRecord,
+    variable: &Variable,
+) -> Result<Option<AttributeValue>, CdfError> {
+    let num = **variable.num();
+    let entry = if variable.is_zvariable() {
+        adr.azedr_vec
+            .iter()
+            .find(|e| *e.num == num)
+            .map(|e| (&e.data_type, &e.num_strings, &e.value))
+    } else {
+        adr.agredr_vec
+            .iter()
+            .find(|e| *e.num == num)
+            .map(|e| (&e.data_type, &e.num_strings, &e.value))
+    };
+    entry
+        .map(|(data_type, num_strings, value)| entry_value(data_type, num_strings, value))
+        .transpose()
+}
+
+fn entry_value(
+    data_type: &CdfInt4,
+    num_strings: &CdfInt4,
+    value: &[CdfType],
+) -> Result<AttributeValue, CdfError> {
+    Ok(
+        AttributeValue::new(DataType::try_from(data_type.clone())?, value.to_vec())?
+            .with_num_strings(checked_usize("num_strings", **num_strings)?),
+    )
+}
+
+/// Concatenate the records of the CDF files read from `readers` into one file written to
+/// `writer`, in the order of the readers, and return the writer. This consolidates daily files
+/// into monthly ones, for instance.
+///
+/// All files must hold the same variables, with the same data types and record shapes. Records
+/// are copied one chunk at a time as in [`transcode`]. Variables that do not vary across records
+/// keep the records of the first file, and so do the attributes. The times of variables holding
+/// epochs must not go back, within a file or from one file to the next, so that the merged file
+/// stays sorted. Fill values are ignored.
+///
+/// ```
+/// use cdf::cdf::Cdf;
+/// use cdf::transcode::merge;
+/// use cdf::types::{CdfEpoch, CdfType, DataType};
+/// use cdf::writer::{CdfWriter, VariableSpec};
+/// use std::io::Cursor;
+///
+/// let day = |epoch: f64| {
+///     let mut writer = CdfWriter::builder()
+///         .variable(VariableSpec::new("Epoch", DataType::Epoch))
+///         .build(Cursor::new(Vec::new()))
+///         .unwrap();
+///     let record = vec![CdfType::Epoch(CdfEpoch::from(epoch))];
+///     writer.write_records("Epoch", &[record]).unwrap();
+///     Cursor::new(writer.close().unwrap().into_inner())
+/// };
+/// let readers = vec![day(6.3e13), day(6.3e13 + 86_400_000.0)];
+/// let writer = merge(readers, Cursor::new(Vec::new())).unwrap();
+///
+/// let cdf = Cdf::from_bytes(writer.get_ref()).unwrap();
+/// assert_eq!(cdf.variable("Epoch").unwrap().n_records(), 2);
+/// ```
+/// # Errors
+/// Returns a [`CdfError`] if there are no readers, if a file cannot be decoded, if the variables
+/// of the files differ, if times go back, or if the new file cannot be written.
+pub fn merge<R, W>(readers: Vec<R>, writer: W) -> Result<W, CdfError>
+where
+    R: io::Read + io::Seek + Send + 'static,
+    W: io::Write + io::Seek,
+{
+    let options = ReadOptions {
+        lazy: true,
+        decompress: true,
+        ..Default::default()
+    };
+    let sources = readers
+        .into_iter()
+        .map(|reader| Cdf::from_reader(reader, options.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let Some(first) = sources.first() else {
+        return Err(CdfError::Other("There are no files to merge.".to_string()));
+    };
+
+    let mut builder = writer_builder(first)?;
+    let mut variables = Vec::new();
+    for variable in first.variables() {
+        let spec = VariableSpec::from_variable(&variable)?;
+        variables.push((variable, spec.name().to_string()));
+        builder = builder.variable(spec);
+    }
+    // The variables of each file, in the order of the variables of the first file.
+    let mut inputs = Vec::with_capacity(sources.len());
+    for (k, source) in sources.iter().enumerate() {
+        if let Some(extra) = source
+            .variables()
+            .into_iter()
+            .find(|v| first.variable(v.name()).is_err())
+        {
+            return Err(CdfError::Other(format!(
+                "Variable {} of file {} is not in the first file.",
+                extra.name(),
+                k + 1
+            )));
+        }
+        let mut matched = Vec::with_capacity(variables.len());
+        for (variable, _) in &variables {
+            let other = source.variable(variable.name()).map_err(|_| {
+                CdfError::Other(format!(
+                    "Variable {} is missing from file {}.",
+                    variable.name(),
+                    k + 1
+                ))
+            })?;
+            if other.data_type() != variable.data_type()
+                || other.record_shape()? != variable.record_shape()?
+                || other.num_elements()? != variable.num_elements()?
+            {
+                return Err(CdfError::Other(format!(
+                    "Variable {} of file {} does not have the data type and shape it has in the \
+                     first file.",
+                    variable.name(),
+                    k + 1
+                )));
+            }
+            matched.push(other);
+        }
+        inputs.push(matched);
+    }
+
+    let mut writer = builder.build(writer)?;
+    copy_attributes(first, &variables, &mut writer, &mut ())?;
+
+    for (i, (variable, name)) in variables.iter().enumerate() {
+        let chunk = writer.blocking_factor(name).unwrap_or(1);
+        let files = if variable.record_varies() {
+            inputs.len()
+        } else {
+            1
+        };
+        let mut last = None;
+        for (k, input) in inputs.iter().take(files).enumerate() {
+            let input = &input[i];
+            let n_records = input.n_records();
+            for start in (0..n_records).step_by(chunk) {
+                let records: Vec<_> = input
+                    .read_records(start..n_records.min(start + chunk))?
+                    .into_iter()
+                    .map(|record| record.data)
+                    .collect();
+                for (j, record) in records.iter().enumerate() {
+                    let Some(time) = record.first().and_then(|t| unix_nanos(t).ok().flatten())
+                    else {
+                        continue;
+                    };
+                    if last.is_some_and(|last| time < last) {
+                        return Err(CdfError::Other(format!(
+                            "Times of variable {name} go back at record {} of file {}.",
+                            start + j,
+                            k + 1
+                        )));
+                    }
+                    last = Some(time);
+                }
+                writer.write_records(name, &records)?;
+            }
+        }
+    }
+    writer.close()
+}
+
+/// Copy the CDF file read from `reader`, from its start, to `writer` byte for byte, and return the
+/// writer.
+///
+/// All records are decoded, keeping their exact bytes as with
+/// [`ReadOptions::keep_raw`], and written back at their offsets, so that record order, reserved
+/// fields and padding are kept. Bytes that no decoded record covers, such as unused internal
+/// records (UIRs) or records left behind by edits, are copied as they are. Unlike [`transcode`],
+/// nothing is encoded again, which makes the copy a safe starting point for targeted edits, and
+/// the copy can be compared with the output of the encoder for the same records.
+///
+/// ```
+/// use cdf::transcode::copy_lossless;
+/// use std::io::Cursor;
+///
+/// # let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/data/ulysses.cdf");
+/// let bytes = std::fs::read(path).unwrap();
+/// let writer = copy_lossless(Cursor::new(&bytes), Vec::new()).unwrap();
+/// assert_eq!(writer, bytes);
+/// ```
+/// # Errors
+/// Returns a [`CdfError`] if the file cannot be decoded or read, or if the copy cannot be written.
+pub fn copy_lossless<R, W>(mut reader: R, mut writer: W) -> Result<W, CdfError>
+where
+    R: io::Read + io::Seek,
+    W: io::Write,
+{
+    _ = reader.seek(io::SeekFrom::Start(0))?;
+    let mut decoder = Decoder::builder()
+        .decompress(true)
+        .keep_raw(true)
+        .build(&mut reader)?;
+    let cdf = Cdf::decode_be(&mut decoder)?;
+    drop(decoder);
+
+    let mut position = 0;
+    for (offset, bytes) in cdf.raw_records() {
+        if *offset > position {
+            let mut gap = vec![0; usize::try_from(offset - position)?];
+            _ = reader.seek(io::SeekFrom::Start(position))?;
+            reader.read_exact(&mut gap)?;
+            writer.write_all(&gap)?;
+            position = *offset;
+        }
+        let skip = usize::try_from(position - offset)?.min(bytes.len());
+        writer.write_all(&bytes[skip..])?;
+        position = position.max(offset + bytes.len() as u64);
+    }
+    Ok(writer)
+}
+
+/// Concatenate the records of the CDF files at `input_paths` into a new file created at
+/// `output_path`, see [`merge`].
+/// # Errors
+/// Returns a [`CdfError`] if a file cannot be opened, or if the merge fails.
+#[cfg(feature = "std-fs")]
+pub fn merge_files<P, Q>(input_paths: &[P], output_path: Q) -> Result<(), CdfError>
+where
+    P: AsRef<std::path::Path>,
+    Q: AsRef<std::path::Path>,
+{
+    use std::{fs::File, io::BufWriter};
+
+    let readers = input_paths
+        .iter()
+        .map(|path| Ok(io::BufferedReader::new(File::open(path)?)))
+        .collect::<Result<Vec<_>, CdfError>>()?;
+    let writer = BufWriter::new(File::create(output_path)?);
+    _ = merge(readers, writer)?;
+    Ok(())
+}
+
+/// Copy the CDF file at `input_path` to a new file created at `output_path`, applying
+/// `transform`, see [`transcode`].
+/// # Errors
+/// Returns a [`CdfError`] if a file cannot be opened, or if the copy fails.
+#[cfg(feature = "std-fs")]
+pub fn transcode_file<P, Q, T>(input_path: P, output_path: Q, transform: T) -> Result<(), CdfError>
+where
+    P: AsRef<std::path::Path>,
+    Q: AsRef<std::path::Path>,
+    T: RecordTransform,
+{
+    use std::{fs::File, io::BufWriter};
+
+    let reader = io::BufferedReader::new(File::open(input_path)?);
+    let writer = BufWriter::new(File::create(output_path)?);
+    _ = transcode(reader, writer, transform)?;
+    Ok(())
+}
+
+/// Copy the CDF file at `input_path` byte for byte to a new file created at `output_path`, see
+/// [`copy_lossless`].
+/// # Errors
+/// Returns a [`CdfError`] if a file cannot be opened, or if the copy fails.
+#[cfg(feature = "std-fs")]
+pub fn copy_lossless_file<P, Q>(input_path: P, output_path: Q) -> Result<(), CdfError>
+where
+    P: AsRef<std::path::Path>,
+    Q: AsRef<std::path::Path>,
+{
+    use std::{fs::File, io::BufWriter};
+
+    let reader = io::BufferedReader::new(File::open(input_path)?);
+    let writer = BufWriter::new(File::create(output_path)?);
+    _ = copy_lossless(reader, writer)?;
+    Ok(())
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use super::*;
+    use crate::types::{CdfEpoch, CdfReal4, CdfReal8};
+    use alloc::{format, vec};
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    fn _reader(name: &str) -> Result<Cursor<Vec<u8>>, CdfError> {
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "examples", "data", name]
+            .iter()
+            .collect();
+        Ok(Cursor::new(std::fs::read(path)?))
+    }
+
+    // Doubles the values of B_scalar, whose units change accordingly, and drops the TEXT global
+    // attribute.
+    struct Double;
+
+    impl RecordTransform for Double {
+        fn attribute(
+            &mut self,
+            name: &str,
+            variable: Option<&Variable>,
+            value: AttributeValue,
+        ) -> Option<AttributeValue> {
+            match (name, variable.map(|v| v.name())) {
+                ("TEXT", None) => None,
+                ("UNITS", Some("B_scalar")) => Some(AttributeValue::from("2 nT")),
+                _ => Some(value),
+            }
+        }
+
+        fn records(
+            &mut self,
+            variable: &Variable,
+            records: &mut Vec<Vec<CdfType>>,
+        ) -> Result<(), CdfError> {
+            if variable.name() == "B_scalar" {
+                for value in records.iter_mut().flatten() {
+                    if let CdfType::Real4(v) = value {
+                        *value = CdfType::Real4(CdfReal4::from(**v * 2.0));
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_transcode_identity() -> Result<(), CdfError> {
+        let source = Cdf::from_cursor(_reader("test_alltypes.cdf")?)?;
+        let writer = transcode(_reader("test_alltypes.cdf")?, Cursor::new(Vec::new()), ())?;
+        let copy = Cdf::from_bytes(writer.get_ref())?;
+        assert_eq!(copy.variables().len(), source.variables().len());
+        for variable in source.variables() {
+            let copied = copy.variable(variable.name())?;
+            assert_eq!(copied.is_zvariable(), variable.is_zvariable());
+            assert_eq!(copied.data_type(), variable.data_type());
+            for (x, y) in copied.read_all()?.iter().zip(&variable.read_all()?) {
+                assert_eq!(format!("{:?}", x.data), format!("{:?}", y.data));
+            }
+            assert_eq!(copied.n_records(), variable.n_records());
+            for ((name, x), (_, y)) in copied.attributes().iter().zip(variable.attributes().iter())
+            {
+                assert_eq!(format!("{name} {x:?}"), format!("{name} {y:?}"));
+            }
+            assert_eq!(
+                copied.attributes().iter().count(),
+                variable.attributes().iter().count()
+            );
+        }
+        let names = |cdf: &Cdf| -> Vec<String> {
+            cdf.cdr
+                .gdr
+                .adr_vec
+                .iter()
+                .map(|adr| adr.name.to_string())
+                .collect()
+        };
+        assert_eq!(names(&copy), names(&source));
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_lossless() -> Result<(), CdfError> {
+        for name in ["test_alltypes.cdf", "ulysses.cdf"] {
+            let source = _reader(name)?;
+            let writer = copy_lossless(source.clone(), Vec::new())?;
+            assert!(writer == *source.get_ref(), "{name} was not copied");
+        }
+
+        // Renaming the epoch moves the entries that refer to it to the end of the file, leaving
+        // the old ones behind as bytes that no record links to.
+        let mut edited = _reader("ulysses.cdf")?;
+        crate::edit::rename_variable(&mut edited, "Epoch", "Epoch_UT", true)?;
+        let writer = copy_lossless(edited.clone(), Vec::new())?;
+        assert!(writer == *edited.get_ref());
+        let options = ReadOptions {
+            keep_raw: true,
+            ..Default::default()
+        };
+        let mut zeroed = Vec::new();
+        Cdf::from_bytes_with(edited.get_ref(), options)?.write_raw(&mut zeroed)?;
+        assert!(zeroed != *edited.get_ref());
+
+        assert!(copy_lossless(Cursor::new(vec![0u8; 64]), Vec::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_transcode_entry_numbers() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("Epoch", DataType::Epoch))
+            .build(Cursor::new(Vec::new()))?;
+        writer
+            .global_attr("Logical_source")?
+            .entry_at(5, "five")
+            .entry_at(0, "zero")
+            .entry_at(2, "two")
+            .entry("six")
+            .entry_at(2, "two again");
+        let source = writer.close()?.into_inner();
+        let expected = [(0, "zero"), (2, "two again"), (5, "five"), (6, "six")];
+        let numbered = |cdf: &Cdf| -> Vec<(usize, String)> {
+            cdf.global_attributes()
+                .numbered_entries("Logical_source")
+                .unwrap()
+                .unwrap_or_default()
+                .iter()
+                .map(|(num, value)| (*num, format!("{value:?}")))
+                .collect()
+        };
+        let expected: Vec<(usize, String)> = expected
+            .iter()
+            .map(|(num, s)| (*num, format!("[String({s})]")))
+            .collect();
+        assert_eq!(numbered(&Cdf::from_bytes(&source)?), expected);
+
+        let copy = transcode(Cursor::new(source), Cursor::new(Vec::new()), ())?;
+        let copy = Cdf::from_bytes(copy.get_ref())?;
+        assert_eq!(numbered(&copy), expected);
+        assert_eq!(*copy.cdr.gdr.adr_vec[0].max_gr_entry, 6);
+
+        // A negative entry number is an error rather than entry 0.
+        let mut copy = copy;
+        copy.cdr.gdr.adr_vec[0].agredr_vec[0].num = CdfInt4::from(-1);
+        assert!(copy
+            .global_attributes()
+            .numbered_entries("Logical_source")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_transcode_transforms() -> Result<(), CdfError> {
+        let source = Cdf::from_cursor(_reader("ulysses.cdf")?)?;
+        let transform = (
+            (RenameVariable::new("B_scalar", "B_mag"), Double),
+            DropVariables::new(["BR_RTN"]),
+        );
+        let writer = transcode(_reader("ulysses.cdf")?, Cursor::new(Vec::new()), transform)?;
+        let copy = Cdf::from_bytes(writer.get_ref())?;
+
+        assert!(copy.variable("B_scalar").is_err());
+        assert!(copy.variable("BR_RTN").is_err());
+        assert_eq!(copy.variables().len(), source.variables().len() - 1);
+        let original = source.variable("B_scalar")?.read_all()?;
+        let doubled = copy.variable("B_mag")?.read_all()?;
+        assert_eq!(original.len(), doubled.len());
+        for (a, b) in original.iter().zip(&doubled) {
+            match (a.data.as_slice(), b.data.as_slice()) {
+                ([CdfType::Real4(a)], [CdfType::Real4(b)]) => assert_eq!(**a * 2.0, **b),
+                _ => panic!("B_scalar should hold Real4 values."),
+            }
+        }
+        let b_mag = copy.variable("B_mag")?;
+        assert_eq!(b_mag.attribute_str("UNITS"), Some("2 nT"));
+        assert_eq!(b_mag.attribute_str("DEPEND_0"), Some("Epoch"));
+        let text = copy.cdr.gdr.adr_vec.iter().find(|adr| *adr.name == "TEXT");
+        assert_eq!(text.map(|adr| adr.agredr_vec.len()), Some(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_transcode_attributes() -> Result<(), CdfError> {
+        let source = Cdf::from_cursor(_reader("ulysses.cdf")?)?;
+        let transform = (
+            (
+                RenameAttribute::new("UNITS", "Units"),
+                RenameAttribute::new("TEXT", "Text"),
+            ),
+            OrderAttributes::new(["Units", "Project", "Missing"]),
+        );
+        let writer = transcode(_reader("ulysses.cdf")?, Cursor::new(Vec::new()), transform)?;
+        let copy = Cdf::from_bytes(writer.get_ref())?;
+
+        let names = |cdf: &Cdf| -> Vec<String> {
+            cdf.cdr
+                .gdr
+                .adr_vec
+                .iter()
+                .map(|adr| adr.name.to_string())
+                .collect()
+        };
+        // DICT_KEY has no entries, so it is not copied.
+        let mut expected: Vec<String> = names(&source)
+            .into_iter()
+            .filter(|name| !["UNITS", "Project", "DICT_KEY"].contains(&name.as_str()))
+            .map(|name| {
+                if name == "TEXT" {
+                    "Text".to_string()
+                } else {
+                    name
+                }
+            })
+            .collect();
+        expected.splice(0..0, ["Units".to_string(), "Project".to_string()]);
+        assert_eq!(names(&copy), expected);
+        let b_scalar = copy.variable("B_scalar")?;
+        assert_eq!(b_scalar.attribute_str("Units"), Some("nT"));
+        assert!(b_scalar.attribute("UNITS").is_none());
+
+        let transform = (
+            RenameAttribute::new("UNITS", "Units"),
+            RenameAttribute::new("FIELDNAM", "Units"),
+        );
+        let result = transcode(_reader("ulysses.cdf")?, Cursor::new(Vec::new()), transform);
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    fn _day(name: &str, data_type: DataType, epochs: &[f64]) -> Result<Cursor<Vec<u8>>, CdfError> {
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("Epoch", DataType::Epoch))
+            .variable(VariableSpec::new(name, data_type))
+            .build(Cursor::new(Vec::new()))?;
+        let times: Vec<_> = epochs
+            .iter()
+            .map(|&t| vec![CdfType::Epoch(CdfEpoch::from(t))])
+            .collect();
+        writer.write_records("Epoch", &times)?;
+        let values: Vec<_> = (0..epochs.len())
+            .map(|i| match data_type {
+                DataType::Real8 => vec![CdfType::Real8(CdfReal8::from(i as f64))],
+                _ => vec![CdfType::Real4(CdfReal4::from(i as f32))],
+            })
+            .collect();
+        writer.write_records(name, &values)?;
+        writer.var_attr(name, "DEPEND_0", "Epoch")?;
+        Ok(Cursor::new(writer.close()?.into_inner()))
+    }
+
+    #[test]
+    fn test_merge() -> Result<(), CdfError> {
+        let readers = vec![
+            _day("B", DataType::Real4, &[1.0e13, 2.0e13])?,
+            _day("B", DataType::Real4, &[3.0e13, 4.0e13, 5.0e13])?,
+        ];
+        let writer = merge(readers, Cursor::new(Vec::new()))?;
+        let cdf = Cdf::from_bytes(writer.get_ref())?;
+        let b = cdf.variable("B")?;
+        assert_eq!(b.n_records(), 5);
+        assert_eq!(b.attribute_str("DEPEND_0"), Some("Epoch"));
+        assert_eq!(
+            format!("{:?}", b.read_records(1..3)?[1].data),
+            format!("{:?}", vec![CdfType::Real4(CdfReal4::from(0.0))])
+        );
+        assert_eq!(cdf.variable("Epoch")?.n_records(), 5);
+
+        // Times that go back, variables that differ, and no files at all.
+        let readers = vec![
+            _day("B", DataType::Real4, &[1.0e13, 2.0e13])?,
+            _day("B", DataType::Real4, &[1.5e13])?,
+        ];
+        let err = merge(readers, Cursor::new(Vec::new())).unwrap_err();
+        assert!(err.to_string().contains("go back at record 0 of file 2"));
+        let readers = vec![
+            _day("B", DataType::Real4, &[1.0e13])?,
+            _day("B", DataType::Real8, &[2.0e13])?,
+        ];
+        assert!(merge(readers, Cursor::new(Vec::new())).is_err());
+        let readers = vec![
+            _day("B", DataType::Real4, &[1.0e13])?,
+            _day("C", DataType::Real4, &[2.0e13])?,
+        ];
+        let err = merge(readers, Cursor::new(Vec::new())).unwrap_err();
+        assert!(err.to_string().contains("Variable C of file 2"));
+        assert!(merge(Vec::<Cursor<Vec<u8>>>::new(), Cursor::new(Vec::new())).is_err());
+        Ok(())
+    }
+}
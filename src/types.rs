@@ -1,20 +1,25 @@
+use crate::decode::{checked_usize, Decodable, DecodeWarning, Decoder};
+use crate::encode::{Encodable, Encoder};
+use crate::error::CdfError;
 /// The CDF format supports different data types like ints and floats of
 /// different sizes. This module defines these fundamental types (CdfXXXX) and
 /// there conversions from and into byte arrays and native Rust types.
-use crate::decode::{Decodable, Decoder};
-use crate::error::CdfError;
+use alloc::{format, string::String, vec, vec::Vec};
 
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use std::fmt::{self, Debug, Display, Formatter};
-use std::io;
-use std::mem;
-use std::ops::Deref;
+use crate::io;
+use core::fmt::{self, Debug, Display, Formatter};
+use core::mem;
+use core::ops::Deref;
 
 macro_rules! impl_cdf_type {
     ($cdf_type:ident, $rust_type:ty) => {
         #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+        #[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
         #[derive(PartialEq, Clone)]
         #[doc = concat!("CDF-consistent type that is a wrapper around [`", stringify!($rust_type), "`].")]
         pub struct $cdf_type($rust_type);
@@ -118,6 +123,28 @@ macro_rules! impl_decodable {
     };
 }
 
+macro_rules! impl_encodable {
+    ($cdf_type:ident) => {
+        impl Encodable for $cdf_type {
+            fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+            where
+                W: io::Write + io::Seek,
+            {
+                encoder.writer.write_all(&self.clone().to_be_bytes())?;
+                Ok(())
+            }
+
+            fn encode_le<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+            where
+                W: io::Write + io::Seek,
+            {
+                encoder.writer.write_all(&self.clone().to_le_bytes())?;
+                Ok(())
+            }
+        }
+    };
+}
+
 impl_cdf_type!(CdfInt1, i8);
 impl_cdf_type!(CdfInt2, i16);
 impl_cdf_type!(CdfInt4, i32);
@@ -157,6 +184,19 @@ impl_cdf_rust_ptr!(CdfEpoch, f64);
 impl_cdf_rust_ptr!(CdfTimeTt2000, i64);
 impl_cdf_rust_ptr!(CdfByte, i8);
 
+impl_encodable!(CdfInt1);
+impl_encodable!(CdfInt2);
+impl_encodable!(CdfInt4);
+impl_encodable!(CdfInt8);
+impl_encodable!(CdfUint1);
+impl_encodable!(CdfUint2);
+impl_encodable!(CdfUint4);
+impl_encodable!(CdfReal4);
+impl_encodable!(CdfReal8);
+impl_encodable!(CdfEpoch);
+impl_encodable!(CdfTimeTt2000);
+impl_encodable!(CdfByte);
+
 impl_cdf_display_debug!(CdfInt1);
 impl_cdf_display_debug!(CdfInt2);
 impl_cdf_display_debug!(CdfInt4);
@@ -189,6 +229,7 @@ impl_decodable!(CdfByte);
 /// v3.8.1 allows for UTF-8 encoding.
 /// This type is equivalent to [`CdfUchar`].
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(PartialEq, Clone)]
 pub struct CdfChar(char);
 
@@ -238,6 +279,7 @@ impl From<CdfChar> for char {
 impl_cdf_rust_ptr!(CdfChar, char);
 impl_cdf_display_debug!(CdfChar);
 impl_decodable!(CdfChar);
+impl_encodable!(CdfChar);
 
 /// Alias for [`CdfUchar`].  Using either of these types for creating new CDF files is not
 /// recommended and the new approach using [`CdfString`] is preferred due to UTF-8 support.
@@ -245,6 +287,7 @@ pub type CdfUchar = CdfChar;
 
 #[doc = concat!("CDF-consistent type that is a wrapper around `([`CdfReal8`], [`CdfReal8`])`.")]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Clone)]
 pub struct CdfEpoch16(CdfReal8, CdfReal8);
 
@@ -253,6 +296,21 @@ impl CdfEpoch16 {
     pub const fn size() -> usize {
         16
     }
+
+    /// Create an instance from seconds since 0000-01-01 and the picoseconds within that second.
+    pub fn new(seconds: f64, picoseconds: f64) -> Self {
+        Self(CdfReal8(seconds), CdfReal8(picoseconds))
+    }
+
+    /// Seconds since 0000-01-01.
+    pub fn seconds(&self) -> f64 {
+        *self.0
+    }
+
+    /// Picoseconds within the second.
+    pub fn picoseconds(&self) -> f64 {
+        *self.1
+    }
     /// Create an instance from a byte array using big-endian endianness.
     pub fn from_be_bytes(bytes: [u8; 16]) -> Self {
         Self(
@@ -299,10 +357,12 @@ impl Debug for CdfEpoch16 {
     }
 }
 impl_decodable!(CdfEpoch16);
+impl_encodable!(CdfEpoch16);
 
 /// CDF-consistent type that is a wrapper around [`String`]. This is not defined in the CDF
 /// specification but is useful for string operations.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Clone)]
 pub struct CdfString(String);
 
@@ -323,11 +383,83 @@ impl CdfString {
     {
         let mut buffer = vec![0u8; num_bytes];
         _ = decoder.reader.read_exact(&mut buffer);
-        Ok(
-            String::from_utf8(buffer.into_iter().take_while(|c| *c != 0).collect())
-                .map_err(|e| CdfError::Decode(format!("Error decoding string - {e}")))?
-                .into(),
-        )
+        let len = buffer.iter().position(|c| *c == 0).unwrap_or(num_bytes);
+        let text = String::from_utf8(buffer[..len].to_vec())
+            .map_err(|e| CdfError::Decode(format!("Error decoding string - {e}")))?;
+        if buffer[len..].iter().any(|c| *c != 0) {
+            decoder.report_warning(DecodeWarning::EmbeddedNul { text: text.clone() });
+        }
+        Ok(text.into())
+    }
+
+    /// Encode this string into a field of exactly `num_bytes`, padded with NUL bytes, as used for
+    /// names stored in records.
+    /// # Errors
+    /// Returns a [`CdfError::Encode`] if the string is longer than `num_bytes`.
+    pub fn encode_string_to_numbytes<W>(
+        &self,
+        encoder: &mut Encoder<W>,
+        num_bytes: usize,
+    ) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        let bytes = self.0.as_bytes();
+        if bytes.len() > num_bytes {
+            return Err(CdfError::Encode(format!(
+                "String {} is longer than {num_bytes} bytes.",
+                self.0
+            )));
+        }
+        encoder.writer.write_all(bytes)?;
+        encoder
+            .writer
+            .write_all(&vec![0u8; num_bytes - bytes.len()])?;
+        Ok(())
+    }
+
+    /// Split a string that holds `num_strings` strings, as stored in attribute entries with
+    /// several strings. Strings are separated by the `\N ` delimiter, or by `\N` or newlines in
+    /// files written by other tools.
+    pub fn split_strings(&self, num_strings: usize) -> Vec<String> {
+        split_strings(&self.0, num_strings)
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+}
+
+// Split `text` into `num_strings` strings, see [`CdfString::split_strings`].
+pub(crate) fn split_strings(text: &str, num_strings: usize) -> Vec<&str> {
+    if num_strings <= 1 {
+        return vec![text];
+    }
+    let split = |delimiter: &str| -> Vec<&str> { text.split(delimiter).collect() };
+    [STRING_DELIMITER, "\\N", "\n"]
+        .into_iter()
+        .map(split)
+        .find(|strings| strings.len() == num_strings)
+        .unwrap_or_else(|| split(STRING_DELIMITER))
+}
+
+/// Delimiter between the strings of attribute entries that hold several strings.
+pub const STRING_DELIMITER: &str = "\\N ";
+
+// Strings are stored as characters, one byte each, so endianness does not apply.
+impl Encodable for CdfString {
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        encoder.writer.write_all(self.0.as_bytes())?;
+        Ok(())
+    }
+
+    fn encode_le<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        self.encode_be(encoder)
     }
 }
 
@@ -346,6 +478,7 @@ impl_cdf_display_debug!(CdfString);
 /// contain a mixture of different primitive CDF types.
 #[repr(i32)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 #[derive(Debug, Clone)]
 pub enum CdfType {
     /// Wraps [`CdfInt1`].
@@ -382,6 +515,141 @@ pub enum CdfType {
     String(CdfString) = 101,
 }
 
+/// Data types of the values stored in a variable or attribute entry, as defined in the CDF
+/// specification (Table 5.8).  Records store these as a [`CdfInt4`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DataType {
+    /// 1-byte signed integer.
+    Int1 = 1,
+    /// 2-byte signed integer.
+    Int2 = 2,
+    /// 4-byte signed integer.
+    Int4 = 4,
+    /// 8-byte signed integer.
+    Int8 = 8,
+    /// 1-byte unsigned integer.
+    Uint1 = 11,
+    /// 2-byte unsigned integer.
+    Uint2 = 12,
+    /// 4-byte unsigned integer.
+    Uint4 = 14,
+    /// 4-byte floating point.
+    Real4 = 21,
+    /// 8-byte floating point.
+    Real8 = 22,
+    /// 8-byte floating point, milliseconds since 0000-01-01.
+    Epoch = 31,
+    /// Two 8-byte floating points, seconds and picoseconds since 0000-01-01.
+    Epoch16 = 32,
+    /// 8-byte signed integer, nanoseconds since J2000 including leap seconds.
+    TimeTt2000 = 33,
+    /// 1-byte signed integer.
+    Byte = 41,
+    /// 4-byte floating point.
+    Float = 44,
+    /// 8-byte floating point.
+    Double = 45,
+    /// 1-byte signed character.
+    Char = 51,
+    /// 1-byte unsigned character.
+    Uchar = 52,
+}
+
+impl DataType {
+    /// Size in bytes of one element of this data type.
+    pub fn size(&self) -> usize {
+        match self {
+            DataType::Int1 | DataType::Uint1 | DataType::Byte => 1,
+            DataType::Char | DataType::Uchar => 1,
+            DataType::Int2 | DataType::Uint2 => 2,
+            DataType::Int4 | DataType::Uint4 | DataType::Real4 | DataType::Float => 4,
+            DataType::Int8 | DataType::Real8 | DataType::Double => 8,
+            DataType::Epoch | DataType::TimeTt2000 => 8,
+            DataType::Epoch16 => 16,
+        }
+    }
+
+    /// Whether values of this data type are characters, which are decoded as a [`CdfString`].
+    pub fn is_string(&self) -> bool {
+        matches!(self, DataType::Char | DataType::Uchar)
+    }
+}
+
+impl TryFrom<CdfInt4> for DataType {
+    type Error = CdfError;
+    fn try_from(value: CdfInt4) -> Result<Self, CdfError> {
+        match *value {
+            1 => Ok(DataType::Int1),
+            2 => Ok(DataType::Int2),
+            4 => Ok(DataType::Int4),
+            8 => Ok(DataType::Int8),
+            11 => Ok(DataType::Uint1),
+            12 => Ok(DataType::Uint2),
+            14 => Ok(DataType::Uint4),
+            21 => Ok(DataType::Real4),
+            22 => Ok(DataType::Real8),
+            31 => Ok(DataType::Epoch),
+            32 => Ok(DataType::Epoch16),
+            33 => Ok(DataType::TimeTt2000),
+            41 => Ok(DataType::Byte),
+            44 => Ok(DataType::Float),
+            45 => Ok(DataType::Double),
+            51 => Ok(DataType::Char),
+            52 => Ok(DataType::Uchar),
+            e => Err(CdfError::Decode(format!(
+                "Invalid CDF data_type received - {}",
+                e
+            ))),
+        }
+    }
+}
+
+impl From<DataType> for CdfInt4 {
+    fn from(value: DataType) -> Self {
+        CdfInt4(value as i32)
+    }
+}
+
+macro_rules! encode_cdf_type {
+    ($self:ident, $encoder:ident, $method:ident) => {
+        match $self {
+            CdfType::Int1(v) => v.$method($encoder),
+            CdfType::Int2(v) => v.$method($encoder),
+            CdfType::Int4(v) => v.$method($encoder),
+            CdfType::Int8(v) => v.$method($encoder),
+            CdfType::Uint1(v) => v.$method($encoder),
+            CdfType::Uint2(v) => v.$method($encoder),
+            CdfType::Uint4(v) => v.$method($encoder),
+            CdfType::Real4(v) => v.$method($encoder),
+            CdfType::Real8(v) => v.$method($encoder),
+            CdfType::Epoch(v) => v.$method($encoder),
+            CdfType::Epoch16(v) => v.$method($encoder),
+            CdfType::TimeTt2000(v) => v.$method($encoder),
+            CdfType::Byte(v) => v.$method($encoder),
+            CdfType::Char(v) | CdfType::Uchar(v) => v.$method($encoder),
+            CdfType::String(v) => v.$method($encoder),
+        }
+    };
+}
+
+impl Encodable for CdfType {
+    fn encode_be<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        encode_cdf_type!(self, encoder, encode_be)
+    }
+
+    fn encode_le<W>(&self, encoder: &mut Encoder<W>) -> Result<(), CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        encode_cdf_type!(self, encoder, encode_le)
+    }
+}
+
 impl CdfType {
     // /// Size of underlying data_type
     // pub fn size(data_type: &CdfInt4) -> Result<usize, CdfError> {
@@ -410,6 +678,116 @@ impl CdfType {
     //     }
     // }
 
+    /// Data type of this value. Strings are characters of type [`DataType::Char`].
+    pub fn data_type(&self) -> DataType {
+        match self {
+            CdfType::Int1(_) => DataType::Int1,
+            CdfType::Int2(_) => DataType::Int2,
+            CdfType::Int4(_) => DataType::Int4,
+            CdfType::Int8(_) => DataType::Int8,
+            CdfType::Uint1(_) => DataType::Uint1,
+            CdfType::Uint2(_) => DataType::Uint2,
+            CdfType::Uint4(_) => DataType::Uint4,
+            CdfType::Real4(_) => DataType::Real4,
+            CdfType::Real8(_) => DataType::Real8,
+            CdfType::Epoch(_) => DataType::Epoch,
+            CdfType::Epoch16(_) => DataType::Epoch16,
+            CdfType::TimeTt2000(_) => DataType::TimeTt2000,
+            CdfType::Byte(_) => DataType::Byte,
+            CdfType::Char(_) | CdfType::String(_) => DataType::Char,
+            CdfType::Uchar(_) => DataType::Uchar,
+        }
+    }
+
+    /// Whether this value can be stored in a variable or attribute entry of `data_type`.
+    pub fn is_of_type(&self, data_type: &DataType) -> bool {
+        matches!(
+            (self, data_type),
+            (CdfType::Int1(_), DataType::Int1)
+                | (CdfType::Int2(_), DataType::Int2)
+                | (CdfType::Int4(_), DataType::Int4)
+                | (CdfType::Int8(_), DataType::Int8)
+                | (CdfType::Uint1(_), DataType::Uint1)
+                | (CdfType::Uint2(_), DataType::Uint2)
+                | (CdfType::Uint4(_), DataType::Uint4)
+                | (CdfType::Real4(_), DataType::Real4 | DataType::Float)
+                | (CdfType::Real8(_), DataType::Real8 | DataType::Double)
+                | (CdfType::Epoch(_), DataType::Epoch)
+                | (CdfType::Epoch16(_), DataType::Epoch16)
+                | (CdfType::TimeTt2000(_), DataType::TimeTt2000)
+                | (CdfType::Byte(_), DataType::Byte)
+                | (
+                    CdfType::Char(_) | CdfType::Uchar(_) | CdfType::String(_),
+                    DataType::Char | DataType::Uchar
+                )
+        )
+    }
+
+    /// This value as a number, for integer and real values. Returns `None` for times and strings.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            CdfType::Int1(v) => Some(f64::from(**v)),
+            CdfType::Int2(v) => Some(f64::from(**v)),
+            CdfType::Int4(v) => Some(f64::from(**v)),
+            CdfType::Int8(v) => Some(**v as f64),
+            CdfType::Uint1(v) => Some(f64::from(**v)),
+            CdfType::Uint2(v) => Some(f64::from(**v)),
+            CdfType::Uint4(v) => Some(f64::from(**v)),
+            CdfType::Real4(v) => Some(f64::from(**v)),
+            CdfType::Real8(v) => Some(**v),
+            CdfType::Byte(v) => Some(f64::from(**v)),
+            _ => None,
+        }
+    }
+
+    /// This value as an integer, for integer values. Returns `None` for reals, times and strings.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            CdfType::Int1(v) => Some(i64::from(**v)),
+            CdfType::Int2(v) => Some(i64::from(**v)),
+            CdfType::Int4(v) => Some(i64::from(**v)),
+            CdfType::Int8(v) => Some(**v),
+            CdfType::Uint1(v) => Some(i64::from(**v)),
+            CdfType::Uint2(v) => Some(i64::from(**v)),
+            CdfType::Uint4(v) => Some(i64::from(**v)),
+            CdfType::Byte(v) => Some(i64::from(**v)),
+            _ => None,
+        }
+    }
+
+    /// The default pad values for `data_type` as defined in the CDF specification. These are used
+    /// for records that are not written to the file when a variable has no pad value of its own.
+    /// As when decoding, character types are returned as a single [`CdfString`].
+    pub fn default_pad(
+        data_type: &CdfInt4,
+        num_elements: &CdfInt4,
+    ) -> Result<Vec<CdfType>, CdfError> {
+        let n = checked_usize("num_elements", **num_elements)?;
+        let value = match **data_type {
+            1 => CdfType::Int1(CdfInt1(-127)),
+            2 => CdfType::Int2(CdfInt2(-32767)),
+            4 => CdfType::Int4(CdfInt4(-2147483647)),
+            8 => CdfType::Int8(CdfInt8(-9223372036854775807)),
+            11 => CdfType::Uint1(CdfUint1(254)),
+            12 => CdfType::Uint2(CdfUint2(65534)),
+            14 => CdfType::Uint4(CdfUint4(4294967294)),
+            21 | 44 => CdfType::Real4(CdfReal4(-1.0e30)),
+            22 | 45 => CdfType::Real8(CdfReal8(-1.0e30)),
+            31 => CdfType::Epoch(CdfEpoch(0.0)),
+            32 => CdfType::Epoch16(CdfEpoch16(CdfReal8(0.0), CdfReal8(0.0))),
+            33 => CdfType::TimeTt2000(CdfTimeTt2000(-9223372036854775807)),
+            41 => CdfType::Byte(CdfByte(-127)),
+            51 | 52 => return Ok(vec![CdfType::String(CdfString(" ".repeat(n)))]),
+            e => {
+                return Err(CdfError::Decode(format!(
+                    "Invalid CDF data_type received - {}",
+                    e
+                )))
+            }
+        };
+        Ok(vec![value; n])
+    }
+
     /// Decode a vector of a CdfType whose type is not known at compile time, using big-endian
     /// encoding.
     pub fn decode_vec_be<R>(
@@ -422,7 +800,9 @@ impl CdfType {
     {
         macro_rules! get_vec_type {
             ($cdf_type:ty, $enum_variant:ident) => {{
-                let mut result: Vec<CdfType> = Vec::with_capacity(usize::try_from(**num_elements)?);
+                let n = checked_usize("num_elements", **num_elements)?;
+                decoder.check_alloc(n.saturating_mul(mem::size_of::<CdfType>()))?;
+                let mut result: Vec<CdfType> = Vec::with_capacity(n);
                 for _ in 0..**num_elements {
                     result.push(CdfType::$enum_variant(<$cdf_type>::decode_be(decoder)?));
                 }
@@ -472,7 +852,9 @@ impl CdfType {
     {
         macro_rules! get_vec_type {
             ($cdf_type:ty, $enum_variant:ident) => {{
-                let mut result: Vec<CdfType> = Vec::with_capacity(usize::try_from(**num_elements)?);
+                let n = checked_usize("num_elements", **num_elements)?;
+                decoder.check_alloc(n.saturating_mul(mem::size_of::<CdfType>()))?;
+                let mut result: Vec<CdfType> = Vec::with_capacity(n);
                 for _ in 0..**num_elements {
                     result.push(CdfType::$enum_variant(<$cdf_type>::decode_le(decoder)?));
                 }
@@ -518,6 +900,19 @@ mod tests {
     use crate::error::CdfError;
     use paste::paste;
 
+    #[test]
+    fn test_data_type() -> Result<(), CdfError> {
+        for code in [
+            1, 2, 4, 8, 11, 12, 14, 21, 22, 31, 32, 33, 41, 44, 45, 51, 52,
+        ] {
+            let data_type = DataType::try_from(CdfInt4(code))?;
+            assert_eq!(*CdfInt4::from(data_type), code);
+        }
+        assert_eq!(DataType::try_from(CdfInt4(32))?.size(), 16);
+        assert!(DataType::try_from(CdfInt4(3)).is_err());
+        Ok(())
+    }
+
     macro_rules! test_type {
         ($t1:ty, $t2:ty, $val:literal) => {
             paste! {
@@ -525,7 +920,7 @@ mod tests {
                 fn [< test_convert_ $t1:lower _ $t2 >]() {
                     let x: $t2 = $val;
                     let y: $t1 = x.into();
-                    assert_eq!(x, y.into());
+                    assert_eq!(x, <$t2>::from(y));
                 }
 
                 #[test]
@@ -553,15 +948,26 @@ mod tests {
     test_type!(CdfReal4, f32, -7.0);
     test_type!(CdfReal8, f64, -7.0);
 
+    #[test]
+    fn test_split_strings() {
+        let s = CdfString::from(String::from("Bx\\N By\\N Bz"));
+        assert_eq!(s.split_strings(3), vec!["Bx", "By", "Bz"]);
+        assert_eq!(s.split_strings(1), vec!["Bx\\N By\\N Bz"]);
+        let s = CdfString::from(String::from("first\nsecond"));
+        assert_eq!(s.split_strings(2), vec!["first", "second"]);
+        let s = CdfString::from(String::from("a\\Nb"));
+        assert_eq!(s.split_strings(2), vec!["a", "b"]);
+    }
+
     #[test]
     fn test_convert_cdfchar_char() {
         let x: char = 'a'; // ASCII
         let y: CdfChar = x.try_into().unwrap();
-        assert_eq!(x, y.into());
+        assert_eq!(x, char::from(y));
 
         let x: char = 'ñ'; // Extended ASCII
         let y: CdfChar = x.try_into().unwrap();
-        assert_eq!(x, y.into());
+        assert_eq!(x, char::from(y));
 
         let x: char = 'Ā'; // Valid Unicode but not ASCII.
         let y: Result<CdfChar, CdfError> = x.try_into();
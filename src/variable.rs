@@ -0,0 +1,2554 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::io::{self, SeekFrom};
+#[cfg(feature = "bytemuck")]
+use alloc::borrow::Cow;
+use core::fmt;
+use core::ops::{Index, Range};
+use core::time::Duration;
+
+use crate::cdf::{Cdf, ReadSeek};
+use crate::dataset::unix_nanos;
+use crate::decode::{
+    checked_usize, decode_version3_int4_int8, Decodable, DecodeContext, Decoder, DEFAULT_MAX_DEPTH,
+};
+use crate::encode::{encode_to_vec, Encoder};
+use crate::error::CdfError;
+use crate::record::adr::AttributeDescriptorRecord;
+use crate::record::cpr::CompressedParametersRecord;
+use crate::record::rvdr::RVariableDescriptorRecord;
+use crate::record::vvr::VariableRecord;
+use crate::record::vxr::{check_index, VariableIndexRecord, VariableIndexRecordChild};
+use crate::record::zvdr::{ZVariableDescriptorRecord, ZVariableFlags};
+use crate::repr::Endian;
+use crate::time::CdfTime;
+use crate::types::{
+    CdfEpoch, CdfEpoch16, CdfInt4, CdfInt8, CdfReal8, CdfString, CdfTimeTt2000, CdfType, DataType,
+};
+
+/// Number of records read at a time by [`Variable::stats`], [`Variable::aggregate_by`] and the
+/// summaries of the values of a variable.
+const STATS_RECORDS: usize = 4096;
+
+/// Size of the header of a VVR of a v3 file, which holds the record size and the record type.
+const VVR_HEADER_SIZE: usize = 12;
+
+/// The descriptor record of a variable, which describes either an rVariable or a zVariable.
+#[derive(Debug, Clone, Copy)]
+pub enum VariableDescriptor<'a> {
+    /// An rVariable. The dimensions of all rVariables are stored in the GDR.
+    R(&'a RVariableDescriptorRecord),
+    /// A zVariable, which stores its own dimensions.
+    Z(&'a ZVariableDescriptorRecord),
+}
+
+/// A handle to one variable stored in a [`Cdf`], used to inspect the variable and to read its
+/// values, either from memory or on demand from the file.
+#[derive(Debug, Clone, Copy)]
+pub struct Variable<'a> {
+    cdf: &'a Cdf,
+    descriptor: VariableDescriptor<'a>,
+    virtual_variable: Option<&'a VirtualVariable>,
+}
+
+/// Function that computes one record of a virtual variable from the records of its inputs with
+/// the same number, see [`Cdf::register_virtual`].
+pub type VirtualFn = dyn Fn(&[&[CdfType]]) -> Result<Vec<CdfType>, CdfError> + Send + Sync;
+
+// A variable computed from other variables of a file, which is described by a zVDR that is not
+// stored in the file.
+pub(crate) struct VirtualVariable {
+    vdr: ZVariableDescriptorRecord,
+    inputs: Vec<String>,
+    compute: Box<VirtualFn>,
+}
+
+impl fmt::Debug for VirtualVariable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VirtualVariable")
+            .field("name", &*self.vdr.name)
+            .field("inputs", &self.inputs)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VirtualVariable {
+    // Describe the variable `name` of `cdf` computed from `inputs`. Its data type and shape are
+    // those of the record computed from the pad records of the inputs, and it has as many records
+    // as the shortest input that varies from record to record.
+    pub(crate) fn new(
+        cdf: &Cdf,
+        name: &str,
+        inputs: &[&str],
+        compute: Box<VirtualFn>,
+    ) -> Result<Self, CdfError> {
+        let variables = inputs
+            .iter()
+            .map(|input| cdf.variable(input))
+            .collect::<Result<Vec<_>, _>>()?;
+        let pads = variables
+            .iter()
+            .map(|v| v.pad_record())
+            .collect::<Result<Vec<_>, _>>()?;
+        let args: Vec<&[CdfType]> = pads.iter().map(|r| r.data.as_slice()).collect();
+        let record = compute(&args)?;
+        let (data_type, num_elements, dims) = match record.as_slice() {
+            [] => {
+                return Err(CdfError::Other(format!(
+                    "Virtual variable {name} computes records without values."
+                )))
+            }
+            [CdfType::String(s)] => (DataType::Char, s.len(), vec![]),
+            [value] => (value.data_type(), 1, vec![]),
+            values => (values[0].data_type(), 1, vec![values.len()]),
+        };
+
+        let varies = variables.iter().any(Variable::record_varies);
+        let n_records = variables
+            .iter()
+            .filter(|v| v.record_varies() == varies)
+            .map(Variable::n_records)
+            .min()
+            .unwrap_or(0);
+        let num = cdf.cdr.gdr.zvdr_vec.len() + cdf.virtual_variables.len();
+        let vdr = ZVariableDescriptorRecord {
+            record_size: CdfInt8::from(0),
+            record_type: CdfInt4::from(8),
+            zvdr_next: None,
+            data_type: CdfInt4::from(data_type),
+            max_record: CdfInt4::from(i32::try_from(n_records)? - 1),
+            vxr_head: None,
+            vxr_tail: None,
+            flags: ZVariableFlags {
+                variance: varies,
+                has_padding: false,
+                is_compressed: false,
+            },
+            sparse_records: CdfInt4::from(0),
+            rfu_b: CdfInt4::from(0),
+            rfu_c: CdfInt4::from(-1),
+            rfu_f: CdfInt4::from(-1),
+            num_elements: CdfInt4::from(i32::try_from(num_elements)?),
+            num: CdfInt4::from(i32::try_from(num)?),
+            cpr_spr_offset: None,
+            blocking_factor: CdfInt4::from(0),
+            name: name.to_string().into(),
+            num_z_dims: CdfInt4::from(i32::try_from(dims.len())?),
+            size_z_dims: dims
+                .iter()
+                .map(|d| Ok(CdfInt4::from(i32::try_from(*d)?)))
+                .collect::<Result<_, CdfError>>()?,
+            dim_variances: vec![true; dims.len()],
+            pad_value: vec![],
+            cpr: None,
+            vxr_vec: vec![],
+        };
+        Ok(VirtualVariable {
+            vdr,
+            inputs: inputs.iter().map(|input| input.to_string()).collect(),
+            compute,
+        })
+    }
+
+    // Compute every `step`-th record in `records` of `variable`, reading the same records of the
+    // inputs. Inputs that do not vary from record to record give their first record to each.
+    fn read(
+        &self,
+        variable: &Variable,
+        records: Range<usize>,
+        step: usize,
+    ) -> Result<Vec<VariableRecord>, CdfError> {
+        let mut columns = Vec::with_capacity(self.inputs.len());
+        for input in &self.inputs {
+            let input = variable.cdf.variable(input)?;
+            columns.push(match (input.record_varies(), input.n_records()) {
+                (true, _) => input.read_strided(records.clone(), step)?,
+                (false, 0) => vec![input.pad_record()?],
+                (false, _) => input.read_records(0..1)?,
+            });
+        }
+
+        let values_per_record = variable.values_per_record()?;
+        let data_type = DataType::try_from(variable.data_type().clone())?;
+        (0..records.len().div_ceil(step))
+            .map(|i| {
+                let args: Vec<&[CdfType]> = columns
+                    .iter()
+                    .map(|column| column[i.min(column.len() - 1)].data.as_slice())
+                    .collect();
+                let data = (self.compute)(&args)?;
+                let matches = match data.as_slice() {
+                    [CdfType::String(s)] => s.len() == values_per_record,
+                    values => {
+                        values.len() == values_per_record
+                            && values.iter().all(|v| v.is_of_type(&data_type))
+                    }
+                };
+                if !matches {
+                    return Err(CdfError::Other(format!(
+                        "Virtual variable {} computed record {} with another type or shape than \
+                         its description.",
+                        variable.name(),
+                        records.start + i * step
+                    )));
+                }
+                Ok(VariableRecord {
+                    data_type: variable.data_type().clone(),
+                    data_len: CdfInt4::from(i32::try_from(values_per_record)?),
+                    data,
+                })
+            })
+            .collect()
+    }
+}
+
+// Both descriptor records share most of their fields, so access them through one macro.
+macro_rules! descriptor_field {
+    ($self:ident, $field:ident) => {
+        match $self.descriptor {
+            VariableDescriptor::R(vdr) => &vdr.$field,
+            VariableDescriptor::Z(vdr) => &vdr.$field,
+        }
+    };
+}
+
+impl<'a> Variable<'a> {
+    /// Create a handle to the variable described by `descriptor` stored in `cdf`.
+    pub fn new(cdf: &'a Cdf, descriptor: VariableDescriptor<'a>) -> Self {
+        Variable {
+            cdf,
+            descriptor,
+            virtual_variable: None,
+        }
+    }
+
+    // Create a handle to a virtual variable registered on `cdf`.
+    pub(crate) fn new_virtual(cdf: &'a Cdf, virtual_variable: &'a VirtualVariable) -> Self {
+        Variable {
+            cdf,
+            descriptor: VariableDescriptor::Z(&virtual_variable.vdr),
+            virtual_variable: Some(virtual_variable),
+        }
+    }
+
+    /// The descriptor record of this variable.
+    pub fn descriptor(&self) -> VariableDescriptor<'a> {
+        self.descriptor
+    }
+
+    /// Name of this variable.
+    pub fn name(&self) -> &'a str {
+        descriptor_field!(self, name)
+    }
+
+    /// Number (identifier) of this variable among the rVariables or zVariables of the file.
+    pub fn num(&self) -> &'a CdfInt4 {
+        descriptor_field!(self, num)
+    }
+
+    /// Whether this is a zVariable. Virtual variables are described as zVariables.
+    pub fn is_zvariable(&self) -> bool {
+        matches!(self.descriptor, VariableDescriptor::Z(_))
+    }
+
+    /// Whether this is a virtual variable computed from other variables, see
+    /// [`Cdf::register_virtual`].
+    pub fn is_virtual(&self) -> bool {
+        self.virtual_variable.is_some()
+    }
+
+    /// Type of data stored in this variable.
+    pub fn data_type(&self) -> &'a CdfInt4 {
+        descriptor_field!(self, data_type)
+    }
+
+    /// Number of elements of the data type in each value of this variable.
+    pub fn num_elements(&self) -> Result<usize, CdfError> {
+        checked_usize("num_elements", **descriptor_field!(self, num_elements))
+    }
+
+    /// Sizes of all dimensions of this variable.
+    pub fn dim_sizes(&self) -> Result<Vec<usize>, CdfError> {
+        let sizes = match self.descriptor {
+            VariableDescriptor::R(vdr) => &vdr.size_r_dims,
+            VariableDescriptor::Z(vdr) => &vdr.size_z_dims,
+        };
+        sizes
+            .iter()
+            .map(|s| checked_usize("dim_sizes", **s))
+            .collect()
+    }
+
+    /// Dimension variances of this variable. Values only vary along dimensions that are `true`.
+    pub fn dim_variances(&self) -> &'a [bool] {
+        descriptor_field!(self, dim_variances)
+    }
+
+    /// Shape of the values stored in each record, i.e. the sizes of the dimensions that vary.
+    pub fn shape(&self) -> Result<Vec<usize>, CdfError> {
+        Ok(self
+            .dim_sizes()?
+            .into_iter()
+            .zip(self.dim_variances())
+            .filter(|(_, v)| **v)
+            .map(|(s, _)| s)
+            .collect())
+    }
+
+    /// Shape of the values of the data type stored in each record, i.e. [`Variable::shape`]
+    /// followed by the number of elements of each value if it is more than one. Values of
+    /// character data are strings of `num_elements` characters, which add no dimension.
+    /// # Errors
+    /// Returns a [`CdfError`] if the dimensions or the data type of the variable are invalid.
+    pub fn record_shape(&self) -> Result<Vec<usize>, CdfError> {
+        let mut shape = self.shape()?;
+        let num_elements = self.num_elements()?;
+        if num_elements > 1 && !DataType::try_from(self.data_type().clone())?.is_string() {
+            shape.push(num_elements);
+        }
+        Ok(shape)
+    }
+
+    /// Whether each record of this variable holds a single value, because the variable has no
+    /// dimensions or does not vary along any of them, and each value is one element. Character
+    /// data holds one string per record.
+    pub fn is_scalar(&self) -> bool {
+        self.record_shape().is_ok_and(|shape| shape.is_empty())
+    }
+
+    /// Shape of all the values of this variable, with the records as the first dimension, which
+    /// is `[n_records]` for scalar variables, see [`Variable::record_shape`].
+    /// # Errors
+    /// Returns a [`CdfError`] if the dimensions of the variable are invalid.
+    pub fn array_shape(&self) -> Result<Vec<usize>, CdfError> {
+        let mut shape = vec![self.n_records()];
+        shape.extend(self.record_shape()?);
+        Ok(shape)
+    }
+
+    /// Whether the values of this variable vary from record to record.
+    pub fn record_varies(&self) -> bool {
+        match self.descriptor {
+            VariableDescriptor::R(vdr) => vdr.flags.variance,
+            VariableDescriptor::Z(vdr) => vdr.flags.variance,
+        }
+    }
+
+    /// Compressed Parameters Record of this variable, if its values are compressed.
+    pub fn compression(&self) -> Option<&'a CompressedParametersRecord> {
+        descriptor_field!(self, cpr).as_ref()
+    }
+
+    /// Number of records of this variable, which is zero for a variable without records, whose
+    /// `max_record` is -1.
+    pub fn n_records(&self) -> usize {
+        usize::try_from(descriptor_field!(self, max_record).saturating_add(1)).unwrap_or(0)
+    }
+
+    /// Find the record of this variable at `time`, a [`CdfType::Epoch`], [`CdfType::Epoch16`] or
+    /// [`CdfType::TimeTt2000`] of any of the three types, such as for a point query of the value
+    /// at a given time. The times are those of this variable if it holds times, or else of the
+    /// epoch named by its `DEPEND_0` attribute, which must increase monotonically. Only the
+    /// records visited by a binary search are read. Returns `None` if there is no such record.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if there is no epoch, if `time` or a visited record is not a
+    /// time or is a fill value, or if a record cannot be read.
+    pub fn find_record_at(
+        &self,
+        time: &CdfType,
+        mode: RecordMatch,
+    ) -> Result<Option<usize>, CdfError> {
+        let epoch = match DataType::try_from(self.data_type().clone())? {
+            DataType::Epoch | DataType::Epoch16 | DataType::TimeTt2000 => *self,
+            _ => self.companion("DEPEND_0").ok_or_else(|| {
+                CdfError::Other(format!(
+                    "Variable {} has no epoch named by DEPEND_0.",
+                    self.name()
+                ))
+            })?,
+        };
+        let fill = |time: &CdfType| {
+            CdfError::Other(format!(
+                "Cannot find the record at {time:?}, which is a fill value."
+            ))
+        };
+        let target = unix_nanos(time)?.ok_or_else(|| fill(time))?;
+        let time_at = |record: usize| -> Result<i128, CdfError> {
+            let records = epoch.read_records(record..record + 1)?;
+            let value = records[0]
+                .data
+                .first()
+                .ok_or_else(|| CdfError::Other(format!("Epoch {} has no values.", epoch.name())))?;
+            unix_nanos(value)?.ok_or_else(|| fill(value))
+        };
+
+        // Index of the first record that is not before the target.
+        let n_records = epoch.n_records();
+        let (mut low, mut high) = (0, n_records);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if time_at(mid)? < target {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        let with_time = |record: usize| time_at(record).map(|t| (record, t));
+        let after = (low < n_records).then(|| with_time(low)).transpose()?;
+        let before = match after {
+            Some((record, t)) if t == target => Some((record, t)),
+            _ => low.checked_sub(1).map(with_time).transpose()?,
+        };
+        Ok(match (mode, before, after) {
+            (RecordMatch::Before, before, _) => before.map(|(r, _)| r),
+            (RecordMatch::After, _, after) => after.map(|(r, _)| r),
+            (RecordMatch::Nearest, Some((r0, t0)), Some((r1, t1))) => {
+                Some(if target - t0 <= t1 - target { r0 } else { r1 })
+            }
+            (RecordMatch::Nearest, before, after) => before.or(after).map(|(r, _)| r),
+        })
+    }
+
+    /// Problems with the index of the values of this variable, see [`check_index`].
+    pub fn check_index(&self) -> Vec<String> {
+        if self.is_virtual() {
+            return vec![];
+        }
+        check_index(
+            descriptor_field!(self, vxr_vec),
+            **descriptor_field!(self, max_record),
+            self.is_sparse(),
+        )
+    }
+
+    /// Whether records of this variable that are not stored in the file are virtual, in which case
+    /// they are read as the pad value or as the previous record, see [`Variable::read_sparse`].
+    pub fn is_sparse(&self) -> bool {
+        **descriptor_field!(self, sparse_records) != 0
+    }
+
+    /// Number of values of the data type stored in each record, which is the number of elements
+    /// for scalar variables.
+    pub fn values_per_record(&self) -> Result<usize, CdfError> {
+        self.shape()?
+            .iter()
+            .try_fold(self.num_elements()?, |n, size| n.checked_mul(*size))
+            .ok_or_else(|| {
+                CdfError::Decode(format!(
+                    "Invalid dimension sizes for variable {}.",
+                    self.name()
+                ))
+            })
+    }
+
+    /// The variable attributes of this variable.
+    pub fn attributes(&self) -> VariableAttributes<'a> {
+        VariableAttributes { variable: *self }
+    }
+
+    /// Value of the variable attribute called `name` for this variable, if there is one.
+    pub fn attribute(&self, name: &str) -> Option<&'a [CdfType]> {
+        let num = **self.num();
+        let adr = self.attribute_descriptor(name)?;
+        match self.descriptor {
+            VariableDescriptor::R(_) => adr
+                .agredr_vec
+                .iter()
+                .find(|e| *e.num == num)
+                .map(|e| e.value.as_slice()),
+            VariableDescriptor::Z(_) => adr
+                .azedr_vec
+                .iter()
+                .find(|e| *e.num == num)
+                .map(|e| e.value.as_slice()),
+        }
+    }
+
+    /// Strings of the variable attribute called `name` for this variable, if it holds characters.
+    /// Entries that hold several strings are split into one string each.
+    pub fn attribute_strings(&self, name: &str) -> Option<Vec<String>> {
+        let num = **self.num();
+        let adr = self.attribute_descriptor(name)?;
+        match self.descriptor {
+            VariableDescriptor::R(_) => adr.agredr_vec.iter().find(|e| *e.num == num)?.strings(),
+            VariableDescriptor::Z(_) => adr.azedr_vec.iter().find(|e| *e.num == num)?.strings(),
+        }
+    }
+
+    fn attribute_descriptor(&self, name: &str) -> Option<&'a AttributeDescriptorRecord> {
+        self.cdf
+            .cdr
+            .gdr
+            .adr_vec
+            .iter()
+            .find(|adr| matches!(*adr.scope, 2 | 4) && *adr.name == name)
+    }
+
+    /// Value of the variable attribute called `name` for this variable, if it is a string.
+    pub fn attribute_str(&self, name: &str) -> Option<&'a str> {
+        match self.attribute(name)? {
+            [CdfType::String(s)] => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The variable named by the variable attribute `name` of this variable, such as the quality
+    /// flags named by `QUALITY` or the epoch named by `DEPEND_0`.
+    pub fn companion(&self, name: &str) -> Option<Variable<'a>> {
+        self.cdf.variable(self.attribute_str(name)?).ok()
+    }
+
+    /// Label of this variable for plot axes, from its `LABLAXIS` attribute, or else its name.
+    pub fn label(&self) -> &'a str {
+        self.attribute_str("LABLAXIS")
+            .unwrap_or_else(|| self.name())
+    }
+
+    /// Labels of the values along each dimension of [`Variable::shape`], such as `Bx`, `By` and
+    /// `Bz` for the components of a vector. These are read from the label variables named by the
+    /// `LABL_PTR_1`, `LABL_PTR_2`, ... attributes of this variable, and are `None` for dimensions
+    /// without a label variable.
+    /// # Errors
+    /// Returns a [`CdfError`] if a label variable cannot be read, or if it does not hold one label
+    /// per value along its dimension.
+    pub fn dim_labels(&self) -> Result<Vec<Option<Vec<String>>>, CdfError> {
+        self.shape()?
+            .into_iter()
+            .enumerate()
+            .map(|(i, size)| {
+                let Some(labels) = self.companion(&format!("LABL_PTR_{}", i + 1)) else {
+                    return Ok(None);
+                };
+                let text: String = match labels.n_records() {
+                    0 => String::new(),
+                    _ => labels.read_records(0..1)?[0]
+                        .data
+                        .iter()
+                        .filter_map(|value| match value {
+                            CdfType::String(s) => Some(s.as_str()),
+                            _ => None,
+                        })
+                        .collect(),
+                };
+                let width = labels.num_elements()?.max(1);
+                let chars: Vec<char> = text.chars().collect();
+                let strings: Vec<String> = chars
+                    .chunks(width)
+                    .map(|c| {
+                        c.iter()
+                            .collect::<String>()
+                            .trim_end_matches([' ', '\0'])
+                            .to_string()
+                    })
+                    .collect();
+                if strings.len() != size {
+                    return Err(CdfError::Other(format!(
+                        "Label variable {} has {} labels for dimension {} of {} with size {size}.",
+                        labels.name(),
+                        strings.len(),
+                        i + 1,
+                        self.name()
+                    )));
+                }
+                Ok(Some(strings))
+            })
+            .collect()
+    }
+
+    /// The record used in place of records that are not stored in the file. This is made of the
+    /// pad value of the variable, or of the default pad value for its data type.
+    pub fn pad_record(&self) -> Result<VariableRecord, CdfError> {
+        let pad_value = descriptor_field!(self, pad_value);
+        let pad_value = if pad_value.is_empty() {
+            CdfType::default_pad(self.data_type(), descriptor_field!(self, num_elements))?
+        } else {
+            pad_value.clone()
+        };
+
+        let num_values: usize = self.shape()?.iter().product();
+        let data = match pad_value.as_slice() {
+            [CdfType::String(s)] => {
+                vec![CdfType::String(CdfString::from(s.repeat(num_values)))]
+            }
+            _ => (0..num_values)
+                .flat_map(|_| pad_value.iter().cloned())
+                .collect(),
+        };
+        Ok(VariableRecord {
+            data_type: self.data_type().clone(),
+            data_len: CdfInt4::from(i32::try_from(self.values_per_record()?)?),
+            data,
+        })
+    }
+
+    /// Read all records of this variable.
+    pub fn read_all(&self) -> Result<Vec<VariableRecord>, CdfError> {
+        self.read_records(0..self.n_records())
+    }
+
+    /// Read the records in `records`. Records that were loaded while decoding the file are taken
+    /// from memory, and the rest are read from the file. Records that are not stored in the file
+    /// are filled with the pad record.
+    /// # Errors
+    /// Returns a [`CdfError`] if the range is out of bounds, or if a record cannot be read.
+    pub fn read_records(&self, records: Range<usize>) -> Result<Vec<VariableRecord>, CdfError> {
+        self.read_strided(records, 1)
+    }
+
+    /// Read every `step`-th record in `records`, starting with the first one. Uncompressed records
+    /// that are read from the file are seeked to directly, so records in between are never read.
+    /// # Errors
+    /// Returns a [`CdfError`] if the range is out of bounds, if `step` is zero, or if a record
+    /// cannot be read.
+    pub fn read_strided(
+        &self,
+        records: Range<usize>,
+        step: usize,
+    ) -> Result<Vec<VariableRecord>, CdfError> {
+        let (slots, _) = self.read_with(records, step, false)?;
+        let pad = self.pad_record()?;
+        Ok(slots
+            .into_iter()
+            .map(|r| self.promote(r.unwrap_or_else(|| pad.clone())))
+            .collect())
+    }
+
+    /// Read the value of each record in `records` of a scalar variable, see
+    /// [`Variable::is_scalar`], one value per record.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if the variable is not scalar, or a [`CdfError`] if the range
+    /// is out of bounds or a record cannot be read.
+    pub fn read_scalars(&self, records: Range<usize>) -> Result<Vec<CdfType>, CdfError> {
+        if !self.is_scalar() {
+            return Err(CdfError::Other(format!(
+                "Variable {} is not scalar, its records have shape {:?}.",
+                self.name(),
+                self.record_shape()?
+            )));
+        }
+        self.read_records(records)?
+            .into_iter()
+            .map(|record| match <[CdfType; 1]>::try_from(record.data) {
+                Ok([value]) => Ok(value),
+                Err(data) => Err(CdfError::Other(format!(
+                    "Record of scalar variable {} holds {} values.",
+                    self.name(),
+                    data.len()
+                ))),
+            })
+            .collect()
+    }
+
+    /// Read the records in `records` and mask out those flagged as bad by the companion variable
+    /// `flags`, e.g. the variable named by the `QUALITY` attribute, see [`Variable::companion`].
+    /// A record is masked, i.e. `None`, if `is_bad` returns true for any value of the record of
+    /// `flags` with the same number. Records past the last one of `flags` use its pad record.
+    /// # Errors
+    /// Returns a [`CdfError`] if the range is out of bounds for this variable, or if the records
+    /// of either variable cannot be read.
+    pub fn read_masked<F>(
+        &self,
+        flags: &Variable,
+        records: Range<usize>,
+        mut is_bad: F,
+    ) -> Result<Vec<Option<VariableRecord>>, CdfError>
+    where
+        F: FnMut(&CdfType) -> bool,
+    {
+        let data = self.read_records(records.clone())?;
+        let stored = records.start.min(flags.n_records())..records.end.min(flags.n_records());
+        let mut masks: Vec<bool> = flags
+            .read_records(stored)?
+            .iter()
+            .map(|record| record.data.iter().any(&mut is_bad))
+            .collect();
+        if masks.len() < data.len() {
+            let pad = flags.pad_record()?.data.iter().any(&mut is_bad);
+            masks.resize(data.len(), pad);
+        }
+        Ok(data
+            .into_iter()
+            .zip(masks)
+            .map(|(record, bad)| (!bad).then_some(record))
+            .collect())
+    }
+
+    /// Read the records in `records` together with their record numbers, leaving out the records
+    /// that are not stored in the file instead of filling them with the pad record. Records of
+    /// variables with sparse records are virtual where data is missing, and these show up as
+    /// `None`, so that gaps in the data can be told apart from stored values.
+    /// # Errors
+    /// Returns a [`CdfError`] if the range is out of bounds, or if a record cannot be read.
+    pub fn read_sparse(
+        &self,
+        records: Range<usize>,
+    ) -> Result<impl Iterator<Item = (usize, Option<VariableRecord>)>, CdfError> {
+        let (slots, _) = self.read_with(records.clone(), 1, false)?;
+        let promotion = self.cdf.promotion;
+        Ok(records.zip(slots.into_iter().map(move |r| match promotion {
+            Some(promotion) => r.map(|r| promotion.apply(r)),
+            None => r,
+        })))
+    }
+
+    /// Read the records in `records` as the bytes they are stored with in the file, one record
+    /// after the other, without decoding each value. Uncompressed records that are read from the
+    /// file are copied into the buffer directly, and records that are not stored are filled with
+    /// the pad record. With the `bytemuck` feature, [`RawRecords::values`] views the bytes as a
+    /// slice of numbers without copying them when the file has the byte order of the host.
+    /// # Errors
+    /// Returns a [`CdfError`] if the range is out of bounds, or if a record cannot be read.
+    pub fn read_raw(&self, records: Range<usize>) -> Result<RawRecords, CdfError> {
+        let (_, bytes) = self.read_with(records.clone(), 1, true)?;
+        let data_type = DataType::try_from(self.data_type().clone())?;
+        Ok(RawRecords {
+            data_type,
+            endianness: self.cdf.cdr.encoding.get_endian()?,
+            record_size: data_type.size() * self.values_per_record()?,
+            records,
+            bytes,
+        })
+    }
+
+    /// Sizes of the records of this variable in the file: the bytes of the VVRs and CVVRs that
+    /// hold them, and their size once uncompressed, to compare how well variables compress. Blocks
+    /// that were not loaded are sized from their headers in the file, without reading their values.
+    /// # Errors
+    /// Returns a [`CdfError`] if the index of the variable is invalid, or if a block was not loaded
+    /// and cannot be read from the file.
+    pub fn storage_info(&self) -> Result<StorageInfo, CdfError> {
+        let mut source = self.cdf.source.as_ref().map(|source| source.lock());
+        let context = match &source {
+            Some(source) => source.context.clone(),
+            None => self.detached_context()?,
+        };
+        let record_size =
+            DataType::try_from(self.data_type().clone())?.size() * self.values_per_record()?;
+        let mut walker = StorageWalker {
+            decoder: source.as_deref_mut(),
+            context,
+            record_size: u64::try_from(record_size)?,
+            depth: 0,
+            info: StorageInfo::default(),
+        };
+        for vxr in descriptor_field!(self, vxr_vec) {
+            if let Err(e) = walker.add_vxr(vxr) {
+                return Err(match walker.decoder {
+                    Some(decoder) => decoder.explain_truncation(e),
+                    None => e,
+                });
+            }
+        }
+        Ok(walker.info)
+    }
+
+    /// Summary statistics of the values of this variable, computed in a single pass over its
+    /// records, which are read a block at a time rather than all at once. Values equal to the
+    /// `FILLVAL` of the variable and NaN values are counted as fill values and left out.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if the variable does not hold numbers, or a [`CdfError`] if
+    /// its records cannot be read.
+    pub fn stats(&self) -> Result<VariableStats, CdfError> {
+        let mut stats = VariableStats::default();
+        let (mut mean, mut m2) = (0.0, 0.0);
+        self.visit_numbers(|x| {
+            let Some(x) = x else {
+                stats.fill_count += 1;
+                return;
+            };
+            // Welford's algorithm, which is stable in a single pass.
+            stats.count += 1;
+            let delta = x - mean;
+            mean += delta / stats.count as f64;
+            m2 += delta * (x - mean);
+            stats.min = Some(stats.min.map_or(x, |min| min.min(x)));
+            stats.max = Some(stats.max.map_or(x, |max| max.max(x)));
+        })?;
+        if stats.count > 0 {
+            stats.mean = Some(mean);
+            stats.stddev = Some(sqrt(m2 / stats.count as f64));
+        }
+        Ok(stats)
+    }
+
+    // Call `visit` with each value of this variable as a number, or with `None` for fill values,
+    // reading its records a block at a time. Fill values are equal to the `FILLVAL` of the
+    // variable or are NaN.
+    pub(crate) fn visit_numbers<F>(&self, mut visit: F) -> Result<(), CdfError>
+    where
+        F: FnMut(Option<f64>),
+    {
+        let fill = self.number_fill()?;
+        let n_records = self.n_records();
+        for start in (0..n_records).step_by(STATS_RECORDS) {
+            let records = self.read_records(start..(start + STATS_RECORDS).min(n_records))?;
+            for x in records
+                .iter()
+                .flat_map(|r| r.data.iter().filter_map(CdfType::as_f64))
+            {
+                visit((!x.is_nan() && fill != Some(x)).then_some(x));
+            }
+        }
+        Ok(())
+    }
+
+    /// Aggregate the values of this variable over bins of its epoch, named by its `DEPEND_0`
+    /// attribute, that are `bin` long, such as 1-minute averages of a product with a 3-second
+    /// cadence. Bins start at multiples of `bin` since the Unix epoch and only bins holding
+    /// records are returned. Each value of a record is aggregated separately, leaving out values
+    /// equal to the `FILLVAL` of the variable, NaN values, and records whose time is a fill value.
+    /// The records are read a block at a time rather than all at once.
+    /// # Errors
+    /// Returns a [`CdfError::Other`] if the variable does not hold numbers, if it has no epoch, if
+    /// `bin` is zero or if the epoch does not increase monotonically, or a [`CdfError`] if its
+    /// records cannot be read.
+    pub fn aggregate_by(
+        &self,
+        bin: Duration,
+        aggregation: Aggregation,
+    ) -> Result<AggregatedSeries, CdfError> {
+        let fill = self.number_fill()?;
+        let epoch = self.companion("DEPEND_0").ok_or_else(|| {
+            CdfError::Other(format!(
+                "Variable {} has no epoch named by DEPEND_0.",
+                self.name()
+            ))
+        })?;
+        let epoch_type = DataType::try_from(epoch.data_type().clone())?;
+        let bin = i128::try_from(bin.as_nanos()).unwrap_or(i128::MAX);
+        if bin == 0 {
+            return Err(CdfError::Other(format!(
+                "Cannot aggregate variable {} over bins of no duration.",
+                self.name()
+            )));
+        }
+
+        let mut series = AggregatedSeries::default();
+        let mut flush = |index: i128, bins: &[Accumulator]| -> Result<(), CdfError> {
+            let start = index * bin;
+            series.times.push(match epoch_type {
+                DataType::Epoch => CdfType::Epoch(CdfEpoch::from_unix_nanos(start)?),
+                DataType::Epoch16 => CdfType::Epoch16(CdfEpoch16::from_unix_nanos(start)?),
+                DataType::TimeTt2000 => CdfType::TimeTt2000(CdfTimeTt2000::from_unix_nanos(start)?),
+                other => {
+                    return Err(CdfError::Other(format!(
+                        "Epoch {} holds values of type {other:?}, which are not times.",
+                        epoch.name()
+                    )))
+                }
+            });
+            series
+                .values
+                .push(bins.iter().map(|b| b.value(aggregation)).collect());
+            Ok(())
+        };
+        let mut current = None;
+        let mut bins = vec![Accumulator::default(); self.values_per_record()?];
+        let n_records = epoch.n_records().min(self.n_records());
+        for start in (0..n_records).step_by(STATS_RECORDS) {
+            let records = start..(start + STATS_RECORDS).min(n_records);
+            let times = epoch.read_records(records.clone())?;
+            for (time, record) in times.iter().zip(self.read_records(records)?) {
+                let time = time.data.first().ok_or_else(|| {
+                    CdfError::Other(format!("Epoch {} has no values.", epoch.name()))
+                })?;
+                let Some(time) = unix_nanos(time)? else {
+                    continue;
+                };
+                let index = time.div_euclid(bin);
+                match current {
+                    Some(current) if index == current => {}
+                    Some(current) if index < current => {
+                        return Err(CdfError::Other(format!(
+                            "Epoch {} does not increase monotonically.",
+                            epoch.name()
+                        )));
+                    }
+                    _ => {
+                        if let Some(current) = current {
+                            flush(current, &bins)?;
+                        }
+                        bins.fill(Accumulator::default());
+                        current = Some(index);
+                    }
+                }
+                for (bin, x) in bins.iter_mut().zip(record.data.iter().map(CdfType::as_f64)) {
+                    match x {
+                        Some(x) if !x.is_nan() && fill != Some(x) => bin.add(x),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        if let Some(current) = current {
+            flush(current, &bins)?;
+        }
+        Ok(series)
+    }
+
+    // The `FILLVAL` of this variable as a number, checking that its values are numbers.
+    fn number_fill(&self) -> Result<Option<f64>, CdfError> {
+        let data_type = DataType::try_from(self.data_type().clone())?;
+        if data_type.is_string()
+            || matches!(
+                data_type,
+                DataType::Epoch | DataType::Epoch16 | DataType::TimeTt2000
+            )
+        {
+            return Err(CdfError::Other(format!(
+                "Variable {} holds values of type {data_type:?}, which are not numbers.",
+                self.name()
+            )));
+        }
+        Ok(self
+            .attribute("FILLVAL")
+            .and_then(|fill| fill.first())
+            .and_then(CdfType::as_f64))
+    }
+
+    // Read every `step`-th record in `records`, either decoded into slots, which are `None` for
+    // records that are not stored, or as the bytes of the file if `raw` is set.
+    fn read_with(
+        &self,
+        records: Range<usize>,
+        step: usize,
+        raw: bool,
+    ) -> Result<(Vec<Option<VariableRecord>>, Vec<u8>), CdfError> {
+        let n_records = self.n_records();
+        if records.start > records.end || records.end > n_records {
+            return Err(CdfError::Other(format!(
+                "Records {}..{} are out of bounds for variable {} with {n_records} records.",
+                records.start,
+                records.end,
+                self.name()
+            )));
+        }
+        if step == 0 {
+            return Err(CdfError::Other(format!(
+                "Cannot read records of variable {} with a step of 0.",
+                self.name()
+            )));
+        }
+        if let Some(virtual_variable) = self.virtual_variable {
+            let computed = virtual_variable.read(self, records, step)?;
+            if raw {
+                let context = self.detached_context()?;
+                let mut bytes = Vec::new();
+                for record in &computed {
+                    bytes.extend(encode_record(&context, record)?);
+                }
+                return Ok((vec![], bytes));
+            }
+            return Ok((computed.into_iter().map(Some).collect(), vec![]));
+        }
+
+        let mut borrowed;
+        let mut detached;
+        let decoder: &mut Decoder<Box<dyn ReadSeek>> = match &self.cdf.source {
+            Some(source) => {
+                borrowed = source.lock();
+                &mut borrowed
+            }
+            None => {
+                detached = Decoder::new(Box::new(io::empty()) as Box<dyn ReadSeek>)?;
+                detached.context = self.detached_context()?;
+                &mut detached
+            }
+        };
+        decoder.context.var_data_type = Some(self.data_type().clone());
+        decoder.context.var_data_len =
+            Some(CdfInt4::from(i32::try_from(self.values_per_record()?)?));
+        decoder.context.compression = self.compression().map(|c| c.compression_type.clone());
+        decoder.context.variable_name = Some(self.name().to_string());
+        let context = decoder.context.clone();
+
+        let record_size =
+            DataType::try_from(self.data_type().clone())?.size() * self.values_per_record()?;
+        let mut reader = RecordReader {
+            decoder,
+            context,
+            has_source: self.cdf.source.is_some(),
+            record_size,
+            records: records.clone(),
+            step,
+            slots: vec![None; records.len().div_ceil(step)],
+            depth: 0,
+            raw: None,
+        };
+        if raw {
+            // Records that are not stored keep the pad record written here.
+            let pad = encode_record(&reader.context, &self.pad_record()?)?;
+            reader.slots = Vec::new();
+            reader.raw = Some(pad.repeat(records.len()));
+        }
+
+        // Consecutive VVRs are read ahead together, up to the size of the records to read along
+        // with the header of the VVR of each record.
+        let prefetch = reader
+            .decoder
+            .options
+            .prefetch
+            .min(records.len().saturating_mul(record_size + VVR_HEADER_SIZE));
+        if !reader.has_source || prefetch == 0 {
+            reader.read_all(descriptor_field!(self, vxr_vec))?;
+            return Ok((reader.slots, reader.raw.unwrap_or_default()));
+        }
+        let decoder = reader.decoder;
+        let mut prefetching = Decoder {
+            reader: io::BufferedReader::with_capacity(prefetch, &mut decoder.reader),
+            context: decoder.context.clone(),
+            options: decoder.options.clone(),
+        };
+        let mut reader = RecordReader {
+            decoder: &mut prefetching,
+            context: reader.context,
+            has_source: reader.has_source,
+            record_size,
+            records: reader.records,
+            step,
+            slots: reader.slots,
+            depth: 0,
+            raw: reader.raw,
+        };
+        reader.read_all(descriptor_field!(self, vxr_vec))?;
+        Ok((reader.slots, reader.raw.unwrap_or_default()))
+    }
+
+    // Convert the values of `record` as set by `ReadOptions::promote`.
+    fn promote(&self, record: VariableRecord) -> VariableRecord {
+        match self.cdf.promotion {
+            Some(promotion) => promotion.apply(record),
+            None => record,
+        }
+    }
+
+    // The context needed to decode values when the file is no longer available.
+    fn detached_context(&self) -> Result<DecodeContext, CdfError> {
+        let cdr = &self.cdf.cdr;
+        Ok(DecodeContext {
+            encoding: Some(cdr.encoding.clone()),
+            endianness: Some(cdr.encoding.get_endian()?),
+            version: Some(cdr.cdf_version.clone()),
+            num_r_dims: Some(cdr.gdr.num_r_dims.clone()),
+            size_r_dims: Some(cdr.gdr.size_r_dims.clone()),
+            row_major: Some(cdr.flags.row_major),
+            ..Default::default()
+        })
+    }
+}
+
+/// Which record [`Variable::find_record_at`] returns for a time.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordMatch {
+    /// The record closest in time, or the earlier one if both are as close.
+    Nearest,
+    /// The last record at or before the time.
+    Before,
+    /// The first record at or after the time.
+    After,
+}
+
+/// How [`Variable::aggregate_by`] combines the values within a bin.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    /// Mean of the values.
+    Mean,
+    /// Smallest value.
+    Min,
+    /// Largest value.
+    Max,
+    /// Number of values, leaving out fill values.
+    Count,
+}
+
+/// Values of a variable aggregated over bins of time, see [`Variable::aggregate_by`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default)]
+pub struct AggregatedSeries {
+    /// Start of each bin, as a time of the same type as the epoch of the variable.
+    pub times: Vec<CdfType>,
+    /// Aggregated values of each bin, one for each value of a record of the variable. Values
+    /// are `None` if all values within the bin are fill values, except when counting them.
+    pub values: Vec<Vec<Option<f64>>>,
+}
+
+// Running aggregates of one value of a record within a bin.
+#[derive(Debug, Clone, Copy, Default)]
+struct Accumulator {
+    count: usize,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl Accumulator {
+    fn add(&mut self, x: f64) {
+        self.count += 1;
+        self.sum += x;
+        self.min = Some(self.min.map_or(x, |min| min.min(x)));
+        self.max = Some(self.max.map_or(x, |max| max.max(x)));
+    }
+
+    fn value(&self, aggregation: Aggregation) -> Option<f64> {
+        match aggregation {
+            Aggregation::Mean => (self.count > 0).then(|| self.sum / self.count as f64),
+            Aggregation::Min => self.min,
+            Aggregation::Max => self.max,
+            Aggregation::Count => Some(self.count as f64),
+        }
+    }
+}
+
+/// Type that the values of numeric variables are converted to when they are read, see
+/// [`ReadOptions::promote`](crate::cdf::ReadOptions::promote).
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Promotion {
+    /// Integer and real values are read as [`CdfType::Real8`]. Integers beyond 2^53 lose
+    /// precision.
+    Float64,
+    /// Integer values are read as [`CdfType::Int8`]. Real values are left as they are, since they
+    /// cannot be converted without loss.
+    Int64,
+}
+
+impl Promotion {
+    /// Convert the values of `record` to the promoted type. Records of times, strings, and of
+    /// reals for [`Promotion::Int64`], are returned unchanged.
+    pub fn apply(self, mut record: VariableRecord) -> VariableRecord {
+        let (data_type, data) = match self {
+            Promotion::Float64 => (
+                DataType::Real8,
+                record
+                    .data
+                    .iter()
+                    .map(|v| v.as_f64().map(|x| CdfType::Real8(CdfReal8::from(x))))
+                    .collect::<Option<Vec<_>>>(),
+            ),
+            Promotion::Int64 => (
+                DataType::Int8,
+                record
+                    .data
+                    .iter()
+                    .map(|v| v.as_i64().map(|x| CdfType::Int8(CdfInt8::from(x))))
+                    .collect::<Option<Vec<_>>>(),
+            ),
+        };
+        if let Some(data) = data {
+            record.data_type = CdfInt4::from(data_type as i32);
+            record.data = data;
+        }
+        record
+    }
+}
+
+/// Sizes of the records of a variable as stored in the file, see [`Variable::storage_info`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageInfo {
+    /// Number of records stored in the file, including records that are allocated but not yet
+    /// written.
+    pub stored_records: usize,
+    /// Number of VVRs holding uncompressed records.
+    pub vvrs: usize,
+    /// Number of CVVRs holding compressed records.
+    pub cvvrs: usize,
+    /// Size in bytes of the VVRs, headers included.
+    pub vvr_bytes: u64,
+    /// Size in bytes of the CVVRs, headers included.
+    pub cvvr_bytes: u64,
+    /// Size in bytes of the stored records once uncompressed, which is the number of stored
+    /// records times the size of a record.
+    pub logical_bytes: u64,
+}
+
+impl StorageInfo {
+    /// Size in bytes of the records in the file.
+    pub fn stored_bytes(&self) -> u64 {
+        self.vvr_bytes + self.cvvr_bytes
+    }
+
+    /// Uncompressed size of the records divided by their size in the file, which is above 1 when
+    /// compression saves space. Returns `None` if no records are stored.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        (self.stored_bytes() > 0).then(|| self.logical_bytes as f64 / self.stored_bytes() as f64)
+    }
+}
+
+/// Summary statistics of the values of a variable, see [`Variable::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VariableStats {
+    /// Number of values that are not fill values.
+    pub count: usize,
+    /// Number of fill values, which are equal to the `FILLVAL` of the variable or are NaN.
+    pub fill_count: usize,
+    /// Smallest value, or `None` if all values are fill values.
+    pub min: Option<f64>,
+    /// Largest value, or `None` if all values are fill values.
+    pub max: Option<f64>,
+    /// Mean of the values, or `None` if all values are fill values.
+    pub mean: Option<f64>,
+    /// Population standard deviation of the values, or `None` if all values are fill values.
+    pub stddev: Option<f64>,
+}
+
+/// Records of a variable as stored in the file, read by [`Variable::read_raw`]. The values of all
+/// records follow each other in the byte order of the file, and in its majority for
+/// multi-dimensional variables.
+#[derive(Debug, Clone)]
+pub struct RawRecords {
+    data_type: DataType,
+    endianness: Endian,
+    record_size: usize,
+    records: Range<usize>,
+    bytes: Vec<u8>,
+}
+
+impl RawRecords {
+    /// Data type of the values.
+    pub fn data_type(&self) -> DataType {
+        self.data_type
+    }
+
+    /// Byte order of the values, which is the byte order of the file.
+    pub fn endianness(&self) -> &Endian {
+        &self.endianness
+    }
+
+    /// Whether the values are stored in the byte order of the host.
+    pub fn is_native(&self) -> bool {
+        matches!(
+            (&self.endianness, cfg!(target_endian = "little")),
+            (Endian::Little, true) | (Endian::Big, false)
+        )
+    }
+
+    /// Records that were read.
+    pub fn records(&self) -> Range<usize> {
+        self.records.clone()
+    }
+
+    /// Number of bytes of each record.
+    pub fn record_size(&self) -> usize {
+        self.record_size
+    }
+
+    /// Bytes of all records.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Bytes of the record at `index` within the records that were read.
+    pub fn record(&self, index: usize) -> Option<&[u8]> {
+        self.bytes
+            .get(index * self.record_size..(index + 1) * self.record_size)
+    }
+
+    /// Convert the values to the byte order of the host, swapping the bytes of each value in place
+    /// if the file has the other byte order. Values of [`DataType::Epoch16`] are swapped as pairs
+    /// of 8-byte floating points.
+    pub fn into_native(mut self) -> Self {
+        if !self.is_native() {
+            let size = match self.data_type {
+                DataType::Epoch16 => 8,
+                data_type => data_type.size(),
+            };
+            for value in self.bytes.chunks_exact_mut(size) {
+                value.reverse();
+            }
+            self.endianness = match self.endianness {
+                Endian::Big => Endian::Little,
+                Endian::Little => Endian::Big,
+            };
+        }
+        self
+    }
+
+    /// The values of all records as numbers of type `T`, which must match the data type. The
+    /// bytes are borrowed if they are suitably aligned for `T`, and copied otherwise.
+    /// Returns `None` if the values are not in the byte order of the host, see
+    /// [`RawRecords::into_native`], or if `T` does not match the data type.
+    #[cfg(feature = "bytemuck")]
+    pub fn values<T: RawValue>(&self) -> Option<Cow<'_, [T]>> {
+        if !self.is_native() || !T::matches(self.data_type) {
+            return None;
+        }
+        Some(match bytemuck::try_cast_slice(&self.bytes) {
+            Ok(values) => Cow::Borrowed(values),
+            Err(_) => Cow::Owned(bytemuck::pod_collect_to_vec(&self.bytes)),
+        })
+    }
+}
+
+/// Numbers that the values of [`RawRecords`] can be viewed as.
+#[cfg(feature = "bytemuck")]
+pub trait RawValue: bytemuck::Pod {
+    /// Whether values of `data_type` are stored as this type.
+    fn matches(data_type: DataType) -> bool;
+}
+
+#[cfg(feature = "bytemuck")]
+macro_rules! impl_raw_value {
+    ($rust_type:ty, $($data_type:ident),+) => {
+        impl RawValue for $rust_type {
+            fn matches(data_type: DataType) -> bool {
+                matches!(data_type, $(DataType::$data_type)|+)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "bytemuck")]
+impl_raw_value!(i8, Int1, Byte, Char);
+#[cfg(feature = "bytemuck")]
+impl_raw_value!(i16, Int2);
+#[cfg(feature = "bytemuck")]
+impl_raw_value!(i32, Int4);
+#[cfg(feature = "bytemuck")]
+impl_raw_value!(i64, Int8, TimeTt2000);
+#[cfg(feature = "bytemuck")]
+impl_raw_value!(u8, Uint1, Uchar);
+#[cfg(feature = "bytemuck")]
+impl_raw_value!(u16, Uint2);
+#[cfg(feature = "bytemuck")]
+impl_raw_value!(u32, Uint4);
+#[cfg(feature = "bytemuck")]
+impl_raw_value!(f32, Real4, Float);
+#[cfg(feature = "bytemuck")]
+impl_raw_value!(f64, Real8, Double, Epoch, Epoch16);
+
+/// The variable attributes of one variable, as passed to [`Cdf::find_variables`]. Indexing with
+/// the name of an attribute, as in `variable.attributes()["UNITS"]`, gives its value and panics if
+/// the variable has no entry for it.
+#[derive(Debug, Clone, Copy)]
+pub struct VariableAttributes<'a> {
+    variable: Variable<'a>,
+}
+
+impl<'a> VariableAttributes<'a> {
+    /// Value of the attribute called `name`, if it is a string.
+    pub fn get(&self, name: &str) -> Option<&'a str> {
+        self.variable.attribute_str(name)
+    }
+
+    /// Value of the attribute called `name`, whatever its type.
+    pub fn value(&self, name: &str) -> Option<&'a [CdfType]> {
+        self.variable.attribute(name)
+    }
+
+    /// Strings of the attribute called `name`, if it holds characters, with entries that hold
+    /// several strings split into one string each.
+    pub fn strings(&self, name: &str) -> Option<Vec<String>> {
+        self.variable.attribute_strings(name)
+    }
+
+    /// Whether the variable has an entry for the attribute called `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.value(name).is_some()
+    }
+
+    /// Names and values of all attributes that have an entry for the variable.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a [CdfType])> + 'a {
+        let variable = self.variable;
+        variable
+            .cdf
+            .cdr
+            .gdr
+            .adr_vec
+            .iter()
+            .filter_map(move |adr| Some((&**adr.name, variable.attribute(&adr.name)?)))
+    }
+}
+
+impl Index<&str> for VariableAttributes<'_> {
+    type Output = [CdfType];
+
+    fn index(&self, name: &str) -> &[CdfType] {
+        self.value(name).unwrap_or_else(|| {
+            panic!(
+                "Variable {} has no entry for attribute {name}.",
+                self.variable.name()
+            )
+        })
+    }
+}
+
+/// Whether the variable attribute called `name` holds the name of another variable, such as
+/// `DEPEND_0`, `LABL_PTR_1` or `DELTA_PLUS_VAR` in the ISTP guidelines, or `QUALITY`.
+pub fn is_reference_attribute(name: &str) -> bool {
+    ["DEPEND_", "LABL_PTR_", "COMPONENT_"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+        || matches!(
+            name,
+            "UNIT_PTR" | "FORM_PTR" | "SCAL_PTR" | "DELTA_PLUS_VAR" | "DELTA_MINUS_VAR" | "QUALITY"
+        )
+}
+
+// Square root of `x`, which is not `f64::sqrt` without `std`.
+fn sqrt(x: f64) -> f64 {
+    #[cfg(feature = "std")]
+    {
+        x.sqrt()
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        if x <= 0.0 || !x.is_finite() {
+            return x.max(0.0);
+        }
+        // Newton's method from a guess that halves the exponent. After the first step, the
+        // estimates decrease towards the root until they stop changing.
+        let mut root = f64::from_bits((x.to_bits() + (1023 << 52)) >> 1);
+        root = 0.5 * (root + x / root);
+        loop {
+            let next = 0.5 * (root + x / root);
+            if next >= root {
+                return root;
+            }
+            root = next;
+        }
+    }
+}
+
+// Walks the VXR tree of one variable and adds up the sizes of the blocks that hold its records.
+struct StorageWalker<'d> {
+    decoder: Option<&'d mut Decoder<Box<dyn ReadSeek>>>,
+    context: DecodeContext,
+    record_size: u64,
+    // Number of levels of VXRs below the top-level VXR that is being walked.
+    depth: usize,
+    info: StorageInfo,
+}
+
+impl StorageWalker<'_> {
+    fn add_vxr(&mut self, vxr: &VariableIndexRecord) -> Result<(), CdfError> {
+        for (i, offset) in vxr.offset_vec.iter().enumerate() {
+            let (Some(Some(_)), Some(offset)) = (vxr.first_vec.get(i), offset) else {
+                continue;
+            };
+            let num_records = vxr.num_records(i)?;
+            let (record_type, size) = match vxr.children.get(i) {
+                Some(Some(VariableIndexRecordChild::VXR(child))) => {
+                    self.add_child(child)?;
+                    continue;
+                }
+                Some(Some(VariableIndexRecordChild::VVR(vvr))) => (7, *vvr.record_size),
+                Some(Some(VariableIndexRecordChild::CVVR(cvvr))) => (13, *cvvr.record_size),
+                Some(Some(VariableIndexRecordChild::Unknown(record))) => {
+                    (*record.record_type, *record.record_size)
+                }
+                _ => {
+                    let Some(decoder) = self.decoder.as_deref_mut() else {
+                        return Err(CdfError::Other(
+                            "Variable records were not loaded and the file is not available."
+                                .to_string(),
+                        ));
+                    };
+                    decoder.context = self.context.clone();
+                    decoder.context.skip_values = true;
+                    let offset = decoder.seek_offset(**offset)?;
+                    decoder.context.record_start = Some(offset);
+                    let size = decode_version3_int4_int8(decoder)?;
+                    let record_type = CdfInt4::decode_be(decoder)?;
+                    if *record_type == 6 {
+                        if let Some(VariableIndexRecordChild::VXR(child)) =
+                            vxr.read_child(decoder, i)?
+                        {
+                            self.add_child(&child)?;
+                        }
+                        continue;
+                    }
+                    (*record_type, *size)
+                }
+            };
+            let size = u64::try_from(size)?;
+            match record_type {
+                7 => {
+                    self.info.vvrs += 1;
+                    self.info.vvr_bytes += size;
+                }
+                13 => {
+                    self.info.cvvrs += 1;
+                    self.info.cvvr_bytes += size;
+                }
+                e => {
+                    return Err(CdfError::Decode(format!(
+                        "Invalid record_type for VXR entry - expected 6, 7 or 13, received {e}"
+                    )))
+                }
+            }
+            self.info.stored_records += num_records;
+            self.info.logical_bytes += u64::try_from(num_records)? * self.record_size;
+        }
+        Ok(())
+    }
+
+    fn add_child(&mut self, vxr: &VariableIndexRecord) -> Result<(), CdfError> {
+        let max_depth = self
+            .decoder
+            .as_ref()
+            .map_or(DEFAULT_MAX_DEPTH, |decoder| decoder.options.max_depth);
+        if self.depth >= max_depth {
+            return Err(CdfError::MaxDepthExceeded { max_depth });
+        }
+        self.depth += 1;
+        let result = self.add_vxr(vxr);
+        self.depth -= 1;
+        result
+    }
+}
+
+// Walks the VXR tree of one variable and collects the records that fall in the requested range.
+struct RecordReader<'d, R>
+where
+    R: io::Read + io::Seek,
+{
+    decoder: &'d mut Decoder<R>,
+    context: DecodeContext,
+    has_source: bool,
+    record_size: usize,
+    records: Range<usize>,
+    step: usize,
+    slots: Vec<Option<VariableRecord>>,
+    // Number of levels of VXRs below the top-level VXR that is being read.
+    depth: usize,
+    // Bytes of the records to read, stored as in the file, when reading raw records.
+    raw: Option<Vec<u8>>,
+}
+
+impl<R> RecordReader<'_, R>
+where
+    R: io::Read + io::Seek,
+{
+    fn read_all(&mut self, vxrs: &[VariableIndexRecord]) -> Result<(), CdfError> {
+        for vxr in vxrs {
+            if let Err(e) = self.read_vxr(vxr) {
+                return Err(self.decoder.explain_truncation(e));
+            }
+        }
+        Ok(())
+    }
+
+    fn read_vxr(&mut self, vxr: &VariableIndexRecord) -> Result<(), CdfError> {
+        for (i, offset) in vxr.offset_vec.iter().enumerate() {
+            let (Some(Some(first)), Some(offset)) = (vxr.first_vec.get(i), offset) else {
+                continue;
+            };
+            let first = checked_usize("first", **first)?;
+            let num_records = vxr.num_records(i)?;
+            if self.first_wanted(first) >= (first + num_records).min(self.records.end) {
+                continue;
+            }
+
+            if let Some(Some(child)) = vxr.children.get(i) {
+                self.read_child(child, first, num_records)?;
+                continue;
+            }
+            if !self.has_source {
+                return Err(CdfError::Other(
+                    "Variable records were not loaded and the file is not available.".to_string(),
+                ));
+            }
+
+            // Read the child from the file. Records of a VVR are read one by one, and lower-level
+            // VXRs are only indexed here and their children are read as they are reached.
+            self.decoder.context = self.context.clone();
+            self.decoder.context.num_records = Some(num_records);
+            self.decoder.context.skip_values = true;
+            let offset = self.decoder.seek_offset(**offset)?;
+            self.decoder.context.record_start = Some(offset);
+            _ = decode_version3_int4_int8(self.decoder)?;
+            let record_type = CdfInt4::decode_be(self.decoder)?;
+            if *record_type == 7 {
+                let values_offset = self.decoder.reader.stream_position()?;
+                self.read_vvr(values_offset, first, num_records)?;
+                self.decoder.report_progress(&record_type)?;
+                continue;
+            }
+            if let Some(child) = vxr.read_child(self.decoder, i)? {
+                self.read_child(&child, first, num_records)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_child(
+        &mut self,
+        child: &VariableIndexRecordChild,
+        first: usize,
+        num_records: usize,
+    ) -> Result<(), CdfError> {
+        match child {
+            VariableIndexRecordChild::VXR(vxr) => {
+                let max_depth = self.decoder.options.max_depth;
+                if self.depth >= max_depth {
+                    return Err(CdfError::MaxDepthExceeded { max_depth });
+                }
+                self.depth += 1;
+                let result = self.read_vxr(vxr);
+                self.depth -= 1;
+                result
+            }
+            VariableIndexRecordChild::VVR(vvr) => self.store(first, &vvr.records),
+            VariableIndexRecordChild::CVVR(cvvr) if !cvvr.records.is_empty() => {
+                self.store(first, &cvvr.records)
+            }
+            VariableIndexRecordChild::CVVR(cvvr) => {
+                self.decoder.context = self.context.clone();
+                self.decoder.context.num_records = Some(num_records);
+                let records = cvvr.decompress_records(self.decoder)?;
+                self.store(first, &records)
+            }
+            VariableIndexRecordChild::Unknown(record) => Err(CdfError::Decode(format!(
+                "Records {first} to {} are stored in a record of unknown type {} at offset {}.",
+                first + num_records - 1,
+                *record.record_type,
+                record.offset
+            ))),
+        }
+    }
+
+    // Read the wanted records of a VVR whose values start at `values_offset` in the file.
+    fn read_vvr(
+        &mut self,
+        values_offset: u64,
+        first: usize,
+        num_records: usize,
+    ) -> Result<(), CdfError> {
+        let start = self.first_wanted(first);
+        let end = (first + num_records).min(self.records.end);
+        if let Some(raw) = &mut self.raw {
+            // Raw records are contiguous, so all wanted records of the VVR are read at once.
+            let position = values_offset + u64::try_from((start - first) * self.record_size)?;
+            _ = self.decoder.reader.seek(SeekFrom::Start(position))?;
+            let from = (start - self.records.start) * self.record_size;
+            let to = (end - self.records.start) * self.record_size;
+            self.decoder.reader.read_exact(&mut raw[from..to])?;
+            return Ok(());
+        }
+        for r in (start..end).step_by(self.step) {
+            self.decoder.check_cancelled()?;
+            let slot = (r - self.records.start) / self.step;
+            let position = values_offset + u64::try_from((r - first) * self.record_size)?;
+            _ = self.decoder.reader.seek(SeekFrom::Start(position))?;
+            self.slots[slot] = Some(VariableRecord::decode_be(self.decoder)?);
+        }
+        Ok(())
+    }
+
+    fn store(&mut self, first: usize, records: &[VariableRecord]) -> Result<(), CdfError> {
+        for (i, record) in records.iter().enumerate() {
+            let Some(slot) = self.slot(first + i) else {
+                continue;
+            };
+            let Some(raw) = &mut self.raw else {
+                self.slots[slot] = Some(record.clone());
+                continue;
+            };
+            let bytes = encode_record(&self.context, record)?;
+            let target = raw
+                .get_mut(slot * self.record_size..(slot + 1) * self.record_size)
+                .filter(|target| target.len() == bytes.len())
+                .ok_or_else(|| {
+                    CdfError::Decode(format!(
+                        "Record {} of {} bytes does not match the record size {}.",
+                        first + i,
+                        bytes.len(),
+                        self.record_size
+                    ))
+                })?;
+            target.copy_from_slice(&bytes);
+        }
+        Ok(())
+    }
+
+    // The first record to read that is not before record `r`.
+    fn first_wanted(&self, r: usize) -> usize {
+        let start = self.records.start;
+        start + r.saturating_sub(start).div_ceil(self.step) * self.step
+    }
+
+    // Position in the result of record `r`, if it is one of the records to read.
+    fn slot(&self, r: usize) -> Option<usize> {
+        let offset = r.checked_sub(self.records.start)?;
+        (r < self.records.end && offset % self.step == 0).then_some(offset / self.step)
+    }
+}
+
+// Encode the values of `record` as they are stored in the file described by `context`.
+fn encode_record(context: &DecodeContext, record: &VariableRecord) -> Result<Vec<u8>, CdfError> {
+    let encoder = Encoder::new(
+        io::Cursor::new(Vec::new()),
+        context.version()?,
+        context.encoding()?,
+    )?;
+    encode_to_vec(&encoder, record)
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+
+    use crate::cdf::ReadOptions;
+    use crate::error::CdfError;
+    use crate::types::CdfReal4;
+    use crate::view::CdfView;
+    use crate::writer::{CdfWriter, VariableSpec};
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn _path(filename: &str) -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "examples", "data", filename]
+            .iter()
+            .collect()
+    }
+
+    fn _assert_same_records(a: &[VariableRecord], b: &[VariableRecord]) {
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert_eq!(format!("{:?}", x.data), format!("{:?}", y.data));
+        }
+    }
+
+    #[test]
+    fn test_variable_attributes() -> Result<(), CdfError> {
+        let cdf = Cdf::read_cdf_file(_path("ulysses.cdf"))?;
+        let attributes = cdf.variable("B_scalar")?.attributes();
+        assert_eq!(attributes.get("DEPEND_0"), Some("Epoch"));
+        assert_eq!(attributes.get("VAR_TYPE"), Some("data"));
+        assert!(attributes.contains("FILLVAL"));
+        assert!(!attributes.contains("Project"));
+        assert!(attributes.iter().any(|(name, _)| name == "UNITS"));
+
+        let metadata = cdf.find_variables(|a| a.get("VAR_TYPE") == Some("metadata"));
+        let names: Vec<&str> = metadata.iter().map(|v| v.name()).collect();
+        assert_eq!(names, ["label_time", "unit_time", "format_time"]);
+        assert_eq!(
+            cdf.find_variables(|a| a.get("VAR_TYPE") == Some("data"))
+                .len(),
+            12
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_attribute_strings() -> Result<(), CdfError> {
+        let mut cdf = Cdf::read_cdf_file(_path("ulysses.cdf"))?;
+        let num = **cdf.variable("B_scalar")?.num();
+        let units = cdf
+            .cdr
+            .gdr
+            .adr_vec
+            .iter_mut()
+            .find(|adr| *adr.name == "UNITS")
+            .unwrap();
+        let entry = units.agredr_vec.iter_mut().find(|e| *e.num == num).unwrap();
+        entry.value = vec![CdfType::String(String::from("nT\\N gauss").into())];
+        entry.num_strings = CdfInt4::from(2);
+
+        let variable = cdf.variable("B_scalar")?;
+        assert_eq!(
+            variable.attribute_strings("UNITS"),
+            Some(vec!["nT".to_string(), "gauss".to_string()])
+        );
+        assert_eq!(
+            variable.attributes().strings("DEPEND_0"),
+            Some(vec!["Epoch".to_string()])
+        );
+        assert_eq!(variable.attribute_strings("FILLVAL"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_find_record_at() -> Result<(), CdfError> {
+        use crate::time::CdfTime;
+        use crate::types::{CdfEpoch, CdfTimeTt2000};
+
+        let options = ReadOptions {
+            lazy: true,
+            ..Default::default()
+        };
+        let cdf = Cdf::read_cdf_file_with(_path("ulysses.cdf"), options)?;
+        let b = cdf.variable("B_scalar")?;
+        let epoch = cdf.variable("Epoch")?;
+        let time = |record: usize| -> Result<f64, CdfError> {
+            match &epoch.read_records(record..record + 1)?[0].data[0] {
+                CdfType::Epoch(t) => Ok(**t),
+                other => Err(CdfError::Other(format!(
+                    "Expected an Epoch, got {other:?}."
+                ))),
+            }
+        };
+        let at = |t: f64| CdfType::Epoch(CdfEpoch::from(t));
+        let find = |t: &CdfType, mode| b.find_record_at(t, mode);
+
+        let (t0, t1) = (time(1000)?, time(1001)?);
+        for mode in [
+            RecordMatch::Nearest,
+            RecordMatch::Before,
+            RecordMatch::After,
+        ] {
+            assert_eq!(find(&at(t0), mode)?, Some(1000));
+            assert_eq!(epoch.find_record_at(&at(t1), mode)?, Some(1001));
+        }
+        let early = at(t0 + (t1 - t0) / 4.0);
+        assert_eq!(find(&early, RecordMatch::Nearest)?, Some(1000));
+        assert_eq!(find(&early, RecordMatch::Before)?, Some(1000));
+        assert_eq!(find(&early, RecordMatch::After)?, Some(1001));
+        let late = at(t1 - (t1 - t0) / 4.0);
+        assert_eq!(find(&late, RecordMatch::Nearest)?, Some(1001));
+
+        // Times of another type are compared as the same instant.
+        let nanos = CdfEpoch::from(t0).to_unix_nanos().unwrap_or_default();
+        let tt2000 = CdfType::TimeTt2000(CdfTimeTt2000::from_unix_nanos(nanos)?);
+        assert_eq!(find(&tt2000, RecordMatch::Nearest)?, Some(1000));
+
+        let last = b.n_records() - 1;
+        let (first, end) = (time(0)?, time(last)?);
+        assert_eq!(find(&at(first - 1e3), RecordMatch::Before)?, None);
+        assert_eq!(find(&at(first - 1e3), RecordMatch::Nearest)?, Some(0));
+        assert_eq!(find(&at(end + 1e3), RecordMatch::After)?, None);
+        assert_eq!(find(&at(end + 1e3), RecordMatch::Nearest)?, Some(last));
+
+        assert!(find(&at(CdfEpoch::FILL), RecordMatch::Nearest).is_err());
+        assert!(find(&CdfType::Real8(CdfReal8::from(t0)), RecordMatch::Nearest).is_err());
+        assert!(cdf
+            .variable("label_time")?
+            .find_record_at(&at(t0), RecordMatch::Nearest)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_dim_labels() -> Result<(), CdfError> {
+        let cdf = Cdf::read_cdf_file(_path("ulysses.cdf"))?;
+        let time = cdf.variable("Time_PB5")?;
+        assert_eq!(
+            time.dim_labels()?,
+            vec![Some(vec![
+                "Year".to_string(),
+                "Day of Year (Jan 1 = Day 1)".to_string(),
+                "Elapsed millisecond of day".to_string(),
+            ])]
+        );
+        assert!(cdf.variable("B_scalar")?.dim_labels()?.is_empty());
+        assert_eq!(cdf.variable("label_time")?.dim_labels()?, vec![None]);
+        assert_eq!(cdf.variable("label_time")?.label(), "label_time");
+
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("B", DataType::Real4).dims(&[3]))
+            .variable(
+                VariableSpec::new("B_labels", DataType::Char)
+                    .num_elements(2)
+                    .dims(&[2]),
+            )
+            .build(io::Cursor::new(Vec::new()))?;
+        writer.write_records(
+            "B_labels",
+            &[vec![CdfType::String(CdfString::from("BxBy".to_string()))]],
+        )?;
+        writer.var_attr("B", "LABLAXIS", "B")?;
+        writer.var_attr("B", "LABL_PTR_1", "B_labels")?;
+        let bytes = writer.close()?.into_inner();
+        let cdf = Cdf::from_bytes(&bytes)?;
+        let b = cdf.variable("B")?;
+        assert_eq!(b.label(), "B");
+        assert!(b.dim_labels().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_lazy_matches_eager() -> Result<(), CdfError> {
+        for (filename, name) in [
+            ("test_alltypes.cdf", "Longitude"),
+            ("test_alltypes.cdf", "Temp"),
+            ("test_alltypes.cdf", "Latitude"),
+            ("ulysses.cdf", "Epoch"),
+            ("ulysses.cdf", "Time_PB5"),
+        ] {
+            let eager = Cdf::read_cdf_file_with(
+                _path(filename),
+                ReadOptions {
+                    decompress: true,
+                    ..Default::default()
+                },
+            )?;
+            let lazy = Cdf::read_cdf_file_with(
+                _path(filename),
+                ReadOptions {
+                    lazy: true,
+                    ..Default::default()
+                },
+            )?;
+
+            let eager = eager.variable(name)?;
+            let lazy = lazy.variable(name)?;
+            assert_eq!(eager.n_records(), lazy.n_records());
+            _assert_same_records(&eager.read_all()?, &lazy.read_all()?);
+
+            let n = lazy.n_records();
+            let range = n / 3..n;
+            let records = lazy.read_records(range.clone())?;
+            assert_eq!(records.len(), range.len());
+            _assert_same_records(&records, &eager.read_all()?[range]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_strided() -> Result<(), CdfError> {
+        for (filename, name) in [
+            ("test_alltypes.cdf", "Longitude"),
+            ("test_alltypes.cdf", "Temp"),
+            ("ulysses.cdf", "B_scalar"),
+        ] {
+            for lazy in [false, true] {
+                let cdf = Cdf::read_cdf_file_with(
+                    _path(filename),
+                    ReadOptions {
+                        lazy,
+                        decompress: true,
+                        ..Default::default()
+                    },
+                )?;
+                let variable = cdf.variable(name)?;
+                let all = variable.read_all()?;
+                let n = variable.n_records();
+                for (range, step) in [(0..n, 1), (0..n, 3), (1..n, 7), (n / 2..n, 1000)] {
+                    let expected: Vec<VariableRecord> =
+                        all[range.clone()].iter().step_by(step).cloned().collect();
+                    _assert_same_records(&variable.read_strided(range, step)?, &expected);
+                }
+                assert!(variable.read_strided(0..n, 0).is_err());
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_selection() -> Result<(), CdfError> {
+        let cdf = Cdf::read_cdf_file_with(
+            _path("test_alltypes.cdf"),
+            ReadOptions {
+                variables: Some(vec!["Temp".to_string()]),
+                ..Default::default()
+            },
+        )?;
+        for variable in cdf.variables() {
+            let VariableDescriptor::Z(zvdr) = variable.descriptor() else {
+                panic!("test_alltypes.cdf only has zVariables");
+            };
+            let loaded = zvdr
+                .vxr_vec
+                .iter()
+                .any(|vxr| vxr.children.iter().any(Option::is_some));
+            assert_eq!(loaded, variable.name() == "Temp");
+        }
+
+        // Values that were not loaded are still read from the file.
+        let latitude = cdf.variable("Latitude")?.read_all()?;
+        assert_eq!(latitude.len(), 1);
+        assert!(cdf.variable("no_such_variable").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_scalars() -> Result<(), CdfError> {
+        let cdf = Cdf::read_cdf_file(_path("test_alltypes.cdf"))?;
+        for name in ["Temperature", "Temperature2", "ep", "ep16", "tt2000"] {
+            let variable = cdf.variable(name)?;
+            assert!(variable.dim_sizes()?.is_empty());
+            assert!(variable.is_scalar());
+            assert_eq!(variable.values_per_record()?, 1);
+            let n = variable.n_records();
+            assert_eq!(variable.array_shape()?, [n]);
+            let scalars = variable.read_scalars(0..n)?;
+            let records = variable.read_all()?;
+            assert_eq!(scalars.len(), n);
+            assert_eq!(
+                format!("{scalars:?}"),
+                format!(
+                    "{:?}",
+                    records
+                        .iter()
+                        .flat_map(|r| r.data.clone())
+                        .collect::<Vec<_>>()
+                )
+            );
+        }
+        let longitude = cdf.variable("Longitude")?;
+        assert!(!longitude.is_scalar());
+        assert_eq!(longitude.array_shape()?, [20, 3]);
+        assert!(longitude.read_scalars(0..1).is_err());
+
+        // rVariables that do not vary along the dimensions of the file are scalar too.
+        let cdf = Cdf::read_cdf_file(_path("ulysses.cdf"))?;
+        let b = cdf.variable("B_scalar")?;
+        assert_eq!((b.dim_sizes()?, b.is_scalar()), (vec![3], true));
+        assert_eq!(b.array_shape()?, [134640]);
+        assert_eq!(b.read_scalars(0..10)?.len(), 10);
+        assert!(!cdf.variable("Time_PB5")?.is_scalar());
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_pad_records() -> Result<(), CdfError> {
+        let cdf = Cdf::read_cdf_file(_path("test_alltypes.cdf"))?;
+        let temp = cdf.variable("Temp")?;
+        let records = temp.read_all()?;
+        let pad = format!("{:?}", temp.pad_record()?.data);
+        assert!(records.iter().any(|r| format!("{:?}", r.data) == pad));
+        assert!(temp.read_records(0..temp.n_records() + 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_concurrent_reads() -> Result<(), CdfError> {
+        fn _assert_send_sync<T: Send + Sync>() {}
+        _assert_send_sync::<Cdf>();
+        _assert_send_sync::<Variable<'_>>();
+
+        let eager = Cdf::read_cdf_file(_path("ulysses.cdf"))?;
+        let lazy = Cdf::read_cdf_file_with(
+            _path("ulysses.cdf"),
+            ReadOptions {
+                lazy: true,
+                ..Default::default()
+            },
+        )?;
+        std::thread::scope(|scope| -> Result<(), CdfError> {
+            let handles: Vec<_> = ["Epoch", "Time_PB5", "Dist_HGI", "Lat_HGI"]
+                .into_iter()
+                .enumerate()
+                .map(|(i, name)| {
+                    let lazy = &lazy;
+                    scope.spawn(move || {
+                        let start = i * 1000;
+                        lazy.variable(name)?
+                            .read_records(start..start + 5000)
+                            .map(|records| (name, start, records))
+                    })
+                })
+                .collect();
+            for handle in handles {
+                let (name, start, records) = handle.join().expect("Reader thread panicked.")?;
+                let expected = eager.variable(name)?.read_records(start..start + 5000)?;
+                _assert_same_records(&records, &expected);
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_variable_reader_pool() -> Result<(), CdfError> {
+        let eager = Cdf::read_cdf_file(_path("ulysses.cdf"))?;
+        let pooled = Cdf::read_cdf_file_with(
+            _path("ulysses.cdf"),
+            ReadOptions {
+                lazy: true,
+                readers: 4,
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(pooled.num_readers(), 4);
+        std::thread::scope(|scope| -> Result<(), CdfError> {
+            let handles: Vec<_> = (0..8)
+                .map(|i| {
+                    let pooled = &pooled;
+                    scope.spawn(move || {
+                        let start = i * 500;
+                        pooled
+                            .variable("Dist_HGI")?
+                            .read_records(start..start + 2000)
+                            .map(|records| (start, records))
+                    })
+                })
+                .collect();
+            for handle in handles {
+                let (start, records) = handle.join().expect("Reader thread panicked.")?;
+                let expected = eager
+                    .variable("Dist_HGI")?
+                    .read_records(start..start + 2000)?;
+                _assert_same_records(&records, &expected);
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_variable_read_raw() -> Result<(), CdfError> {
+        for filename in ["test_alltypes.cdf", "ulysses.cdf"] {
+            let eager = Cdf::read_cdf_file(_path(filename))?;
+            let lazy = Cdf::read_cdf_file_with(
+                _path(filename),
+                ReadOptions {
+                    lazy: true,
+                    ..Default::default()
+                },
+            )?;
+            let encoder = Encoder::new(
+                io::Cursor::new(Vec::new()),
+                eager.cdr.cdf_version.clone(),
+                eager.cdr.encoding.clone(),
+            )?;
+            for (e, l) in eager.variables().iter().zip(lazy.variables().iter()) {
+                if e.compression().is_some() {
+                    continue;
+                }
+                let range = e.n_records() / 3..e.n_records();
+                let mut expected = Vec::new();
+                for record in e.read_records(range.clone())? {
+                    expected.extend(encode_to_vec(&encoder, &record)?);
+                }
+                let raw = l.read_raw(range.clone())?;
+                assert_eq!(raw.as_bytes(), expected, "{filename}: {}", e.name());
+                assert_eq!(e.read_raw(range)?.as_bytes(), expected);
+                assert_eq!(
+                    raw.as_bytes().len(),
+                    raw.records().len() * raw.record_size()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_storage_info() -> Result<(), CdfError> {
+        for lazy in [false, true] {
+            let options = |filename| {
+                Cdf::read_cdf_file_with(
+                    _path(filename),
+                    ReadOptions {
+                        lazy,
+                        decompress: true,
+                        ..Default::default()
+                    },
+                )
+            };
+            let ulysses = options("ulysses.cdf")?;
+            let info = ulysses.variable("B_scalar")?.storage_info()?;
+            // Records are allocated past the last record written, up to the end of the last VVR.
+            assert_eq!(
+                (info.stored_records, info.cvvrs, info.cvvr_bytes),
+                (134_656, 0, 0)
+            );
+            assert_eq!(info.logical_bytes, 134_656 * 4);
+            // Each VVR of this version 2 file has a header of 8 bytes.
+            assert_eq!(
+                info.stored_bytes(),
+                info.logical_bytes + 8 * info.vvrs as u64
+            );
+            assert!(info.compression_ratio().is_some_and(|r| r < 1.0));
+
+            let alltypes = options("test_alltypes.cdf")?;
+            let info = alltypes.variable("Longitude")?.storage_info()?;
+            assert_eq!((info.vvrs, info.vvr_bytes), (0, 0));
+            assert!(info.cvvrs > 0);
+            assert_eq!(info.logical_bytes, info.stored_records as u64 * 6);
+            assert!(info.compression_ratio().is_some_and(|r| r > 1.0));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_stats() -> Result<(), CdfError> {
+        let cdf = Cdf::read_cdf_file(_path("ulysses.cdf"))?;
+        let b = cdf.variable("B_scalar")?;
+        let stats = b.stats()?;
+
+        let fill = b.attribute("FILLVAL").and_then(|f| f[0].as_f64());
+        let values: Vec<f64> = b
+            .read_all()?
+            .iter()
+            .flat_map(|r| r.data.iter().filter_map(CdfType::as_f64))
+            .collect();
+        let (valid, fills): (Vec<f64>, Vec<f64>) = values
+            .iter()
+            .partition(|x| fill != Some(**x) && !x.is_nan());
+        assert!(!fills.is_empty());
+        assert_eq!((stats.count, stats.fill_count), (valid.len(), fills.len()));
+        let mean = valid.iter().sum::<f64>() / valid.len() as f64;
+        let variance = valid.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / valid.len() as f64;
+        assert!((stats.mean.unwrap_or_default() - mean).abs() < 1e-9 * mean.abs());
+        assert!((stats.stddev.unwrap_or_default() - variance.sqrt()).abs() < 1e-9);
+        assert_eq!(stats.min, valid.iter().copied().reduce(f64::min));
+        assert_eq!(stats.max, valid.iter().copied().reduce(f64::max));
+        assert!(stats.max < fill);
+
+        assert!(cdf.variable("Epoch")?.stats().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_aggregate_by() -> Result<(), CdfError> {
+        let cdf = Cdf::read_cdf_file(_path("ulysses.cdf"))?;
+        let b = cdf.variable("B_scalar")?;
+        let day = Duration::from_secs(86_400);
+        let counts = b.aggregate_by(day, Aggregation::Count)?;
+        let means = b.aggregate_by(day, Aggregation::Mean)?;
+        let (mins, maxs) = (
+            b.aggregate_by(day, Aggregation::Min)?,
+            b.aggregate_by(day, Aggregation::Max)?,
+        );
+        assert!(counts.times.len() > 1);
+        assert_eq!(counts.values.len(), counts.times.len());
+        let total: f64 = counts.values.iter().flatten().flatten().sum();
+        assert_eq!(total as usize, b.stats()?.count);
+
+        let fill = b.attribute("FILLVAL").and_then(|f| f[0].as_f64());
+        let times = cdf.variable("Epoch")?.read_all()?;
+        let values = b.read_all()?;
+        for (i, start) in counts.times.iter().enumerate().take(20) {
+            let start = match start {
+                CdfType::Epoch(epoch) => epoch.to_unix_nanos().unwrap_or_default(),
+                other => panic!("{other:?} is not an Epoch"),
+            };
+            assert_eq!(start % 86_400_000_000_000, 0);
+            let within: Vec<f64> = times
+                .iter()
+                .zip(&values)
+                .filter(|(t, _)| {
+                    let t = match &t.data[0] {
+                        CdfType::Epoch(epoch) => epoch.to_unix_nanos().unwrap_or_default(),
+                        _ => unreachable!(),
+                    };
+                    (start..start + 86_400_000_000_000).contains(&t)
+                })
+                .filter_map(|(_, r)| r.data[0].as_f64())
+                .filter(|x| fill != Some(*x) && !x.is_nan())
+                .collect();
+            assert_eq!(counts.values[i], vec![Some(within.len() as f64)]);
+            let mean = within.iter().sum::<f64>() / within.len() as f64;
+            let expected = (!within.is_empty()).then_some(mean);
+            assert!(means.values[i][0]
+                .zip(expected)
+                .is_none_or(|(a, b)| (a - b).abs() < 1e-9));
+            assert_eq!(means.values[i][0].is_some(), expected.is_some());
+            assert_eq!(mins.values[i][0], within.iter().copied().reduce(f64::min));
+            assert_eq!(maxs.values[i][0], within.iter().copied().reduce(f64::max));
+        }
+
+        assert!(b.aggregate_by(Duration::ZERO, Aggregation::Mean).is_err());
+        assert!(cdf
+            .variable("Epoch")?
+            .aggregate_by(day, Aggregation::Count)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_promotion() -> Result<(), CdfError> {
+        let stored = Cdf::read_cdf_file(_path("test_alltypes.cdf"))?;
+        for (promotion, data_type) in [
+            (Promotion::Float64, DataType::Real8),
+            (Promotion::Int64, DataType::Int8),
+        ] {
+            let cdf = Cdf::read_cdf_file_with(
+                _path("test_alltypes.cdf"),
+                ReadOptions {
+                    lazy: true,
+                    promote: Some(promotion),
+                    ..Default::default()
+                },
+            )?;
+            for variable in cdf.variables() {
+                let expected = stored.variable(variable.name())?.read_all()?;
+                let promoted = variable.read_all()?;
+                assert_eq!(promoted.len(), expected.len());
+                for (promoted, expected) in promoted.iter().zip(expected) {
+                    assert_eq!(promoted.data.len(), expected.data.len());
+                    if *promoted.data_type != data_type as i32 {
+                        // Only times, strings and reals promoted to integers are left as stored.
+                        assert_eq!(format!("{promoted:?}"), format!("{expected:?}"));
+                        continue;
+                    }
+                    for (x, y) in promoted.data.iter().zip(&expected.data) {
+                        assert_eq!(x.data_type(), data_type);
+                        assert_eq!(x.as_f64(), y.as_f64());
+                    }
+                }
+            }
+            let longitude = cdf.variable("Longitude")?.read_records(0..1)?;
+            assert_eq!(*longitude[0].data_type, data_type as i32);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn test_variable_raw_values() -> Result<(), CdfError> {
+        let cdf = Cdf::read_cdf_file(_path("ulysses.cdf"))?;
+        let epoch = cdf.variable("Epoch")?;
+        let raw = epoch.read_raw(0..100)?.into_native();
+        assert!(raw.is_native());
+        assert!(raw.values::<f32>().is_none());
+        let values = raw.values::<f64>().expect("Epoch values are f64.");
+        assert_eq!(values.len(), 100);
+        for (value, record) in values.iter().zip(epoch.read_records(0..100)?) {
+            let CdfType::Epoch(expected) = &record.data[0] else {
+                panic!("Unexpected value {:?}", record.data);
+            };
+            assert_eq!(*value, **expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_read_sparse() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("scalar", DataType::Int4).blocking_factor(2))
+            .build(io::Cursor::new(Vec::new()))?;
+        let records: Vec<_> = (0..10)
+            .map(|r| vec![CdfType::Int4(CdfInt4::from(r))])
+            .collect();
+        writer.write_records("scalar", &records)?;
+        let bytes = writer.close()?.into_inner();
+
+        // Drop the block of records 2 and 3 from the index, as if they were never written.
+        let mut cdf = Cdf::from_bytes(&bytes)?;
+        let zvdr = &mut cdf.cdr.gdr.zvdr_vec[0];
+        zvdr.sparse_records = CdfInt4::from(1);
+        assert_eq!(zvdr.vxr_vec[0].first_vec[1].as_deref(), Some(&2));
+        zvdr.vxr_vec[0].first_vec[1] = None;
+
+        let scalar = cdf.variable("scalar")?;
+        assert!(scalar.is_sparse());
+        let sparse: Vec<_> = scalar.read_sparse(1..6)?.collect();
+        let numbers: Vec<_> = sparse.iter().map(|(r, _)| *r).collect();
+        assert_eq!(numbers, [1, 2, 3, 4, 5]);
+        let stored: Vec<_> = sparse.iter().map(|(_, v)| v.is_some()).collect();
+        assert_eq!(stored, [true, false, false, true, true]);
+        let (4, Some(record)) = &sparse[3] else {
+            panic!("Record 4 is stored.");
+        };
+        assert_eq!(format!("{:?}", record.data), "[Int4(4)]");
+
+        // Reading the same records fills the gap with the pad record instead.
+        let padded = scalar.read_records(1..6)?;
+        assert_eq!(
+            format!("{:?}", padded[1].data),
+            format!("{:?}", scalar.pad_record()?.data)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_prefetch() -> Result<(), CdfError> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        // Reader that counts the reads of the underlying cursor.
+        struct Counting(io::Cursor<Vec<u8>>, Arc<AtomicUsize>);
+        impl io::Read for Counting {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                _ = self.1.fetch_add(1, Ordering::Relaxed);
+                self.0.read(buf)
+            }
+        }
+        impl io::Seek for Counting {
+            fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+                self.0.seek(pos)
+            }
+        }
+
+        // Blocks of 4 records make 25 VVRs, one after the other.
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("counts", DataType::Int4).blocking_factor(4))
+            .build(io::Cursor::new(Vec::new()))?;
+        let records: Vec<_> = (0..100)
+            .map(|i| vec![CdfType::Int4(CdfInt4::from(i))])
+            .collect();
+        writer.write_records("counts", &records)?;
+        let bytes = writer.close()?.into_inner();
+
+        let mut read = vec![];
+        for prefetch in [0, 1 << 20] {
+            let reads = Arc::new(AtomicUsize::new(0));
+            let options = ReadOptions {
+                lazy: true,
+                prefetch,
+                ..Default::default()
+            };
+            let cdf = Cdf::from_reader(
+                Counting(io::Cursor::new(bytes.clone()), reads.clone()),
+                options,
+            )?;
+            let before = reads.load(Ordering::Relaxed);
+            let counts = cdf.variable("counts")?;
+            read.push(format!("{:?}", counts.read_records(10..90)?));
+            assert_eq!(counts.read_strided(1..100, 7)?.len(), 15);
+            assert_eq!(counts.read_raw(0..100)?.as_bytes().len(), 400);
+            let after = reads.load(Ordering::Relaxed);
+            match prefetch {
+                0 => assert!(after - before > 100),
+                _ => assert_eq!(after - before, 3),
+            }
+        }
+        assert_eq!(read[0], read[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_without_records() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("empty", DataType::Real8).dims(&[3]))
+            .variable(VariableSpec::new("scalar", DataType::Int4))
+            .build(io::Cursor::new(Vec::new()))?;
+        writer.write_records("scalar", &[vec![CdfType::Int4(CdfInt4::from(1))]])?;
+        let bytes = writer.close()?.into_inner();
+
+        for lazy in [false, true] {
+            let cdf = Cdf::from_reader(
+                io::Cursor::new(bytes.clone()),
+                ReadOptions {
+                    lazy,
+                    ..Default::default()
+                },
+            )?;
+            let empty = cdf.variable("empty")?;
+            assert_eq!(**descriptor_field!(empty, max_record), -1);
+            assert_eq!(empty.n_records(), 0);
+            assert!(empty.check_index().is_empty());
+            assert!(empty.read_all()?.is_empty());
+            assert!(empty.read_strided(0..0, 2)?.is_empty());
+            assert_eq!(empty.read_sparse(0..0)?.count(), 0);
+            assert!(empty.read_raw(0..0)?.as_bytes().is_empty());
+            assert!(empty.read_records(0..1).is_err());
+            assert_eq!(empty.storage_info()?, StorageInfo::default());
+            assert_eq!(empty.stats()?, VariableStats::default());
+        }
+
+        let view = CdfView::parse(&bytes)?;
+        let empty = view.variable("empty").expect("empty is in the file");
+        assert_eq!(empty.n_records(), 0);
+        assert!(empty.blocks().is_empty());
+        assert!(empty.read_record(0)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_read_masked() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("data", DataType::Real8))
+            .variable(VariableSpec::new("quality", DataType::Int4))
+            .build(io::Cursor::new(Vec::new()))?;
+        let data: Vec<_> = (0..10)
+            .map(|r| vec![CdfType::Real8(CdfReal8::from(f64::from(r)))])
+            .collect();
+        let quality: Vec<_> = [0, 0, 1, 0, 2, 0, 0, 0]
+            .into_iter()
+            .map(|q| vec![CdfType::Int4(CdfInt4::from(q))])
+            .collect();
+        writer.write_records("data", &data)?;
+        writer.write_records("quality", &quality)?;
+        let cdf = Cdf::from_bytes(&writer.close()?.into_inner())?;
+
+        // Records past the last flag use the pad value of the flags, which is not zero.
+        let flags = cdf.variable("quality")?;
+        let masked = cdf.variable("data")?.read_masked(
+            &flags,
+            1..10,
+            |q| !matches!(q, CdfType::Int4(q) if **q == 0),
+        )?;
+        let kept: Vec<_> = masked.iter().map(Option::is_some).collect();
+        assert_eq!(
+            kept,
+            [true, false, true, false, true, true, true, false, false]
+        );
+        let Some(record) = &masked[2] else {
+            panic!("Record 3 is not flagged.");
+        };
+        assert_eq!(format!("{:?}", record.data), "[Real8(3)]");
+
+        let ulysses = Cdf::read_cdf_file(_path("ulysses.cdf"))?;
+        let b_scalar = ulysses.variable("B_scalar")?;
+        assert_eq!(
+            b_scalar.companion("DEPEND_0").map(|v| v.name()),
+            Some("Epoch")
+        );
+        assert!(b_scalar.companion("QUALITY").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_virtual_variable() -> Result<(), CdfError> {
+        let options = ReadOptions {
+            lazy: true,
+            ..Default::default()
+        };
+        let mut cdf = Cdf::read_cdf_file_with(_path("ulysses.cdf"), options)?;
+        let real4 = |value: &CdfType| match value {
+            CdfType::Real4(v) => Ok(**v),
+            _ => Err(CdfError::Other("Expected a Real4 value.".to_string())),
+        };
+        cdf.register_virtual("B_mag", &["BR_RTN", "BT_RTN", "BN_RTN"], move |b| {
+            let squares = b
+                .iter()
+                .map(|c| Ok(real4(&c[0])?.powi(2)))
+                .sum::<Result<f32, CdfError>>()?;
+            Ok(vec![CdfType::Real4(CdfReal4::from(squares.sqrt()))])
+        })?;
+        // Virtual variables can depend on each other, and on variables that do not vary.
+        cdf.register_virtual("B_label", &["B_mag", "label_time"], |args| {
+            Ok(args[1].to_vec())
+        })?;
+
+        assert_eq!(cdf.variables().len(), 17);
+        let b_mag = cdf.variable("B_mag")?;
+        assert!(b_mag.is_virtual() && b_mag.is_zvariable());
+        assert_eq!(**b_mag.data_type(), 21);
+        assert_eq!(b_mag.n_records(), 134640);
+        assert!(b_mag.check_index().is_empty());
+        let computed = b_mag.read_records(100..105)?;
+        let components: Vec<_> = ["BR_RTN", "BT_RTN", "BN_RTN"]
+            .iter()
+            .map(|name| cdf.variable(name)?.read_records(100..105))
+            .collect::<Result<_, _>>()?;
+        for (i, record) in computed.iter().enumerate() {
+            let squares: f32 = components
+                .iter()
+                .map(|c| real4(&c[i].data[0]).unwrap().powi(2))
+                .sum();
+            assert_eq!(real4(&record.data[0])?, squares.sqrt());
+        }
+        assert_eq!(b_mag.read_strided(0..10, 5)?.len(), 2);
+        assert_eq!(b_mag.read_raw(0..3)?.as_bytes().len(), 12);
+        assert!(b_mag.read_records(0..134641).is_err());
+
+        let label = cdf.variable("B_label")?;
+        assert_eq!(label.n_records(), 134640);
+        assert_eq!(
+            format!("{:?}", label.read_records(7..8)?[0].data),
+            format!(
+                "{:?}",
+                cdf.variable("label_time")?.read_records(0..1)?[0].data
+            )
+        );
+        assert!(format!("{cdf}").contains("B_mag        virtual"));
+
+        assert!(cdf
+            .register_virtual("B_mag", &["N"], |n| Ok(n[0].to_vec()))
+            .is_err());
+        assert!(cdf
+            .register_virtual("bad", &["missing"], |n| Ok(n[0].to_vec()))
+            .is_err());
+        // Records that do not match the first computed record cannot be read.
+        let calls = core::sync::atomic::AtomicUsize::new(0);
+        cdf.register_virtual("growing", &["N"], move |n| {
+            let count = calls.fetch_add(1, core::sync::atomic::Ordering::Relaxed) + 1;
+            Ok(vec![n[0][0].clone(); count])
+        })?;
+        assert!(cdf.variable("growing")?.read_records(0..1).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,889 @@
+use alloc::{collections::BTreeSet, format, vec::Vec};
+use core::ops::Range;
+use core::str;
+
+use crate::cdf::Majority;
+use crate::compression::decompress;
+use crate::decode::{
+    checked_dim_size, checked_num_dims, checked_usize, Decoder, DEFAULT_MAX_DEPTH,
+};
+use crate::error::CdfError;
+use crate::io;
+use crate::record::{cdr::copyright_len, cpr::CdfCompressionKind};
+use crate::repr::{CdfEncoding, CdfVersion, Endian};
+use crate::types::{split_strings, CdfInt4, CdfType, DataType};
+
+/// Zero-copy view of a CDF file held in memory, such as a memory-mapped file or a downloaded
+/// buffer. Only the descriptors of the file are parsed: names and copyright are `&'a str` slices
+/// of the bytes, and the values of attribute entries and variable records are `&'a [u8]` slices
+/// that are decoded on request. This uses far less memory than [`crate::cdf::Cdf::from_bytes`]
+/// for files with a lot of metadata, at the cost of decoding values every time they are read.
+/// Compressed files cannot be viewed, as their records only exist once decompressed.
+///
+/// ```
+/// use cdf::view::CdfView;
+///
+/// let bytes = std::fs::read("examples/data/ulysses.cdf").unwrap();
+/// let view = CdfView::parse(&bytes).unwrap();
+/// let b_scalar = view.variable("B_scalar").unwrap();
+/// assert_eq!(b_scalar.n_records(), 134_640);
+/// let units = view.attribute("UNITS").unwrap().entry_for(b_scalar).unwrap();
+/// assert_eq!(units.as_str(), Some("nT"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CdfView<'a> {
+    bytes: &'a [u8],
+    version: CdfVersion,
+    encoding: CdfEncoding,
+    majority: Majority,
+    copyright: &'a str,
+    variables: Vec<VariableView<'a>>,
+    attributes: Vec<AttributeView<'a>>,
+}
+
+/// A variable of a [`CdfView`], described by its rVDR or zVDR.
+#[derive(Debug, Clone)]
+pub struct VariableView<'a> {
+    name: &'a str,
+    num: usize,
+    zvariable: bool,
+    data_type: DataType,
+    num_elements: usize,
+    shape: Vec<usize>,
+    record_varies: bool,
+    n_records: usize,
+    pad_value: Option<&'a [u8]>,
+    compression: Option<CdfCompressionKind>,
+    blocks: Vec<RecordBlock<'a>>,
+    encoding: CdfEncoding,
+}
+
+/// Records of a variable that are stored together in one VVR or CVVR.
+#[derive(Debug, Clone)]
+pub struct RecordBlock<'a> {
+    /// Numbers of the records stored in this block.
+    pub records: Range<usize>,
+    /// Whether the records are compressed, as stored in a CVVR.
+    pub compressed: bool,
+    /// Values of the records one after the other, or the compressed data of a CVVR.
+    pub bytes: &'a [u8],
+}
+
+/// An attribute of a [`CdfView`], described by its ADR.
+#[derive(Debug, Clone)]
+pub struct AttributeView<'a> {
+    name: &'a str,
+    num: usize,
+    global: bool,
+    entries: Vec<EntryView<'a>>,
+}
+
+/// An entry of an [`AttributeView`], described by its AGREDR or AZEDR.
+#[derive(Debug, Clone)]
+pub struct EntryView<'a> {
+    /// Number of the entry, which is the number of the variable it belongs to for variable
+    /// attributes.
+    pub num: usize,
+    /// Whether the entry is stored in an AZEDR, i.e. belongs to a zVariable.
+    pub zvariable: bool,
+    /// Data type of the values of the entry.
+    pub data_type: DataType,
+    /// Number of values of the entry, or of characters for strings.
+    pub num_elements: usize,
+    /// Number of strings held by a string entry.
+    pub num_strings: usize,
+    /// Values of the entry, as stored in the file.
+    pub bytes: &'a [u8],
+    encoding: CdfEncoding,
+}
+
+impl<'a> CdfView<'a> {
+    /// Parse the descriptors of the CDF file stored in `bytes`.
+    /// # Errors
+    /// Returns a [`CdfError::Decode`] if the file is compressed or is not a valid CDF file, and a
+    /// [`CdfError::CyclicRecordList`] or [`CdfError::MaxDepthExceeded`] if its records loop back
+    /// on themselves or are nested too deeply.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, CdfError> {
+        let mut magic = Fields {
+            bytes,
+            position: 0,
+            version3: true,
+        };
+        let version3 = match magic.int4()? as u32 {
+            0xcdf30001 => true,
+            0xcdf26002 | 0x0000ffff => false,
+            v => return Err(CdfError::Decode(format!("Invalid magic number - {v}"))),
+        };
+        match magic.int4()? as u32 {
+            0x0000ffff => {}
+            0xcccc0001 => {
+                return Err(CdfError::Decode(
+                    "Compressed files cannot be viewed - decode them with Cdf::from_bytes.".into(),
+                ))
+            }
+            v => return Err(CdfError::Decode(format!("Invalid magic number - {v}"))),
+        }
+        let mut parser = Parser {
+            bytes,
+            version3,
+            version: CdfVersion::new(2, 0, 0),
+        };
+
+        let mut cdr = parser.record(8, 1, "CDR")?;
+        let gdr_offset = cdr.offset()?;
+        let (version, release) = (cdr.int4()?, cdr.int4()?);
+        let encoding = CdfEncoding::try_from(CdfInt4::from(cdr.int4()?))?;
+        _ = encoding.get_endian()?;
+        let majority = if cdr.int4()? & 1 == 1 {
+            Majority::Row
+        } else {
+            Majority::Column
+        };
+        _ = (cdr.int4()?, cdr.int4()?);
+        let increment = cdr.int4()?;
+        let version = CdfVersion::new(
+            u16::try_from(version)?,
+            u16::try_from(release)?,
+            u16::try_from(increment)?,
+        );
+        _ = (cdr.int4()?, cdr.int4()?);
+        let copyright = cdr.text(copyright_len(&version))?;
+        parser.version = version.clone();
+
+        let mut gdr = parser.record(gdr_offset, 2, "GDR")?;
+        let rvdr_head = gdr.offset()?;
+        let zvdr_head = gdr.offset()?;
+        let zvdr_head = if parser.has_z_records() { zvdr_head } else { 0 };
+        let adr_head = gdr.offset()?;
+        _ = gdr.offset()?;
+        _ = (gdr.int4()?, gdr.int4()?, gdr.int4()?);
+        let num_r_dims = checked_num_dims("num_r_dims", gdr.int4()?)?;
+        _ = gdr.int4()?;
+        _ = gdr.offset()?;
+        _ = (gdr.int4()?, gdr.int4()?, gdr.int4()?);
+        let r_dim_sizes = (0..num_r_dims)
+            .map(|dim| checked_dim_size("size_r_dims", dim, gdr.int4()?))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut variables = Vec::new();
+        parser.chain(rvdr_head, |offset| {
+            let (variable, next) = parser.variable(offset, Some(&r_dim_sizes), &encoding)?;
+            variables.push(variable);
+            Ok(next)
+        })?;
+        parser.chain(zvdr_head, |offset| {
+            let (variable, next) = parser.variable(offset, None, &encoding)?;
+            variables.push(variable);
+            Ok(next)
+        })?;
+
+        let mut attributes = Vec::new();
+        parser.chain(adr_head, |offset| {
+            let (attribute, next) = parser.attribute(offset, &encoding)?;
+            attributes.push(attribute);
+            Ok(next)
+        })?;
+
+        Ok(CdfView {
+            bytes,
+            version,
+            encoding,
+            majority,
+            copyright,
+            variables,
+            attributes,
+        })
+    }
+
+    /// The bytes of the file.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Version of the CDF library that wrote the file.
+    pub fn version(&self) -> &CdfVersion {
+        &self.version
+    }
+
+    /// Encoding of the values stored in the file.
+    pub fn encoding(&self) -> &CdfEncoding {
+        &self.encoding
+    }
+
+    /// Order in which the values of multi-dimensional records are stored.
+    pub fn majority(&self) -> Majority {
+        self.majority
+    }
+
+    /// Copyright text stored in the CDR.
+    pub fn copyright(&self) -> &'a str {
+        self.copyright
+    }
+
+    /// All rVariables, followed by all zVariables.
+    pub fn variables(&self) -> &[VariableView<'a>] {
+        &self.variables
+    }
+
+    /// The variable called `name`, if any.
+    pub fn variable(&self, name: &str) -> Option<&VariableView<'a>> {
+        self.variables.iter().find(|v| v.name == name)
+    }
+
+    /// All global and variable attributes.
+    pub fn attributes(&self) -> &[AttributeView<'a>] {
+        &self.attributes
+    }
+
+    /// The attribute called `name`, if any.
+    pub fn attribute(&self, name: &str) -> Option<&AttributeView<'a>> {
+        self.attributes.iter().find(|a| a.name == name)
+    }
+}
+
+impl<'a> VariableView<'a> {
+    /// Name of the variable.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Number of the variable among the rVariables or the zVariables.
+    pub fn num(&self) -> usize {
+        self.num
+    }
+
+    /// Whether this is a zVariable rather than an rVariable.
+    pub fn is_zvariable(&self) -> bool {
+        self.zvariable
+    }
+
+    /// Data type of the values of the variable.
+    pub fn data_type(&self) -> DataType {
+        self.data_type
+    }
+
+    /// Number of elements of each value, which is the number of characters for strings.
+    pub fn num_elements(&self) -> usize {
+        self.num_elements
+    }
+
+    /// Sizes of the dimensions that vary within each record.
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    /// Whether the values vary from record to record.
+    pub fn record_varies(&self) -> bool {
+        self.record_varies
+    }
+
+    /// Number of records of the variable, which is one more than its last record.
+    pub fn n_records(&self) -> usize {
+        self.n_records
+    }
+
+    /// Size in bytes of each record.
+    pub fn record_size(&self) -> usize {
+        self.data_type.size() * self.num_elements * self.shape.iter().product::<usize>()
+    }
+
+    /// Pad value of the variable as stored in the file, if it has one.
+    pub fn pad_value(&self) -> Option<&'a [u8]> {
+        self.pad_value
+    }
+
+    /// Compression of the values of the variable, if they are compressed.
+    pub fn compression(&self) -> Option<&CdfCompressionKind> {
+        self.compression.as_ref()
+    }
+
+    /// Blocks of records of the variable, in the order of their records.
+    pub fn blocks(&self) -> &[RecordBlock<'a>] {
+        &self.blocks
+    }
+
+    /// The bytes of `record`, or `None` if the record is not stored in the file.
+    /// # Errors
+    /// Returns a [`CdfError::Decode`] if the record is stored in a compressed block, whose bytes
+    /// can only be read with [`VariableView::read_record`].
+    pub fn raw_record(&self, record: usize) -> Result<Option<&'a [u8]>, CdfError> {
+        let Some(block) = self.block(record) else {
+            return Ok(None);
+        };
+        if block.compressed {
+            return Err(CdfError::Decode(format!(
+                "Record {record} of {} is compressed.",
+                self.name
+            )));
+        }
+        let size = self.record_size();
+        let start = (record - block.records.start) * size;
+        Ok(block.bytes.get(start..start + size))
+    }
+
+    /// Decode the values of `record`, or `None` if the record is not stored in the file.
+    /// Character data is decoded as one [`CdfType::String`] per record, as
+    /// [`crate::variable::Variable::read_records`] does. Records of compressed blocks are read
+    /// by decompressing their whole block.
+    /// # Errors
+    /// Returns a [`CdfError::Decode`] if the record cannot be decompressed or decoded.
+    pub fn read_record(&self, record: usize) -> Result<Option<Vec<CdfType>>, CdfError> {
+        let Some(block) = self.block(record) else {
+            return Ok(None);
+        };
+        let size = self.record_size();
+        let start = (record - block.records.start) * size;
+        let decompressed;
+        let bytes = if block.compressed {
+            let kind = self
+                .compression
+                .as_ref()
+                .unwrap_or(&CdfCompressionKind::None);
+            decompressed = decompress(kind, block.bytes)?;
+            decompressed.get(start..start + size)
+        } else {
+            block.bytes.get(start..start + size)
+        };
+        let Some(bytes) = bytes else {
+            return Ok(None);
+        };
+        let num_values = self.num_elements * self.shape.iter().product::<usize>();
+        decode_values(bytes, &self.encoding, self.data_type, num_values).map(Some)
+    }
+
+    fn block(&self, record: usize) -> Option<&RecordBlock<'a>> {
+        let index = self.blocks.partition_point(|b| b.records.end <= record);
+        self.blocks
+            .get(index)
+            .filter(|b| b.records.contains(&record))
+    }
+}
+
+impl<'a> AttributeView<'a> {
+    /// Name of the attribute.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Number of the attribute.
+    pub fn num(&self) -> usize {
+        self.num
+    }
+
+    /// Whether this is a global attribute rather than a variable attribute.
+    pub fn is_global(&self) -> bool {
+        self.global
+    }
+
+    /// All entries of the attribute.
+    pub fn entries(&self) -> &[EntryView<'a>] {
+        &self.entries
+    }
+
+    /// The entry of this variable attribute that belongs to `variable`, if any.
+    pub fn entry_for(&self, variable: &VariableView) -> Option<&EntryView<'a>> {
+        self.entries
+            .iter()
+            .find(|e| e.num == variable.num && e.zvariable == variable.zvariable)
+    }
+}
+
+impl<'a> EntryView<'a> {
+    /// The text of a string entry, up to its first NUL, or `None` if the entry does not hold
+    /// characters or they are not valid UTF-8.
+    pub fn as_str(&self) -> Option<&'a str> {
+        self.data_type
+            .is_string()
+            .then(|| text(self.bytes).ok())
+            .flatten()
+    }
+
+    /// The strings of a string entry that holds `num_strings` of them, see
+    /// [`crate::types::CdfString::split_strings`].
+    pub fn strings(&self) -> Option<Vec<&'a str>> {
+        self.as_str().map(|s| split_strings(s, self.num_strings))
+    }
+
+    /// Decode the values of the entry.
+    /// # Errors
+    /// Returns a [`CdfError::Decode`] if the values cannot be decoded.
+    pub fn values(&self) -> Result<Vec<CdfType>, CdfError> {
+        decode_values(
+            self.bytes,
+            &self.encoding,
+            self.data_type,
+            self.num_elements,
+        )
+    }
+}
+
+// Parses the records of a file from its bytes.
+struct Parser<'a> {
+    bytes: &'a [u8],
+    version3: bool,
+    // Version of the file, which is only known once the CDR is read.
+    version: CdfVersion,
+}
+
+impl<'a> Parser<'a> {
+    // The fields of the record at `offset`, after its size and type, which must be `record_type`.
+    fn record(&self, offset: i64, record_type: i32, kind: &str) -> Result<Fields<'a>, CdfError> {
+        let (fields, found) = self.open(offset)?;
+        if found != record_type {
+            return Err(CdfError::Decode(format!(
+                "Invalid record_type for {kind} - expected {record_type}, received {found}"
+            )));
+        }
+        Ok(fields)
+    }
+
+    // The fields of the record at `offset` after its size and type, and its type. The fields end
+    // with the record.
+    fn open(&self, offset: i64) -> Result<(Fields<'a>, i32), CdfError> {
+        let start = usize::try_from(offset)
+            .ok()
+            .filter(|start| *start < self.bytes.len())
+            .ok_or(CdfError::OffsetOutOfBounds {
+                offset,
+                end: self.bytes.len() as u64,
+            })?;
+        let mut fields = Fields {
+            bytes: self.bytes,
+            position: start,
+            version3: self.version3,
+        };
+        let size = checked_usize("record_size", fields.offset()?)?;
+        let record_type = fields.int4()?;
+        let end = start
+            .checked_add(size)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| {
+                CdfError::Decode(format!(
+                    "Record of {size} bytes at offset {start} ends after the end of the file."
+                ))
+            })?;
+        fields.bytes = &self.bytes[..end];
+        Ok((fields, record_type))
+    }
+
+    // Call `visit` with the offset of each record of a linked list, which returns the offset of
+    // the next record.
+    fn chain<F>(&self, head: i64, mut visit: F) -> Result<(), CdfError>
+    where
+        F: FnMut(i64) -> Result<i64, CdfError>,
+    {
+        let mut visited = BTreeSet::new();
+        let mut offset = head;
+        while offset != 0 {
+            if !visited.insert(offset) {
+                return Err(CdfError::CyclicRecordList { offset });
+            }
+            offset = visit(offset)?;
+        }
+        Ok(())
+    }
+
+    // Whether the file can hold zVariables and their attribute entries, which were introduced in
+    // CDF v2.2.
+    fn has_z_records(&self) -> bool {
+        self.version >= CdfVersion::new(2, 2, 0)
+    }
+
+    fn name_length(&self) -> usize {
+        if self.version3 {
+            256
+        } else {
+            64
+        }
+    }
+
+    // The rVDR, if `r_dim_sizes` is set, or zVDR at `offset`, and the offset of the next one.
+    fn variable(
+        &self,
+        offset: i64,
+        r_dim_sizes: Option<&[usize]>,
+        encoding: &CdfEncoding,
+    ) -> Result<(VariableView<'a>, i64), CdfError> {
+        let mut vdr = match r_dim_sizes {
+            Some(_) => self.record(offset, 3, "RVDR")?,
+            None => self.record(offset, 8, "ZVDR")?,
+        };
+        let next = vdr.offset()?;
+        let data_type = DataType::try_from(CdfInt4::from(vdr.int4()?))?;
+        let max_record = vdr.int4()?;
+        if max_record < -1 {
+            return Err(CdfError::InvalidCount {
+                field: "max_record",
+                value: i64::from(max_record),
+            });
+        }
+        let vxr_head = vdr.offset()?;
+        _ = vdr.offset()?;
+        let flags = vdr.int4()?;
+        _ = (vdr.int4()?, vdr.int4()?, vdr.int4()?, vdr.int4()?);
+        let num_elements = vdr.count("num_elements")?;
+        let num = vdr.count("num")?;
+        let cpr_offset = vdr.offset()?;
+        _ = vdr.int4()?;
+        let name = vdr.text(self.name_length())?;
+        let dim_sizes = match r_dim_sizes {
+            Some(sizes) => sizes.to_vec(),
+            None => {
+                let num_dims = checked_num_dims("num_z_dims", vdr.int4()?)?;
+                (0..num_dims)
+                    .map(|dim| checked_dim_size("size_z_dims", dim, vdr.int4()?))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+        let mut shape = Vec::new();
+        for size in &dim_sizes {
+            if vdr.int4()? == -1 {
+                shape.push(*size);
+            }
+        }
+        if num_elements == 0 || shape.contains(&0) {
+            return Err(CdfError::Decode(format!(
+                "Invalid number of elements or dimension sizes for variable {name}."
+            )));
+        }
+        let pad_value = if flags & 2 == 2 {
+            Some(vdr.take(data_type.size() * num_elements)?)
+        } else {
+            None
+        };
+
+        let compression = if flags & 4 == 4 && self.version >= CdfVersion::new(2, 6, 0) {
+            let mut cpr = self.record(cpr_offset, 11, "CPR")?;
+            Some(CdfCompressionKind::try_from(cpr.int4()?)?)
+        } else {
+            None
+        };
+
+        let mut variable = VariableView {
+            name,
+            num,
+            zvariable: r_dim_sizes.is_none(),
+            data_type,
+            num_elements,
+            shape,
+            record_varies: flags & 1 == 1,
+            n_records: usize::try_from(max_record.saturating_add(1)).unwrap_or(0),
+            pad_value,
+            compression,
+            blocks: Vec::new(),
+            encoding: encoding.clone(),
+        };
+        let mut blocks = Vec::new();
+        self.blocks(&variable, vxr_head, 0, &mut blocks)?;
+        blocks.sort_by_key(|b| b.records.start);
+        variable.blocks = blocks;
+        Ok((variable, next))
+    }
+
+    // Collect the blocks of records indexed by the VXRs chained from `head`.
+    fn blocks(
+        &self,
+        variable: &VariableView,
+        head: i64,
+        depth: usize,
+        blocks: &mut Vec<RecordBlock<'a>>,
+    ) -> Result<(), CdfError> {
+        if depth > DEFAULT_MAX_DEPTH {
+            return Err(CdfError::MaxDepthExceeded {
+                max_depth: DEFAULT_MAX_DEPTH,
+            });
+        }
+        self.chain(head, |offset| {
+            let mut vxr = self.record(offset, 6, "VXR")?;
+            let next = vxr.offset()?;
+            let num_entries = vxr.count("num_entries")?;
+            let num_used = vxr.count("num_used_entries")?.min(num_entries);
+            // Unused entries hold -1, so only the used ones are converted to record numbers.
+            let mut firsts = Vec::new();
+            for _ in 0..num_entries {
+                firsts.push(vxr.int4()?);
+            }
+            let mut lasts = Vec::new();
+            for _ in 0..num_entries {
+                lasts.push(vxr.int4()?);
+            }
+            let mut offsets = Vec::new();
+            for _ in 0..num_entries {
+                offsets.push(vxr.offset()?);
+            }
+            for i in 0..num_used {
+                // Blocks may be allocated for more records than were written.
+                let first = checked_usize("first", firsts[i])?;
+                let records = first..(checked_usize("last", lasts[i])? + 1).min(variable.n_records);
+                let (mut child, record_type) = self.open(offsets[i])?;
+                match record_type {
+                    6 => self.blocks(variable, offsets[i], depth + 1, blocks)?,
+                    7 => {
+                        let bytes = child.take(records.len() * variable.record_size())?;
+                        blocks.push(RecordBlock {
+                            records,
+                            compressed: false,
+                            bytes,
+                        });
+                    }
+                    13 => {
+                        _ = child.int4()?;
+                        let size = checked_usize("compressed_size", child.offset()?)?;
+                        let bytes = child.take(size)?;
+                        blocks.push(RecordBlock {
+                            records,
+                            compressed: true,
+                            bytes,
+                        });
+                    }
+                    t => {
+                        return Err(CdfError::Decode(format!(
+                            "Invalid record_type for a child of a VXR - received {t}"
+                        )))
+                    }
+                }
+            }
+            Ok(next)
+        })
+    }
+
+    // The ADR at `offset`, with its entries, and the offset of the next one.
+    fn attribute(
+        &self,
+        offset: i64,
+        encoding: &CdfEncoding,
+    ) -> Result<(AttributeView<'a>, i64), CdfError> {
+        let mut adr = self.record(offset, 4, "ADR")?;
+        let next = adr.offset()?;
+        let agredr_head = adr.offset()?;
+        let scope = adr.int4()?;
+        let num = adr.count("num")?;
+        _ = (adr.int4()?, adr.int4()?, adr.int4()?);
+        let azedr_head = adr.offset()?;
+        let azedr_head = if self.has_z_records() { azedr_head } else { 0 };
+        _ = (adr.int4()?, adr.int4()?, adr.int4()?);
+        let name = adr.text(self.name_length())?;
+
+        let mut entries = Vec::new();
+        for (head, zvariable) in [(agredr_head, false), (azedr_head, true)] {
+            self.chain(head, |offset| {
+                let mut aedr = if zvariable {
+                    self.record(offset, 9, "AZEDR")?
+                } else {
+                    self.record(offset, 5, "AGREDR")?
+                };
+                let next = aedr.offset()?;
+                _ = aedr.int4()?;
+                let data_type = DataType::try_from(CdfInt4::from(aedr.int4()?))?;
+                let num = aedr.count("num")?;
+                let num_elements = aedr.count("num_elements")?;
+                let num_strings = aedr.count("num_strings")?;
+                _ = (aedr.int4()?, aedr.int4()?, aedr.int4()?, aedr.int4()?);
+                let bytes = aedr.take(data_type.size() * num_elements)?;
+                entries.push(EntryView {
+                    num,
+                    zvariable,
+                    data_type,
+                    num_elements,
+                    num_strings,
+                    bytes,
+                    encoding: encoding.clone(),
+                });
+                Ok(next)
+            })?;
+        }
+
+        let attribute = AttributeView {
+            name,
+            num,
+            global: scope == 1 || scope == 3,
+            entries,
+        };
+        Ok((attribute, next))
+    }
+}
+
+// Reads the big-endian fields of a record from the bytes of the file.
+struct Fields<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    version3: bool,
+}
+
+impl<'a> Fields<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], CdfError> {
+        let end = self
+            .position
+            .checked_add(n)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| {
+                CdfError::Decode(format!(
+                    "Reading {n} bytes at offset {} goes past the end of the record.",
+                    self.position
+                ))
+            })?;
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+
+    fn int4(&mut self) -> Result<i32, CdfError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(i32::from_be_bytes(bytes))
+    }
+
+    // A number of items held in `field`, which must not be negative.
+    fn count(&mut self, field: &'static str) -> Result<usize, CdfError> {
+        checked_usize(field, self.int4()?)
+    }
+
+    // An offset or size, which is 8 bytes long from version 3 and 4 bytes long before.
+    fn offset(&mut self) -> Result<i64, CdfError> {
+        if !self.version3 {
+            return Ok(i64::from(self.int4()?));
+        }
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(i64::from_be_bytes(bytes))
+    }
+
+    fn text(&mut self, n: usize) -> Result<&'a str, CdfError> {
+        text(self.take(n)?)
+    }
+}
+
+// Text stored in a fixed-size field, up to its first NUL.
+fn text(bytes: &[u8]) -> Result<&str, CdfError> {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    str::from_utf8(&bytes[..end])
+        .map_err(|e| CdfError::Decode(format!("Text is not valid UTF-8 - {e}")))
+}
+
+// Decode `num_values` values of `data_type` from `bytes`.
+fn decode_values(
+    bytes: &[u8],
+    encoding: &CdfEncoding,
+    data_type: DataType,
+    num_values: usize,
+) -> Result<Vec<CdfType>, CdfError> {
+    let endianness = encoding.get_endian()?;
+    let mut decoder = Decoder::new(io::Cursor::new(bytes))?;
+    decoder.context.endianness = Some(endianness.clone());
+    let data_type = CdfInt4::from(data_type);
+    let num_values = CdfInt4::from(i32::try_from(num_values)?);
+    match endianness {
+        Endian::Big => CdfType::decode_vec_be(&mut decoder, &data_type, &num_values),
+        Endian::Little => CdfType::decode_vec_le(&mut decoder, &data_type, &num_values),
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+
+    use crate::cdf::Cdf;
+    use crate::error::CdfError;
+    use std::path::PathBuf;
+    use std::vec;
+
+    use super::*;
+
+    fn _read(name: &str) -> Vec<u8> {
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "examples", "data", name]
+            .iter()
+            .collect();
+        std::fs::read(path).expect("test file exists")
+    }
+
+    #[test]
+    fn test_view_alltypes() -> Result<(), CdfError> {
+        let bytes = _read("test_alltypes.cdf");
+        let view = CdfView::parse(&bytes)?;
+        let cdf = Cdf::from_bytes(&bytes)?;
+        assert_eq!(view.version(), &cdf.info().version);
+        assert_eq!(view.encoding(), &cdf.info().encoding);
+        assert_eq!(view.majority(), cdf.info().majority);
+
+        let variables = cdf.variables();
+        assert_eq!(view.variables().len(), variables.len());
+        for (v, variable) in view.variables().iter().zip(&variables) {
+            assert_eq!(v.name(), variable.name());
+            assert_eq!(v.n_records(), variable.n_records());
+            assert_eq!(v.shape(), variable.shape()?.as_slice());
+            assert_eq!(v.record_varies(), variable.record_varies());
+            let records = variable.read_all()?;
+            for (i, record) in records.iter().enumerate() {
+                // Records that are not stored, such as those of sparse variables, are padded.
+                let Some(values) = v.read_record(i)? else {
+                    continue;
+                };
+                assert_eq!(format!("{values:?}"), format!("{:?}", record.data));
+            }
+            assert!(v.read_record(records.len())?.is_none());
+        }
+
+        let longitude = view
+            .variable("Longitude")
+            .expect("Longitude is in the file");
+        assert!(longitude.compression().is_some());
+        assert!(longitude.blocks().iter().all(|b| b.compressed));
+        assert!(longitude.raw_record(0).is_err());
+
+        let adrs = &cdf.cdr.gdr.adr_vec;
+        assert_eq!(view.attributes().len(), adrs.len());
+        for (a, adr) in view.attributes().iter().zip(adrs) {
+            assert_eq!(a.name(), &*adr.name);
+            let values = adr.agredr_vec.iter().map(|e| &e.value);
+            let values = values.chain(adr.azedr_vec.iter().map(|e| &e.value));
+            assert_eq!(a.entries().len(), values.clone().count());
+            for (entry, value) in a.entries().iter().zip(values) {
+                assert_eq!(format!("{:?}", entry.values()?), format!("{value:?}"));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_view_borrows() -> Result<(), CdfError> {
+        let bytes = _read("ulysses.cdf");
+        let view = CdfView::parse(&bytes)?;
+        let range = bytes.as_ptr_range();
+        assert!(range.contains(&view.copyright().as_ptr()));
+
+        let b_scalar = view.variable("B_scalar").expect("B_scalar is in the file");
+        assert!(range.contains(&b_scalar.name().as_ptr()));
+        assert_eq!(b_scalar.record_size(), 4);
+        let record = b_scalar.raw_record(10)?.expect("record is stored");
+        assert!(range.contains(&record.as_ptr()));
+
+        let cdf = Cdf::from_bytes(&bytes)?;
+        let expected = cdf.variable("B_scalar")?.read_records(10..11)?;
+        assert_eq!(
+            format!("{:?}", b_scalar.read_record(10)?),
+            format!("{:?}", Some(&expected[0].data))
+        );
+
+        let depend = view.attribute("DEPEND_0").expect("DEPEND_0 is in the file");
+        assert!(!depend.is_global());
+        let epoch = depend.entry_for(b_scalar).expect("B_scalar has an epoch");
+        assert_eq!(epoch.as_str(), Some("Epoch"));
+        assert!(range.contains(&epoch.as_str().expect("epoch is a string").as_ptr()));
+        assert_eq!(epoch.strings(), Some(vec!["Epoch"]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_view_invalid() {
+        let bytes = _read("ulysses.cdf");
+        for len in [0, 4, 8, 100, 1000] {
+            assert!(CdfView::parse(&bytes[..len]).is_err());
+        }
+
+        let mut compressed = bytes[..8].to_vec();
+        compressed[4..8].copy_from_slice(&0xcccc0001u32.to_be_bytes());
+        assert!(matches!(
+            CdfView::parse(&compressed),
+            Err(CdfError::Decode(message)) if message.contains("Compressed")
+        ));
+    }
+}
@@ -0,0 +1,462 @@
+use alloc::string::ToString;
+
+use crate::cdf::Cdf;
+use crate::error::CdfError;
+use crate::record::{
+    adr::AttributeDescriptorRecord,
+    agredr::AttributeGREntryDescriptorRecord,
+    azedr::AttributeZEntryDescriptorRecord,
+    cdr::CdfDescriptorRecord,
+    cpr::CompressedParametersRecord,
+    cvvr::CompressedVariableValuesRecord,
+    gdr::GlobalDescriptorRecord,
+    rvdr::RVariableDescriptorRecord,
+    uir::UnusedInternalRecord,
+    unknown::UnknownRecord,
+    vvr::VariableValuesRecord,
+    vxr::{VariableIndexRecord, VariableIndexRecordChild},
+    zvdr::ZVariableDescriptorRecord,
+};
+use crate::types::CdfInt8;
+
+/// Where a record visited by [`Cdf::walk`] is stored, and what it belongs to.
+#[derive(Debug, Clone, Copy)]
+pub struct RecordContext<'a> {
+    /// File offset of the record. Offsets of compressed files are those of the file once
+    /// decompressed.
+    pub offset: u64,
+    /// File offset of the record that holds this one: the GDR for descriptors and UIRs, the ADR for
+    /// entries, the VDR for the CPR and top-level VXRs of a variable, and the VXR for its children.
+    /// `None` for the CDR.
+    pub parent: Option<u64>,
+    /// Level of the record in the tree, zero for the CDR.
+    pub depth: usize,
+    /// Name of the variable that the record describes or holds values of, if any.
+    pub variable: Option<&'a str>,
+    /// Name of the attribute that the record describes or holds an entry of, if any.
+    pub attribute: Option<&'a str>,
+}
+
+/// Callbacks invoked by [`Cdf::walk`] for each record of a file, along with its
+/// [`RecordContext`].
+/// All methods do nothing by default, so a visitor only implements the records it looks at.
+///
+/// Records are visited parent first: the CDR, the GDR, each rVDR and zVDR followed by its CPR
+/// and its VXRs, each VXR followed by its children, each ADR followed by its entries, and the
+/// UIRs. Records that were not decoded, such as the values of variables that are read on
+/// demand, are not visited. An error returned by a callback stops the walk.
+///
+/// ```
+/// use cdf::cdf::Cdf;
+/// use cdf::error::CdfError;
+/// use cdf::record::adr::AttributeDescriptorRecord;
+/// use cdf::walk::{RecordContext, RecordVisitor};
+///
+/// #[derive(Default)]
+/// struct Attributes(Vec<(u64, String)>);
+///
+/// impl RecordVisitor for Attributes {
+///     fn visit_adr(
+///         &mut self,
+///         adr: &AttributeDescriptorRecord,
+///         context: &RecordContext,
+///     ) -> Result<(), CdfError> {
+///         self.0.push((context.offset, adr.name.to_string()));
+///         Ok(())
+///     }
+/// }
+///
+/// let cdf = Cdf::read_cdf_file("examples/data/ulysses.cdf").unwrap();
+/// let mut attributes = Attributes::default();
+/// cdf.walk(&mut attributes).unwrap();
+/// assert_eq!(attributes.0.len(), 27);
+/// ```
+pub trait RecordVisitor {
+    /// Visit the CDR.
+    /// # Errors
+    /// A [`CdfError`] returned here stops the walk, as for every other callback.
+    fn visit_cdr(
+        &mut self,
+        cdr: &CdfDescriptorRecord,
+        context: &RecordContext,
+    ) -> Result<(), CdfError> {
+        _ = (cdr, context);
+        Ok(())
+    }
+
+    /// Visit the GDR.
+    fn visit_gdr(
+        &mut self,
+        gdr: &GlobalDescriptorRecord,
+        context: &RecordContext,
+    ) -> Result<(), CdfError> {
+        _ = (gdr, context);
+        Ok(())
+    }
+
+    /// Visit the descriptor of an rVariable.
+    fn visit_rvdr(
+        &mut self,
+        rvdr: &RVariableDescriptorRecord,
+        context: &RecordContext,
+    ) -> Result<(), CdfError> {
+        _ = (rvdr, context);
+        Ok(())
+    }
+
+    /// Visit the descriptor of a zVariable.
+    fn visit_zvdr(
+        &mut self,
+        zvdr: &ZVariableDescriptorRecord,
+        context: &RecordContext,
+    ) -> Result<(), CdfError> {
+        _ = (zvdr, context);
+        Ok(())
+    }
+
+    /// Visit the compression parameters of a variable.
+    fn visit_cpr(
+        &mut self,
+        cpr: &CompressedParametersRecord,
+        context: &RecordContext,
+    ) -> Result<(), CdfError> {
+        _ = (cpr, context);
+        Ok(())
+    }
+
+    /// Visit a VXR, at the top level of the index of a variable or below another VXR.
+    fn visit_vxr(
+        &mut self,
+        vxr: &VariableIndexRecord,
+        context: &RecordContext,
+    ) -> Result<(), CdfError> {
+        _ = (vxr, context);
+        Ok(())
+    }
+
+    /// Visit a VVR.
+    fn visit_vvr(
+        &mut self,
+        vvr: &VariableValuesRecord,
+        context: &RecordContext,
+    ) -> Result<(), CdfError> {
+        _ = (vvr, context);
+        Ok(())
+    }
+
+    /// Visit a CVVR.
+    fn visit_cvvr(
+        &mut self,
+        cvvr: &CompressedVariableValuesRecord,
+        context: &RecordContext,
+    ) -> Result<(), CdfError> {
+        _ = (cvvr, context);
+        Ok(())
+    }
+
+    /// Visit an ADR.
+    fn visit_adr(
+        &mut self,
+        adr: &AttributeDescriptorRecord,
+        context: &RecordContext,
+    ) -> Result<(), CdfError> {
+        _ = (adr, context);
+        Ok(())
+    }
+
+    /// Visit a global or rVariable entry of an attribute.
+    fn visit_agredr(
+        &mut self,
+        agredr: &AttributeGREntryDescriptorRecord,
+        context: &RecordContext,
+    ) -> Result<(), CdfError> {
+        _ = (agredr, context);
+        Ok(())
+    }
+
+    /// Visit a zVariable entry of an attribute.
+    fn visit_azedr(
+        &mut self,
+        azedr: &AttributeZEntryDescriptorRecord,
+        context: &RecordContext,
+    ) -> Result<(), CdfError> {
+        _ = (azedr, context);
+        Ok(())
+    }
+
+    /// Visit a UIR.
+    fn visit_uir(
+        &mut self,
+        uir: &UnusedInternalRecord,
+        context: &RecordContext,
+    ) -> Result<(), CdfError> {
+        _ = (uir, context);
+        Ok(())
+    }
+
+    /// Visit a record of a type that is not known, found as the child of a VXR.
+    fn visit_unknown(
+        &mut self,
+        record: &UnknownRecord,
+        context: &RecordContext,
+    ) -> Result<(), CdfError> {
+        _ = (record, context);
+        Ok(())
+    }
+}
+
+impl Cdf {
+    /// Visit each record of this file with `visitor`, see [`RecordVisitor`].
+    /// # Errors
+    /// Returns the first [`CdfError`] returned by `visitor`, or a [`CdfError::Other`] if a list of
+    /// records ends before its last decoded record.
+    pub fn walk<V>(&self, visitor: &mut V) -> Result<(), CdfError>
+    where
+        V: RecordVisitor + ?Sized,
+    {
+        let cdr = &self.cdr;
+        let gdr = &cdr.gdr;
+        let root = RecordContext {
+            offset: 8,
+            parent: None,
+            depth: 0,
+            variable: None,
+            attribute: None,
+        };
+        visitor.visit_cdr(cdr, &root)?;
+        let gdr_context = root.child(offset(&Some(cdr.gdr_offset.clone()))?);
+        visitor.visit_gdr(gdr, &gdr_context)?;
+
+        let mut next = gdr.rvdr_head.clone();
+        for rvdr in &gdr.rvdr_vec {
+            let context = RecordContext {
+                variable: Some(&rvdr.name),
+                ..gdr_context.child(offset(&next)?)
+            };
+            next = rvdr.rvdr_next.clone();
+            visitor.visit_rvdr(rvdr, &context)?;
+            walk_values(
+                visitor,
+                &context,
+                (&rvdr.cpr_spr_offset, rvdr.cpr.as_ref()),
+                &rvdr.vxr_head,
+                &rvdr.vxr_vec,
+            )?;
+        }
+        let mut next = gdr.zvdr_head.clone();
+        for zvdr in &gdr.zvdr_vec {
+            let context = RecordContext {
+                variable: Some(&zvdr.name),
+                ..gdr_context.child(offset(&next)?)
+            };
+            next = zvdr.zvdr_next.clone();
+            visitor.visit_zvdr(zvdr, &context)?;
+            walk_values(
+                visitor,
+                &context,
+                (&zvdr.cpr_spr_offset, zvdr.cpr.as_ref()),
+                &zvdr.vxr_head,
+                &zvdr.vxr_vec,
+            )?;
+        }
+
+        let mut next = gdr.adr_head.clone();
+        for adr in &gdr.adr_vec {
+            let context = RecordContext {
+                attribute: Some(&adr.name),
+                ..gdr_context.child(offset(&next)?)
+            };
+            next = adr.adr_next.clone();
+            visitor.visit_adr(adr, &context)?;
+            let mut next = adr.agredr_head.clone();
+            for agredr in &adr.agredr_vec {
+                visitor.visit_agredr(agredr, &context.child(offset(&next)?))?;
+                next = agredr.agredr_next.clone();
+            }
+            let mut next = adr.azedr_head.clone();
+            for azedr in &adr.azedr_vec {
+                visitor.visit_azedr(azedr, &context.child(offset(&next)?))?;
+                next = azedr.azedr_next.clone();
+            }
+        }
+
+        let mut next = gdr.uir_head.clone();
+        for uir in &gdr.uir_vec {
+            visitor.visit_uir(uir, &gdr_context.child(offset(&next)?))?;
+            next = uir.uir_next.clone();
+        }
+        Ok(())
+    }
+}
+
+impl<'a> RecordContext<'a> {
+    // Context of a record at `offset` held by the record of this context.
+    fn child(&self, offset: u64) -> Self {
+        RecordContext {
+            offset,
+            parent: Some(self.offset),
+            depth: self.depth + 1,
+            ..*self
+        }
+    }
+}
+
+fn offset(offset: &Option<CdfInt8>) -> Result<u64, CdfError> {
+    let offset = offset
+        .as_ref()
+        .ok_or_else(|| CdfError::Other("A list of records ends early.".to_string()))?;
+    Ok(u64::try_from(**offset)?)
+}
+
+// Visit the CPR and the index of a variable whose descriptor has the context `vdr`.
+fn walk_values<V>(
+    visitor: &mut V,
+    vdr: &RecordContext,
+    (cpr_offset, cpr): (&Option<CdfInt8>, Option<&CompressedParametersRecord>),
+    vxr_head: &Option<CdfInt8>,
+    vxrs: &[VariableIndexRecord],
+) -> Result<(), CdfError>
+where
+    V: RecordVisitor + ?Sized,
+{
+    if let Some(cpr) = cpr {
+        visitor.visit_cpr(cpr, &vdr.child(offset(cpr_offset)?))?;
+    }
+    let mut next = vxr_head.clone();
+    for vxr in vxrs {
+        walk_vxr(visitor, vxr, &vdr.child(offset(&next)?))?;
+        next = vxr.vxr_next.clone();
+    }
+    Ok(())
+}
+
+fn walk_vxr<V>(
+    visitor: &mut V,
+    vxr: &VariableIndexRecord,
+    context: &RecordContext,
+) -> Result<(), CdfError>
+where
+    V: RecordVisitor + ?Sized,
+{
+    visitor.visit_vxr(vxr, context)?;
+    for (child, at) in vxr.children.iter().zip(&vxr.offset_vec) {
+        let Some(child) = child else {
+            continue;
+        };
+        let context = context.child(offset(at)?);
+        match child {
+            VariableIndexRecordChild::VVR(vvr) => visitor.visit_vvr(vvr, &context)?,
+            VariableIndexRecordChild::CVVR(cvvr) => visitor.visit_cvvr(cvvr, &context)?,
+            VariableIndexRecordChild::VXR(vxr) => walk_vxr(visitor, vxr, &context)?,
+            VariableIndexRecordChild::Unknown(record) => visitor.visit_unknown(record, &context)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use super::*;
+    use crate::cdf::ReadOptions;
+    use alloc::{
+        collections::{BTreeMap, BTreeSet},
+        vec::Vec,
+    };
+    use std::path::PathBuf;
+
+    // Visitor that keeps the context and record size of every record.
+    #[derive(Default)]
+    struct Records(
+        Vec<(
+            RecordContext<'static>,
+            i64,
+            &'static str,
+            Option<alloc::string::String>,
+        )>,
+    );
+
+    macro_rules! keep {
+        ($method:ident, $record:ty, $kind:literal) => {
+            fn $method(
+                &mut self,
+                record: &$record,
+                context: &RecordContext,
+            ) -> Result<(), CdfError> {
+                let kept = RecordContext {
+                    variable: None,
+                    attribute: None,
+                    ..*context
+                };
+                let name = context
+                    .variable
+                    .or(context.attribute)
+                    .map(ToString::to_string);
+                self.0.push((kept, *record.record_size, $kind, name));
+                Ok(())
+            }
+        };
+    }
+
+    impl RecordVisitor for Records {
+        keep!(visit_cdr, CdfDescriptorRecord, "CDR");
+        keep!(visit_gdr, GlobalDescriptorRecord, "GDR");
+        keep!(visit_rvdr, RVariableDescriptorRecord, "rVDR");
+        keep!(visit_zvdr, ZVariableDescriptorRecord, "zVDR");
+        keep!(visit_cpr, CompressedParametersRecord, "CPR");
+        keep!(visit_vxr, VariableIndexRecord, "VXR");
+        keep!(visit_vvr, VariableValuesRecord, "VVR");
+        keep!(visit_cvvr, CompressedVariableValuesRecord, "CVVR");
+        keep!(visit_adr, AttributeDescriptorRecord, "ADR");
+        keep!(visit_agredr, AttributeGREntryDescriptorRecord, "AgrEDR");
+        keep!(visit_azedr, AttributeZEntryDescriptorRecord, "AzEDR");
+        keep!(visit_uir, UnusedInternalRecord, "UIR");
+    }
+
+    #[test]
+    fn test_walk() -> Result<(), CdfError> {
+        for (filename, expected) in [
+            ("test_alltypes.cdf", [("zVDR", 21), ("ADR", 11), ("UIR", 3)]),
+            ("ulysses.cdf", [("rVDR", 15), ("ADR", 27), ("UIR", 0)]),
+        ] {
+            let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "examples", "data", filename]
+                .iter()
+                .collect();
+            let options = ReadOptions {
+                keep_raw: true,
+                ..Default::default()
+            };
+            let cdf = Cdf::from_reader(std::fs::File::open(path)?, options)?;
+            let mut records = Records::default();
+            cdf.walk(&mut records)?;
+
+            // Every record decoded is visited once, at the offset it was decoded from.
+            let raw = cdf.raw_records();
+            let mut counts = BTreeMap::new();
+            for (context, size, kind, name) in &records.0 {
+                assert_eq!(raw[&context.offset].len() as i64, *size, "{kind}");
+                *counts.entry(*kind).or_insert(0) += 1;
+                if let Some(parent) = context.parent {
+                    assert!(raw.contains_key(&parent));
+                }
+                assert_eq!(
+                    name.is_some(),
+                    context.depth >= 2 && *kind != "UIR",
+                    "{kind}"
+                );
+            }
+            // The only other bytes kept are the magic numbers and the bytes after the last record.
+            let visited: BTreeSet<u64> = records.0.iter().map(|(c, ..)| c.offset).collect();
+            assert_eq!(visited.len(), records.0.len());
+            let eof = u64::try_from(**cdf.cdr.gdr.eof.as_ref().unwrap())?;
+            assert!(raw
+                .keys()
+                .all(|offset| visited.contains(offset) || *offset == 0 || *offset >= eof));
+            for (kind, count) in expected {
+                assert_eq!(counts.get(kind).copied().unwrap_or(0), count, "{filename}");
+            }
+            assert_eq!(records.0[0].0.depth, 0);
+            assert!(records.0.iter().skip(1).all(|(c, ..)| c.depth > 0));
+        }
+        Ok(())
+    }
+}
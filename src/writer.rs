@@ -0,0 +1,2161 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use core::mem;
+
+use crate::checksum::md5_prefix;
+use crate::compression::{compress, compress_portable};
+use crate::decode::CDF_MAX_DIMS;
+use crate::encode::{encode_to_vec, encode_values, Encodable, Encoder};
+use crate::error::{CdfError, ShapeError};
+use crate::io::{self, Seek, SeekFrom};
+use crate::record::{
+    adr::AttributeDescriptorRecord,
+    agredr::AttributeGREntryDescriptorRecord,
+    azedr::AttributeZEntryDescriptorRecord,
+    cdr::{CdfDescriptorRecord, CdrFlags, COPYRIGHT},
+    cpr::{CdfCompressionKind, CompressedParametersRecord},
+    cvvr::CompressedVariableValuesRecord,
+    gdr::GlobalDescriptorRecord,
+    rvdr::{RVariableDescriptorRecord, RVariableFlags},
+    vvr::VariableValuesRecord,
+    vxr::VariableIndexRecord,
+    zvdr::{ZVariableDescriptorRecord, ZVariableFlags},
+};
+use crate::repr::{CdfEncoding, CdfVersion};
+use crate::time::{leap_seconds, LeapSecondTable};
+use crate::types::{
+    CdfEpoch, CdfEpoch16, CdfInt1, CdfInt2, CdfInt4, CdfInt8, CdfReal4, CdfReal8, CdfString,
+    CdfTimeTt2000, CdfType, CdfUint1, CdfUint2, CdfUint4, DataType, STRING_DELIMITER,
+};
+use crate::variable::{Variable, VariableDescriptor};
+
+/// Version of the CDF format that is written.
+const WRITE_VERSION: CdfVersion = CdfVersion {
+    major: 3,
+    minor: 9,
+    patch: 0,
+};
+
+/// Offset of the CDR, which follows the two magic numbers.
+const CDR_OFFSET: u64 = 8;
+
+/// Number of entries in each VXR by default, as written by the CDF C library.
+const VXR_ENTRIES: usize = 7;
+
+/// Minimum number of bytes in each VVR of a variable whose blocking factor is not set, as used by
+/// the CDF C library.
+const MIN_BLOCKING_BYTES: usize = 1024;
+
+/// Minimum number of bytes in each CVVR of a variable whose blocking factor is not set, before
+/// compression.
+const MIN_BLOCKING_BYTES_COMPRESSED: usize = 8192;
+
+/// Description of a zVariable or an rVariable to be written by a [`CdfWriter`].
+#[derive(Debug, Clone)]
+pub struct VariableSpec {
+    name: String,
+    data_type: DataType,
+    is_rvariable: bool,
+    num_elements: usize,
+    dims: Vec<usize>,
+    dim_variances: Option<Vec<bool>>,
+    record_varies: bool,
+    compression: CdfCompressionKind,
+    blocking_factor: Option<usize>,
+    expected_records: Option<usize>,
+    pad_value: Option<CdfType>,
+}
+
+impl VariableSpec {
+    /// Describe a scalar variable called `name` that stores values of `data_type`, with one
+    /// element per value and no compression.
+    pub fn new(name: &str, data_type: DataType) -> Self {
+        VariableSpec {
+            name: name.to_string(),
+            data_type,
+            is_rvariable: false,
+            num_elements: 1,
+            dims: vec![],
+            dim_variances: None,
+            record_varies: true,
+            compression: CdfCompressionKind::None,
+            blocking_factor: None,
+            expected_records: None,
+            pad_value: None,
+        }
+    }
+
+    /// Describe an rVariable called `name` that stores values of `data_type`. Its dimensions are
+    /// the dimensions shared by all rVariables, set with [`CdfWriterBuilder::r_dim_sizes`].
+    pub fn new_rvariable(name: &str, data_type: DataType) -> Self {
+        VariableSpec {
+            is_rvariable: true,
+            ..VariableSpec::new(name, data_type)
+        }
+    }
+
+    /// Describe a variable like `variable` of an existing file, such as a master CDF whose
+    /// variables are filled with new records. The name, data type, dimensions, variances,
+    /// compression, pad value and blocking factor are kept. Only the first element of a pad value
+    /// with several elements is kept.
+    /// # Errors
+    /// Returns a [`CdfError`] if the data type or the dimensions of the variable are invalid.
+    pub fn from_variable(variable: &Variable) -> Result<Self, CdfError> {
+        let data_type = DataType::try_from(variable.data_type().clone())?;
+        let mut spec = if variable.is_zvariable() {
+            VariableSpec::new(variable.name(), data_type).dims(&variable.dim_sizes()?)
+        } else {
+            VariableSpec::new_rvariable(variable.name(), data_type)
+        };
+        spec = spec
+            .num_elements(variable.num_elements()?)
+            .dim_variances(variable.dim_variances())
+            .record_varies(variable.record_varies());
+        if let Some(cpr) = variable.compression() {
+            spec = spec.compression(cpr.compression_type.clone());
+        }
+        let (pad_value, blocking_factor) = match variable.descriptor() {
+            VariableDescriptor::R(vdr) => (&vdr.pad_value, *vdr.blocking_factor),
+            VariableDescriptor::Z(vdr) => (&vdr.pad_value, *vdr.blocking_factor),
+        };
+        if let Some(pad_value) = pad_value.first() {
+            spec = spec.pad_value(pad_value.clone());
+        }
+        if blocking_factor > 0 {
+            spec = spec.blocking_factor(usize::try_from(blocking_factor)?);
+        }
+        Ok(spec)
+    }
+
+    /// Name of the variable.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Data type of the values.
+    pub fn data_type(&self) -> DataType {
+        self.data_type
+    }
+
+    /// Give the variable another name.
+    pub fn rename(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Set the number of elements of each value, which is the length of the strings for
+    /// character data.
+    pub fn num_elements(mut self, num_elements: usize) -> Self {
+        self.num_elements = num_elements;
+        self
+    }
+
+    /// Set the dimension sizes of each record of a zVariable.
+    pub fn dims(mut self, dims: &[usize]) -> Self {
+        self.dims = dims.to_vec();
+        self
+    }
+
+    /// Set which dimensions vary. Values are only stored along dimensions that vary, so a record
+    /// holds the values of the varying dimensions only. All dimensions vary by default.
+    pub fn dim_variances(mut self, dim_variances: &[bool]) -> Self {
+        self.dim_variances = Some(dim_variances.to_vec());
+        self
+    }
+
+    /// Set whether the values vary from record to record, which they do by default. A variable
+    /// that does not vary (NRV) holds a single record that applies to all records, such as an
+    /// energy table or a calibration matrix, and at most one record can be written to it.
+    pub fn record_varies(mut self, record_varies: bool) -> Self {
+        self.record_varies = record_varies;
+        self
+    }
+
+    /// Compress the values of each chunk of records using `compression`.
+    pub fn compression(mut self, compression: CdfCompressionKind) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Set the value that readers use for records that were not written, instead of the default
+    /// pad value of the data type. Character data is padded with a [`CdfType::String`] of
+    /// `num_elements` characters, while other data is padded with `num_elements` copies of
+    /// `pad_value`.
+    pub fn pad_value(mut self, pad_value: CdfType) -> Self {
+        self.pad_value = Some(pad_value);
+        self
+    }
+
+    /// Set the number of records written together in each VVR or CVVR. Larger blocks compress
+    /// better, while smaller blocks hold fewer records in memory before they are written.
+    pub fn blocking_factor(mut self, blocking_factor: usize) -> Self {
+        self.blocking_factor = Some(blocking_factor);
+        self
+    }
+
+    /// Set the number of records that are expected to be written, so that the chosen blocking
+    /// factor does not exceed it.
+    pub fn expected_records(mut self, expected_records: usize) -> Self {
+        self.expected_records = Some(expected_records);
+        self
+    }
+
+    // The blocking factor that was set, or else the number of records needed to fill a minimum
+    // number of bytes, as done by the CDF C library, or a single record for variables that do not
+    // vary by record.
+    fn choose_blocking_factor(&self) -> Result<usize, CdfError> {
+        if let Some(blocking_factor) = self.blocking_factor {
+            if blocking_factor == 0 {
+                return Err(CdfError::Encode(format!(
+                    "Invalid blocking factor 0 for variable {}.",
+                    self.name
+                )));
+            }
+            return Ok(blocking_factor);
+        }
+        if !self.record_varies {
+            return Ok(1);
+        }
+        let min_bytes = match self.compression {
+            CdfCompressionKind::None => MIN_BLOCKING_BYTES,
+            _ => MIN_BLOCKING_BYTES_COMPRESSED,
+        };
+        let record_bytes = self
+            .values_per_record()?
+            .saturating_mul(self.data_type.size());
+        let blocking_factor = min_bytes.div_ceil(record_bytes.max(1));
+        Ok(self
+            .expected_records
+            .map_or(blocking_factor, |n| blocking_factor.min(n))
+            .max(1))
+    }
+
+    // Sizes of the dimensions along which values are stored.
+    fn varying_dims(&self) -> Vec<usize> {
+        match &self.dim_variances {
+            Some(variances) => self
+                .dims
+                .iter()
+                .zip(variances)
+                .filter(|(_, v)| **v)
+                .map(|(d, _)| *d)
+                .collect(),
+            None => self.dims.clone(),
+        }
+    }
+
+    // Values of the pad value stored in the VDR, which are `num_elements` copies of the pad value
+    // for data other than characters.
+    fn pad_values(&self) -> Vec<CdfType> {
+        match &self.pad_value {
+            Some(pad_value) if !self.data_type.is_string() => {
+                vec![pad_value.clone(); self.num_elements]
+            }
+            pad_value => pad_value.iter().cloned().collect(),
+        }
+    }
+
+    // Number of values of `data_type` in each record.
+    fn values_per_record(&self) -> Result<usize, CdfError> {
+        self.varying_dims()
+            .iter()
+            .try_fold(self.num_elements, |n, d| n.checked_mul(*d))
+            .ok_or_else(|| {
+                CdfError::Encode(format!(
+                    "Invalid dimension sizes for variable {}.",
+                    self.name
+                ))
+            })
+    }
+}
+
+/// Value of an attribute entry written by a [`CdfWriter`], which is one or more values of the
+/// same data type, or one or more strings. Values are usually converted from Rust values, such as
+/// `"Voyager"`, `-1e31f32` or `vec![0, 1, 2]`.
+#[derive(Debug, Clone)]
+pub struct AttributeValue {
+    data_type: DataType,
+    num_strings: usize,
+    values: Vec<CdfType>,
+}
+
+impl AttributeValue {
+    /// Create an entry value holding `values` of `data_type`. Character data is given as a single
+    /// [`CdfType::String`].
+    /// # Errors
+    /// Returns a [`CdfError::Encode`] if there are no values or they are not of `data_type`.
+    pub fn new(data_type: DataType, values: Vec<CdfType>) -> Result<Self, CdfError> {
+        let valid = if data_type.is_string() {
+            matches!(values.as_slice(), [CdfType::String(_)])
+        } else {
+            !values.is_empty() && values.iter().all(|v| v.is_of_type(&data_type))
+        };
+        if !valid {
+            return Err(CdfError::Encode(format!(
+                "Attribute entry values do not match data type {data_type:?}."
+            )));
+        }
+        Ok(AttributeValue {
+            data_type,
+            num_strings: usize::from(data_type.is_string()),
+            values,
+        })
+    }
+
+    /// Create an entry value holding several strings, separated by [`STRING_DELIMITER`] as done by
+    /// the CDF C library.
+    pub fn strings<I, S>(strings: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let strings: Vec<String> = strings
+            .into_iter()
+            .map(|s| s.as_ref().to_string())
+            .collect();
+        AttributeValue {
+            num_strings: strings.len(),
+            ..AttributeValue::from(strings.join(STRING_DELIMITER))
+        }
+    }
+
+    // Set the number of strings held by character data, as recorded by an existing entry.
+    pub(crate) fn with_num_strings(mut self, num_strings: usize) -> Self {
+        if self.data_type.is_string() {
+            self.num_strings = num_strings;
+        }
+        self
+    }
+
+    /// Data type of the values.
+    pub fn data_type(&self) -> DataType {
+        self.data_type
+    }
+
+    /// The values, or a single [`CdfType::String`] for character data.
+    pub fn values(&self) -> &[CdfType] {
+        &self.values
+    }
+
+    // Number of values, or of characters for character data.
+    fn num_elements(&self) -> usize {
+        match self.values.as_slice() {
+            [CdfType::String(s)] => s.len(),
+            values => values.len(),
+        }
+    }
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        AttributeValue::from(value.to_string())
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        AttributeValue {
+            data_type: DataType::Char,
+            num_strings: 1,
+            values: vec![CdfType::String(CdfString::from(value))],
+        }
+    }
+}
+
+macro_rules! impl_attribute_value_from {
+    ($rust_type:ty, $variant:ident, $cdf_type:ident) => {
+        impl From<$rust_type> for AttributeValue {
+            fn from(value: $rust_type) -> Self {
+                AttributeValue::from(vec![value])
+            }
+        }
+
+        impl From<&[$rust_type]> for AttributeValue {
+            fn from(values: &[$rust_type]) -> Self {
+                AttributeValue::from(values.to_vec())
+            }
+        }
+
+        impl From<Vec<$rust_type>> for AttributeValue {
+            fn from(values: Vec<$rust_type>) -> Self {
+                AttributeValue {
+                    data_type: DataType::$variant,
+                    num_strings: 0,
+                    values: values
+                        .into_iter()
+                        .map(|v| CdfType::$variant($cdf_type::from(v)))
+                        .collect(),
+                }
+            }
+        }
+    };
+}
+
+impl_attribute_value_from!(i8, Int1, CdfInt1);
+impl_attribute_value_from!(i16, Int2, CdfInt2);
+impl_attribute_value_from!(i32, Int4, CdfInt4);
+impl_attribute_value_from!(i64, Int8, CdfInt8);
+impl_attribute_value_from!(u8, Uint1, CdfUint1);
+impl_attribute_value_from!(u16, Uint2, CdfUint2);
+impl_attribute_value_from!(u32, Uint4, CdfUint4);
+impl_attribute_value_from!(f32, Real4, CdfReal4);
+impl_attribute_value_from!(f64, Real8, CdfReal8);
+impl_attribute_value_from!(CdfEpoch, Epoch, CdfEpoch);
+impl_attribute_value_from!(CdfEpoch16, Epoch16, CdfEpoch16);
+impl_attribute_value_from!(CdfTimeTt2000, TimeTt2000, CdfTimeTt2000);
+
+/// How the VXRs that index the records of each variable are arranged.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VxrLayout {
+    /// A single level of VXRs linked one after the other, as written by the CDF C library.
+    /// Finding a record has to walk the list, which is slow for very long variables.
+    #[default]
+    Chain,
+    /// VXRs that point to lower-level VXRs, so that any record is found after reading a number
+    /// of VXRs that grows with the logarithm of the number of chunks.
+    Tree,
+}
+
+/// Builder for a [`CdfWriter`] that declares the variables of the file.
+#[derive(Debug, Clone)]
+pub struct CdfWriterBuilder {
+    variables: Vec<VariableSpec>,
+    vxr_entries: usize,
+    vxr_layout: VxrLayout,
+    encoding: Option<CdfEncoding>,
+    row_major: bool,
+    r_dim_sizes: Vec<usize>,
+    deterministic: bool,
+}
+
+impl Default for CdfWriterBuilder {
+    fn default() -> Self {
+        CdfWriterBuilder {
+            variables: vec![],
+            vxr_entries: VXR_ENTRIES,
+            vxr_layout: VxrLayout::default(),
+            encoding: None,
+            row_major: true,
+            r_dim_sizes: vec![],
+            deterministic: false,
+        }
+    }
+}
+
+impl CdfWriterBuilder {
+    /// Create a new builder with no variables.
+    pub fn new() -> Self {
+        CdfWriterBuilder::default()
+    }
+
+    /// Store values using `encoding`, which is [`CdfEncoding::host`] by default so that values are
+    /// written without swapping their bytes.
+    pub fn encoding(mut self, encoding: CdfEncoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Set whether the values of multi-dimensional records are stored in row-major order, which
+    /// is the default, or in column-major order. Records are always passed to
+    /// [`CdfWriter::write_records`] in row-major order.
+    pub fn row_major(mut self, row_major: bool) -> Self {
+        self.row_major = row_major;
+        self
+    }
+
+    /// Set the dimension sizes shared by all rVariables, stored in the GDR.
+    pub fn r_dim_sizes(mut self, r_dim_sizes: &[usize]) -> Self {
+        self.r_dim_sizes = r_dim_sizes.to_vec();
+        self
+    }
+
+    /// Set the number of entries of each VXR, which is 7 by default.
+    pub fn vxr_entries(mut self, vxr_entries: usize) -> Self {
+        self.vxr_entries = vxr_entries;
+        self
+    }
+
+    /// Set how the VXRs of each variable are arranged.
+    pub fn vxr_layout(mut self, vxr_layout: VxrLayout) -> Self {
+        self.vxr_layout = vxr_layout;
+        self
+    }
+
+    /// Set whether the file is written the same way on every machine and with every build of this
+    /// crate, byte for byte, for reproducible pipelines and content-addressed storage. The file
+    /// then only depends on the variables, attributes and records passed to the writer, and on the
+    /// order in which they are passed:
+    /// - values are stored in [`CdfEncoding::Network`] unless another encoding is set, rather
+    ///   than in the encoding of the host,
+    /// - the date of the last leap second update in the GDR is that of the built-in leap second
+    ///   table, whatever table is used for time conversions,
+    /// - GZIP compression uses the built-in DEFLATE implementation, whatever the enabled
+    ///   features, at the cost of larger blocks, see [`crate::compression::DEFLATE_BACKEND`].
+    ///
+    /// Files never hold a creation time, and the copyright text of the CDR is fixed, so these do
+    /// not need to change.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Declare a variable of the file.
+    pub fn variable(mut self, spec: VariableSpec) -> Self {
+        self.variables.push(spec);
+        self
+    }
+
+    /// Write the descriptors of the file and of its variables to `writer`, and return a
+    /// [`CdfWriter`] to which records can be pushed.
+    /// # Errors
+    /// Returns a [`CdfError::Encode`] if the variables are invalid, or a [`CdfError::Io`] if
+    /// writing fails.
+    pub fn build<W>(mut self, writer: W) -> Result<CdfWriter<W>, CdfError>
+    where
+        W: io::Write + io::Seek,
+    {
+        let min_entries = match self.vxr_layout {
+            VxrLayout::Chain => 1,
+            VxrLayout::Tree => 2,
+        };
+        if self.vxr_entries < min_entries {
+            return Err(CdfError::Encode(format!(
+                "Invalid number of VXR entries {} - at least {min_entries} are needed.",
+                self.vxr_entries
+            )));
+        }
+        for spec in self.variables.iter_mut().filter(|v| v.is_rvariable) {
+            if !spec.dims.is_empty() {
+                return Err(CdfError::Encode(format!(
+                    "rVariable {} cannot have its own dimensions.",
+                    spec.name
+                )));
+            }
+            spec.dims = self.r_dim_sizes.clone();
+        }
+        for (i, spec) in self.variables.iter().enumerate() {
+            if self.variables[..i].iter().any(|v| v.name == spec.name) {
+                return Err(CdfError::Encode(format!(
+                    "Variable {} is declared more than once.",
+                    spec.name
+                )));
+            }
+            if spec.num_elements == 0 {
+                return Err(CdfError::Encode(format!(
+                    "Invalid number of elements {} for variable {}.",
+                    spec.num_elements, spec.name
+                )));
+            }
+            if spec
+                .dim_variances
+                .as_ref()
+                .is_some_and(|v| v.len() != spec.dims.len())
+            {
+                return Err(CdfError::Encode(format!(
+                    "Dimension variances of variable {} do not match its dimensions.",
+                    spec.name
+                )));
+            }
+            if spec.dims.len() > CDF_MAX_DIMS {
+                let field = if spec.is_rvariable {
+                    "num_r_dims"
+                } else {
+                    "num_z_dims"
+                };
+                let num_dims = spec.dims.len();
+                return Err(ShapeError::TooManyDimensions { field, num_dims }.into());
+            }
+            if spec.dims.contains(&0) {
+                return Err(CdfError::Encode(format!(
+                    "Invalid dimension sizes {:?} for variable {}.",
+                    spec.dims, spec.name
+                )));
+            }
+            spec.values_per_record()?;
+            compress(&spec.compression, &[])?;
+            if let Some(pad_value) = &spec.pad_value {
+                let pad_len = if spec.data_type.is_string() {
+                    spec.num_elements
+                } else {
+                    1
+                };
+                check_record(spec, pad_len, core::slice::from_ref(pad_value)).map_err(|_| {
+                    CdfError::Encode(format!("Invalid pad value for variable {}.", spec.name))
+                })?;
+            }
+        }
+
+        let encoding = self.encoding.unwrap_or(if self.deterministic {
+            CdfEncoding::Network
+        } else {
+            CdfEncoding::host()
+        });
+        let encoder = Encoder::new(writer, WRITE_VERSION, encoding)?;
+        let mut counts = [0, 0];
+        let mut cdf_writer = CdfWriter {
+            encoder,
+            variables: self
+                .variables
+                .into_iter()
+                .map(|spec| {
+                    let count = &mut counts[usize::from(spec.is_rvariable)];
+                    *count += 1;
+                    Ok(WriterVariable {
+                        blocking_factor: spec.choose_blocking_factor()?,
+                        num: *count - 1,
+                        spec,
+                        offset: 0,
+                        cpr_offset: None,
+                        num_records: 0,
+                        entries: vec![],
+                        pending: vec![],
+                        pending_records: 0,
+                    })
+                })
+                .collect::<Result<_, CdfError>>()?,
+            gdr_offset: 0,
+            vxr_entries: self.vxr_entries,
+            vxr_layout: self.vxr_layout,
+            row_major: self.row_major,
+            r_dim_sizes: self.r_dim_sizes,
+            attributes: vec![],
+            adr_head: None,
+            deterministic: self.deterministic,
+        };
+        cdf_writer.write_header()?;
+        Ok(cdf_writer)
+    }
+}
+
+/// Writes a CDF file with zVariables and rVariables while holding at most one block of records per
+/// variable in memory.
+///
+/// The variables are declared up front using [`CdfWriter::builder`]. Records pushed with
+/// [`CdfWriter::write_records`] are written as soon as they fill a block of the blocking factor
+/// of the variable, as a VVR, or a CVVR if the variable is compressed, and only the location of
+/// the block is kept. The last block and the index of each variable are written by
+/// [`CdfWriter::close`], which must be called for the file to be valid.
+///
+/// ```
+/// use cdf::cdf::Cdf;
+/// use cdf::types::{CdfInt4, CdfType, DataType};
+/// use cdf::writer::{CdfWriter, VariableSpec};
+/// use std::io::Cursor;
+///
+/// let mut writer = CdfWriter::builder()
+///     .variable(VariableSpec::new("counts", DataType::Int4))
+///     .build(Cursor::new(Vec::new()))
+///     .unwrap();
+/// let records: Vec<_> = (0..5)
+///     .map(|i| vec![CdfType::Int4(CdfInt4::from(i))])
+///     .collect();
+/// writer.write_records("counts", &records).unwrap();
+/// let bytes = writer.close().unwrap().into_inner();
+///
+/// let cdf = Cdf::from_bytes(&bytes).unwrap();
+/// assert_eq!(cdf.variable("counts").unwrap().n_records(), 5);
+/// ```
+pub struct CdfWriter<W>
+where
+    W: io::Write + io::Seek,
+{
+    encoder: Encoder<W>,
+    variables: Vec<WriterVariable>,
+    gdr_offset: u64,
+    vxr_entries: usize,
+    vxr_layout: VxrLayout,
+    row_major: bool,
+    r_dim_sizes: Vec<usize>,
+    attributes: Vec<WriterAttribute>,
+    adr_head: Option<u64>,
+    deterministic: bool,
+}
+
+// A declared variable along with the location of its descriptor and of the chunks written so far.
+struct WriterVariable {
+    spec: VariableSpec,
+    // Number of the variable among the variables of the same kind.
+    num: usize,
+    offset: u64,
+    cpr_offset: Option<u64>,
+    num_records: usize,
+    // First record, last record and offset of each VVR or CVVR.
+    entries: Vec<(usize, usize, u64)>,
+    blocking_factor: usize,
+    // Encoded values of the records that are not written yet, fewer than `blocking_factor`.
+    pending: Vec<u8>,
+    pending_records: usize,
+}
+
+// An attribute along with its entries, numbered by entry for a global attribute, or by the index
+// of the variable they describe for a variable attribute.
+struct WriterAttribute {
+    name: String,
+    global: bool,
+    entries: Vec<(usize, AttributeValue)>,
+}
+
+/// Builder returned by [`CdfWriter::global_attr`] that adds entries to a global attribute,
+/// numbered in the order they are added unless given a number with
+/// [`GlobalAttribute::entry_at`].
+pub struct GlobalAttribute<'a, W>
+where
+    W: io::Write + io::Seek,
+{
+    writer: &'a mut CdfWriter<W>,
+    index: usize,
+}
+
+impl<W> GlobalAttribute<'_, W>
+where
+    W: io::Write + io::Seek,
+{
+    /// Append an entry holding `value`, whose data type follows from its Rust type, numbered one
+    /// after the highest entry number so far.
+    pub fn entry(self, value: impl Into<AttributeValue>) -> Self {
+        let entries = &self.writer.attributes[self.index].entries;
+        let num = entries.iter().map(|(num, _)| num + 1).max().unwrap_or(0);
+        self.entry_at(num, value)
+    }
+
+    /// Set the entry numbered `num` to `value`, replacing an entry set before with the same
+    /// number. Entry numbers may leave gaps, which are kept in the file, and some tools give them
+    /// a meaning, so this keeps the numbers of the entries of a file that is copied.
+    pub fn entry_at(self, num: usize, value: impl Into<AttributeValue>) -> Self {
+        let entries = &mut self.writer.attributes[self.index].entries;
+        entries.retain(|(n, _)| *n != num);
+        entries.push((num, value.into()));
+        self
+    }
+
+    /// Append an entry holding an EPOCH value, the number of milliseconds since 0000-01-01,
+    /// which would otherwise be written as a REAL8.
+    pub fn entry_epoch(self, epoch: f64) -> Self {
+        self.entry(CdfEpoch::from(epoch))
+    }
+
+    /// Append an entry holding a TT2000 value, the number of nanoseconds since J2000, which would
+    /// otherwise be written as an INT8.
+    pub fn entry_tt2000(self, tt2000: i64) -> Self {
+        self.entry(CdfTimeTt2000::from(tt2000))
+    }
+}
+
+impl CdfWriter<io::Empty> {
+    /// Returns a [`CdfWriterBuilder`] to declare the variables of the file.
+    pub fn builder() -> CdfWriterBuilder {
+        CdfWriterBuilder::new()
+    }
+}
+
+impl<W> CdfWriter<W>
+where
+    W: io::Write + io::Seek,
+{
+    /// Append `records` to the variable called `name`. Each record holds the values of one
+    /// record in row-major order, or a single [`CdfType::String`] for character data, as
+    /// returned when reading the file.
+    /// # Errors
+    /// Returns a [`CdfError::Encode`] if the variable does not exist, if a record does not match
+    /// its description or if it would hold more than one record without varying by record, or a
+    /// [`CdfError::Io`] if writing fails.
+    pub fn write_records(&mut self, name: &str, records: &[Vec<CdfType>]) -> Result<(), CdfError> {
+        let index = self
+            .variables
+            .iter()
+            .position(|v| v.spec.name == name)
+            .ok_or_else(|| CdfError::Encode(format!("Variable {name} is not declared.")))?;
+        if records.is_empty() {
+            return Ok(());
+        }
+        let spec = &self.variables[index].spec;
+        if !spec.record_varies && self.variables[index].num_records + records.len() > 1 {
+            return Err(CdfError::Encode(format!(
+                "Variable {name} does not vary by record and holds a single record."
+            )));
+        }
+        let values_per_record = spec.values_per_record()?;
+        for record in records {
+            check_record(spec, values_per_record, record)?;
+        }
+
+        let column_major = !self.row_major && spec.varying_dims().len() > 1;
+
+        for record in records {
+            let reordered =
+                column_major.then(|| to_column_major(&self.variables[index].spec, record));
+            let pending = mem::take(&mut self.variables[index].pending);
+            let mut data = self.encoder.nested(io::Cursor::new(pending));
+            data.writer.seek(SeekFrom::End(0))?;
+            encode_values(&mut data, reordered.as_deref().unwrap_or(record))?;
+
+            let variable = &mut self.variables[index];
+            variable.pending = data.writer.into_inner();
+            variable.pending_records += 1;
+            variable.num_records += 1;
+            if variable.pending_records == variable.blocking_factor {
+                self.write_block(index)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Declare the global attribute called `name`, or add to it if it is already declared, and
+    /// return a [`GlobalAttribute`] to append its entries.
+    ///
+    /// ```
+    /// use cdf::cdf::Cdf;
+    /// use cdf::types::DataType;
+    /// use cdf::writer::{CdfWriter, VariableSpec};
+    /// use std::io::Cursor;
+    ///
+    /// let mut writer = CdfWriter::builder()
+    ///     .variable(VariableSpec::new("B_RTN", DataType::Real4).dims(&[3]))
+    ///     .build(Cursor::new(Vec::new()))
+    ///     .unwrap();
+    /// writer
+    ///     .global_attr("Mission_group")
+    ///     .unwrap()
+    ///     .entry("Voyager")
+    ///     .entry_epoch(62_987_673_600_000.0);
+    /// writer.var_attr("B_RTN", "FILLVAL", -1e31f32).unwrap();
+    /// let bytes = writer.close().unwrap().into_inner();
+    ///
+    /// let cdf = Cdf::from_bytes(&bytes).unwrap();
+    /// let b = cdf.variable("B_RTN").unwrap();
+    /// assert!(b.attributes().contains("FILLVAL"));
+    /// ```
+    /// # Errors
+    /// Returns a [`CdfError::Encode`] if `name` is already declared as a variable attribute.
+    pub fn global_attr(&mut self, name: &str) -> Result<GlobalAttribute<'_, W>, CdfError> {
+        let index = self.attribute(name, true)?;
+        Ok(GlobalAttribute {
+            writer: self,
+            index,
+        })
+    }
+
+    /// Set the entry of the variable attribute called `name` for the variable called `variable`
+    /// to `value`, whose data type follows from its Rust type. The attribute is declared if
+    /// needed, and an entry that was set before is replaced.
+    /// # Errors
+    /// Returns a [`CdfError::Encode`] if the variable does not exist, or if `name` is already
+    /// declared as a global attribute.
+    pub fn var_attr(
+        &mut self,
+        variable: &str,
+        name: &str,
+        value: impl Into<AttributeValue>,
+    ) -> Result<(), CdfError> {
+        let variable = self
+            .variables
+            .iter()
+            .position(|v| v.spec.name == variable)
+            .ok_or_else(|| CdfError::Encode(format!("Variable {variable} is not declared.")))?;
+        let index = self.attribute(name, false)?;
+        let entries = &mut self.attributes[index].entries;
+        entries.retain(|(v, _)| *v != variable);
+        entries.push((variable, value.into()));
+        Ok(())
+    }
+
+    // Index of the attribute called `name`, which is declared with the given scope if needed.
+    fn attribute(&mut self, name: &str, global: bool) -> Result<usize, CdfError> {
+        if let Some(index) = self.attributes.iter().position(|a| a.name == name) {
+            if self.attributes[index].global != global {
+                return Err(CdfError::Encode(format!(
+                    "Attribute {name} is already declared with another scope."
+                )));
+            }
+            return Ok(index);
+        }
+        self.attributes.push(WriterAttribute {
+            name: name.to_string(),
+            global,
+            entries: vec![],
+        });
+        Ok(self.attributes.len() - 1)
+    }
+
+    // Descriptions of the declared variables, in the order they were declared.
+    pub(crate) fn specs(&self) -> impl Iterator<Item = &VariableSpec> {
+        self.variables.iter().map(|v| &v.spec)
+    }
+
+    // Whether the attribute called `name` has an entry, for the variable called `variable` if
+    // given.
+    pub(crate) fn has_entry(&self, name: &str, variable: Option<&str>) -> bool {
+        let variable =
+            variable.map(|variable| self.variables.iter().position(|v| v.spec.name == variable));
+        self.attributes
+            .iter()
+            .filter(|a| a.name == name)
+            .flat_map(|a| &a.entries)
+            .any(|(num, _)| variable.is_none_or(|v| v == Some(*num)))
+    }
+
+    /// The number of records of each VVR or CVVR of the variable called `name`, which is either
+    /// set with [`VariableSpec::blocking_factor`] or chosen from the size of its records.
+    pub fn blocking_factor(&self, name: &str) -> Option<usize> {
+        self.variables
+            .iter()
+            .find(|v| v.spec.name == name)
+            .map(|v| v.blocking_factor)
+    }
+
+    /// Write the index of each variable and complete the descriptors, then return the writer.
+    /// # Errors
+    /// Returns a [`CdfError`] if encoding or writing fails.
+    pub fn close(mut self) -> Result<W, CdfError> {
+        self.finish(false)?;
+        self.encoder.writer.flush()?;
+        Ok(self.encoder.writer)
+    }
+
+    // Write the last blocks, the index of each variable and the final descriptors, leaving the
+    // writer at the end of the file. Returns the size of the file.
+    fn finish(&mut self, md5_checksum: bool) -> Result<u64, CdfError> {
+        let mut vxrs = Vec::with_capacity(self.variables.len());
+        for i in 0..self.variables.len() {
+            self.write_block(i)?;
+            let entries = mem::take(&mut self.variables[i].entries);
+            vxrs.push(self.write_vxrs(&entries)?);
+        }
+        self.adr_head = self.write_attributes()?;
+        let eof = self.encoder.writer.seek(SeekFrom::End(0))?;
+
+        for (i, vxrs) in vxrs.into_iter().enumerate() {
+            let is_rvariable = self.variables[i].spec.is_rvariable;
+            let next = self.variables[i + 1..]
+                .iter()
+                .find(|v| v.spec.is_rvariable == is_rvariable)
+                .map(|v| v.offset);
+            self.encoder
+                .writer
+                .seek(SeekFrom::Start(self.variables[i].offset))?;
+            self.write_descriptor(i, next, vxrs, false)?;
+        }
+        self.encoder.writer.seek(SeekFrom::Start(CDR_OFFSET))?;
+        let cdr = self.cdr(Some(eof), md5_checksum)?;
+        cdr.encode_be(&mut self.encoder)?;
+        cdr.gdr.encode_be(&mut self.encoder)?;
+
+        self.encoder.writer.seek(SeekFrom::Start(eof))?;
+        Ok(eof)
+    }
+
+    // Write the magic numbers, the CDR, the GDR and the descriptors of all variables. The GDR and
+    // the zVDRs are written again on close, once the location of everything else is known.
+    fn write_header(&mut self) -> Result<(), CdfError> {
+        self.encoder.writer.seek(SeekFrom::Start(0))?;
+        self.encoder
+            .writer
+            .write_all(&0xcdf3_0001u32.to_be_bytes())?;
+        self.encoder
+            .writer
+            .write_all(&0x0000_ffffu32.to_be_bytes())?;
+
+        let cdr = self.cdr(None, false)?;
+        self.gdr_offset = u64::try_from(*cdr.gdr_offset)?;
+        cdr.encode_be(&mut self.encoder)?;
+        cdr.gdr.encode_be(&mut self.encoder)?;
+
+        for i in 0..self.variables.len() {
+            self.variables[i].offset = self.encoder.position()?;
+            self.write_descriptor(i, None, None, true)?;
+        }
+        Ok(())
+    }
+
+    // Write the rVDR or zVDR of the variable `index`, followed by its CPR if `with_cpr` is set
+    // and the variable is compressed.
+    fn write_descriptor(
+        &mut self,
+        index: usize,
+        next: Option<u64>,
+        vxrs: Option<(u64, u64)>,
+        with_cpr: bool,
+    ) -> Result<(), CdfError> {
+        let mut zvdr = self.zvdr(index, next, vxrs)?;
+        let cpr = zvdr.cpr.take();
+        if self.variables[index].spec.is_rvariable {
+            rvdr(&self.encoder, zvdr)?.encode_be(&mut self.encoder)?;
+        } else {
+            zvdr.encode_be(&mut self.encoder)?;
+        }
+        if let (true, Some(cpr)) = (with_cpr, cpr) {
+            self.variables[index].cpr_offset = Some(self.encoder.position()?);
+            cpr.encode_be(&mut self.encoder)?;
+        }
+        Ok(())
+    }
+
+    // Write the records of the variable `index` that are pending as a single VVR, or a CVVR if
+    // the variable is compressed.
+    fn write_block(&mut self, index: usize) -> Result<(), CdfError> {
+        let variable = &mut self.variables[index];
+        if variable.pending_records == 0 {
+            return Ok(());
+        }
+        let data = mem::take(&mut variable.pending);
+        let first = variable.num_records - variable.pending_records;
+        variable.pending_records = 0;
+        let compression = variable.spec.compression.clone();
+
+        let offset = self.encoder.writer.seek(SeekFrom::End(0))?;
+        if compression == CdfCompressionKind::None {
+            let mut vvr = VariableValuesRecord {
+                record_size: CdfInt8::from(0),
+                record_type: CdfInt4::from(7),
+                records: vec![],
+            };
+            vvr.record_size = record_size(&self.encoder, &vvr, data.len())?;
+            vvr.encode_be(&mut self.encoder)?;
+            self.encoder.writer.write_all(&data)?;
+        } else {
+            let data = if self.deterministic {
+                compress_portable(&compression, &data)?
+            } else {
+                compress(&compression, &data)?
+            };
+            let mut cvvr = CompressedVariableValuesRecord {
+                record_size: CdfInt8::from(0),
+                record_type: CdfInt4::from(13),
+                rfu_a: CdfInt4::from(0),
+                compressed_size: CdfInt8::from(i64::try_from(data.len())?),
+                data,
+                records: vec![],
+            };
+            cvvr.record_size = record_size(&self.encoder, &cvvr, 0)?;
+            cvvr.encode_be(&mut self.encoder)?;
+        }
+
+        let variable = &mut self.variables[index];
+        variable
+            .entries
+            .push((first, variable.num_records - 1, offset));
+        Ok(())
+    }
+
+    // Write the ADR of each attribute followed by its entries, returning the offset of the first
+    // ADR. Entries of global attributes and of rVariables are written as AGREDRs, and entries of
+    // zVariables as AZEDRs.
+    fn write_attributes(&mut self) -> Result<Option<u64>, CdfError> {
+        let to_int8 =
+            |v: u64| -> Result<CdfInt8, CdfError> { Ok(CdfInt8::from(i64::try_from(v)?)) };
+        let mut offset = self.encoder.writer.seek(SeekFrom::End(0))?;
+        let head = (!self.attributes.is_empty()).then_some(offset);
+
+        for (i, attribute) in self.attributes.iter().enumerate() {
+            let attr_num = CdfInt4::from(i32::try_from(i)?);
+            let mut gr_entries = vec![];
+            let mut z_entries = vec![];
+            for (num, value) in &attribute.entries {
+                let (num, is_z) = if attribute.global {
+                    (*num, false)
+                } else {
+                    let variable = &self.variables[*num];
+                    (variable.num, !variable.spec.is_rvariable)
+                };
+                let mut agredr = AttributeGREntryDescriptorRecord {
+                    record_size: CdfInt8::from(0),
+                    record_type: CdfInt4::from(if is_z { 9 } else { 5 }),
+                    agredr_next: None,
+                    attr_num: attr_num.clone(),
+                    data_type: CdfInt4::from(value.data_type),
+                    num: CdfInt4::from(i32::try_from(num)?),
+                    num_elements: CdfInt4::from(i32::try_from(value.num_elements())?),
+                    num_strings: CdfInt4::from(i32::try_from(value.num_strings)?),
+                    rfu_b: CdfInt4::from(0),
+                    rfu_c: CdfInt4::from(0),
+                    rfu_d: CdfInt4::from(-1),
+                    rfu_e: CdfInt4::from(-1),
+                    value: value.values.clone(),
+                };
+                agredr.record_size = record_size(&self.encoder, &agredr, 0)?;
+                if is_z {
+                    z_entries.push(agredr);
+                } else {
+                    gr_entries.push(agredr);
+                }
+            }
+            gr_entries.sort_by_key(|e| *e.num);
+            z_entries.sort_by_key(|e| *e.num);
+
+            let mut adr = AttributeDescriptorRecord {
+                record_size: CdfInt8::from(0),
+                record_type: CdfInt4::from(4),
+                adr_next: None,
+                agredr_head: None,
+                scope: CdfInt4::from(if attribute.global { 1 } else { 2 }),
+                num: attr_num,
+                num_gr_entries: CdfInt4::from(i32::try_from(gr_entries.len())?),
+                max_gr_entry: CdfInt4::from(gr_entries.last().map_or(-1, |e| *e.num)),
+                rfu_a: CdfInt4::from(0),
+                azedr_head: None,
+                num_z_entries: CdfInt4::from(i32::try_from(z_entries.len())?),
+                max_z_entry: CdfInt4::from(z_entries.last().map_or(-1, |e| *e.num)),
+                rfu_e: CdfInt4::from(-1),
+                name: attribute.name.clone().into(),
+                agredr_vec: vec![],
+                azedr_vec: vec![],
+            };
+            adr.record_size = record_size(&self.encoder, &adr, 0)?;
+
+            // Entries follow their ADR, each list chained in the order it is written.
+            let entries_offset = offset + u64::try_from(*adr.record_size)?;
+            let mut next = entries_offset;
+            for entries in [&mut gr_entries, &mut z_entries] {
+                let n = entries.len();
+                for (j, entry) in entries.iter_mut().enumerate() {
+                    next += u64::try_from(*entry.record_size)?;
+                    if j + 1 < n {
+                        entry.agredr_next = Some(to_int8(next)?);
+                    }
+                }
+            }
+            let gr_size: u64 = gr_entries
+                .iter()
+                .map(|e| u64::try_from(*e.record_size))
+                .sum::<Result<_, _>>()?;
+            adr.agredr_head = (!gr_entries.is_empty())
+                .then(|| to_int8(entries_offset))
+                .transpose()?;
+            adr.azedr_head = (!z_entries.is_empty())
+                .then(|| to_int8(entries_offset + gr_size))
+                .transpose()?;
+            if i + 1 < self.attributes.len() {
+                adr.adr_next = Some(to_int8(next)?);
+            }
+
+            adr.encode_be(&mut self.encoder)?;
+            for entry in gr_entries {
+                entry.encode_be(&mut self.encoder)?;
+            }
+            for entry in z_entries {
+                azedr(entry).encode_be(&mut self.encoder)?;
+            }
+            offset = next;
+        }
+        Ok(head)
+    }
+
+    fn cdr(&self, eof: Option<u64>, md5_checksum: bool) -> Result<CdfDescriptorRecord, CdfError> {
+        let mut cdr = CdfDescriptorRecord {
+            record_size: CdfInt8::from(0),
+            record_type: CdfInt4::from(1),
+            gdr_offset: CdfInt8::from(0),
+            cdf_version: WRITE_VERSION,
+            encoding: self.encoder.context.encoding.clone(),
+            flags: CdrFlags {
+                row_major: self.row_major,
+                single_file: true,
+                has_checksum: md5_checksum,
+                md5_checksum,
+            },
+            rfu_a: CdfInt4::from(0),
+            rfu_b: CdfInt4::from(0),
+            identifier: CdfInt4::from(-1),
+            rfu_e: CdfInt4::from(-1),
+            copyright: COPYRIGHT.to_string().into(),
+            gdr: self.gdr(eof)?,
+        };
+        cdr.record_size = record_size(&self.encoder, &cdr, 0)?;
+        let gdr_offset = CDR_OFFSET + u64::try_from(*cdr.record_size)?;
+        cdr.gdr_offset = CdfInt8::from(i64::try_from(gdr_offset)?);
+        Ok(cdr)
+    }
+
+    // Write the VXRs that point to `entries`, returning the offsets of the first and last VXR of
+    // the top level. For a tree, lower levels are written first until the entries fit in a single
+    // VXR.
+    fn write_vxrs(
+        &mut self,
+        entries: &[(usize, usize, u64)],
+    ) -> Result<Option<(u64, u64)>, CdfError> {
+        let mut entries = entries.to_vec();
+        if self.vxr_layout == VxrLayout::Tree {
+            while entries.len() > self.vxr_entries {
+                entries = self.write_vxr_level(&entries, false)?;
+            }
+        }
+        let top = self.write_vxr_level(&entries, true)?;
+        Ok(top
+            .first()
+            .zip(top.last())
+            .map(|(head, tail)| (head.2, tail.2)))
+    }
+
+    // Write VXRs of `vxr_entries` entries each that point to `entries`, optionally chained, and
+    // return an entry for each VXR with the records it spans.
+    fn write_vxr_level(
+        &mut self,
+        entries: &[(usize, usize, u64)],
+        chained: bool,
+    ) -> Result<Vec<(usize, usize, u64)>, CdfError> {
+        let n = self.vxr_entries;
+        let mut level = Vec::with_capacity(entries.len().div_ceil(n));
+        let mut offset = self.encoder.writer.seek(SeekFrom::End(0))?;
+        let num_vxrs = entries.len().div_ceil(n);
+        for (i, chunk) in entries.chunks(n).enumerate() {
+            let mut vxr = VariableIndexRecord {
+                record_size: CdfInt8::from(0),
+                record_type: CdfInt4::from(6),
+                vxr_next: None,
+                num_entries: CdfInt4::from(i32::try_from(n)?),
+                num_used_entries: CdfInt4::from(i32::try_from(chunk.len())?),
+                first_vec: vec![None; n],
+                last_vec: vec![None; n],
+                offset_vec: vec![None; n],
+                children: vec![],
+            };
+            for (j, (first, last, entry)) in chunk.iter().enumerate() {
+                vxr.first_vec[j] = Some(CdfInt4::from(i32::try_from(*first)?));
+                vxr.last_vec[j] = Some(CdfInt4::from(i32::try_from(*last)?));
+                vxr.offset_vec[j] = Some(CdfInt8::from(i64::try_from(*entry)?));
+            }
+            vxr.record_size = record_size(&self.encoder, &vxr, 0)?;
+            let next = offset + u64::try_from(*vxr.record_size)?;
+            if chained && i + 1 < num_vxrs {
+                vxr.vxr_next = Some(CdfInt8::from(i64::try_from(next)?));
+            }
+            vxr.encode_be(&mut self.encoder)?;
+
+            level.push((chunk[0].0, chunk[chunk.len() - 1].1, offset));
+            offset = next;
+        }
+        Ok(level)
+    }
+
+    fn gdr(&self, eof: Option<u64>) -> Result<GlobalDescriptorRecord, CdfError> {
+        let offset = |v: Option<u64>| -> Result<Option<CdfInt8>, CdfError> {
+            Ok(match v {
+                Some(v) => Some(CdfInt8::from(i64::try_from(v)?)),
+                None => None,
+            })
+        };
+        let head = |is_rvariable: bool| {
+            self.variables
+                .iter()
+                .find(|v| v.spec.is_rvariable == is_rvariable)
+                .map(|v| v.offset)
+        };
+        let rvariables = self.variables.iter().filter(|v| v.spec.is_rvariable);
+        let max_rvar = rvariables.clone().map(|v| v.num_records).max().unwrap_or(0);
+        let num_rvars = rvariables.count();
+        let mut gdr = GlobalDescriptorRecord {
+            record_size: CdfInt8::from(0),
+            record_type: CdfInt4::from(2),
+            rvdr_head: offset(head(true))?,
+            zvdr_head: offset(head(false))?,
+            adr_head: offset(self.adr_head)?,
+            eof: offset(eof)?,
+            num_rvars: CdfInt4::from(i32::try_from(num_rvars)?),
+            num_attributes: CdfInt4::from(i32::try_from(self.attributes.len())?),
+            max_rvar: CdfInt4::from(i32::try_from(max_rvar)? - 1),
+            num_r_dims: CdfInt4::from(i32::try_from(self.r_dim_sizes.len())?),
+            num_zvars: CdfInt4::from(i32::try_from(self.variables.len() - num_rvars)?),
+            uir_head: None,
+            rfu_c: CdfInt4::from(0),
+            date_last_leapsecond_update: CdfInt4::from(if self.deterministic {
+                LeapSecondTable::builtin().last_update()
+            } else {
+                leap_seconds().last_update()
+            }),
+            rfu_e: CdfInt4::from(-1),
+            size_r_dims: self
+                .r_dim_sizes
+                .iter()
+                .map(|d| Ok(CdfInt4::from(i32::try_from(*d)?)))
+                .collect::<Result<_, CdfError>>()?,
+            rvdr_vec: vec![],
+            zvdr_vec: vec![],
+            adr_vec: vec![],
+            uir_vec: vec![],
+        };
+        gdr.record_size = record_size(&self.encoder, &gdr, 0)?;
+        Ok(gdr)
+    }
+
+    fn zvdr(
+        &self,
+        index: usize,
+        next: Option<u64>,
+        vxrs: Option<(u64, u64)>,
+    ) -> Result<ZVariableDescriptorRecord, CdfError> {
+        let variable = &self.variables[index];
+        let spec = &variable.spec;
+        let to_int8 =
+            |v: u64| -> Result<CdfInt8, CdfError> { Ok(CdfInt8::from(i64::try_from(v)?)) };
+        let is_compressed = spec.compression != CdfCompressionKind::None;
+        let mut zvdr = ZVariableDescriptorRecord {
+            record_size: CdfInt8::from(0),
+            record_type: CdfInt4::from(8),
+            zvdr_next: next.map(to_int8).transpose()?,
+            data_type: CdfInt4::from(spec.data_type),
+            max_record: CdfInt4::from(i32::try_from(variable.num_records)? - 1),
+            vxr_head: vxrs.map(|(head, _)| to_int8(head)).transpose()?,
+            vxr_tail: vxrs.map(|(_, tail)| to_int8(tail)).transpose()?,
+            flags: ZVariableFlags {
+                variance: spec.record_varies,
+                has_padding: spec.pad_value.is_some(),
+                is_compressed,
+            },
+            sparse_records: CdfInt4::from(0),
+            rfu_b: CdfInt4::from(0),
+            rfu_c: CdfInt4::from(-1),
+            rfu_f: CdfInt4::from(-1),
+            num_elements: CdfInt4::from(i32::try_from(spec.num_elements)?),
+            num: CdfInt4::from(i32::try_from(variable.num)?),
+            cpr_spr_offset: variable.cpr_offset.map(to_int8).transpose()?,
+            blocking_factor: CdfInt4::from(i32::try_from(variable.blocking_factor)?),
+            name: spec.name.clone().into(),
+            num_z_dims: CdfInt4::from(i32::try_from(spec.dims.len())?),
+            size_z_dims: spec
+                .dims
+                .iter()
+                .map(|d| Ok(CdfInt4::from(i32::try_from(*d)?)))
+                .collect::<Result<_, CdfError>>()?,
+            dim_variances: spec
+                .dim_variances
+                .clone()
+                .unwrap_or_else(|| vec![true; spec.dims.len()]),
+            pad_value: spec.pad_values(),
+            cpr: is_compressed.then(|| CompressedParametersRecord {
+                record_size: CdfInt8::from(28),
+                record_type: CdfInt4::from(11),
+                compression_type: spec.compression.clone(),
+                rfu_a: CdfInt4::from(0),
+                compressed_parameter_count: CdfInt4::from(1),
+                compression_level: CdfInt4::from(compression_level(&spec.compression)),
+            }),
+            vxr_vec: vec![],
+        };
+        zvdr.record_size = record_size(&self.encoder, &zvdr, 0)?;
+        Ok(zvdr)
+    }
+}
+
+impl<W> CdfWriter<W>
+where
+    W: io::Read + io::Write + io::Seek,
+{
+    /// Like [`CdfWriter::close`], but also flag the file as having an MD5 checksum and append the
+    /// checksum, which is computed by reading the file back.
+    ///
+    /// The checksum cannot be computed as the records are written: it covers the file from its
+    /// start, where the descriptors of the file and of its variables are only completed on close,
+    /// once the location of the index of each variable and the size of the file are known. The
+    /// file is read back once, in order and in blocks of constant size.
+    /// # Errors
+    /// Returns a [`CdfError`] if encoding, writing or reading fails.
+    pub fn close_with_checksum(mut self) -> Result<W, CdfError> {
+        let eof = self.finish(true)?;
+        let writer = &mut self.encoder.writer;
+        let checksum = md5_prefix(writer, eof)?;
+        writer.write_all(&checksum)?;
+        writer.flush()?;
+        Ok(self.encoder.writer)
+    }
+}
+
+// Size of `record` once encoded, along with `extra` bytes that are written after it.
+fn record_size<W, T>(encoder: &Encoder<W>, record: &T, extra: usize) -> Result<CdfInt8, CdfError>
+where
+    W: io::Write + io::Seek,
+    T: Encodable,
+{
+    let size = encode_to_vec(encoder, record)?.len() + extra;
+    Ok(CdfInt8::from(i64::try_from(size)?))
+}
+
+// The rVDR with the same fields as `zvdr`, whose dimensions are those of the GDR.
+fn rvdr<W>(
+    encoder: &Encoder<W>,
+    zvdr: ZVariableDescriptorRecord,
+) -> Result<RVariableDescriptorRecord, CdfError>
+where
+    W: io::Write + io::Seek,
+{
+    let mut rvdr = RVariableDescriptorRecord {
+        record_size: CdfInt8::from(0),
+        record_type: CdfInt4::from(3),
+        rvdr_next: zvdr.zvdr_next,
+        data_type: zvdr.data_type,
+        max_record: zvdr.max_record,
+        vxr_head: zvdr.vxr_head,
+        vxr_tail: zvdr.vxr_tail,
+        flags: RVariableFlags {
+            variance: zvdr.flags.variance,
+            has_padding: zvdr.flags.has_padding,
+            is_compressed: zvdr.flags.is_compressed,
+        },
+        sparse_records: zvdr.sparse_records,
+        rfu_b: zvdr.rfu_b,
+        rfu_c: zvdr.rfu_c,
+        rfu_f: zvdr.rfu_f,
+        num_elements: zvdr.num_elements,
+        num: zvdr.num,
+        cpr_spr_offset: zvdr.cpr_spr_offset,
+        blocking_factor: zvdr.blocking_factor,
+        name: zvdr.name,
+        size_r_dims: zvdr.size_z_dims,
+        dim_variances: zvdr.dim_variances,
+        pad_value: zvdr.pad_value,
+        cpr: zvdr.cpr,
+        vxr_vec: zvdr.vxr_vec,
+    };
+    rvdr.record_size = record_size(encoder, &rvdr, 0)?;
+    Ok(rvdr)
+}
+
+// The AZEDR with the same fields as `agredr`, which share their layout.
+fn azedr(agredr: AttributeGREntryDescriptorRecord) -> AttributeZEntryDescriptorRecord {
+    AttributeZEntryDescriptorRecord {
+        record_size: agredr.record_size,
+        record_type: agredr.record_type,
+        azedr_next: agredr.agredr_next,
+        attr_num: agredr.attr_num,
+        data_type: agredr.data_type,
+        num: agredr.num,
+        num_elements: agredr.num_elements,
+        num_strings: agredr.num_strings,
+        rfu_b: agredr.rfu_b,
+        rfu_c: agredr.rfu_c,
+        rfu_d: agredr.rfu_d,
+        rfu_e: agredr.rfu_e,
+        value: agredr.value,
+    }
+}
+
+// Reorder the values of a record from row-major to column-major order. Elements are moved in
+// groups of `num_elements`, which make up one value.
+fn to_column_major(spec: &VariableSpec, record: &[CdfType]) -> Vec<CdfType> {
+    let dims = spec.varying_dims();
+    let num_values: usize = dims.iter().product();
+    let row_major_index = |mut i: usize| {
+        let mut index = 0;
+        let mut stride = 1;
+        let mut strides = vec![0; dims.len()];
+        for (k, d) in dims.iter().enumerate().rev() {
+            strides[k] = stride;
+            stride *= d;
+        }
+        for (k, d) in dims.iter().enumerate() {
+            index += (i % d) * strides[k];
+            i /= d;
+        }
+        index
+    };
+    match record {
+        [CdfType::String(s)] => {
+            let bytes = s.as_bytes();
+            let n = spec.num_elements;
+            let reordered: Vec<u8> = (0..num_values)
+                .flat_map(|i| {
+                    let j = row_major_index(i);
+                    bytes[j * n..(j + 1) * n].iter().copied()
+                })
+                .collect();
+            vec![CdfType::String(
+                String::from_utf8_lossy(&reordered).into_owned().into(),
+            )]
+        }
+        _ => {
+            let n = spec.num_elements;
+            (0..num_values)
+                .flat_map(|i| {
+                    let j = row_major_index(i);
+                    record[j * n..(j + 1) * n].iter().cloned()
+                })
+                .collect()
+        }
+    }
+}
+
+// Level stored in the CPR, which is only meaningful for GZIP.
+fn compression_level(kind: &CdfCompressionKind) -> i32 {
+    match kind {
+        CdfCompressionKind::Gzip => 6,
+        _ => 0,
+    }
+}
+
+fn check_record(
+    spec: &VariableSpec,
+    values_per_record: usize,
+    record: &[CdfType],
+) -> Result<(), CdfError> {
+    let mismatch = || {
+        CdfError::Encode(format!(
+            "Record does not match variable {} - expected {values_per_record} values of {:?}.",
+            spec.name, spec.data_type
+        ))
+    };
+    if spec.data_type.is_string() {
+        match record {
+            [CdfType::String(s)] if s.len() == values_per_record => Ok(()),
+            _ => Err(mismatch()),
+        }
+    } else if record.len() == values_per_record
+        && record.iter().all(|v| v.is_of_type(&spec.data_type))
+    {
+        Ok(())
+    } else {
+        Err(mismatch())
+    }
+}
+
+#[cfg(all(test, feature = "std-fs"))]
+mod tests {
+    use super::*;
+    use crate::cdf::Cdf;
+    use crate::checksum;
+    use crate::dataset::Dataset;
+    use crate::record::vxr::VariableIndexRecordChild;
+    use crate::types::{CdfEpoch, CdfInt2, CdfReal4, CdfReal8, CdfString};
+
+    fn _real8(v: f64) -> CdfType {
+        CdfType::Real8(CdfReal8::from(v))
+    }
+
+    #[test]
+    fn test_write_records() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .variable(
+                VariableSpec::new("plain", DataType::Real8)
+                    .dims(&[3])
+                    .blocking_factor(4),
+            )
+            .variable(
+                VariableSpec::new("rle", DataType::Real8).compression(CdfCompressionKind::Rle),
+            )
+            .variable(
+                VariableSpec::new("gzip", DataType::Int4)
+                    .dims(&[2, 2])
+                    .compression(CdfCompressionKind::Gzip),
+            )
+            .variable(VariableSpec::new("label", DataType::Char).num_elements(4))
+            .build(io::Cursor::new(Vec::new()))?;
+
+        // Write in many chunks so that the index needs more than one VXR.
+        for chunk in 0..25 {
+            let plain: Vec<_> = (0..4)
+                .map(|r| {
+                    (0..3)
+                        .map(|i| _real8((chunk * 12 + r * 3 + i) as f64))
+                        .collect()
+                })
+                .collect();
+            writer.write_records("plain", &plain)?;
+            let rle: Vec<_> = (0..3).map(|r| vec![_real8(f64::from(r % 2))]).collect();
+            writer.write_records("rle", &rle)?;
+            let gzip: Vec<_> = (0..2)
+                .map(|r| {
+                    (0..4)
+                        .map(|i| CdfType::Int4(CdfInt4::from(chunk * 8 + r * 4 + i)))
+                        .collect()
+                })
+                .collect();
+            writer.write_records("gzip", &gzip)?;
+        }
+        let label = CdfType::String(CdfString::from("abcd".to_string()));
+        writer.write_records("label", &[vec![label]])?;
+
+        assert!(writer.write_records("missing", &[]).is_err());
+        assert!(writer.write_records("plain", &[vec![_real8(0.0)]]).is_err());
+        assert!(writer
+            .write_records("gzip", &[vec![_real8(0.0); 4]])
+            .is_err());
+
+        let bytes = writer.close()?.into_inner();
+        let cdf = Cdf::from_bytes(&bytes)?;
+
+        // 25 chunks with the default 7 entries per VXR.
+        assert_eq!(cdf.cdr.gdr.zvdr_vec[0].vxr_vec.len(), 4);
+        let plain = cdf.variable("plain")?;
+        assert_eq!(plain.n_records(), 100);
+        assert_eq!(plain.shape()?, vec![3]);
+        let records = plain.read_records(41..43)?;
+        assert_eq!(
+            format!("{:?}", records[1].data),
+            format!("{:?}", vec![_real8(126.0), _real8(127.0), _real8(128.0)])
+        );
+
+        let rle = cdf.variable("rle")?;
+        assert_eq!(rle.n_records(), 75);
+        assert_eq!(
+            format!("{:?}", rle.read_records(73..74)?[0].data),
+            format!("{:?}", vec![_real8(1.0)])
+        );
+
+        let gzip = cdf.variable("gzip")?;
+        assert_eq!(gzip.n_records(), 50);
+        let values = &gzip.read_records(49..50)?[0].data;
+        assert_eq!(
+            format!("{values:?}"),
+            format!(
+                "{:?}",
+                (196..200)
+                    .map(|i| CdfType::Int4(CdfInt4::from(i)))
+                    .collect::<Vec<_>>()
+            )
+        );
+
+        let label = cdf.variable("label")?;
+        assert_eq!(
+            format!("{:?}", label.read_records(0..1)?[0].data),
+            "[String(abcd)]".to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_vxr_tree() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .vxr_entries(3)
+            .vxr_layout(VxrLayout::Tree)
+            .variable(VariableSpec::new("x", DataType::Real8).blocking_factor(5))
+            .build(io::Cursor::new(Vec::new()))?;
+        for chunk in 0..20 {
+            let records: Vec<_> = (0..5)
+                .map(|r| vec![_real8(f64::from(chunk * 5 + r))])
+                .collect();
+            writer.write_records("x", &records)?;
+        }
+        let bytes = writer.close()?.into_inner();
+        let cdf = Cdf::from_bytes(&bytes)?;
+
+        // 20 chunks in VXRs of 3 entries need 7 and then 3 VXRs below a single top-level VXR.
+        let top = &cdf.cdr.gdr.zvdr_vec[0].vxr_vec;
+        assert_eq!(top.len(), 1);
+        let mut depth = 0;
+        let mut vxr = &top[0];
+        while let Some(Some(VariableIndexRecordChild::VXR(child))) = vxr.children.first() {
+            vxr = child;
+            depth += 1;
+        }
+        assert_eq!(depth, 2);
+
+        let x = cdf.variable("x")?;
+        assert_eq!(x.n_records(), 100);
+        let records = x.read_strided(7..100, 31)?;
+        let values: Vec<_> = records.iter().map(|r| format!("{:?}", r.data)).collect();
+        assert_eq!(values, ["[Real8(7)]", "[Real8(38)]", "[Real8(69)]"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocking_factor() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("scalar", DataType::Real8))
+            .variable(VariableSpec::new("vector", DataType::Int4).dims(&[100]))
+            .variable(VariableSpec::new("short", DataType::Int2).expected_records(10))
+            .variable(
+                VariableSpec::new("gzip", DataType::Real4).compression(CdfCompressionKind::Gzip),
+            )
+            .variable(VariableSpec::new("label", DataType::Char).num_elements(2000))
+            .build(io::Cursor::new(Vec::new()))?;
+        assert_eq!(writer.blocking_factor("scalar"), Some(128));
+        assert_eq!(writer.blocking_factor("vector"), Some(3));
+        assert_eq!(writer.blocking_factor("short"), Some(10));
+        assert_eq!(writer.blocking_factor("gzip"), Some(2048));
+        assert_eq!(writer.blocking_factor("label"), Some(1));
+        assert_eq!(writer.blocking_factor("missing"), None);
+
+        // Records are buffered until a block is full, and the rest is written on close.
+        let records: Vec<_> = (0..300).map(|r| vec![_real8(f64::from(r))]).collect();
+        for chunk in records.chunks(7) {
+            writer.write_records("scalar", chunk)?;
+        }
+        let bytes = writer.close()?.into_inner();
+        let cdf = Cdf::from_bytes(&bytes)?;
+        let zvdr = &cdf.cdr.gdr.zvdr_vec[0];
+        assert_eq!(*zvdr.blocking_factor, 128);
+        assert_eq!(*zvdr.vxr_vec[0].num_used_entries, 3);
+        let last: Vec<_> = zvdr.vxr_vec[0]
+            .last_vec
+            .iter()
+            .flatten()
+            .map(|l| **l)
+            .collect();
+        assert_eq!(last, [127, 255, 299]);
+        let scalar = cdf.variable("scalar")?;
+        assert_eq!(scalar.n_records(), 300);
+        assert_eq!(
+            format!("{:?}", scalar.read_records(299..300)?[0].data),
+            "[Real8(299)]"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_pad_value() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .variable(VariableSpec::new("time", DataType::Int4))
+            .variable(
+                VariableSpec::new("density", DataType::Real8)
+                    .dims(&[2])
+                    .pad_value(_real8(-1.0)),
+            )
+            .variable(
+                VariableSpec::new("flag", DataType::Char)
+                    .num_elements(3)
+                    .pad_value(CdfType::String(CdfString::from("n/a".to_string()))),
+            )
+            .variable(VariableSpec::new("count", DataType::Int2))
+            .build(io::Cursor::new(Vec::new()))?;
+        let time: Vec<_> = (0..4)
+            .map(|t| vec![CdfType::Int4(CdfInt4::from(t))])
+            .collect();
+        writer.write_records("time", &time)?;
+        writer.write_records("density", &[vec![_real8(1.0), _real8(2.0)]])?;
+        let bytes = writer.close()?.into_inner();
+        let cdf = Cdf::from_bytes(&bytes)?;
+
+        assert!(cdf.cdr.gdr.zvdr_vec[1].flags.has_padding);
+        assert!(!cdf.cdr.gdr.zvdr_vec[3].flags.has_padding);
+        let pad = |name: &str| -> Result<String, CdfError> {
+            Ok(format!("{:?}", cdf.variable(name)?.pad_record()?.data))
+        };
+        assert_eq!(pad("density")?, "[Real8(-1), Real8(-1)]");
+        assert_eq!(pad("flag")?, "[String(n/a)]");
+        assert_eq!(pad("count")?, "[Int2(-32767)]");
+
+        // Readers fill the records that were not written with the pad value.
+        let dataset = Dataset::new(cdf.variable("time")?, vec![cdf.variable("density")?]);
+        let column = &dataset.sel_records(0..4)?.columns[0];
+        assert_eq!(column.values.len(), 8);
+        assert_eq!(format!("{:?}", column.values[7]), "Real8(-1)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_record_varying() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .r_dim_sizes(&[2])
+            .variable(VariableSpec::new("time", DataType::Int4))
+            .variable(
+                VariableSpec::new("energy", DataType::Real8)
+                    .dims(&[3])
+                    .record_varies(false),
+            )
+            .variable(VariableSpec::new_rvariable("gain", DataType::Real8).record_varies(false))
+            .build(io::Cursor::new(Vec::new()))?;
+        let time: Vec<_> = (0..5)
+            .map(|t| vec![CdfType::Int4(CdfInt4::from(t))])
+            .collect();
+        writer.write_records("time", &time)?;
+        let energy = vec![_real8(10.0), _real8(100.0), _real8(1000.0)];
+        writer.write_records("energy", core::slice::from_ref(&energy))?;
+        assert!(writer.write_records("energy", &[energy]).is_err());
+        let gain = vec![_real8(0.5), _real8(2.0)];
+        assert!(writer
+            .write_records("gain", &[gain.clone(), gain.clone()])
+            .is_err());
+        writer.write_records("gain", &[gain])?;
+        let bytes = writer.close()?.into_inner();
+        let cdf = Cdf::from_bytes(&bytes)?;
+
+        assert!(cdf.variable("time")?.record_varies());
+        for name in ["energy", "gain"] {
+            let variable = cdf.variable(name)?;
+            assert!(!variable.record_varies());
+            assert_eq!(variable.n_records(), 1);
+            let info = variable.storage_info()?;
+            assert_eq!((info.stored_records, info.vvrs), (1, 1));
+        }
+        assert_eq!(
+            format!("{:?}", cdf.variable("energy")?.read_all()?[0].data),
+            "[Real8(10), Real8(100), Real8(1000)]"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_options() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .encoding(CdfEncoding::IbmPc)
+            .row_major(false)
+            .variable(VariableSpec::new("matrix", DataType::Int4).dims(&[2, 3]))
+            .variable(
+                VariableSpec::new("labels", DataType::Char)
+                    .num_elements(2)
+                    .dims(&[2, 2]),
+            )
+            .build(io::Cursor::new(Vec::new()))?;
+        let matrix: Vec<_> = (0..6).map(|i| CdfType::Int4(CdfInt4::from(i))).collect();
+        writer.write_records("matrix", &[matrix])?;
+        let labels = CdfType::String(CdfString::from("aabbccdd".to_string()));
+        writer.write_records("labels", &[vec![labels]])?;
+        let bytes = writer.close_with_checksum()?.into_inner();
+
+        checksum::verify_md5(&mut io::Cursor::new(bytes.as_slice()))?;
+        let cdf = Cdf::from_bytes(&bytes)?;
+        assert_eq!(cdf.cdr.encoding, CdfEncoding::IbmPc);
+        assert!(!cdf.cdr.flags.row_major);
+        assert!(cdf.cdr.flags.md5_checksum);
+        assert_eq!(cdf.cdr.copyright.to_string(), COPYRIGHT);
+
+        // Values are stored in column-major order.
+        let values = &cdf.variable("matrix")?.read_records(0..1)?[0].data;
+        assert_eq!(
+            format!("{values:?}"),
+            "[Int4(0), Int4(3), Int4(1), Int4(4), Int4(2), Int4(5)]"
+        );
+        let values = &cdf.variable("labels")?.read_records(0..1)?[0].data;
+        assert_eq!(format!("{values:?}"), "[String(aaccbbdd)]");
+
+        let unsupported = CdfWriter::builder()
+            .encoding(CdfEncoding::Vax)
+            .build(io::Cursor::new(Vec::new()));
+        assert!(unsupported.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_num_elements() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .row_major(false)
+            .variable(
+                VariableSpec::new("pairs", DataType::Int2)
+                    .dims(&[2, 2])
+                    .num_elements(2)
+                    .pad_value(CdfType::Int2(CdfInt2::from(-1))),
+            )
+            .variable(VariableSpec::new("time", DataType::Int2))
+            .build(io::Cursor::new(Vec::new()))?;
+        let pairs: Vec<_> = (0..8).map(|i| CdfType::Int2(CdfInt2::from(i))).collect();
+        writer.write_records("pairs", &[pairs])?;
+        let time: Vec<_> = (0..2)
+            .map(|t| vec![CdfType::Int2(CdfInt2::from(t))])
+            .collect();
+        writer.write_records("time", &time)?;
+        let bytes = writer.close()?.into_inner();
+        let cdf = Cdf::from_bytes(&bytes)?;
+
+        // Each value is a pair of elements, which stay together in column-major order.
+        let pairs = cdf.variable("pairs")?;
+        assert_eq!(pairs.record_shape()?, [2, 2, 2]);
+        assert_eq!(pairs.array_shape()?, [1, 2, 2, 2]);
+        assert!(!pairs.is_scalar());
+        assert_eq!(
+            format!("{:?}", pairs.read_records(0..1)?[0].data),
+            "[Int2(0), Int2(1), Int2(4), Int2(5), Int2(2), Int2(3), Int2(6), Int2(7)]"
+        );
+        assert_eq!(
+            format!("{:?}", pairs.pad_record()?.data),
+            format!("{:?}", vec![CdfType::Int2(CdfInt2::from(-1)); 8])
+        );
+
+        let dataset = Dataset::new(cdf.variable("time")?, vec![pairs]);
+        let column = &dataset.sel_records(0..2)?.columns[0];
+        assert_eq!(
+            (column.shape.as_slice(), column.values.len()),
+            (&[2, 2, 2][..], 16)
+        );
+
+        // Variables are copied with their elements.
+        let spec = VariableSpec::from_variable(&pairs)?;
+        let mut writer = CdfWriter::builder()
+            .variable(spec)
+            .build(io::Cursor::new(Vec::new()))?;
+        writer.write_records("pairs", &[pairs.read_records(0..1)?[0].data.clone()])?;
+        let copy = Cdf::from_bytes(&writer.close()?.into_inner())?;
+        let copy = copy.variable("pairs")?;
+        assert_eq!(copy.record_shape()?, [2, 2, 2]);
+        assert_eq!(
+            format!("{:?}", copy.read_all()?),
+            format!("{:?}", pairs.read_all()?)
+        );
+        assert_eq!(
+            format!("{:?}", copy.pad_record()?),
+            format!("{:?}", pairs.pad_record()?)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_deterministic() -> Result<(), CdfError> {
+        let write = || -> Result<Vec<u8>, CdfError> {
+            let mut writer = CdfWriter::builder()
+                .deterministic(true)
+                .variable(
+                    VariableSpec::new("density", DataType::Real8)
+                        .compression(CdfCompressionKind::Gzip),
+                )
+                .build(io::Cursor::new(Vec::new()))?;
+            let records: Vec<_> = (0..100).map(|r| vec![_real8(f64::from(r % 7))]).collect();
+            writer.write_records("density", &records)?;
+            writer.global_attr("Project")?.entry("Reproducible");
+            Ok(writer.close_with_checksum()?.into_inner())
+        };
+        let bytes = write()?;
+        assert_eq!(bytes, write()?);
+
+        let cdf = Cdf::from_bytes(&bytes)?;
+        assert_eq!(cdf.cdr.encoding, CdfEncoding::Network);
+        assert_eq!(
+            *cdf.cdr.gdr.date_last_leapsecond_update,
+            LeapSecondTable::builtin().last_update()
+        );
+        let density = cdf.variable("density")?;
+        assert_eq!(density.n_records(), 100);
+        assert_eq!(
+            format!("{:?}", density.read_records(99..100)?[0].data),
+            "[Real8(1)]"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_encodings() -> Result<(), CdfError> {
+        for encoding in [None, Some(CdfEncoding::Network), Some(CdfEncoding::IbmPc)] {
+            let mut builder = CdfWriter::builder()
+                .variable(VariableSpec::new("density", DataType::Real8).pad_value(_real8(-1.5)));
+            if let Some(encoding) = &encoding {
+                builder = builder.encoding(encoding.clone());
+            }
+            let mut writer = builder.build(io::Cursor::new(Vec::new()))?;
+            writer.write_records("density", &[vec![_real8(2.25)]])?;
+            writer
+                .global_attr("Launch")?
+                .entry_tt2000(-43_135_816_000_000);
+            writer.var_attr("density", "VALIDMAX", vec![1000i32, 2000])?;
+            let bytes = writer.close()?.into_inner();
+
+            let cdf = Cdf::from_bytes(&bytes)?;
+            assert_eq!(cdf.cdr.encoding, encoding.unwrap_or(CdfEncoding::host()));
+            let density = cdf.variable("density")?;
+            assert_eq!(
+                format!("{:?}", density.read_records(0..1)?[0].data),
+                "[Real8(2.25)]"
+            );
+            assert_eq!(format!("{:?}", density.pad_record()?.data), "[Real8(-1.5)]");
+            assert_eq!(
+                format!("{:?}", density.attribute("VALIDMAX")),
+                "Some([Int4(1000), Int4(2000)])"
+            );
+            assert_eq!(
+                format!("{:?}", cdf.cdr.gdr.adr_vec[0].agredr_vec[0].value),
+                "[TimeTt2000(-43135816000000)]"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_rvariables() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .r_dim_sizes(&[3])
+            .variable(VariableSpec::new_rvariable("Epoch", DataType::Epoch).dim_variances(&[false]))
+            .variable(VariableSpec::new("label", DataType::Int2))
+            .variable(
+                VariableSpec::new_rvariable("B", DataType::Real4)
+                    .compression(CdfCompressionKind::Gzip),
+            )
+            .build(io::Cursor::new(Vec::new()))?;
+        let epoch: Vec<_> = (0..4)
+            .map(|r| vec![CdfType::Epoch(CdfEpoch::from(f64::from(r)))])
+            .collect();
+        writer.write_records("Epoch", &epoch)?;
+        let b: Vec<_> = (0..2)
+            .map(|r| {
+                (0..3)
+                    .map(|i| CdfType::Real4(CdfReal4::from((r * 3 + i) as f32)))
+                    .collect()
+            })
+            .collect();
+        writer.write_records("B", &b)?;
+        writer.write_records("label", &[vec![CdfType::Int2(CdfInt2::from(7))]])?;
+        let bytes = writer.close()?.into_inner();
+        let cdf = Cdf::from_bytes(&bytes)?;
+
+        let gdr = &cdf.cdr.gdr;
+        assert_eq!((*gdr.num_rvars, *gdr.num_zvars, *gdr.max_rvar), (2, 1, 3));
+        assert_eq!(gdr.rvdr_vec[1].name.to_string(), "B");
+        assert_eq!(*gdr.rvdr_vec[1].num, 1);
+
+        let epoch = cdf.variable("Epoch")?;
+        assert!(!epoch.is_zvariable());
+        assert_eq!(epoch.dim_sizes()?, vec![3]);
+        assert_eq!(epoch.shape()?, Vec::<usize>::new());
+        assert_eq!(epoch.n_records(), 4);
+        assert_eq!(
+            format!("{:?}", epoch.read_records(3..4)?[0].data),
+            "[Epoch(3)]"
+        );
+        let b = cdf.variable("B")?;
+        assert_eq!(b.shape()?, vec![3]);
+        assert_eq!(
+            format!("{:?}", b.read_records(1..2)?[0].data),
+            "[Real4(3), Real4(4), Real4(5)]"
+        );
+        assert!(cdf.variable("label")?.is_zvariable());
+
+        let dims = CdfWriter::builder()
+            .variable(VariableSpec::new_rvariable("a", DataType::Int4).dims(&[2]))
+            .build(io::Cursor::new(Vec::new()));
+        assert!(dims.is_err());
+        let variances = CdfWriter::builder()
+            .r_dim_sizes(&[2, 2])
+            .variable(VariableSpec::new_rvariable("a", DataType::Int4).dim_variances(&[true]))
+            .build(io::Cursor::new(Vec::new()));
+        assert!(variances.is_err());
+        let empty = CdfWriter::builder()
+            .r_dim_sizes(&[0])
+            .variable(VariableSpec::new_rvariable("a", DataType::Int4))
+            .build(io::Cursor::new(Vec::new()));
+        assert!(empty.is_err());
+        let empty = CdfWriter::builder()
+            .variable(VariableSpec::new("a", DataType::Int4).dims(&[3, 0]))
+            .build(io::Cursor::new(Vec::new()));
+        assert!(empty.is_err());
+        let many = CdfWriter::builder()
+            .variable(VariableSpec::new("a", DataType::Int4).dims(&[1; 11]))
+            .build(io::Cursor::new(Vec::new()));
+        assert!(matches!(
+            many,
+            Err(CdfError::Shape(ShapeError::TooManyDimensions {
+                num_dims: 11,
+                ..
+            }))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_attributes() -> Result<(), CdfError> {
+        let mut writer = CdfWriter::builder()
+            .r_dim_sizes(&[3])
+            .variable(VariableSpec::new("Epoch", DataType::Epoch))
+            .variable(VariableSpec::new_rvariable("B_RTN", DataType::Real4))
+            .build(io::Cursor::new(Vec::new()))?;
+        writer
+            .global_attr("Mission_group")?
+            .entry("Voyager")
+            .entry_epoch(62_987_673_600_000.0);
+        writer
+            .global_attr("TEXT")?
+            .entry(AttributeValue::strings(["first line", "second line"]));
+        writer.var_attr("B_RTN", "FILLVAL", -1e31f32)?;
+        writer.var_attr("B_RTN", "UNITS", "nT")?;
+        writer.var_attr("Epoch", "UNITS", "ms")?;
+        writer.var_attr("Epoch", "UNITS", "milliseconds")?;
+        writer.var_attr("Epoch", "VALIDMIN", vec![0i64, 1])?;
+        assert!(writer.var_attr("missing", "UNITS", "nT").is_err());
+        assert!(writer.var_attr("Epoch", "TEXT", "text").is_err());
+        assert!(writer.global_attr("UNITS").is_err());
+        let bytes = writer.close()?.into_inner();
+        let cdf = Cdf::from_bytes(&bytes)?;
+
+        let gdr = &cdf.cdr.gdr;
+        assert_eq!(*gdr.num_attributes, 5);
+        let mission = &gdr.adr_vec[0];
+        assert_eq!(mission.name.to_string(), "Mission_group");
+        assert_eq!(*mission.scope, 1);
+        assert_eq!(*mission.num_gr_entries, 2);
+        assert_eq!(
+            format!("{:?}", mission.agredr_vec[1].value),
+            "[Epoch(62987673600000)]"
+        );
+        assert_eq!(
+            gdr.adr_vec[1].agredr_vec[0].strings(),
+            Some(vec!["first line".to_string(), "second line".to_string()])
+        );
+
+        let b = cdf.variable("B_RTN")?;
+        assert!(matches!(b.attribute("FILLVAL"), Some([CdfType::Real4(v)]) if **v == -1e31));
+        assert_eq!(b.attribute_str("UNITS"), Some("nT"));
+        let epoch = cdf.variable("Epoch")?;
+        assert_eq!(epoch.attribute_str("UNITS"), Some("milliseconds"));
+        assert_eq!(
+            format!("{:?}", epoch.attribute("VALIDMIN")),
+            "Some([Int8(0), Int8(1)])"
+        );
+        let units = &gdr.adr_vec[3];
+        assert_eq!((units.agredr_vec.len(), units.azedr_vec.len()), (1, 1));
+        assert_eq!(*units.azedr_vec[0].num_elements, 12);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_variables() {
+        let duplicate = CdfWriter::builder()
+            .variable(VariableSpec::new("a", DataType::Int4))
+            .variable(VariableSpec::new("a", DataType::Int8))
+            .build(io::Cursor::new(Vec::new()));
+        assert!(duplicate.is_err());
+        let elements = CdfWriter::builder()
+            .variable(VariableSpec::new("a", DataType::Int4).num_elements(0))
+            .build(io::Cursor::new(Vec::new()));
+        assert!(elements.is_err());
+        let compression = CdfWriter::builder()
+            .variable(VariableSpec::new("a", DataType::Int4).compression(CdfCompressionKind::Huff))
+            .build(io::Cursor::new(Vec::new()));
+        assert!(compression.is_err());
+        let vxr_entries = CdfWriter::builder()
+            .vxr_entries(1)
+            .vxr_layout(VxrLayout::Tree)
+            .build(io::Cursor::new(Vec::new()));
+        assert!(vxr_entries.is_err());
+        let blocking_factor = CdfWriter::builder()
+            .variable(VariableSpec::new("a", DataType::Int4).blocking_factor(0))
+            .build(io::Cursor::new(Vec::new()));
+        assert!(blocking_factor.is_err());
+        let pad_value = CdfWriter::builder()
+            .variable(VariableSpec::new("a", DataType::Int4).pad_value(_real8(0.0)))
+            .build(io::Cursor::new(Vec::new()));
+        assert!(pad_value.is_err());
+    }
+
+    #[test]
+    fn test_spec_from_variable() -> Result<(), CdfError> {
+        for filename in ["test_alltypes.cdf", "ulysses.cdf"] {
+            let path: std::path::PathBuf =
+                [env!("CARGO_MANIFEST_DIR"), "examples", "data", filename]
+                    .iter()
+                    .collect();
+            let master = Cdf::read_cdf_file(path)?;
+            let r_dim_sizes: Vec<usize> = master
+                .cdr
+                .gdr
+                .size_r_dims
+                .iter()
+                .map(|s| usize::try_from(**s))
+                .collect::<Result<_, _>>()?;
+            let mut builder = CdfWriter::builder()
+                .encoding(master.cdr.encoding.clone())
+                .r_dim_sizes(&r_dim_sizes);
+            for variable in master.variables() {
+                builder = builder.variable(VariableSpec::from_variable(&variable)?);
+            }
+            let bytes = builder
+                .build(io::Cursor::new(Vec::new()))?
+                .close()?
+                .into_inner();
+            let cdf = Cdf::from_bytes(&bytes)?;
+            for (a, b) in cdf.variables().iter().zip(master.variables().iter()) {
+                assert_eq!(a.name(), b.name());
+                assert_eq!(a.data_type(), b.data_type());
+                assert_eq!(a.shape()?, b.shape()?);
+                assert_eq!(a.record_varies(), b.record_varies());
+                assert_eq!(a.compression().is_some(), b.compression().is_some());
+                assert_eq!(
+                    format!("{:?}", a.pad_record()?.data),
+                    format!("{:?}", b.pad_record()?.data)
+                );
+            }
+        }
+        Ok(())
+    }
+}
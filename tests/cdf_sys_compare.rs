@@ -0,0 +1,356 @@
+//! Cross-validation of this crate against the official NASA CDF C library.
+//!
+//! This test is only built with the dev-only `cdf-sys-compare` feature and links to `libcdf`,
+//! which must be installed separately. Point the linker to it if needed, e.g.
+//!
+//! ```shell
+//! $ RUSTFLAGS="-L /usr/local/cdf/lib" cargo test --features cdf-sys-compare --test cdf_sys_compare
+//! ```
+#![cfg(feature = "cdf-sys-compare")]
+
+use std::ffi::{c_char, c_int, c_long, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+
+use cdf::cdf::Cdf;
+use cdf::error::CdfError;
+use cdf::record::adr::AttributeDescriptorRecord;
+use cdf::types::{CdfInt4, CdfType, DataType};
+use cdf::variable::Variable;
+
+type CdfId = *mut c_void;
+type CdfStatus = c_long;
+
+// Status codes below this value are errors, above it are warnings or informational.
+const CDF_WARN: CdfStatus = -2000;
+const CDF_VAR_NAME_LEN256: usize = 256;
+const CDF_ATTR_NAME_LEN256: usize = 256;
+
+// Kinds of attribute entries. The CDFgetAttrgEntry*, CDFgetAttrrEntry* and CDFgetAttrzEntry*
+// functions of cdf.h are macros that pass these to the CDFgetAttrEntry* functions.
+const G_ENTRY: c_int = 1;
+const R_ENTRY: c_int = 2;
+const Z_ENTRY: c_int = 3;
+
+#[link(name = "cdf")]
+extern "C" {
+    fn CDFopenCDF(name: *const c_char, id: *mut CdfId) -> CdfStatus;
+    fn CDFcloseCDF(id: CdfId) -> CdfStatus;
+    fn CDFgetNumVars(id: CdfId, z_var: c_int, num_vars: *mut c_long) -> CdfStatus;
+    fn CDFgetVarName(id: CdfId, z_var: c_int, var_num: c_long, name: *mut c_char) -> CdfStatus;
+    fn CDFgetVarDataType(
+        id: CdfId,
+        z_var: c_int,
+        var_num: c_long,
+        data_type: *mut c_long,
+    ) -> CdfStatus;
+    fn CDFgetVarMaxWrittenRecNum(
+        id: CdfId,
+        z_var: c_int,
+        var_num: c_long,
+        max_rec: *mut c_long,
+    ) -> CdfStatus;
+    fn CDFgetVarRecordData(
+        id: CdfId,
+        z_var: c_int,
+        var_num: c_long,
+        rec_num: c_long,
+        buffer: *mut c_void,
+    ) -> CdfStatus;
+    fn CDFgetNumAttributes(id: CdfId, num_attrs: *mut c_long) -> CdfStatus;
+    fn CDFgetAttrName(id: CdfId, attr_num: c_long, name: *mut c_char) -> CdfStatus;
+    fn CDFgetAttrEntryDataType(
+        id: CdfId,
+        grz_entry: c_int,
+        attr_num: c_long,
+        entry_num: c_long,
+        data_type: *mut c_long,
+    ) -> CdfStatus;
+    fn CDFgetAttrEntryNumElems(
+        id: CdfId,
+        grz_entry: c_int,
+        attr_num: c_long,
+        entry_num: c_long,
+        num_elems: *mut c_long,
+    ) -> CdfStatus;
+    fn CDFgetAttrEntry(
+        id: CdfId,
+        grz_entry: c_int,
+        attr_num: c_long,
+        entry_num: c_long,
+        value: *mut c_void,
+    ) -> CdfStatus;
+}
+
+fn check(status: CdfStatus, what: &str) -> Result<(), CdfError> {
+    if status < CDF_WARN {
+        return Err(CdfError::Other(format!(
+            "C library call {what} failed with status {status}."
+        )));
+    }
+    Ok(())
+}
+
+/// A file opened with the C library, closed when dropped.
+struct CFile(CdfId);
+
+impl CFile {
+    fn open(path: &Path) -> Result<Self, CdfError> {
+        // The C library appends the extension itself.
+        let name = path.with_extension("");
+        let name = CString::new(name.to_string_lossy().as_bytes())
+            .map_err(|e| CdfError::Other(e.to_string()))?;
+        let mut id: CdfId = std::ptr::null_mut();
+        check(unsafe { CDFopenCDF(name.as_ptr(), &mut id) }, "CDFopenCDF")?;
+        Ok(CFile(id))
+    }
+
+    fn num_vars(&self, z_var: bool) -> Result<c_long, CdfError> {
+        let mut n: c_long = 0;
+        check(
+            unsafe { CDFgetNumVars(self.0, c_int::from(z_var), &mut n) },
+            "CDFgetNumVars",
+        )?;
+        Ok(n)
+    }
+
+    fn var_name(&self, z_var: bool, num: c_long) -> Result<String, CdfError> {
+        let mut buffer = [0 as c_char; CDF_VAR_NAME_LEN256 + 1];
+        check(
+            unsafe { CDFgetVarName(self.0, c_int::from(z_var), num, buffer.as_mut_ptr()) },
+            "CDFgetVarName",
+        )?;
+        Ok(unsafe { CStr::from_ptr(buffer.as_ptr()) }
+            .to_string_lossy()
+            .into_owned())
+    }
+
+    fn var_data_type(&self, z_var: bool, num: c_long) -> Result<c_long, CdfError> {
+        let mut data_type: c_long = 0;
+        check(
+            unsafe { CDFgetVarDataType(self.0, c_int::from(z_var), num, &mut data_type) },
+            "CDFgetVarDataType",
+        )?;
+        Ok(data_type)
+    }
+
+    fn var_max_record(&self, z_var: bool, num: c_long) -> Result<c_long, CdfError> {
+        let mut max_rec: c_long = 0;
+        check(
+            unsafe { CDFgetVarMaxWrittenRecNum(self.0, c_int::from(z_var), num, &mut max_rec) },
+            "CDFgetVarMaxWrittenRecNum",
+        )?;
+        Ok(max_rec)
+    }
+
+    fn var_record(
+        &self,
+        z_var: bool,
+        num: c_long,
+        record: c_long,
+        num_bytes: usize,
+    ) -> Result<Vec<u8>, CdfError> {
+        let mut buffer = vec![0u8; num_bytes];
+        check(
+            unsafe {
+                CDFgetVarRecordData(
+                    self.0,
+                    c_int::from(z_var),
+                    num,
+                    record,
+                    buffer.as_mut_ptr().cast(),
+                )
+            },
+            "CDFgetVarRecordData",
+        )?;
+        Ok(buffer)
+    }
+
+    fn attribute_names(&self) -> Result<Vec<String>, CdfError> {
+        let mut n: c_long = 0;
+        check(
+            unsafe { CDFgetNumAttributes(self.0, &mut n) },
+            "CDFgetNumAttributes",
+        )?;
+        (0..n)
+            .map(|i| {
+                let mut buffer = [0 as c_char; CDF_ATTR_NAME_LEN256 + 1];
+                check(
+                    unsafe { CDFgetAttrName(self.0, i, buffer.as_mut_ptr()) },
+                    "CDFgetAttrName",
+                )?;
+                Ok(unsafe { CStr::from_ptr(buffer.as_ptr()) }
+                    .to_string_lossy()
+                    .into_owned())
+            })
+            .collect()
+    }
+
+    /// Data type, number of elements and value bytes of an attribute entry.
+    fn entry(
+        &self,
+        grz_entry: c_int,
+        attr_num: c_long,
+        entry_num: c_long,
+    ) -> Result<(c_long, c_long, Vec<u8>), CdfError> {
+        let mut data_type: c_long = 0;
+        check(
+            unsafe {
+                CDFgetAttrEntryDataType(self.0, grz_entry, attr_num, entry_num, &mut data_type)
+            },
+            "CDFgetAttrEntryDataType",
+        )?;
+        let mut num_elems: c_long = 0;
+        check(
+            unsafe {
+                CDFgetAttrEntryNumElems(self.0, grz_entry, attr_num, entry_num, &mut num_elems)
+            },
+            "CDFgetAttrEntryNumElems",
+        )?;
+        let size = DataType::try_from(CdfInt4::from(data_type as i32))?.size();
+        let mut buffer = vec![0u8; num_elems as usize * size];
+        check(
+            unsafe {
+                CDFgetAttrEntry(
+                    self.0,
+                    grz_entry,
+                    attr_num,
+                    entry_num,
+                    buffer.as_mut_ptr().cast(),
+                )
+            },
+            "CDFgetAttrEntry",
+        )?;
+        Ok((data_type, num_elems, buffer))
+    }
+}
+
+impl Drop for CFile {
+    fn drop(&mut self) {
+        _ = unsafe { CDFcloseCDF(self.0) };
+    }
+}
+
+/// Values in the native byte order, as returned by the C library.
+fn native_bytes(value: &CdfType) -> Vec<u8> {
+    macro_rules! ne {
+        ($v:expr) => {
+            if cfg!(target_endian = "little") {
+                $v.clone().to_le_bytes().to_vec()
+            } else {
+                $v.clone().to_be_bytes().to_vec()
+            }
+        };
+    }
+    match value {
+        CdfType::Int1(v) => ne!(v),
+        CdfType::Int2(v) => ne!(v),
+        CdfType::Int4(v) => ne!(v),
+        CdfType::Int8(v) => ne!(v),
+        CdfType::Uint1(v) => ne!(v),
+        CdfType::Uint2(v) => ne!(v),
+        CdfType::Uint4(v) => ne!(v),
+        CdfType::Real4(v) => ne!(v),
+        CdfType::Real8(v) => ne!(v),
+        CdfType::Epoch(v) => ne!(v),
+        CdfType::Epoch16(v) => ne!(v),
+        CdfType::TimeTt2000(v) => ne!(v),
+        CdfType::Byte(v) => ne!(v),
+        CdfType::Char(v) | CdfType::Uchar(v) => ne!(v),
+        CdfType::String(s) => s.as_bytes().to_vec(),
+    }
+}
+
+fn compare_variable(c_file: &CFile, variable: &Variable) -> Result<(), CdfError> {
+    let z_var = variable.is_zvariable();
+    let num = c_long::from(**variable.num());
+    let name = variable.name();
+    assert_eq!(c_file.var_name(z_var, num)?, name);
+    assert_eq!(
+        c_file.var_data_type(z_var, num)?,
+        c_long::from(**variable.data_type()),
+        "data type of {name}"
+    );
+
+    let records = variable.read_all()?;
+    let max_record = c_file.var_max_record(z_var, num)?;
+    assert_eq!(max_record + 1, records.len() as c_long, "records of {name}");
+
+    for (i, record) in records.iter().enumerate() {
+        let expected: Vec<u8> = record.data.iter().flat_map(native_bytes).collect();
+        let actual = c_file.var_record(z_var, num, i as c_long, expected.len())?;
+        assert_eq!(actual, expected, "record {i} of {name}");
+    }
+    Ok(())
+}
+
+fn compare_attribute(c_file: &CFile, adr: &AttributeDescriptorRecord) -> Result<(), CdfError> {
+    let attr_num = c_long::from(*adr.num);
+    let name = &*adr.name;
+    // Entries of global attributes are gEntries, those of variable attributes rEntries.
+    let gr_entry = if matches!(*adr.scope, 1 | 3) {
+        G_ENTRY
+    } else {
+        R_ENTRY
+    };
+    let entries = adr
+        .agredr_vec
+        .iter()
+        .map(|e| (gr_entry, *e.num, *e.data_type, *e.num_elements, &e.value))
+        .chain(
+            adr.azedr_vec
+                .iter()
+                .map(|e| (Z_ENTRY, *e.num, *e.data_type, *e.num_elements, &e.value)),
+        );
+    for (grz_entry, num, data_type, num_elements, value) in entries {
+        let (c_data_type, c_num_elements, c_value) =
+            c_file.entry(grz_entry, attr_num, c_long::from(num))?;
+        let entry = format!("entry {num} of kind {grz_entry} of attribute {name}");
+        assert_eq!(c_data_type, c_long::from(data_type), "data type of {entry}");
+        assert_eq!(
+            c_num_elements,
+            c_long::from(num_elements),
+            "number of elements of {entry}"
+        );
+        let expected: Vec<u8> = value.iter().flat_map(native_bytes).collect();
+        assert_eq!(c_value, expected, "value of {entry}");
+    }
+    Ok(())
+}
+
+fn compare_file(filename: &str) -> Result<(), CdfError> {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "examples", "data", filename]
+        .iter()
+        .collect();
+
+    let cdf = Cdf::read_cdf_file(&path)?;
+    let c_file = CFile::open(&path)?;
+
+    let attributes: Vec<String> = cdf
+        .cdr
+        .gdr
+        .adr_vec
+        .iter()
+        .map(|adr| adr.name.to_string())
+        .collect();
+    assert_eq!(c_file.attribute_names()?, attributes);
+    for adr in &cdf.cdr.gdr.adr_vec {
+        compare_attribute(&c_file, adr)?;
+    }
+
+    let variables = cdf.variables();
+    let num_r = c_file.num_vars(false)?;
+    let num_z = c_file.num_vars(true)?;
+    assert_eq!((num_r + num_z) as usize, variables.len());
+
+    for variable in &variables {
+        compare_variable(&c_file, variable)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_compare_with_c_library() -> Result<(), CdfError> {
+    compare_file("test_alltypes.cdf")?;
+    compare_file("ulysses.cdf")?;
+    Ok(())
+}